@@ -1,4 +1,6 @@
 pub (crate) mod midi_setup;
+pub (crate) mod state_file;
+pub (crate) mod stop_signals;
 
 use clap::ArgMatches;
 
@@ -7,11 +9,11 @@ use directories::ProjectDirs;
 use std::path::{PathBuf, Path};
 use std::fs;
 
-use mmpd_lib::config::Config;
-use mmpd_lib::config::input_formats::get_parser_for_extension;
+use mmpd_lib::config::{Config, ConfigError};
+use mmpd_lib::config::loader::load_config;
 use std::io::Write;
 
-fn get_project_dir() -> Option<ProjectDirs> {
+pub (crate) fn get_project_dir() -> Option<ProjectDirs> {
     ProjectDirs::from("me","michd", "mmpd")
 }
 
@@ -49,9 +51,11 @@ fn create_config_file_from_template(config_file_path: &PathBuf) -> Option<PathBu
 }
 
 fn get_default_config_file() -> Option<PathBuf> {
-    const DEFAULT_FILENAMES: [&str; 2] = [
+    const DEFAULT_FILENAMES: [&str; 4] = [
         "mmpd.yml",
-        "mmpd.yaml"
+        "mmpd.yaml",
+        "mmpd.toml",
+        "mmpd.json"
     ];
 
     let config_dir = get_project_dir()
@@ -120,34 +124,15 @@ pub (crate) fn get_config(cli_matches: Option<&ArgMatches>) -> Option<(Config, S
 pub (crate) fn read_config(config_file: PathBuf) -> Option<Config> {
     let config_file_name = config_file.to_str().unwrap_or("[none]");
 
-    // Read config file to text
-    let config_text = fs::read_to_string(&config_file).or_else(|read_err| {
-        eprintln!("Unable to read config file {}", config_file_name);
-        eprintln!("{}", read_err);
-        Err(read_err)
-    }).ok()?;
-
-    // Get an appropriate parser based on the config file extension
-    let ext = config_file.extension()
-        .map(|s| s.to_str().unwrap_or("yml")).unwrap_or("yml");
-
-    // Find a parser for this config file format
-    let parser = get_parser_for_extension(ext).or_else(||{
-        eprintln!("Error: unknown config file format {}", ext);
-        None
-    })?;
-
-    // Parse configuration in RawConfig intermediary format
-    let intermediate_config = parser.parse(&config_text).or_else(|parse_err| {
-        eprintln!("Error: unable to parse config file {}", config_file_name);
-        eprintln!("{}", parse_err.description());
-        Err(parse_err)
-    }).ok()?;
-
-    // Process into Config object
-    match intermediate_config.process() {
+    // Read the config file, along with any files it includes, into a Config object
+    match load_config(&config_file) {
         Ok(config) => Some(config),
 
+        Err(ConfigError::ReadError(desc)) => {
+            eprintln!("Error: {}", desc);
+            None
+        }
+
         Err(e) => {
             eprintln!("Error: unable to parse config file {}", config_file_name);
             eprintln!("{}", e.description());
@@ -1,13 +1,30 @@
 use clap::ArgMatches;
-use mmpd_lib::match_checker::{StringMatcher, MatchChecker};
+use mmpd_lib::match_checker::StringMatcher;
 use mmpd_lib::midi::adapters::MidiAdapter;
 use mmpd_lib::midi;
-use mmpd_lib::config::Config;
+use mmpd_lib::midi::supervisor::MidiSupervisor;
+use mmpd_lib::config::{Config, MidiDevice};
 
+/// MIDI adapter along with what to listen to through it
+pub (crate) struct MidiSetup {
+    pub (crate) adapter: Box<dyn MidiAdapter>,
+
+    /// Devices to listen to, once they're connected
+    pub (crate) devices: Vec<MidiDevice>,
+
+    /// Name of the virtual input port to create, if any
+    pub (crate) virtual_port: Option<String>
+}
+
+/// Sets up a MIDI adapter, and determines which MIDI devices to listen to, and which virtual port
+/// to create, if any.
+///
+/// The devices don't have to be connected yet; they are connected to by a `MidiSupervisor` as
+/// soon as they're available.
 pub (crate) fn get_midi_setup(
     cli_matches: Option<&ArgMatches>,
     config: Option<&Config>
-) -> Option<(Box<dyn MidiAdapter>, String)> {
+) -> Option<MidiSetup> {
     let midi_adapter = midi::get_adapter();
 
     if midi_adapter.is_none() {
@@ -17,14 +34,16 @@ pub (crate) fn get_midi_setup(
 
     let midi_adapter = midi_adapter.unwrap();
 
-    let midi_device_name = get_selected_midi_device_name(
-        cli_matches,
-        config,
-        &midi_adapter
-    );
+    let midi_devices = get_midi_devices(cli_matches, config);
+    let virtual_port = get_virtual_port(cli_matches);
+
+    // A virtual port on its own is enough to receive events through
+    if midi_devices.is_empty() && virtual_port.is_none() {
+        if config.is_none() {
+            eprintln!("Specify a midi device with --midi-device (part of it is enough)");
+        }
 
-    if midi_device_name.is_none() {
-        eprintln!("Error: No matching MIDI device found.");
+        eprintln!("Error: No MIDI device specified.");
         eprintln!("\nAvailable devices:\n");
 
         let ports = midi_adapter.list_ports();
@@ -42,47 +61,56 @@ pub (crate) fn get_midi_setup(
         return None;
     }
 
-    let midi_device_name = midi_device_name.unwrap();
-
-    Some((midi_adapter, midi_device_name))
+    Some(MidiSetup { adapter: midi_adapter, devices: midi_devices, virtual_port })
 }
 
-fn get_midi_port_matcher(
-    cli_matches: Option<&ArgMatches>,
-    config: Option<&Config>
-) -> Option<StringMatcher> {
-    cli_matches.map(|matches| {
-        matches.value_of("midi-device").map_or_else(|| {
-            if let Some(config) = config {
-                config.midi_device_matcher.clone()
-            } else {
-                eprintln!("Specify a midi device with --midi-device (part of it is enough)");
-                None
-            }
-        }, |str_pattern| {
-            Some(StringMatcher::Contains(str_pattern.to_string()))
-        })
-    }).flatten()
+/// Describes a device for printing, by its name if it has one, otherwise by its port matcher
+pub (crate) fn describe_midi_device(device: &MidiDevice) -> String {
+    match &device.name {
+        Some(name) => name.to_string(),
+        None => format!("{:?}", device.port_matcher)
+    }
 }
 
-fn get_selected_midi_device_name(
-    cli_matches: Option<&ArgMatches>,
-    config: Option<&Config>,
-    midi_adapter: &Box<dyn MidiAdapter>
-) -> Option<String> {
+/// Creates a virtual input port named `port_name` through `supervisor`, printing whether that
+/// worked
+pub (crate) fn open_virtual_port(supervisor: &mut MidiSupervisor, port_name: &str) -> bool {
+    if !supervisor.open_virtual_port(port_name) {
+        eprintln!("Error: Unable to create virtual MIDI port '{}'.", port_name);
 
-    let midi_port_pattern = get_midi_port_matcher(cli_matches, config);
+        if cfg!(windows) {
+            eprintln!("Virtual MIDI ports are not supported on Windows.");
+        }
 
-    if midi_port_pattern.is_none() {
-        return None;
+        return false;
     }
 
-    let midi_port_pattern = midi_port_pattern.unwrap();
+    println!("Created virtual MIDI port: '{}'", port_name);
+    true
+}
 
-    let ports = midi_adapter.list_ports();
+fn get_midi_devices(
+    cli_matches: Option<&ArgMatches>,
+    config: Option<&Config>
+) -> Vec<MidiDevice> {
+    let cli_matches = match cli_matches {
+        Some(cli_matches) => cli_matches,
+        None => return vec![]
+    };
+
+    if let Some(str_pattern) = cli_matches.value_of("midi-device") {
+        return vec![MidiDevice {
+            name: None,
+            port_matcher: StringMatcher::Contains(str_pattern.to_string())
+        }];
+    }
 
-    ports.iter().find(|d| {
-        // Don't even ask, I don't know
-        midi_port_pattern.matches(&&***d)
-    }).map(|s| s.to_string())
-}
\ No newline at end of file
+    match config {
+        Some(config) => config.midi_devices.clone(),
+        None => vec![]
+    }
+}
+
+fn get_virtual_port(cli_matches: Option<&ArgMatches>) -> Option<String> {
+    cli_matches?.value_of("virtual-port").map(|port_name| port_name.to_string())
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use mmpd_lib::config::StateFileOptions;
+use mmpd_lib::state::State;
+use mmpd_lib::state::persistence;
+use crate::init::get_project_dir;
+
+/// Name of the state file in the default data directory
+const DEFAULT_FILENAME: &str = "state.yml";
+
+/// File that state is saved to, so it can be restored after restarting
+pub (crate) struct StateFile {
+    path: PathBuf,
+    save_interval: Duration,
+    last_saved: Instant
+}
+
+impl StateFile {
+    /// Sets up a state file according to `options`, in the default data directory unless they
+    /// specify a path. Returns `None` if there is no path and the data directory can't be
+    /// determined.
+    pub (crate) fn new(options: &StateFileOptions) -> Option<StateFile> {
+        let path = match &options.path {
+            Some(path) => path.to_path_buf(),
+
+            None => get_project_dir()
+                .map(|pd| pd.data_dir().join(DEFAULT_FILENAME))
+                .or_else(|| {
+                    eprintln!("Error: Couldn't determine default data directory for state file");
+                    None
+                })?
+        };
+
+        Some(StateFile { path, save_interval: options.save_interval, last_saved: Instant::now() })
+    }
+
+    pub (crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Restores `state` from the file, if there is one yet. If it can't be read, that's printed,
+    /// and `state` is left as it is.
+    pub (crate) fn restore(&self, state: &mut dyn State) {
+        if !self.path.exists() {
+            return;
+        }
+
+        let saved = fs::read_to_string(&self.path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| persistence::from_yaml(&text).map_err(|err| err.description()));
+
+        match saved {
+            Ok(saved) => state.restore(saved),
+
+            Err(e) => {
+                eprintln!("Unable to restore state from {}", self.path.display());
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    /// Saves `state` to the file, printing if that fails.
+    ///
+    /// It's written to a temporary file first, which then replaces the state file, so the state
+    /// file is never left half written.
+    pub (crate) fn save(&mut self, state: &dyn State) {
+        self.last_saved = Instant::now();
+
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+
+        let temp_path = self.path.with_extension("yml.tmp");
+
+        let result = fs::write(&temp_path, persistence::to_yaml(&state.save()))
+            .and_then(|_| fs::rename(&temp_path, &self.path));
+
+        if let Err(e) = result {
+            eprintln!("Unable to save state to {}", self.path.display());
+            eprintln!("{}", e);
+        }
+    }
+
+    /// Saves `state` if it's been at least the save interval since it was last saved
+    pub (crate) fn save_if_due(&mut self, state: &dyn State) {
+        if self.last_saved.elapsed() >= self.save_interval {
+            self.save(state);
+        }
+    }
+}
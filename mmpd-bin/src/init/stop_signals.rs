@@ -0,0 +1,27 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+/// Set once mmpd has been asked to stop by a signal
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+static HANDLER: Once = Once::new();
+
+/// Handles SIGINT and SIGTERM (Ctrl+C and closing the console on Windows) by noting that mmpd
+/// should stop, see `stop_requested`, rather than letting the process be killed on the spot. This
+/// way, the main loop can wrap up and save state first.
+///
+/// The handler is only installed once, so this can be called again when mmpd restarts itself.
+pub (crate) fn handle_stop_signals() {
+    HANDLER.call_once(|| {
+        let result = ctrlc::set_handler(|| STOP_REQUESTED.store(true, Ordering::SeqCst));
+
+        if let Err(e) = result {
+            eprintln!("Unable to handle stop signals, so state won't be saved when stopped: {}", e);
+        }
+    });
+}
+
+/// Whether a SIGINT or SIGTERM came in since `handle_stop_signals`
+pub (crate) fn stop_requested() -> bool {
+    STOP_REQUESTED.load(Ordering::SeqCst)
+}
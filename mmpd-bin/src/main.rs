@@ -10,6 +10,10 @@ use tasks::*;
 fn main() {
     const CMD_MONITOR: &str = "monitor";
     const CMD_LIST_MIDI_DEVICES: &str = "list-midi-devices";
+    const CMD_CHECK: &str = "check";
+    const CMD_CONVERT: &str = "convert";
+    const CMD_FMT: &str = "fmt";
+    const CMD_MIGRATE: &str = "migrate";
 
     let cli_yaml = load_yaml!("cli.yml");
     let cli_matches = App::from_yaml(cli_yaml).get_matches();
@@ -21,6 +25,10 @@ fn main() {
             match subcommand {
                 CMD_MONITOR => task_monitor(arg_matches),
                 CMD_LIST_MIDI_DEVICES => task_list_midi_devices(arg_matches),
+                CMD_CHECK => std::process::exit(task_check(arg_matches)),
+                CMD_CONVERT => std::process::exit(task_convert(arg_matches)),
+                CMD_FMT => std::process::exit(task_fmt(arg_matches)),
+                CMD_MIGRATE => std::process::exit(task_migrate(arg_matches)),
 
                 _ => {
                     // Will never execute, as only subcommands listed in cli.yml are included
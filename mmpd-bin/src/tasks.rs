@@ -1,7 +1,15 @@
 mod task_main;
 mod task_list_midi_devices;
 mod task_monitor;
+mod task_check;
+mod task_convert;
+mod task_fmt;
+mod task_migrate;
 
 pub(crate) use task_main::task_main;
 pub(crate) use task_list_midi_devices::task_list_midi_devices;
-pub(crate) use task_monitor::task_monitor;
\ No newline at end of file
+pub(crate) use task_monitor::task_monitor;
+pub(crate) use task_check::task_check;
+pub(crate) use task_convert::task_convert;
+pub(crate) use task_fmt::task_fmt;
+pub(crate) use task_migrate::task_migrate;
\ No newline at end of file
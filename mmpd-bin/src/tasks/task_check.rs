@@ -0,0 +1,71 @@
+use clap::ArgMatches;
+use crate::init::get_config_file;
+use mmpd_lib::config::check::{check_config, Problem, Severity};
+
+/// Exit code when the config has errors, or no config file was found
+const EXIT_ERRORS: i32 = 1;
+
+/// Exit code when the config loads, but there are warnings about it
+const EXIT_WARNINGS: i32 = 2;
+
+/// Checks the config file for errors and likely mistakes, without opening any MIDI devices or
+/// running any macros, and prints what it finds. Returns the exit code for the process, which is
+/// 0 only if nothing was found, so it can be used in scripts and CI.
+pub (crate) fn task_check(cli_matches: Option<&ArgMatches>) -> i32 {
+    let config_file = match get_config_file(cli_matches) {
+        Some(config_file) => config_file,
+        None => return EXIT_ERRORS
+    };
+
+    let config_filename = config_file.to_str().unwrap_or("[none]");
+    println!("Checking config file: {}", config_filename);
+
+    let problems = check_config(&config_file);
+
+    for problem in problems.iter() {
+        print_problem(problem, config_filename);
+    }
+
+    let count = |severity: Severity| {
+        problems.iter().filter(|problem| problem.severity == severity).count()
+    };
+
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+
+    if problems.is_empty() {
+        println!("No problems found.");
+    } else {
+        println!("\n{} error(s), {} warning(s).", errors, warnings);
+    }
+
+    if errors > 0 {
+        EXIT_ERRORS
+    } else if warnings > 0 {
+        EXIT_WARNINGS
+    } else {
+        0
+    }
+}
+
+fn print_problem(problem: &Problem, config_filename: &str) {
+    let severity = match problem.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning"
+    };
+
+    let file = problem.file.as_ref().map_or_else(
+        || config_filename.to_string(),
+        |file| file.display().to_string()
+    );
+
+    let file = match &problem.loc {
+        Some(loc) => format!("{}:{}:{}", file, loc.line, loc.col),
+        None => file
+    };
+
+    if problem.location.is_empty() {
+        eprintln!("{}: {}: {}", severity, file, problem.description);
+    } else {
+        eprintln!("{}: {}: {}: {}", severity, file, problem.location, problem.description);
+    }
+}
@@ -0,0 +1,47 @@
+use std::fs;
+use clap::ArgMatches;
+use crate::init::get_config_file;
+use mmpd_lib::config::export::convert_config;
+
+/// Converts the config file to the format given with `--to`, and outputs it, or writes it to the
+/// file given with `--output`. Returns the exit code for the process: 0 if it worked, 1 otherwise.
+pub (crate) fn task_convert(cli_matches: Option<&ArgMatches>) -> i32 {
+    const TO_PARAM: &str = "to";
+    const OUTPUT_PARAM: &str = "output";
+
+    let config_file = match get_config_file(cli_matches) {
+        Some(config_file) => config_file,
+        None => return 1
+    };
+
+    // Required, and limited to the known formats, by cli.yml
+    let format = cli_matches.and_then(|cm| cm.value_of(TO_PARAM)).unwrap_or("yaml");
+
+    let converted = match convert_config(&config_file, format) {
+        Ok(converted) => converted,
+
+        Err(e) => {
+            eprintln!(
+                "Error: unable to convert config file {}",
+                config_file.to_str().unwrap_or("[none]")
+            );
+
+            eprintln!("{}", e.description());
+            return 1;
+        }
+    };
+
+    match cli_matches.and_then(|cm| cm.value_of(OUTPUT_PARAM)) {
+        Some(output_file) => {
+            if let Err(e) = fs::write(output_file, converted) {
+                eprintln!("Error: unable to write {}", output_file);
+                eprintln!("{}", e);
+                return 1;
+            }
+        }
+
+        None => print!("{}", converted)
+    }
+
+    0
+}
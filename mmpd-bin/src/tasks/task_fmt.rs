@@ -0,0 +1,45 @@
+use std::fs;
+use clap::ArgMatches;
+use crate::init::get_config_file;
+use mmpd_lib::config::export::format_config;
+use mmpd_lib::config::versions::Form;
+
+/// Rewrites the config file with its shorthand forms written consistently, either out in full, or
+/// with `--minimal`, as short as possible. Outputs the result, or with `--write`, overwrites the
+/// config file with it. Returns the exit code for the process: 0 if it worked, 1 otherwise.
+pub (crate) fn task_fmt(cli_matches: Option<&ArgMatches>) -> i32 {
+    const MINIMAL_PARAM: &str = "minimal";
+    const WRITE_PARAM: &str = "write";
+
+    let config_file = match get_config_file(cli_matches) {
+        Some(config_file) => config_file,
+        None => return 1
+    };
+
+    let config_filename = config_file.to_str().unwrap_or("[none]");
+    let flag = |param: &str| cli_matches.is_some_and(|cm| cm.is_present(param));
+
+    let form = if flag(MINIMAL_PARAM) { Form::Minimal } else { Form::Explicit };
+
+    let formatted = match format_config(&config_file, form) {
+        Ok(formatted) => formatted,
+
+        Err(e) => {
+            eprintln!("Error: unable to format config file {}", config_filename);
+            eprintln!("{}", e.description());
+            return 1;
+        }
+    };
+
+    if flag(WRITE_PARAM) {
+        if let Err(e) = fs::write(&config_file, formatted) {
+            eprintln!("Error: unable to write {}", config_filename);
+            eprintln!("{}", e);
+            return 1;
+        }
+    } else {
+        print!("{}", formatted);
+    }
+
+    0
+}
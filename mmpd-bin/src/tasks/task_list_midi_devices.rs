@@ -16,4 +16,12 @@ pub (crate) fn task_list_midi_devices(_cli_matches: Option<&ArgMatches>) {
     for port_name in port_names {
         println!("{}", port_name);
     }
+
+    if let Some(midi_output_adapter) = midi::get_output_adapter() {
+        println!("\nAvailable MIDI output devices:\n");
+
+        for port_name in midi_output_adapter.list_ports() {
+            println!("{}", port_name);
+        }
+    }
 }
@@ -1,15 +1,28 @@
 use clap::ArgMatches;
 use mmpd_lib::{focus, state};
+use mmpd_lib::macros::Macro;
 use mmpd_lib::macros::actions::{ActionRunner, ControlAction};
+use mmpd_lib::macros::executor::Executor;
 use mmpd_lib::macros::event_matching::{get_event_bus, Event};
 use crate::init::{get_config_file, read_config};
-use crate::init::midi_setup::get_midi_setup;
-use std::sync::mpsc::Receiver;
+use crate::init::midi_setup::{get_midi_setup, describe_midi_device, open_virtual_port};
+use crate::init::state_file::StateFile;
+use crate::init::stop_signals::{handle_stop_signals, stop_requested};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use mmpd_lib::config::watcher::ConfigWatcher;
 use mmpd_lib::config::Config;
 use mmpd_lib::state::State;
-use std::path::PathBuf;
+use mmpd_lib::midi::supervisor::{MidiSupervisor, POLL_INTERVAL};
+use std::path::{Path, PathBuf};
+
+/// How often to check the config file for changes, when watching it
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
 pub fn task_main(cli_matches: Option<&ArgMatches>) -> bool {
+    const WATCH_FLAG: &str = "watch";
+
     let config_file = get_config_file(cli_matches);
     if config_file.is_none() {
         return false;
@@ -30,7 +43,7 @@ pub fn task_main(cli_matches: Option<&ArgMatches>) -> bool {
         return false;
     }
 
-    let (mut midi_adapter, midi_device_name) = midi_setup.unwrap();
+    let midi_setup = midi_setup.unwrap();
 
     let focus_adapter = focus::get_adapter();
 
@@ -48,19 +61,28 @@ pub fn task_main(cli_matches: Option<&ArgMatches>) -> bool {
         return false;
     }
 
-    let action_runner = action_runner.unwrap();
+    // Actions that may take a while, like running a program, are run on worker threads, each
+    // with their own action runner, so they don't hold up handling events.
+    let executor = Executor::new(action_runner.unwrap(), ActionRunner::new);
 
     let (tx, rx) = get_event_bus();
-    let handle = midi_adapter.start_listening(&midi_device_name, tx);
-
-    if handle.is_none() {
-        eprintln!("Error: unable to start listening for MIDI events.");
-    }
 
     let config_filename = config_file.to_str().unwrap_or("[none]");
     println!("Starting mmpd.");
     println!("Using config file: {}", config_filename);
-    println!("Listening for MIDI events on '{}'", midi_device_name);
+
+    let watch = cli_matches.is_some_and(|matches| matches.is_present(WATCH_FLAG));
+
+    if watch || config.watch {
+        println!("Watching config file for changes.");
+    }
+
+    if !midi_setup.devices.is_empty() {
+        println!(
+            "Listening for MIDI devices: {}",
+            midi_setup.devices.iter().map(describe_midi_device).collect::<Vec<String>>().join(", ")
+        );
+    }
 
     print_macro_info(&config);
 
@@ -68,15 +90,52 @@ pub fn task_main(cli_matches: Option<&ArgMatches>) -> bool {
         return false;
     }
 
+    let mut state = state::new(focus_adapter);
+
+    // Picking up where we left off, if there's state from a previous run
+    let state_file = config.state_file.as_ref().and_then(StateFile::new);
+
+    if let Some(state_file) = &state_file {
+        println!("Keeping state in: {}", state_file.path().display());
+        state_file.restore(state.as_mut());
+    }
+
+    let mut supervisor = MidiSupervisor::new(
+        midi_setup.adapter,
+        midi_setup.devices,
+        config.midi_device_options.clone(),
+        tx
+    );
+
+    if let Some(virtual_port) = &midi_setup.virtual_port {
+        if !open_virtual_port(&mut supervisor, virtual_port) {
+            return false;
+        }
+    }
+
+    // Devices are connected as they become available, and reconnected if they're unplugged and
+    // plugged back in.
+    let supervisor = supervisor.start(POLL_INTERVAL);
+
+    // Stopping mmpd with Ctrl+C or as a service ends the main loop like an exit action, so state
+    // is saved on the way out.
+    handle_stop_signals();
+
     // Now we've verified all the required data and conditions, we can kick off the main loop that
     // does the work.
-    return main_loop(
+    let should_restart = main_loop(
         config_file.to_path_buf(),
         config,
-        state::new(focus_adapter),
+        state,
+        state_file,
         rx,
-        action_runner
+        executor,
+        watch
     );
+
+    supervisor.stop();
+
+    should_restart
 }
 
 /// Prints info and help on macros found in config
@@ -98,58 +157,178 @@ fn print_macro_info(config: &Config) {
     }
 }
 
+/// Starts running any macros matching `event`
+fn handle_event(
+    event: &Event,
+    config: &Config,
+    state: &mut Box<dyn State>,
+    executor: &mut Executor
+) {
+    match event {
+        Event::DeviceConnected { device } => {
+            println!("MIDI device connected: '{}'", device);
+        }
+
+        Event::DeviceDisconnected { device } => {
+            println!("MIDI device disconnected: '{}'", device);
+        }
+
+        _ => {}
+    }
+
+    state.process_event(event);
+
+    for (index, macro_item) in config.macros.iter().enumerate() {
+        if macro_item.evaluate(event, state).is_some() {
+            // A macro mapping a value may skip this event, for example because it ran too
+            // recently, but it still takes the event from any macros after it.
+            if macro_item.apply_map(event, state.as_mut()) {
+                start_macro(index, macro_item, event, executor);
+            }
+
+            break;
+        }
+    }
+}
+
+/// Starts running the first macro mapping values into `variable` that matches `event`, an event
+/// that was held back because the macro ran too recently when it came in.
+fn handle_throttled_event(
+    variable: &str,
+    event: &Event,
+    config: &Config,
+    state: &mut Box<dyn State>,
+    executor: &mut Executor
+) {
+    let macros = config.macros.iter().enumerate();
+
+    for (index, macro_item) in macros.filter(|(_, macro_item)| macro_item.maps_into(variable)) {
+        if macro_item.evaluate(event, state).is_some() {
+            if macro_item.apply_map(event, state.as_mut()) {
+                start_macro(index, macro_item, event, executor);
+            }
+
+            break;
+        }
+    }
+}
+
+/// Starts running the actions of `macro_item`, the macro at `index` in config, for `event`
+fn start_macro(index: usize, macro_item: &Macro, event: &Event, executor: &mut Executor) {
+    let started = executor.start(index, macro_item, event);
+
+    match (macro_item.name(), started) {
+        (Some(macro_name), true) => println!("Executing macro named: '{}'", macro_name),
+        (None, true) => println!("Executing macro. (No name given)"),
+        (Some(macro_name), false) => println!("Skipping macro still running: '{}'", macro_name),
+        (None, false) => println!("Skipping macro still running. (No name given)")
+    }
+}
+
+/// The config file and the files it includes, to reload the config when any of them changes
+fn watched_files(config_file: &Path, config: &Config) -> Vec<PathBuf> {
+    let mut files = vec![config_file.to_path_buf()];
+    files.extend(config.included_files.iter().cloned());
+    files
+}
+
 fn main_loop(
     config_file: PathBuf,
     mut config: Config,
     mut state: Box<dyn State>,
+    mut state_file: Option<StateFile>,
     rx: Receiver<Event>,
-    action_runner: ActionRunner
+    mut executor: Executor,
+    watch: bool
 )-> bool {
 
     let mut should_stop_rx_loop = false;
     let mut should_restart = false;
     let mut should_reload_config = false;
 
-    for event in rx {
-        state.process_event(&event);
+    let mut config_watcher = ConfigWatcher::new(watched_files(&config_file, &config));
+    state.set_press_timing(config.press_timing.clone());
+    executor.set_max_workers(config.max_workers);
+
+    loop {
+        // Rather than blocking until the next event, wake up regularly to check whether the
+        // config file has changed, or sooner if a held button is due a long press event, a
+        // held back mapped value is due, or running macros can carry on with their actions.
+        let deadline = state
+            .next_press_deadline()
+            .into_iter()
+            .chain(state.next_throttle_deadline())
+            .chain(executor.next_deadline())
+            .min();
+
+        let timeout = deadline.map_or(WATCH_INTERVAL, |deadline| {
+            deadline.saturating_duration_since(Instant::now()).min(WATCH_INTERVAL)
+        });
+
+        let received = match rx.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => break
+        };
+
+        // Long presses that are due happened before the event that was just received. Every event
+        // handled may cause more press events, such as a tap after a note off, which are handled
+        // right after it.
+        let mut events: VecDeque<Event> = state.take_press_events().into();
+        events.extend(received);
+
+        // Running macros carry on first, having been triggered before any of these events
+        let mut control_actions = executor.advance(state.as_mut());
+
+        // Mapped values that were held back came in before the event that was just received too
+        for (variable, event) in state.take_throttled_events() {
+            handle_throttled_event(&variable, &event, &config, &mut state, &mut executor);
+        }
+
+        // Actions are run as far as they can after every event, so, for example, variables set by
+        // the macro an event triggered are set by the time the next event is matched.
+        control_actions.extend(executor.advance(state.as_mut()));
+
+        while let Some(event) = events.pop_front() {
+            handle_event(&event, &config, &mut state, &mut executor);
+            control_actions.extend(executor.advance(state.as_mut()));
+            events.extend(state.take_press_events());
+        }
 
-        for macro_item in config.macros.iter() {
-            if let Some(actions) = macro_item.evaluate(&event, &state) {
-                if let Some(macro_name) = macro_item.name() {
-                    println!("Executing macro named: '{}'", macro_name);
-                } else {
-                    println!("Executing macro. (No name given)");
+        for control_action in control_actions {
+            match control_action {
+                ControlAction::ReloadMacros => {
+                    println!("Reloading macros from file");
+                    should_reload_config = true;
                 }
 
-                for action in actions {
-                    let control_action = action_runner.run(action);
-
-                    if let Some(control_action) = control_action {
-                        match control_action {
-                            ControlAction::ReloadMacros => {
-                                println!("Reloading macros from file");
-                                should_reload_config = true;
-                            }
-
-                            ControlAction::Restart => {
-                                println!("Restarting.");
-                                should_stop_rx_loop = true;
-                                should_restart = true;
-                            }
-
-                            ControlAction::Exit => {
-                                println!("Exiting.");
-                                should_stop_rx_loop = true;
-                                should_restart = false;
-                            }
-                        }
-                    }
+                ControlAction::Restart => {
+                    println!("Restarting.");
+                    should_stop_rx_loop = true;
+                    should_restart = true;
                 }
 
-                break;
+                ControlAction::Exit => {
+                    println!("Exiting.");
+                    should_stop_rx_loop = true;
+                    should_restart = false;
+                }
             }
         }
 
+        if let Some(state_file) = state_file.as_mut() {
+            state_file.save_if_due(state.as_ref());
+        }
+
+        // Checked even if not watching, so the next check after enabling `watch` in the config
+        // file only picks up changes made after that.
+        let config_changed = config_watcher.has_changed();
+
+        if config_changed && (watch || config.watch) {
+            println!("Config file changed, reloading macros");
+            should_reload_config = true;
+        }
+
         if should_reload_config {
             should_reload_config = false;
             let new_config = read_config(config_file.to_path_buf());
@@ -157,6 +336,10 @@ fn main_loop(
             match new_config {
                 Some(new_config) => {
                     config = new_config;
+                    config_watcher = ConfigWatcher::new(watched_files(&config_file, &config));
+                    executor.forget_macros();
+                    state.set_press_timing(config.press_timing.clone());
+                    executor.set_max_workers(config.max_workers);
                     println!("Reloaded config.");
 
                     print_macro_info(&config);
@@ -175,10 +358,21 @@ fn main_loop(
             }
         }
 
+        // Noticed within WATCH_INTERVAL, since that's the longest the loop waits for an event
+        if stop_requested() {
+            println!("Stopping.");
+            should_stop_rx_loop = true;
+            should_restart = false;
+        }
+
         if should_stop_rx_loop {
             break;
         }
     }
 
+    if let Some(state_file) = state_file.as_mut() {
+        state_file.save(state.as_ref());
+    }
+
     return should_restart;
 }
@@ -0,0 +1,40 @@
+use std::fs;
+use clap::ArgMatches;
+use crate::init::get_config_file;
+use mmpd_lib::config::export::migrate_config;
+
+/// Rewrites the version 1 config file into version 2. Outputs the result, or with `--write`,
+/// overwrites the config file with it. Returns the exit code for the process: 0 if it worked, 1
+/// otherwise.
+pub (crate) fn task_migrate(cli_matches: Option<&ArgMatches>) -> i32 {
+    const WRITE_PARAM: &str = "write";
+
+    let config_file = match get_config_file(cli_matches) {
+        Some(config_file) => config_file,
+        None => return 1
+    };
+
+    let config_filename = config_file.to_str().unwrap_or("[none]");
+
+    let migrated = match migrate_config(&config_file) {
+        Ok(migrated) => migrated,
+
+        Err(e) => {
+            eprintln!("Error: unable to migrate config file {}", config_filename);
+            eprintln!("{}", e.description());
+            return 1;
+        }
+    };
+
+    if cli_matches.is_some_and(|cm| cm.is_present(WRITE_PARAM)) {
+        if let Err(e) = fs::write(&config_file, migrated) {
+            eprintln!("Error: unable to write {}", config_filename);
+            eprintln!("{}", e);
+            return 1;
+        }
+    } else {
+        print!("{}", migrated);
+    }
+
+    0
+}
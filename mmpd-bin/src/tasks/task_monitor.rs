@@ -1,8 +1,9 @@
 use clap::ArgMatches;
-use crate::init::midi_setup::get_midi_setup;
+use crate::init::midi_setup::{get_midi_setup, describe_midi_device, open_virtual_port};
 use crate::init::get_config;
 use mmpd_lib::macros::event_matching::{get_event_bus, Event};
 use mmpd_lib::midi::MidiMessage;
+use mmpd_lib::midi::supervisor::{MidiSupervisor, POLL_INTERVAL};
 
 pub fn task_monitor(cli_matches: Option<&ArgMatches>) {
     let (config, config_filename) = get_config(cli_matches).map_or(
@@ -16,7 +17,7 @@ pub fn task_monitor(cli_matches: Option<&ArgMatches>) {
         return;
     }
 
-    let (mut midi_adapter, midi_device_name) = midi_setup.unwrap();
+    let midi_setup = midi_setup.unwrap();
     println!("Starting mmpd.");
 
     if let Some(config_filename) = config_filename {
@@ -26,22 +27,49 @@ pub fn task_monitor(cli_matches: Option<&ArgMatches>) {
 
     let (tx, rx) = get_event_bus();
 
-    let handle = midi_adapter.start_listening(&midi_device_name, tx);
+    let device_options = config
+        .as_ref()
+        .map(|config| config.midi_device_options.clone())
+        .unwrap_or_default();
 
-    if let None = handle {
-        eprintln!("Unable to start listening for MIDI events.");
-        return;
+    // Only mention which device a message came from if there's more than one
+    let show_device = midi_setup.devices.len() + midi_setup.virtual_port.iter().len() > 1;
+
+    let mut sources: Vec<String> = midi_setup.devices.iter().map(describe_midi_device).collect();
+    sources.extend(midi_setup.virtual_port.iter().cloned());
+
+    println!("Monitoring MIDI events on: {}\n", sources.join(", "));
+
+    let mut supervisor = MidiSupervisor::new(
+        midi_setup.adapter,
+        midi_setup.devices,
+        device_options,
+        tx
+    );
+
+    if let Some(virtual_port) = &midi_setup.virtual_port {
+        if !open_virtual_port(&mut supervisor, virtual_port) {
+            return;
+        }
     }
 
-    println!("Monitoring MIDI events on: \n{}\n", midi_device_name);
+    let _supervisor = supervisor.start(POLL_INTERVAL);
 
     for msg in rx {
-        if let Event::Midi(msg) = msg {
-            match msg {
-                MidiMessage::Other => {},
-                _ => println!("{:?}", msg)
-            }
+        match msg {
+            Event::Midi(midi_event) => match midi_event.message {
+                // Clock and active sensing messages are sent many times per second by some
+                // devices, and would drown out everything else.
+                MidiMessage::Other
+                | MidiMessage::TimingClock
+                | MidiMessage::ActiveSensing => {},
+                _ if show_device => println!("[{}] {:?}", midi_event.device, midi_event.message),
+                _ => println!("{:?}", midi_event.message)
+            },
+
+            Event::DeviceConnected { device } => println!("Device connected: '{}'", device),
+            Event::DeviceDisconnected { device } => println!("Device disconnected: '{}'", device),
+            Event::Other => {}
         }
     }
 }
-
@@ -0,0 +1,71 @@
+//! Time source for anything that depends on when events happen, such as gesture matching.
+//!
+//! Code that needs the current time takes a `Clock` rather than calling `Instant::now` directly,
+//! so tests can control the passing of time with a `ManualClock`.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Provides the current time
+pub trait Clock: Send {
+    fn now(&self) -> Instant;
+}
+
+/// Clock following the system's monotonic clock
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves forward when told to.
+///
+/// Clones share the same time, so a test can keep one to advance while another is in use.
+#[derive(Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>
+}
+
+impl ManualClock {
+    /// Creates a clock stopped at the current time
+    pub fn new() -> ManualClock {
+        ManualClock { now: Arc::new(Mutex::new(Instant::now())) }
+    }
+
+    /// Moves the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::clock::{Clock, ManualClock};
+
+    #[test]
+    fn manual_clock_only_moves_when_advanced() {
+        let clock = ManualClock::new();
+        let shared = clock.clone();
+        let start = clock.now();
+
+        assert_eq!(clock.now(), start);
+
+        shared.advance(Duration::from_millis(250));
+        assert_eq!(clock.now(), start + Duration::from_millis(250));
+    }
+}
@@ -2,20 +2,108 @@
 //! other types) into a fully formed Config object, ready to be used by the main application.
 
 use crate::macros::Macro;
-use crate::match_checker::StringMatcher;
+use crate::match_checker::{StringMatcher, MatchChecker};
+use crate::midi::assembler::AssemblerOptions;
+use crate::state::PressTiming;
+use std::path::PathBuf;
+use std::time::Duration;
 
 pub mod raw_config;
 pub mod input_formats;
+pub mod output_formats;
 pub mod versions;
+pub mod watcher;
+pub mod loader;
+pub mod check;
+pub mod export;
 
 /// Configuration owner used by the main program. An instance of this holds all data that gets
 /// parsed from a configuration file into relevant data structures like `Macro`.
 pub struct Config {
-    // String matcher for which midi device to use
-    pub midi_device_matcher: Option<StringMatcher>,
+    /// MIDI devices to listen to
+    pub midi_devices: Vec<MidiDevice>,
 
     /// List of macros specified in config file
-    pub macros: Vec<Macro>
+    pub macros: Vec<Macro>,
+
+    /// Options for processing incoming messages, per MIDI device
+    pub midi_device_options: Vec<MidiDeviceOptions>,
+
+    /// Thresholds for telling taps, double taps, and long presses apart
+    pub press_timing: PressTiming,
+
+    /// Whether to reload the config file automatically when it changes
+    pub watch: bool,
+
+    /// Where and how often to save state, so it can be restored after a restart. If `None`, state
+    /// isn't saved.
+    pub state_file: Option<StateFileOptions>,
+
+    /// Most worker threads performing actions that take a while at once, see `Executor`
+    pub max_workers: usize,
+
+    /// Further config files that were included by the config file, see `loader::load_config`
+    pub included_files: Vec<PathBuf>
+}
+
+impl Config {
+    /// Determines the assembler options for a MIDI device by its name, combining the options of
+    /// every entry in `midi_device_options` that matches it.
+    pub fn assembler_options_for(&self, device_name: &str) -> AssemblerOptions {
+        MidiDeviceOptions::combined_assembler_options(&self.midi_device_options, device_name)
+    }
+}
+
+/// A MIDI input device to listen to, as specified in the config file
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiDevice {
+    /// Name that events from this device are tagged with. If `None`, the name of the MIDI port
+    /// is used instead.
+    pub name: Option<String>,
+
+    /// String matcher for which MIDI port belongs to this device
+    pub port_matcher: StringMatcher
+}
+
+/// Options for keeping state in a file, so it survives restarts
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateFileOptions {
+    /// Where to keep the state file. If `None`, a default location is used.
+    pub path: Option<PathBuf>,
+
+    /// How often to save state while running, besides when stopping
+    pub save_interval: Duration
+}
+
+/// Options for processing incoming messages of MIDI devices whose name matches `device_matcher`
+#[derive(Clone)]
+pub struct MidiDeviceOptions {
+    /// String matcher for which MIDI devices these options apply to. If `None`, they apply to
+    /// all devices.
+    pub device_matcher: Option<StringMatcher>,
+
+    /// Which multi-message values to assemble for these devices
+    pub assembler_options: AssemblerOptions
+}
+
+impl MidiDeviceOptions {
+    /// Determines the assembler options for a MIDI device by its name, combining the options of
+    /// every entry in `options` that matches it.
+    pub fn combined_assembler_options(
+        options: &[MidiDeviceOptions],
+        device_name: &str
+    ) -> AssemblerOptions {
+        options
+            .iter()
+            .filter(|options| {
+                options.device_matcher
+                    .as_ref()
+                    .is_none_or(|matcher| matcher.matches(&device_name))
+            })
+            .fold(AssemblerOptions::default(), |acc, options| {
+                acc.merge(&options.assembler_options)
+            })
+    }
 }
 
 /// Represents an error that occurred while trying to load or parse configuration
@@ -40,6 +128,33 @@ pub enum ConfigError {
     InvalidConfig(
         /// Description of the error
         String
+    ),
+
+    /// A config file couldn't be read, or it's in a format there's no parser for.
+    ReadError(
+        /// Description of the error
+        String
+    ),
+
+    /// An error in a file included by the config file, rather than in the config file itself.
+    InFile(
+        /// The included file the error is in
+        PathBuf,
+
+        /// The error itself
+        Box<ConfigError>
+    ),
+
+    /// An error in a specific part of a config file, such as a single macro or action.
+    InPart(
+        /// Breadcrumb path to the part, like `scopes[3].macros[2].actions[0].data`
+        String,
+
+        /// Where the part starts in the file, if known
+        Option<Loc>,
+
+        /// The error itself
+        Box<ConfigError>
     )
 }
 
@@ -53,11 +168,44 @@ impl ConfigError {
 
             ConfigError::UnsupportedVersion(desc) => desc.to_string(),
             ConfigError::InvalidConfig(desc) => desc.to_string(),
+            ConfigError::ReadError(desc) => desc.to_string(),
+
+            ConfigError::InFile(file, err) => {
+                format!("In included file {}: {}", file.display(), err.description())
+            }
+
+            ConfigError::InPart(path, Some(loc), err) => format!(
+                "{} (line {}, column {}): {}",
+                path, loc.line, loc.col, err.description()
+            ),
+
+            ConfigError::InPart(path, None, err) => format!("{}: {}", path, err.description())
         }
     }
+
+    /// Places this error within the part of the config at `segment`, either a field name like
+    /// `data`, or a list item like `actions[0]`, which starts at `loc` in the file. As an error
+    /// passes up through nested parts, this builds up the breadcrumb path to where it happened,
+    /// keeping the location of the innermost part that has one.
+    pub (crate) fn within(self, segment: &str, loc: Option<Loc>) -> ConfigError {
+        match self {
+            ConfigError::InPart(path, inner_loc, err) => {
+                ConfigError::InPart(format!("{}.{}", segment, path), inner_loc.or(loc), err)
+            }
+
+            err => ConfigError::InPart(segment.to_string(), loc, Box::new(err))
+        }
+    }
+
+    /// Places this error within item `index` of the list in field `field`, which starts at `loc`
+    /// in the file. See `within`.
+    pub (crate) fn within_item(self, field: &str, index: usize, loc: Option<Loc>) -> ConfigError {
+        self.within(&format!("{}[{}]", field, index), loc)
+    }
 }
 
-/// Location within a file expressed in line and column
+/// Location within a file expressed in line and column, both starting at 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Loc {
     pub line: usize,
     pub col: usize
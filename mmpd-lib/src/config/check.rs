@@ -0,0 +1,575 @@
+//! Checks a config file for errors and likely mistakes, without connecting to any MIDI devices or
+//! running any macros.
+
+use std::path::{Path, PathBuf};
+
+use crate::config::{ConfigError, Loc};
+use crate::config::loader::{read_config_files, ConfigFiles};
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+use crate::config::versions::get_processor;
+use crate::macros::Macro;
+use crate::macros::event_matching::MatcherType;
+use crate::macros::event_matching::gesture::GestureMatcher;
+use crate::macros::preconditions::{Precondition, PreconditionType};
+use crate::match_checker::{Covers, NumMatch};
+
+const VERSION_FIELD: &str = "version";
+const SCOPES_FIELD: &str = "scopes";
+const MACROS_FIELD: &str = "macros";
+const GLOBAL_MACROS_FIELD: &str = "global_macros";
+
+/// How bad a `Problem` is
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Severity {
+    /// The config can't be loaded like this
+    Error,
+
+    /// The config loads, but probably doesn't do what it was meant to
+    Warning
+}
+
+/// Something wrong with a config file, found by `check_config`
+#[derive(PartialEq, Debug)]
+pub struct Problem {
+    pub severity: Severity,
+
+    /// The included file the problem is in, or `None` if it's in the main config file
+    pub file: Option<PathBuf>,
+
+    /// Path to the part of the file the problem is in, like `scopes[0].macros[1]`, or empty if it
+    /// isn't about any part in particular
+    pub location: String,
+
+    /// Where in the file the part the problem is in starts, if known
+    pub loc: Option<Loc>,
+
+    pub description: String
+}
+
+/// Checks the config file `file`, and the files it includes, for any problems. Where loading a
+/// config stops at the first error, this carries on past errors in individual scopes and macros,
+/// to report all of them at once. It also warns about:
+///
+/// - Fields that aren't part of the config format, such as misspelled ones, which are ignored
+/// - Scopes without any valid window matching, whose macros are ignored
+/// - Macros that can never run, because an earlier macro matches all the same events and always
+///   takes them first
+/// - Matchers for MIDI data that go beyond the values that data can have, like a key above 127
+///
+/// Problems are listed in the order they're found; an empty list means the config is fine.
+pub fn check_config(file: &Path) -> Vec<Problem> {
+    let files = match read_config_files(file) {
+        Ok(files) => files,
+        Err(err) => return vec![Problem::error(None, String::new(), err)]
+    };
+
+    let mut checker = Checker { problems: vec![], macros: vec![] };
+
+    // If the main file's settings are broken, the included files can't be processed either
+    if checker.check_file(&files, &files.main, None) {
+        for (included_file, hash) in files.included.iter() {
+            checker.check_file(&files, hash, Some(included_file));
+        }
+    }
+
+    checker.check_shadowed_macros();
+    checker.problems
+}
+
+impl Problem {
+    /// A problem for `err`, which happened in `file`; unless `err` says which included file it
+    /// happened in itself. `location` is the part of the file that was processed on its own to
+    /// get `err`, and is made more precise by the part of it `err` says it happened in.
+    fn error(file: Option<&Path>, location: String, err: ConfigError) -> Problem {
+        let (file, err) = match err {
+            ConfigError::InFile(file, err) => (Some(file), *err),
+            err => (file.map(Path::to_path_buf), err)
+        };
+
+        let (location, loc, err) = match err {
+            ConfigError::InPart(path, loc, err) => {
+                // The part was processed as the only item of its list, so the start of the path
+                // points at the first item rather than at where the part really is.
+                let depth = location.split('.').filter(|segment| !segment.is_empty()).count();
+
+                let location = match (location.is_empty(), path.splitn(depth + 1, '.').nth(depth)) {
+                    (true, _) => path,
+                    (false, Some(rest)) => format!("{}.{}", location, rest),
+                    (false, None) => location
+                };
+
+                (location, loc, *err)
+            }
+
+            err => (location, None, err)
+        };
+
+        Problem {
+            severity: Severity::Error,
+            file,
+            location,
+            loc,
+            description: err.description()
+        }
+    }
+
+    fn warning(
+        file: Option<&Path>,
+        location: String,
+        loc: Option<Loc>,
+        description: String
+    ) -> Problem {
+        Problem {
+            severity: Severity::Warning,
+            file: file.map(Path::to_path_buf),
+            location,
+            loc,
+            description
+        }
+    }
+}
+
+/// A macro that was built successfully, along with where it came from
+struct CheckedMacro<'a> {
+    file: Option<&'a Path>,
+    location: String,
+    loc: Option<Loc>,
+    macro_item: Macro
+}
+
+struct Checker<'a> {
+    problems: Vec<Problem>,
+
+    /// The macros built so far, in the same order as they end up in `Config`
+    macros: Vec<CheckedMacro<'a>>
+}
+
+impl<'a> Checker<'a> {
+    /// Checks `hash`, the top level of `file`, or of the main config file if `file` is `None`.
+    /// Each scope and macro is processed separately, so an error in one doesn't hide errors in
+    /// the others. Returns false if the settings outside of scopes and macros are broken, in
+    /// which case nothing else is checked.
+    fn check_file(&mut self, files: &ConfigFiles, hash: &RCHash, file: Option<&'a Path>) -> bool {
+        let mut base = files.complete(hash);
+        base.remove(&k(SCOPES_FIELD));
+        base.remove(&k(GLOBAL_MACROS_FIELD));
+
+        if let Err(err) = RawConfig::Hash(base.clone()).process() {
+            self.problems.push(Problem::error(file, String::new(), err));
+            return false;
+        }
+
+        // Processing succeeded, so the version is known to be supported
+        let processor = base.get_integer(VERSION_FIELD).and_then(get_processor);
+
+        for field in processor.map(|p| p.find_unknown_fields(hash)).unwrap_or_default() {
+            self.problems.push(Problem::warning(
+                file,
+                field.path,
+                field.loc,
+                "Unknown field, which is ignored".to_string()
+            ));
+        }
+
+        let raw_scopes = hash.get_array(SCOPES_FIELD).map(|s| s.as_slice()).unwrap_or_default();
+
+        for (i, raw_scope) in raw_scopes.iter().enumerate() {
+            let location = format!("{}[{}]", SCOPES_FIELD, i);
+
+            // Items that aren't hashes are skipped when loading, too
+            let raw_scope = match raw_scope {
+                RawConfig::Hash(raw_scope) => raw_scope,
+                _ => continue
+            };
+
+            let with_macros = |raw_macros: Vec<RawConfig>| {
+                let mut raw_scope = raw_scope.clone();
+                raw_scope.insert(k(MACROS_FIELD), RawConfig::Array(raw_macros));
+                raw_scope
+            };
+
+            if let Err(err) = process_part(&base, SCOPES_FIELD, with_macros(vec![])) {
+                self.problems.push(Problem::error(file, location, err));
+                continue;
+            }
+
+            let raw_macros = raw_scope.get_array(MACROS_FIELD).map(|m| m.as_slice());
+
+            for (j, raw_macro) in raw_macros.unwrap_or_default().iter().enumerate() {
+                if !matches!(raw_macro, RawConfig::Hash(_)) {
+                    continue;
+                }
+
+                let macro_location = format!("{}.{}[{}]", location, MACROS_FIELD, j);
+
+                match process_part(&base, SCOPES_FIELD, with_macros(vec![raw_macro.clone()])) {
+                    Ok(Some(macro_item)) => {
+                        self.add_macro(file, macro_location, raw_macro.loc(), macro_item)
+                    }
+
+                    Err(err) => self.problems.push(Problem::error(file, macro_location, err)),
+
+                    // The scope was skipped, and its macros along with it
+                    Ok(None) => {
+                        let loc = raw_scope.loc();
+
+                        self.problems.push(Problem::warning(file, location, loc, format!(
+                            "Scope has no valid window_class, window_name, executable_path or \
+                            executable_basename matcher, so its {} are ignored",
+                            MACROS_FIELD
+                        )));
+
+                        break;
+                    }
+                }
+            }
+        }
+
+        let raw_macros = hash.get_array(GLOBAL_MACROS_FIELD).map(|m| m.as_slice());
+
+        for (i, raw_macro) in raw_macros.unwrap_or_default().iter().enumerate() {
+            let location = format!("{}[{}]", GLOBAL_MACROS_FIELD, i);
+
+            let raw_macro = match raw_macro {
+                RawConfig::Hash(raw_macro) => raw_macro.clone(),
+                _ => continue
+            };
+
+            let loc = raw_macro.loc();
+
+            match process_part(&base, GLOBAL_MACROS_FIELD, raw_macro) {
+                Ok(Some(macro_item)) => self.add_macro(file, location, loc, macro_item),
+                Ok(None) => {}
+                Err(err) => self.problems.push(Problem::error(file, location, err))
+            }
+        }
+
+        true
+    }
+
+    /// Adds `macro_item`, built from the macro at `location` in `file`, which starts at `loc`,
+    /// checking the ranges of its MIDI matchers.
+    fn add_macro(
+        &mut self,
+        file: Option<&'a Path>,
+        location: String,
+        loc: Option<Loc>,
+        macro_item: Macro
+    ) {
+        let mut bounded_matchers = vec![];
+
+        for (i, event_matcher) in macro_item.match_events.iter().enumerate() {
+            let event_location = format!("{}.matching_events[{}]", location, i);
+
+            for matcher in midi_matchers(&event_matcher.matcher) {
+                for bounded in matcher {
+                    bounded_matchers.push((event_location.clone(), bounded));
+                }
+            }
+
+            let preconditions = event_matcher.required_preconditions.as_deref().unwrap_or_default();
+
+            for (j, bounded) in precondition_matchers(preconditions) {
+                let precondition_location =
+                    format!("{}.required_preconditions[{}]", event_location, j);
+
+                bounded_matchers.push((precondition_location, bounded));
+            }
+        }
+
+        let preconditions = macro_item.required_preconditions.as_deref().unwrap_or_default();
+
+        for (i, bounded) in precondition_matchers(preconditions) {
+            bounded_matchers.push((format!("{}.required_preconditions[{}]", location, i), bounded));
+        }
+
+        for (matcher_location, (field, matcher, max)) in bounded_matchers {
+            if matcher.as_ref().is_some_and(|matcher| matcher.exceeds(max)) {
+                self.problems.push(Problem::warning(file, matcher_location, loc, format!(
+                    "'{}' matches values above {}, the highest it can be in a MIDI message",
+                    field, max
+                )));
+            }
+        }
+
+        self.macros.push(CheckedMacro { file, location, loc, macro_item });
+    }
+
+    /// Warns about any macro that can't run, because an earlier macro matches all of its events
+    /// first. Since only the first matching macro runs for an event, that one always wins.
+    fn check_shadowed_macros(&mut self) {
+        for (i, later) in self.macros.iter().enumerate() {
+            let earlier = match self.macros[..i].iter().find(|earlier| shadows(earlier, later)) {
+                Some(earlier) => earlier,
+                None => continue
+            };
+
+            let mut description =
+                format!("Macro never runs, since the macro at {}", earlier.location);
+
+            if let Some(name) = earlier.macro_item.name() {
+                description.push_str(&format!(" ('{}')", name));
+            }
+
+            if earlier.file != later.file {
+                let file = earlier.file.map_or_else(
+                    || "the main config file".to_string(),
+                    |file| file.display().to_string()
+                );
+
+                description.push_str(&format!(" in {}", file));
+            }
+
+            description.push_str(" matches all of its events first");
+
+            self.problems.push(
+                Problem::warning(later.file, later.location.clone(), later.loc, description)
+            );
+        }
+    }
+}
+
+/// Processes `base` with only `raw_part` in its `field` list, returning the one macro that comes
+/// out of it, if any.
+fn process_part(
+    base: &RCHash,
+    field: &str,
+    raw_part: RCHash
+) -> Result<Option<Macro>, ConfigError> {
+    let mut raw_config = base.clone();
+    raw_config.insert(k(field), RawConfig::Array(vec![RawConfig::Hash(raw_part)]));
+
+    Ok(RawConfig::Hash(raw_config).process()?.macros.pop())
+}
+
+/// Whether `earlier` always takes the events `later` matches, so `later` can't run
+fn shadows(earlier: &CheckedMacro, later: &CheckedMacro) -> bool {
+    let earlier = &earlier.macro_item;
+    let later = &later.macro_item;
+
+    let is_unconditional = earlier.required_preconditions.as_ref().is_none_or(|p| p.is_empty());
+    let covers_scope = earlier.scope.is_none() || earlier.scope == later.scope;
+
+    is_unconditional && covers_scope && later.match_events.iter().all(|later_event| {
+        earlier.match_events.iter().any(|earlier_event| {
+            earlier_event.required_preconditions.is_none()
+                && matcher_covers(&earlier_event.matcher, &later_event.matcher)
+        })
+    })
+}
+
+/// Whether `earlier` matches every event `later` matches. MIDI matchers cover narrower ones,
+/// such as one for any channel covering one for a single channel; other matchers only cover
+/// their equals.
+fn matcher_covers(earlier: &MatcherType, later: &MatcherType) -> bool {
+    match (earlier, later) {
+        (MatcherType::Midi(earlier), MatcherType::Midi(later)) => earlier.covers(later),
+        _ => earlier == later
+    }
+}
+
+/// The bounded number matchers of the MIDI matchers in `matcher`, including the ones in gestures
+fn midi_matchers(matcher: &MatcherType) -> Vec<Vec<(&'static str, &NumMatch, u32)>> {
+    match matcher {
+        MatcherType::Midi(midi_matcher) => vec![midi_matcher.bounded_matchers()],
+
+        MatcherType::Gesture(gesture) => {
+            let matchers = match gesture.as_ref() {
+                GestureMatcher::Chord { events, .. } => events,
+                GestureMatcher::Sequence { steps, .. } => steps
+            };
+
+            matchers.iter().flat_map(midi_matchers).collect()
+        }
+
+        _ => vec![]
+    }
+}
+
+/// The bounded number matchers of the MIDI preconditions in `preconditions`, each with the index
+/// of the precondition it's in
+fn precondition_matchers(
+    preconditions: &[Precondition]
+) -> Vec<(usize, (&'static str, &NumMatch, u32))> {
+    preconditions
+        .iter()
+        .enumerate()
+        .flat_map(|(i, precondition)| match &precondition.condition {
+            PreconditionType::Midi(midi_precondition) => midi_precondition
+                .bounded_matchers()
+                .into_iter()
+                .map(|bounded| (i, bounded))
+                .collect(),
+
+            _ => vec![]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use crate::config::Loc;
+    use crate::config::check::{check_config, Problem, Severity};
+
+    /// Checks `yaml` as a config file
+    fn check_yaml(name: &str, yaml: &str) -> Vec<Problem> {
+        let file_name = format!("mmpd-check-test-{}-{}.yml", name, std::process::id());
+        let file = env::temp_dir().join(file_name);
+        fs::write(&file, yaml).unwrap();
+
+        let problems = check_config(&file);
+        fs::remove_file(&file).unwrap();
+        problems
+    }
+
+    fn locations(problems: &[Problem]) -> Vec<&str> {
+        problems.iter().map(|problem| problem.location.as_str()).collect()
+    }
+
+    #[test]
+    fn reports_every_broken_macro() {
+        let problems = check_yaml("errors", "
+version: 1
+global_macros:
+  - matching_events:
+      - type: midi
+        data: { message_type: bogus }
+    actions:
+      - type: wait
+        data: 10
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on, key: 10 }
+    actions:
+      - type: wait
+        data: 10
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on, key: 11 }
+    actions:
+      - type: bogus
+");
+
+        assert!(problems.iter().all(|problem| problem.severity == Severity::Error));
+        assert_eq!(locations(&problems), vec![
+            "global_macros[0].matching_events[0].data",
+            "global_macros[2].actions[0]"
+        ]);
+
+        assert_eq!(problems[0].loc, Some(Loc { line: 6, col: 15 }));
+        assert_eq!(problems[1].loc, Some(Loc { line: 20, col: 9 }));
+    }
+
+    #[test]
+    fn warns_about_likely_mistakes() {
+        let problems = check_yaml("warnings", "
+version: 1
+scopes:
+  - window_class:
+      start_with: Firefox
+    macros:
+      - matching_events:
+          - type: midi
+            data: { message_type: note_on, key: 10 }
+        actions:
+          - type: wait
+            data: 10
+global_macros:
+  - name: First
+    matching_events:
+      - type: midi
+        data: { message_type: note_on, key: 10 }
+    actions:
+      - type: wait
+        data: 10
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on, key: 10 }
+    required_preconditions:
+      - type: midi
+        data: { condition_type: control, control: 1, value: { min: 64, max: 200 } }
+    actions:
+      - type: wait
+        data: 10
+");
+
+        assert!(problems.iter().all(|problem| problem.severity == Severity::Warning));
+        assert_eq!(locations(&problems), vec![
+            "scopes[0].window_class.start_with",
+            "scopes[0]",
+            "global_macros[1].required_preconditions[0]",
+            "global_macros[1]"
+        ]);
+
+        assert!(problems[3].description.contains("First"));
+    }
+
+    #[test]
+    fn warns_about_macros_behind_broader_ones() {
+        let problems = check_yaml("broader", "
+version: 1
+global_macros:
+  - name: Any note
+    matching_events:
+      - type: midi
+        data: { message_type: note_on, key: { min: 30, max: 40 } }
+    actions: [{ type: wait, data: 10 }]
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on, channel: 3, key: 36 }
+    actions: [{ type: wait, data: 10 }]
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on, key: 50 }
+    actions: [{ type: wait, data: 10 }]
+  - matching_events:
+      - type: midi
+        data: { message_type: note_off, key: 36 }
+    actions: [{ type: wait, data: 10 }]
+");
+
+        assert_eq!(locations(&problems), vec!["global_macros[1]"]);
+        assert!(problems[0].description.contains("Any note"));
+    }
+
+    #[test]
+    fn does_not_warn_about_broader_macros_behind_narrower_ones() {
+        let problems = check_yaml("narrower", "
+version: 1
+global_macros:
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on, channel: 3, key: 36 }
+    actions: [{ type: wait, data: 10 }]
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on }
+    actions: [{ type: wait, data: 10 }]
+");
+
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn points_unknown_field_warnings_at_their_key() {
+        let problems = check_yaml("unknown", "
+version: 1
+verbose: true
+global_macros:
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on, kye: 10 }
+    actions: [{ type: wait, data: 10 }]
+");
+
+        assert_eq!(locations(&problems), vec![
+            "verbose",
+            "global_macros[0].matching_events[0].data.kye"
+        ]);
+
+        assert_eq!(problems[0].loc, Some(Loc { line: 3, col: 1 }));
+        assert_eq!(problems[1].loc, Some(Loc { line: 7, col: 40 }));
+    }
+}
@@ -0,0 +1,218 @@
+//! Writes config files back out as text, either converted to another format, with their
+//! shorthand forms rewritten consistently, or migrated to a newer version of the format.
+//!
+//! All of them work on the `RawConfig` a file is parsed into, so anything that isn't part of it is
+//! lost: comments, the original formatting, and which values were shared through YAML aliases.
+//! Included files are left alone; each can be exported on its own.
+
+use std::path::Path;
+
+use crate::config::ConfigError;
+use crate::config::loader::{read_raw_config, file_format};
+use crate::config::output_formats::get_serializer_for_extension;
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+use crate::config::versions::{Form, get_processor};
+use crate::config::versions::version2::migrate::migrate;
+
+const VERSION_FIELD: &str = "version";
+
+/// Reads the config file `file`, and returns its contents converted to `format`, one of the
+/// extensions the config file formats are known by, like `toml`.
+///
+/// ## Errors
+/// Returns a `ConfigError` if `file` can't be read or parsed, `format` isn't a known format, or
+/// the config has values that can't be expressed in it, like a list containing null in TOML.
+pub fn convert_config(file: &Path, format: &str) -> Result<String, ConfigError> {
+    let serializer = get_serializer_for_extension(format).ok_or_else(|| {
+        ConfigError::InvalidConfig(format!("Unknown config file format {}", format))
+    })?;
+
+    serializer.serialize(&read_raw_config(file)?)
+}
+
+/// Reads the config file `file`, and returns its contents in the same format, with every part that
+/// has a shorthand form written in `form`. Files without a `version`, like included files, are
+/// treated as version 1.
+///
+/// ## Errors
+/// Returns a `ConfigError` if `file` can't be read or parsed, its top level isn't a hash, or its
+/// version isn't supported.
+pub fn format_config(file: &Path, form: Form) -> Result<String, ConfigError> {
+    let mut hash = read_top_level_hash(file)?;
+    let version = hash.get_integer(VERSION_FIELD).unwrap_or(1);
+
+    let processor = get_processor(version).ok_or_else(|| {
+        ConfigError::UnsupportedVersion(format!("Unknown version {}", version))
+    })?;
+
+    processor.canonicalize(&mut hash, form);
+    serialize_as(file, hash)
+}
+
+/// Reads the version 1 config file `file`, and returns it rewritten into version 2, in the same
+/// format, as described for `migrate`. Files without a `version`, like included files, are
+/// treated as version 1, but stay without one; they're read as version 2 once the main file is.
+///
+/// ## Errors
+/// Returns a `ConfigError` if `file` can't be read or parsed, its top level isn't a hash, or it
+/// isn't a version 1 config.
+pub fn migrate_config(file: &Path) -> Result<String, ConfigError> {
+    let mut hash = read_top_level_hash(file)?;
+    let version = hash.get_integer(VERSION_FIELD);
+
+    if version.unwrap_or(1) != 1 {
+        return Err(ConfigError::UnsupportedVersion(format!(
+            "Only version 1 config files can be migrated, this one is version {}",
+            version.unwrap()
+        )));
+    }
+
+    migrate(&mut hash);
+
+    if version.is_none() {
+        hash.remove(&k(VERSION_FIELD));
+    }
+
+    serialize_as(file, hash)
+}
+
+fn read_top_level_hash(file: &Path) -> Result<RCHash, ConfigError> {
+    match read_raw_config(file)? {
+        RawConfig::Hash(hash) => Ok(hash),
+
+        raw_config => Err(ConfigError::InvalidConfig(
+            format!("Top level of config should be Hash, found: {}", raw_config)
+        ))
+    }
+}
+
+/// Serializes `hash` in the format of `file`
+fn serialize_as(file: &Path, hash: RCHash) -> Result<String, ConfigError> {
+    let format = file_format(file);
+
+    let serializer = get_serializer_for_extension(format).ok_or_else(|| {
+        ConfigError::ReadError(format!("Unknown config file format {}", format))
+    })?;
+
+    serializer.serialize(&RawConfig::Hash(hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use crate::config::export::{convert_config, format_config, migrate_config};
+    use crate::config::versions::Form;
+
+    #[test]
+    fn converts_and_formats_config_files() {
+        let file = env::temp_dir().join(format!("mmpd-export-test-{}.yml", std::process::id()));
+
+        fs::write(&file, "
+version: 1
+# Comments aren't kept
+global_macros:
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on, key: C3 }
+    actions:
+      - type: enter_text
+        data: Hello
+").unwrap();
+
+        assert_eq!(convert_config(&file, "json").ok().unwrap(), r#"{
+  "version": 1,
+  "global_macros": [
+    {
+      "matching_events": [
+        {
+          "type": "midi",
+          "data": {
+            "message_type": "note_on",
+            "key": "C3"
+          }
+        }
+      ],
+      "actions": [
+        {
+          "type": "enter_text",
+          "data": "Hello"
+        }
+      ]
+    }
+  ]
+}
+"#);
+
+        assert_eq!(format_config(&file, Form::Explicit).ok().unwrap(), "version: 1
+global_macros:
+  - matching_events:
+      - type: midi
+        data:
+          message_type: note_on
+          key: C3
+    actions:
+      - type: enter_text
+        data:
+          text: Hello
+");
+
+        assert!(convert_config(&file, "ini").is_err());
+
+        fs::write(&file, "version: 3\n").unwrap();
+        assert!(format_config(&file, Form::Minimal).is_err());
+
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn migrates_version_1_config_files() {
+        let file = env::temp_dir().join(format!("mmpd-migrate-test-{}.json", std::process::id()));
+
+        fs::write(&file, r#"{
+  "version": 1,
+  "global_macros": [{
+    "matching_events": [
+      { "type": "midi", "data": { "message_type": "note_on", "channel": 4 } },
+      { "type": "midi", "data": { "message_type": "note_off", "channel": 4 } }
+    ],
+    "actions": [{ "type": "control", "data": "exit" }]
+  }]
+}"#).unwrap();
+
+        assert_eq!(migrate_config(&file).ok().unwrap(), r#"{
+  "version": 2,
+  "defaults": {
+    "channel": 4
+  },
+  "global_macros": [
+    {
+      "matching_events": [
+        {
+          "data": {
+            "message_type": "note_on"
+          }
+        },
+        {
+          "data": {
+            "message_type": "note_off"
+          }
+        }
+      ],
+      "actions": [
+        {
+          "type": "control",
+          "data": "exit"
+        }
+      ]
+    }
+  ]
+}
+"#);
+
+        fs::write(&file, r#"{ "version": 2 }"#).unwrap();
+        assert!(migrate_config(&file).is_err());
+
+        fs::remove_file(&file).unwrap();
+    }
+}
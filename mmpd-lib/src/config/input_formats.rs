@@ -1,8 +1,12 @@
-use crate::config::ConfigError;
-use crate::config::raw_config::RawConfig;
+use crate::config::{ConfigError, Loc};
+use crate::config::raw_config::{RawConfig, k};
 use crate::config::input_formats::yaml_config_parser::YamlConfigInput;
+use crate::config::input_formats::toml_config_parser::TomlConfigInput;
+use crate::config::input_formats::json_config_parser::JsonConfigInput;
 
 pub (crate) mod yaml_config_parser;
+pub (crate) mod toml_config_parser;
+pub (crate) mod json_config_parser;
 
 /// "Low level" parsers that parse into RawConfig should implement this. For example,
 /// YAML, TOML or JSON parsers. They parse into the intermediary RawConfig format, which
@@ -13,11 +17,249 @@ pub trait ConfigInputParser {
     fn parse(&self, raw_input: &str) -> Result<RawConfig, ConfigError>;
 }
 
+/// Where the hashes in a parsed config and their keys start, as byte offsets into the input, for
+/// parsers whose value trees don't keep track of that. It's shaped like the `RawConfig` tree it's
+/// for, see `apply_offsets`.
+pub (crate) enum Offsets {
+    /// A hash's offset, and each of its fields with the offset of its key and those within it
+    Hash(Option<usize>, Vec<(String, usize, Offsets)>),
+
+    Array(Vec<Offsets>),
+
+    /// Anything that isn't a hash or array
+    Other
+}
+
+/// Gives the hashes in `raw_config`, parsed from `input`, and their keys the locations of their
+/// `offsets`
+pub (crate) fn apply_offsets(raw_config: &mut RawConfig, offsets: &Offsets, input: &str) {
+    match (raw_config, offsets) {
+        (RawConfig::Hash(hash), Offsets::Hash(offset, fields)) => {
+            hash.set_loc(offset.map(|offset| loc_at(input, offset)));
+
+            // With duplicate keys, the last one is the one that's kept
+            for (name, key_offset, _) in fields.iter() {
+                hash.set_key_loc(k(name), loc_at(input, *key_offset));
+            }
+
+            for (key, value) in hash.iter_mut() {
+                let field = fields.iter().rev().find(|(name, _, _)| *key == k(name));
+
+                if let Some((_, _, offsets)) = field {
+                    apply_offsets(value, offsets, input);
+                }
+            }
+        }
+
+        (RawConfig::Array(items), Offsets::Array(item_offsets)) => {
+            for (item, offsets) in items.iter_mut().zip(item_offsets) {
+                apply_offsets(item, offsets, input);
+            }
+        }
+
+        _ => {}
+    }
+}
+
+/// The line and column of byte `offset` within `input`
+fn loc_at(input: &str, offset: usize) -> Loc {
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+    Loc {
+        line: before.matches('\n').count() + 1,
+        col: before[line_start..].chars().count() + 1
+    }
+}
+
 pub fn get_parser_for_extension(ext: &str) -> Option<Box<dyn ConfigInputParser>> {
     let ext = ext.to_lowercase();
 
     match ext.as_ref() {
         "yml" | "yaml" => Some(YamlConfigInput::new()),
+        "toml" => Some(TomlConfigInput::new()),
+        "json" => Some(JsonConfigInput::new()),
         _ => None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Loc;
+    use crate::config::input_formats::get_parser_for_extension;
+    use crate::config::raw_config::{RawConfig, AccessHelpers, k};
+
+    const YAML_CONFIG: &str = r#"
+version: 1
+watch: true
+midi_device:
+  contains: KeyStep
+global_macros:
+  - name: Volume
+    matching_events:
+      - type: midi
+        data:
+          message_type: control_change
+          control: 7
+          value: { min: 0, max: 127.0 }
+    actions:
+      - type: shell
+        data:
+          command: amixer
+          args: [set, Master, "unmute"]
+      - type: wait
+        data: 12.5
+"#;
+
+    const TOML_CONFIG: &str = r#"
+version = 1
+watch = true
+
+[midi_device]
+contains = "KeyStep"
+
+[[global_macros]]
+name = "Volume"
+
+[[global_macros.matching_events]]
+type = "midi"
+
+[global_macros.matching_events.data]
+message_type = "control_change"
+control = 7
+value = { min = 0, max = 127.0 }
+
+[[global_macros.actions]]
+type = "shell"
+data = { command = "amixer", args = ["set", "Master", "unmute"] }
+
+[[global_macros.actions]]
+type = "wait"
+data = 12.5
+"#;
+
+    const JSON_CONFIG: &str = r#"
+{
+    "version": 1,
+    "watch": true,
+    "midi_device": { "contains": "KeyStep" },
+    "global_macros": [
+        {
+            "name": "Volume",
+            "matching_events": [
+                {
+                    "type": "midi",
+                    "data": {
+                        "message_type": "control_change",
+                        "control": 7,
+                        "value": { "min": 0, "max": 127.0 }
+                    }
+                }
+            ],
+            "actions": [
+                {
+                    "type": "shell",
+                    "data": { "command": "amixer", "args": ["set", "Master", "unmute"] }
+                },
+                { "type": "wait", "data": 12.5 }
+            ]
+        }
+    ]
+}
+"#;
+
+    fn parse(ext: &str, input: &str) -> RawConfig {
+        get_parser_for_extension(ext).unwrap().parse(input).ok().unwrap()
+    }
+
+    #[test]
+    fn parses_the_same_config_in_every_format() {
+        let yaml = parse("yml", YAML_CONFIG);
+
+        assert_eq!(parse("toml", TOML_CONFIG), yaml);
+        assert_eq!(parse("json", JSON_CONFIG), yaml);
+
+        let config = yaml.process().ok().unwrap();
+        assert_eq!(config.macros.len(), 1);
+        assert!(config.watch);
+    }
+
+    #[test]
+    fn parses_floats_and_nulls_like_yaml_does() {
+        let yaml = parse("yaml", "{ a: 2.9, b: -1.5, c: ~, d: [null, 3] }");
+        let json = parse("json", r#"{ "a": 2.9, "b": -1.5, "c": null, "d": [null, 3] }"#);
+
+        assert_eq!(json, yaml);
+        assert_eq!(parse("toml", "a = 2.9\nb = -1.5\nc = nan\nd = [3]"), parse("json", r#"{
+            "a": 2, "b": -1, "c": null, "d": [3]
+        }"#));
+    }
+
+    #[test]
+    fn points_errors_at_where_hashes_start_in_every_format() {
+        let error = |ext: &str, input: &str| {
+            let input = input.replace("\"wait\"", "\"wiat\"").replace("type: wait", "type: wiat");
+            parse(ext, &input).process().err().unwrap().description()
+        };
+
+        let unknown_type = "Unknown action type 'wiat'";
+
+        assert_eq!(
+            error("yml", YAML_CONFIG),
+            format!("global_macros[0].actions[1] (line 19, column 9): {}", unknown_type)
+        );
+
+        // In TOML, a table starts at its first key
+        assert_eq!(
+            error("toml", TOML_CONFIG),
+            format!("global_macros[0].actions[1] (line 24, column 1): {}", unknown_type)
+        );
+
+        // Dates and times can't be read along with where keys are, which leaves just the path
+        assert_eq!(
+            error("toml", &format!("when = 1979-05-27\n{}", TOML_CONFIG)),
+            format!("global_macros[0].actions[1]: {}", unknown_type)
+        );
+
+        assert_eq!(
+            error("json", JSON_CONFIG),
+            format!("global_macros[0].actions[1] (line 24, column 17): {}", unknown_type)
+        );
+    }
+
+    #[test]
+    fn keeps_where_keys_are_in_every_format() {
+        let key_locs = |ext: &str, input: &str| {
+            let top_level = match parse(ext, input) {
+                RawConfig::Hash(hash) => hash,
+                _ => panic!("Expected a hash")
+            };
+
+            let device = top_level.get_hash("midi_device").unwrap();
+
+            (top_level.key_loc(&k("midi_device")), device.key_loc(&k("contains")))
+        };
+
+        assert_eq!(key_locs("yml", YAML_CONFIG), (
+            Some(Loc { line: 4, col: 1 }),
+            Some(Loc { line: 5, col: 3 })
+        ));
+
+        assert_eq!(key_locs("toml", TOML_CONFIG), (
+            Some(Loc { line: 5, col: 2 }),
+            Some(Loc { line: 6, col: 1 })
+        ));
+
+        assert_eq!(key_locs("json", JSON_CONFIG), (
+            Some(Loc { line: 5, col: 5 }),
+            Some(Loc { line: 5, col: 22 })
+        ));
+    }
+
+    #[test]
+    fn returns_format_errors_and_no_parser_for_unknown_extensions() {
+        assert!(get_parser_for_extension("JSON").unwrap().parse("{ \"version\": ").is_err());
+        assert!(get_parser_for_extension("toml").unwrap().parse("version = ").is_err());
+        assert!(get_parser_for_extension("ini").is_none());
+    }
+}
@@ -0,0 +1,143 @@
+//! Parses JSON string into JSON representation tree, into `RawConfig` tree
+
+use std::fmt::{self, Formatter};
+
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde_json::Value;
+use serde_json::value::RawValue;
+
+use crate::config::{ConfigError, Loc};
+use crate::config::input_formats::{ConfigInputParser, Offsets, apply_offsets};
+use crate::config::raw_config::RawConfig;
+
+/// Config input parser, parsing JSON into `RawConfig`.
+pub struct JsonConfigInput { }
+
+impl JsonConfigInput {
+    /// Creates a new parser instance from the full text contents of the configuration file
+    pub fn new() -> Box<dyn ConfigInputParser> {
+        Box::new(JsonConfigInput {})
+    }
+}
+
+impl ConfigInputParser for JsonConfigInput {
+    /// Attempts parsing the JSON file contents into the intermediary `RawConfig` format.
+    /// If anything about it fails, returns a `ConfigError`.
+    fn parse(&self, raw_input: &str) -> Result<RawConfig, ConfigError> {
+        let json: Value = serde_json::from_str(raw_input).map_err(|err| {
+            ConfigError::FormatError(err.to_string(), Loc { line: err.line(), col: err.column() })
+        })?;
+
+        let mut raw_config = json_to_raw_config(&json);
+
+        // The input parsed fine just now, so this won't fail
+        if let Ok(raw_value) = serde_json::from_str::<&RawValue>(raw_input) {
+            if let Ok(offsets) = json_offsets(raw_value, raw_input) {
+                apply_offsets(&mut raw_config, &offsets, raw_input);
+            }
+        }
+
+        Ok(raw_config)
+    }
+}
+
+/// Finds the `Offsets` within `raw_value`, a part of `input` as is, going by where its text is
+/// within `input`
+fn json_offsets(raw_value: &RawValue, input: &str) -> Result<Offsets, serde_json::Error> {
+    let text = raw_value.get();
+    let offset = offset_of(raw_value, input);
+
+    Ok(match text.as_bytes().first() {
+        Some(b'{') => {
+            let RawFields(raw_fields) = serde_json::from_str(text)?;
+            let mut fields = vec![];
+
+            for (raw_key, raw_value) in raw_fields {
+                let key = serde_json::from_str(raw_key.get())?;
+                fields.push((key, offset_of(raw_key, input), json_offsets(raw_value, input)?));
+            }
+
+            Offsets::Hash(Some(offset), fields)
+        }
+
+        Some(b'[') => {
+            let raw_items: Vec<&RawValue> = serde_json::from_str(text)?;
+            let mut items = vec![];
+
+            for raw_item in raw_items {
+                items.push(json_offsets(raw_item, input)?);
+            }
+
+            Offsets::Array(items)
+        }
+
+        _ => Offsets::Other
+    })
+}
+
+/// Where `raw_value`, a part of `input` as is, starts within `input`
+fn offset_of(raw_value: &RawValue, input: &str) -> usize {
+    raw_value.get().as_ptr() as usize - input.as_ptr() as usize
+}
+
+/// The fields of a JSON object, in order, with their keys and values as they're written
+struct RawFields<'a>(Vec<(&'a RawValue, &'a RawValue)>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawFields<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<RawFields<'a>, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_map(RawFieldsVisitor)
+    }
+}
+
+struct RawFieldsVisitor;
+
+impl<'de> Visitor<'de> for RawFieldsVisitor {
+    type Value = RawFields<'de>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("a JSON object")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<RawFields<'de>, A::Error> where A: MapAccess<'de> {
+        let mut fields = vec![];
+
+        while let Some(field) = map.next_entry()? {
+            fields.push(field);
+        }
+
+        Ok(RawFields(fields))
+    }
+}
+
+fn json_to_raw_config(json: &Value) -> RawConfig {
+    match json {
+        // Like in YAML, floats are cast to an integer, losing whatever they had after the
+        // decimal point.
+        Value::Number(n) => n.as_i64()
+            .or_else(|| n.as_f64().filter(|f| f.is_finite()).map(|f| f as i64))
+            .map_or(RawConfig::Null, RawConfig::Integer),
+
+        Value::String(s) => RawConfig::String(s.to_string()),
+        Value::Bool(b) => RawConfig::Bool(*b),
+
+        Value::Array(arr) => RawConfig::Array(
+            arr
+                .iter()
+                .map(json_to_raw_config)
+                .collect()
+        ),
+
+        Value::Object(object) => RawConfig::Hash(
+            object
+                .iter()
+                .map(|(key, value)| {
+                    (RawConfig::String(key.to_string()), json_to_raw_config(value))
+                })
+                .collect()
+        ),
+
+        Value::Null => RawConfig::Null
+    }
+}
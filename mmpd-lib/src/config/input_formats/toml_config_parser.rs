@@ -0,0 +1,137 @@
+//! Parses TOML string into TOML representation tree, into `RawConfig` tree
+
+use std::fmt::{self, Formatter};
+
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use toml::{Spanned, Value};
+
+use crate::config::{ConfigError, Loc};
+use crate::config::input_formats::{ConfigInputParser, Offsets, apply_offsets};
+use crate::config::raw_config::RawConfig;
+
+/// Config input parser, parsing TOML into `RawConfig`.
+pub struct TomlConfigInput { }
+
+impl TomlConfigInput {
+    /// Creates a new parser instance from the full text contents of the configuration file
+    pub fn new() -> Box<dyn ConfigInputParser> {
+        Box::new(TomlConfigInput {})
+    }
+}
+
+impl ConfigInputParser for TomlConfigInput {
+    /// Attempts parsing the TOML file contents into the intermediary `RawConfig` format.
+    /// If anything about it fails, returns a `ConfigError`.
+    fn parse(&self, raw_input: &str) -> Result<RawConfig, ConfigError> {
+        let toml: Value = raw_input.parse().map_err(|err: toml::de::Error| {
+            match err.line_col() {
+                // The TOML parser counts lines and columns from 0
+                Some((line, col)) => {
+                    ConfigError::FormatError(err.to_string(), Loc { line: line + 1, col: col + 1 })
+                }
+
+                None => ConfigError::InvalidConfig(err.to_string())
+            }
+        })?;
+
+        let mut raw_config = toml_to_raw_config(&toml);
+
+        // Only keys come with their position, so a table is taken to start at its first key. Dates
+        // and times can't be read along with positions; files with those go without locations.
+        if let Ok(offsets) = toml::from_str::<TomlOffsets>(raw_input) {
+            apply_offsets(&mut raw_config, &offsets.0, raw_input);
+        }
+
+        Ok(raw_config)
+    }
+}
+
+/// `Offsets` of a TOML value
+struct TomlOffsets(Offsets);
+
+impl<'de> Deserialize<'de> for TomlOffsets {
+    fn deserialize<D>(deserializer: D) -> Result<TomlOffsets, D::Error> where D: Deserializer<'de> {
+        deserializer.deserialize_any(TomlOffsetsVisitor)
+    }
+}
+
+struct TomlOffsetsVisitor;
+
+impl<'de> Visitor<'de> for TomlOffsetsVisitor {
+    type Value = TomlOffsets;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("a TOML value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<TomlOffsets, E> {
+        Ok(TomlOffsets(Offsets::Other))
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<TomlOffsets, E> {
+        Ok(TomlOffsets(Offsets::Other))
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<TomlOffsets, E> {
+        Ok(TomlOffsets(Offsets::Other))
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<TomlOffsets, E> {
+        Ok(TomlOffsets(Offsets::Other))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<TomlOffsets, A::Error> where A: SeqAccess<'de> {
+        let mut items = vec![];
+
+        while let Some(TomlOffsets(item)) = seq.next_element()? {
+            items.push(item);
+        }
+
+        Ok(TomlOffsets(Offsets::Array(items)))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<TomlOffsets, A::Error> where A: MapAccess<'de> {
+        let mut start = None;
+        let mut fields = vec![];
+
+        while let Some((key, TomlOffsets(value))) = map.next_entry::<Spanned<String>, _>()? {
+            let key_start = key.start();
+            start = start.or(Some(key_start));
+            fields.push((key.into_inner(), key_start, value));
+        }
+
+        Ok(TomlOffsets(Offsets::Hash(start, fields)))
+    }
+}
+
+fn toml_to_raw_config(toml: &Value) -> RawConfig {
+    match toml {
+        // Like in YAML, floats are cast to an integer, losing whatever they had after the
+        // decimal point.
+        Value::Float(f) if f.is_finite() => RawConfig::Integer(*f as i64),
+        Value::Float(_) => RawConfig::Null,
+
+        Value::Integer(i) => RawConfig::Integer(*i),
+        Value::String(s) => RawConfig::String(s.to_string()),
+        Value::Boolean(b) => RawConfig::Bool(*b),
+
+        // Dates and times aren't used anywhere in the config, but can still be read as text
+        Value::Datetime(datetime) => RawConfig::String(datetime.to_string()),
+
+        Value::Array(arr) => RawConfig::Array(
+            arr
+                .iter()
+                .map(toml_to_raw_config)
+                .collect()
+        ),
+
+        Value::Table(table) => RawConfig::Hash(
+            table
+                .iter()
+                .map(|(key, value)| {
+                    (RawConfig::String(key.to_string()), toml_to_raw_config(value))
+                })
+                .collect()
+        )
+    }
+}
@@ -1,12 +1,16 @@
 //! Parses YAML string into YAML representation tree, into `RawConfig` tree
 
+use std::collections::HashMap;
+use std::mem;
 use std::str::FromStr;
 
-use yaml_rust::{Yaml, YamlLoader};
+use yaml_rust::Yaml;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::{Marker, TScalarStyle, TokenType};
 
 use crate::config::{ConfigError, Loc};
 use crate::config::input_formats::ConfigInputParser;
-use crate::config::raw_config::RawConfig;
+use crate::config::raw_config::{RawConfig, RCHash};
 
 extern crate yaml_rust;
 
@@ -24,51 +28,401 @@ impl ConfigInputParser for YamlConfigInput {
     /// Attempts parsing the YAML file contents into the intermediary `RawConfig` format.
     /// If anything about it fails, returns a `ConfigError`.
     fn parse(&self, raw_input: &str) -> Result<RawConfig, ConfigError> {
-        let mut yaml = YamlLoader::load_from_str(raw_input).map_err(|err| {
-           ConfigError::FormatError(
-               err.to_string(),
-               Loc { line: err.marker().line(), col: err.marker().col() }
-           )
+        let mut receiver = RawConfigReceiver {
+            docs: vec![],
+            stack: vec![],
+            anchors: HashMap::new(),
+            error: None
+        };
+
+        Parser::new(raw_input.chars()).load(&mut receiver, false).map_err(|err| {
+            let description = match err.to_string() {
+                desc if desc.contains("found unknown anchor") => format!(
+                    "Alias refers to an anchor that isn't defined before it ({})",
+                    desc
+                ),
+
+                desc => desc
+            };
+
+            ConfigError::FormatError(description, loc(err.marker()))
         })?;
 
-        Ok(if yaml.is_empty() {
-            RawConfig::Null
-        } else {
-            yaml_to_raw_config(&yaml.swap_remove(0))
-        })
+        if let Some(err) = receiver.error {
+            return Err(err);
+        }
+
+        Ok(receiver.docs.into_iter().next().unwrap_or(RawConfig::Null))
+    }
+}
+
+/// Key that merges the entries of another hash, or list of hashes, into the hash it's in
+const MERGE_KEY: &str = "<<";
+
+/// Converts a position from the YAML parser, whose columns start at 0, into a `Loc`
+fn loc(marker: &Marker) -> Loc {
+    Loc { line: marker.line(), col: marker.col() + 1 }
+}
+
+/// Builds `RawConfig` trees from the events of the YAML parser, much like `YamlLoader` builds
+/// `Yaml` trees, except that:
+///
+/// - Each hash keeps where it and its keys start in the file, so errors in it can point there
+/// - Aliases are replaced by a copy of the value they refer to, which keeps pointing to where the
+///   anchored value is in the file
+/// - `<<` merge keys are replaced by the entries of the hash (or list of hashes) they're given,
+///   except for keys the hash has itself
+struct RawConfigReceiver {
+    /// Documents parsed so far
+    docs: Vec<RawConfig>,
+
+    /// Arrays and hashes that are being filled in, from the outermost inwards
+    stack: Vec<OpenNode>,
+
+    /// Values with an anchor, by anchor ID, once they're complete
+    anchors: HashMap<usize, RawConfig>,
+
+    /// The first error found in a document that parsed fine as far as the YAML parser is concerned
+    error: Option<ConfigError>
+}
+
+/// An array or hash that is being filled in by `RawConfigReceiver`
+struct OpenNode {
+    node: RawConfig,
+
+    /// ID of the anchor on the node, or 0 if it has none
+    anchor: usize,
+
+    /// For a hash, what the next node parsed into it is for
+    next: HashSlot
+}
+
+enum HashSlot {
+    Key,
+    Value(RawConfig),
+    Merge
+}
+
+impl RawConfigReceiver {
+    /// Puts a parsed `node` into the array or hash being filled in, or sets it aside as a
+    /// document if it's at the top level. If `node` has an anchor, it's kept for aliases to it.
+    fn insert(&mut self, node: RawConfig, anchor: usize, marker: &Marker) {
+        if anchor > 0 {
+            self.anchors.insert(anchor, node.clone());
+        }
+
+        let open = match self.stack.last_mut() {
+            Some(open) => open,
+
+            None => {
+                self.docs.push(node);
+                return;
+            }
+        };
+
+        match &mut open.node {
+            RawConfig::Array(items) => items.push(node),
+
+            RawConfig::Hash(hash) => match mem::replace(&mut open.next, HashSlot::Key) {
+                HashSlot::Key => {
+                    hash.set_key_loc(node.clone(), loc(marker));
+                    open.next = HashSlot::Value(node);
+                }
+
+                HashSlot::Value(key) => { hash.insert(key, node); }
+
+                HashSlot::Merge => {
+                    if let Err(err) = merge(hash, node) {
+                        self.fail(err, marker);
+                    }
+                }
+            },
+
+            _ => unreachable!("Only arrays and hashes are put on the stack")
+        }
+    }
+
+    /// Whether the next node parsed is a key of the hash being filled in
+    fn expects_key(&self) -> bool {
+        matches!(self.stack.last(), Some(OpenNode { next: HashSlot::Key, .. }))
+    }
+
+    /// Keeps `description` as the error to return, with `marker` as where it is, unless there
+    /// already is one.
+    fn fail(&mut self, description: String, marker: &Marker) {
+        if self.error.is_none() {
+            self.error = Some(ConfigError::FormatError(description, loc(marker)));
+        }
     }
 }
 
-fn yaml_to_raw_config(yaml: &Yaml) -> RawConfig {
+/// Adds the entries of `merged`, the value of a merge key, to `hash`, except for keys it already
+/// has. If `merged` is a list of hashes, earlier hashes in it take precedence over later ones.
+fn merge(hash: &mut RCHash, merged: RawConfig) -> Result<(), String> {
+    let merged = match merged {
+        RawConfig::Hash(merged) => vec![merged],
+
+        RawConfig::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                RawConfig::Hash(merged) => Some(merged),
+                _ => None
+            })
+            .collect::<Option<Vec<RCHash>>>()
+            .ok_or_else(|| format!("Items in a '{}' merge key should be hashes", MERGE_KEY))?,
+
+        _ => return Err(format!("A '{}' merge key should be a hash or list of hashes", MERGE_KEY))
+    };
+
+    for merged in merged {
+        let key_locs: Vec<_> = merged.keys().map(|key| merged.key_loc(key)).collect();
+
+        for ((key, value), key_loc) in merged.into_iter().zip(key_locs) {
+            if !hash.contains_key(&key) {
+                if let Some(key_loc) = key_loc {
+                    hash.set_key_loc(key.clone(), key_loc);
+                }
+
+                hash.insert(key, value);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl MarkedEventReceiver for RawConfigReceiver {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        // A block mapping only starts being reported at the colon after its first key, so it's
+        // moved back to where that key starts.
+        if let Some(OpenNode { node: RawConfig::Hash(hash), next: HashSlot::Key, .. }) =
+            self.stack.last_mut()
+        {
+            let first_key = Some(loc(&marker));
+
+            if hash.is_empty() && first_key < hash.loc() {
+                *hash = RCHash::at(first_key);
+            }
+        }
+
+        match event {
+            Event::DocumentStart => self.anchors.clear(),
+
+            Event::SequenceStart(anchor) => self.stack.push(OpenNode {
+                node: RawConfig::Array(vec![]),
+                anchor,
+                next: HashSlot::Key
+            }),
+
+            Event::MappingStart(anchor) => self.stack.push(OpenNode {
+                node: RawConfig::Hash(RCHash::at(Some(loc(&marker)))),
+                anchor,
+                next: HashSlot::Key
+            }),
+
+            Event::SequenceEnd | Event::MappingEnd => {
+                if let Some(open) = self.stack.pop() {
+                    self.insert(open.node, open.anchor, &marker);
+                }
+            }
+
+            Event::Scalar(value, style, anchor, tag) => {
+                let is_merge_key = value == MERGE_KEY
+                    && style == TScalarStyle::Plain
+                    && tag.is_none()
+                    && self.expects_key();
+
+                if is_merge_key {
+                    if let Some(open) = self.stack.last_mut() {
+                        open.next = HashSlot::Merge;
+                    }
+                } else {
+                    let node = scalar_to_raw_config(scalar_to_yaml(value, style, tag));
+                    self.insert(node, anchor, &marker);
+                }
+            }
+
+            Event::Alias(anchor) => match self.anchors.get(&anchor) {
+                Some(node) => {
+                    let node = node.clone();
+                    self.insert(node, 0, &marker);
+                }
+
+                // The parser only reports aliases to anchors it has seen, so the anchored value
+                // must still be open, with the alias inside of it.
+                None => {
+                    self.fail("Alias refers to a value it is part of itself".to_string(), &marker);
+                    self.insert(RawConfig::Null, 0, &marker);
+                }
+            },
+
+            _ => {}
+        }
+    }
+}
+
+/// Resolves a scalar into a `Yaml` value the way `YamlLoader` does: quoted scalars are always
+/// strings, plain ones are typed by their tag if any, or by what they look like otherwise.
+fn scalar_to_yaml(value: String, style: TScalarStyle, tag: Option<TokenType>) -> Yaml {
+    if style != TScalarStyle::Plain {
+        return Yaml::String(value);
+    }
+
+    match tag {
+        Some(TokenType::Tag(ref handle, ref suffix)) if handle == "!!" => {
+            match suffix.as_ref() {
+                "bool" => value.parse::<bool>().map_or(Yaml::BadValue, Yaml::Boolean),
+                "int" => value.parse::<i64>().map_or(Yaml::BadValue, Yaml::Integer),
+                "float" => value.parse::<f64>().map_or(Yaml::BadValue, |_| Yaml::Real(value)),
+
+                "null" => match value.as_ref() {
+                    "~" | "null" => Yaml::Null,
+                    _ => Yaml::BadValue
+                },
+
+                _ => Yaml::String(value)
+            }
+        }
+
+        Some(TokenType::Tag(_, _)) => Yaml::String(value),
+        _ => Yaml::from_str(&value)
+    }
+}
+
+fn scalar_to_raw_config(yaml: Yaml) -> RawConfig {
     match yaml {
-        Yaml::Real(real) => f64::from_str(real)
+        Yaml::Real(real) => f64::from_str(&real)
             .map_or(
                 RawConfig::Null,
                 |f| RawConfig::Integer(f as i64)
             ),
 
-        Yaml::Integer(i) => RawConfig::Integer(*i),
-        Yaml::String(s) => RawConfig::String(s.to_string()),
-        Yaml::Boolean(b) => RawConfig::Bool(*b),
-
-        Yaml::Array(arr) => RawConfig::Array(
-            arr
-                .iter()
-                .map( yaml_to_raw_config)
-                .collect()
-        ),
-
-        Yaml::Hash(hash) => RawConfig::Hash(
-            hash
-                .iter()
-                .map(|(key, value)| {
-                    (yaml_to_raw_config(key), yaml_to_raw_config(value))
-                })
-                .collect()
-        ),
-
-        Yaml::Alias(_) => RawConfig::Null,
+        Yaml::Integer(i) => RawConfig::Integer(i),
+        Yaml::String(s) => RawConfig::String(s),
+        Yaml::Boolean(b) => RawConfig::Bool(b),
+
+        // Arrays, hashes and aliases are built from parser events rather than from `Yaml` values
+        Yaml::Array(_) | Yaml::Hash(_) | Yaml::Alias(_) => RawConfig::Null,
+
         Yaml::Null => RawConfig::Null,
         Yaml::BadValue => RawConfig::Null
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::config::{ConfigError, Loc};
+    use crate::config::input_formats::yaml_config_parser::YamlConfigInput;
+    use crate::config::raw_config::{RawConfig, AccessHelpers, k};
+
+    #[test]
+    fn parses_values_and_where_hashes_start() {
+        let yaml = "\
+version: 1
+scopes:
+  - window_class: { is: Firefox }
+    macros:
+      - name: \"42\"
+        count: 3.7
+        enabled: !!bool true
+";
+
+        let raw_config = YamlConfigInput::new().parse(yaml).ok().unwrap();
+        assert_eq!(raw_config.loc(), Some(Loc { line: 1, col: 1 }));
+
+        let hash = match &raw_config {
+            RawConfig::Hash(hash) => hash,
+            _ => panic!("Expected a hash")
+        };
+
+        let raw_scope = &hash.get_array("scopes").unwrap()[0];
+        assert_eq!(raw_scope.loc(), Some(Loc { line: 3, col: 5 }));
+
+        let raw_scope = match raw_scope {
+            RawConfig::Hash(raw_scope) => raw_scope,
+            _ => panic!("Expected a hash")
+        };
+
+        assert_eq!(
+            raw_scope.get(&k("window_class")).and_then(RawConfig::loc),
+            Some(Loc { line: 3, col: 19 })
+        );
+
+        let raw_macro = match &raw_scope.get_array("macros").unwrap()[0] {
+            RawConfig::Hash(raw_macro) => raw_macro,
+            _ => panic!("Expected a hash")
+        };
+
+        assert_eq!(raw_macro.loc(), Some(Loc { line: 5, col: 9 }));
+        assert_eq!(raw_macro.get_string("name"), Some("42"));
+        assert_eq!(raw_macro.get_integer("count"), Some(3));
+        assert_eq!(raw_macro.get_bool("enabled"), Some(true));
+    }
+
+    #[test]
+    fn returns_an_error_with_location_for_invalid_syntax() {
+        let error = YamlConfigInput::new().parse("version: 1\nscopes: [\n").err().unwrap();
+        assert!(matches!(error, ConfigError::FormatError(_, Loc { line: 3, .. })));
+    }
+
+    #[test]
+    fn resolves_aliases_and_merge_keys() {
+        let yaml = "\
+common: &common
+  type: midi
+  data: &data { message_type: note_on, channel: 1 }
+other: { data: { key: 60 } }
+copy: *common
+merged:
+  <<: *common
+  data: *data
+  type: overridden
+merged_list:
+  <<: [{ a: 1, b: 1 }, { b: 2, c: 2 }]
+  c: 3
+";
+
+        let raw_config = YamlConfigInput::new().parse(yaml).ok().unwrap();
+
+        let hash = match &raw_config {
+            RawConfig::Hash(hash) => hash,
+            _ => panic!("Expected a hash")
+        };
+
+        assert_eq!(hash.get(&k("copy")), hash.get(&k("common")));
+
+        let merged = hash.get_hash("merged").unwrap();
+        assert_eq!(merged.get_string("type"), Some("overridden"));
+        assert_eq!(merged.get_hash("data").unwrap().get_integer("channel"), Some(1));
+        assert!(!merged.contains_key(&k("<<")));
+
+        // Aliased values still point to where they're defined
+        assert_eq!(merged.get_hash("data").unwrap().loc(), Some(Loc { line: 3, col: 15 }));
+
+        let merged_list = hash.get_hash("merged_list").unwrap();
+        assert_eq!(merged_list.get_integer("a"), Some(1));
+        assert_eq!(merged_list.get_integer("b"), Some(1));
+        assert_eq!(merged_list.get_integer("c"), Some(3));
+    }
+
+    #[test]
+    fn returns_an_error_for_unresolvable_aliases_and_invalid_merge_keys() {
+        let parse_error = |yaml: &str| match YamlConfigInput::new().parse(yaml) {
+            Err(ConfigError::FormatError(desc, loc)) => (desc, loc),
+            _ => panic!("Expected a format error")
+        };
+
+        let (desc, loc) = parse_error("a: 1\nb: *missing\n");
+        assert!(desc.contains("Alias"));
+        assert_eq!(loc.line, 2);
+
+        let (desc, loc) = parse_error("a: &a\n  b: *a\n");
+        assert!(desc.contains("Alias"));
+        assert_eq!(loc.line, 2);
+
+        let (desc, _) = parse_error("a:\n  <<: 3\n");
+        assert!(desc.contains("merge key"));
+
+        let (desc, _) = parse_error("a:\n  <<: [{ b: 1 }, 2]\n");
+        assert!(desc.contains("merge key"));
+    }
+}
@@ -0,0 +1,409 @@
+//! Reads config files from disk, including any further files they `include`
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use regex::Regex;
+
+use crate::config::{Config, ConfigError};
+use crate::config::input_formats::get_parser_for_extension;
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+
+const VERSION_FIELD: &str = "version";
+const INCLUDE_FIELD: &str = "include";
+const SCOPES_FIELD: &str = "scopes";
+const GLOBAL_MACROS_FIELD: &str = "global_macros";
+const ACTION_GROUPS_FIELD: &str = "action_groups";
+const DEFAULTS_FIELD: &str = "defaults";
+const MACRO_TEMPLATES_FIELD: &str = "macro_templates";
+
+/// Fields an included file may have; the rest of the config is up to the main file
+const INCLUDED_FILE_FIELDS: [&str; 5] = [
+    VERSION_FIELD, INCLUDE_FIELD, SCOPES_FIELD, GLOBAL_MACROS_FIELD, ACTION_GROUPS_FIELD
+];
+
+/// Reads the config file `file` into a `Config`, along with the files it includes.
+///
+/// A config file can include other files with an `include` field at the top level, holding a
+/// path or a list of paths, relative to the including file. The file name part of a path may
+/// contain the wildcards `*` and `?`, like `apps/*.yml`. Included files can have `scopes`,
+/// `global_macros`, `action_groups`, and `include` fields of their own; their macros are added
+/// after the main file's, in the order they're included. Files matched by a wildcard are included
+/// in alphabetical order, and any file that was already read is skipped.
+///
+/// Action groups from all files can be called from any of them, but each group may only be
+/// defined once. The main file's `defaults` and `macro_templates`, from version 2 on, apply to
+/// included files as well.
+///
+/// The files that were included are listed in `Config::included_files`.
+///
+/// ## Errors
+/// Besides errors in processing the config itself, returns `ConfigError` if a file can't be read
+/// or parsed, a path without wildcards doesn't exist, or an included file has fields other than
+/// the ones listed above. Errors in included files are wrapped in `ConfigError::InFile`.
+pub fn load_config(file: &Path) -> Result<Config, ConfigError> {
+    let files = read_config_files(file)?;
+    let mut config = RawConfig::Hash(files.complete(&files.main)).process()?;
+
+    // Each included file is processed on its own, so errors can be traced back to it
+    for (included_file, hash) in files.included.iter() {
+        let included_config = RawConfig::Hash(files.complete(hash))
+            .process()
+            .map_err(|err| in_file(included_file, err))?;
+
+        config.macros.extend(included_config.macros);
+        config.included_files.push(included_file.to_path_buf());
+    }
+
+    Ok(config)
+}
+
+/// The contents of a config file and the files it includes, each without its `include` field
+pub (crate) struct ConfigFiles {
+    /// Top level hash of the main config file
+    pub (crate) main: RCHash,
+
+    /// Included files with their top level hashes, in the order they're included
+    pub (crate) included: Vec<(PathBuf, RCHash)>,
+
+    /// Action groups of all files together
+    action_groups: RCHash
+}
+
+impl ConfigFiles {
+    /// Copies `hash`, the main file's or an included file's, adding what's needed to process it
+    /// on its own: the main file's version, defaults and macro templates, and the action groups of
+    /// all files.
+    pub (crate) fn complete(&self, hash: &RCHash) -> RCHash {
+        let mut complete = hash.clone();
+
+        for field in [VERSION_FIELD, DEFAULTS_FIELD, MACRO_TEMPLATES_FIELD].iter() {
+            if let Some(value) = self.main.get(&k(field)) {
+                complete.insert(k(field), value.clone());
+            }
+        }
+
+        complete.insert(k(ACTION_GROUPS_FIELD), RawConfig::Hash(self.action_groups.clone()));
+        complete
+    }
+}
+
+/// Reads the config file `file`, along with the files it includes, as described for
+/// `load_config`, without processing them.
+///
+/// ## Errors
+/// Returns `ConfigError` if a file can't be read or parsed, its top level isn't a hash, a path
+/// without wildcards doesn't exist, an included file has fields other than the ones allowed, or an
+/// action group is defined more than once.
+pub (crate) fn read_config_files(file: &Path) -> Result<ConfigFiles, ConfigError> {
+    let mut main = match read_raw_config(file)? {
+        RawConfig::Hash(hash) => hash,
+
+        raw_config => return Err(ConfigError::InvalidConfig(
+            format!("Top level of config should be Hash, found: {}", raw_config)
+        ))
+    };
+
+    let mut visited = HashSet::new();
+    visited.insert(canonical(file));
+
+    let mut included = vec![];
+    let includes = main.remove(&k(INCLUDE_FIELD));
+    read_includes(file, includes.as_ref(), &mut visited, &mut included)?;
+
+    let mut action_groups = main.get_hash(ACTION_GROUPS_FIELD).cloned().unwrap_or_default();
+
+    for (included_file, hash) in included.iter() {
+        if let Some(groups) = hash.get_hash(ACTION_GROUPS_FIELD) {
+            for (name, group) in groups {
+                if action_groups.contains_key(name) {
+                    return Err(in_file(included_file, ConfigError::InvalidConfig(format!(
+                        "Action group {} is already defined",
+                        name
+                    ))));
+                }
+
+                action_groups.insert(name.clone(), group.clone());
+            }
+        }
+    }
+
+    Ok(ConfigFiles { main, included, action_groups })
+}
+
+/// Reads and parses `file` into `RawConfig`, with a parser picked by its extension
+pub (crate) fn read_raw_config(file: &Path) -> Result<RawConfig, ConfigError> {
+    let text = fs::read_to_string(file).map_err(|err| {
+        ConfigError::ReadError(format!("Unable to read {}: {}", file.display(), err))
+    })?;
+
+    let ext = file_format(file);
+
+    let parser = get_parser_for_extension(ext).ok_or_else(|| {
+        ConfigError::ReadError(format!("Unknown config file format {}", ext))
+    })?;
+
+    parser.parse(&text)
+}
+
+/// The format of `file`, which is its extension, or YAML if it doesn't have one
+pub (crate) fn file_format(file: &Path) -> &str {
+    file.extension().and_then(|ext| ext.to_str()).unwrap_or("yml")
+}
+
+/// Reads the files `raw_includes` of `file` refers to, and the files they include in turn, adding
+/// them to `included` unless they're in `visited`.
+fn read_includes(
+    file: &Path,
+    raw_includes: Option<&RawConfig>,
+    visited: &mut HashSet<PathBuf>,
+    included: &mut Vec<(PathBuf, RCHash)>
+) -> Result<(), ConfigError> {
+    let patterns: Vec<&RawConfig> = match raw_includes {
+        None => vec![],
+        Some(RawConfig::Array(patterns)) => patterns.iter().collect(),
+        Some(pattern) => vec![pattern]
+    };
+
+    let dir = file.parent().unwrap_or_else(|| Path::new(""));
+
+    for pattern in patterns {
+        let pattern = match pattern {
+            RawConfig::String(pattern) => pattern,
+
+            _ => return Err(ConfigError::InvalidConfig(format!(
+                "'{}' should be a path or a list of paths",
+                INCLUDE_FIELD
+            )))
+        };
+
+        for included_file in find_files(dir, pattern)? {
+            if !visited.insert(canonical(&included_file)) {
+                continue;
+            }
+
+            let mut hash = match read_raw_config(&included_file) {
+                Ok(RawConfig::Hash(hash)) => hash,
+                Ok(RawConfig::Null) => RCHash::new(),
+
+                Ok(_) => return Err(in_file(&included_file, ConfigError::InvalidConfig(
+                    "Top level of an included file should be a hash".to_string()
+                ))),
+
+                Err(err) => return Err(in_file(&included_file, err))
+            };
+
+            let unknown_field = hash.keys().find(|key| {
+                !INCLUDED_FILE_FIELDS.iter().any(|field| **key == k(field))
+            });
+
+            if let Some(field) = unknown_field {
+                return Err(in_file(&included_file, ConfigError::InvalidConfig(format!(
+                    "Included files can only contain {}, but found {}",
+                    INCLUDED_FILE_FIELDS.join(", "),
+                    field
+                ))));
+            }
+
+            let includes = hash.remove(&k(INCLUDE_FIELD));
+            included.push((included_file.to_path_buf(), hash));
+
+            read_includes(&included_file, includes.as_ref(), visited, included)
+                .map_err(|err| match err {
+                    // Already attributed to the file it's in
+                    ConfigError::InFile(..) => err,
+                    err => in_file(&included_file, err)
+                })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the files matching `pattern`, relative to `dir`. Only parts of the pattern with a `*`
+/// or `?` in them are matched against directory entries; other parts must exist as they are.
+fn find_files(dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, ConfigError> {
+    let is_wildcard = |part: &str| part.contains('*') || part.contains('?');
+
+    if !is_wildcard(pattern) {
+        let file = dir.join(pattern);
+
+        return if file.is_file() {
+            Ok(vec![file])
+        } else {
+            Err(ConfigError::ReadError(format!("Included file {} not found", file.display())))
+        };
+    }
+
+    let mut paths = vec![dir.to_path_buf()];
+
+    for part in Path::new(pattern).components() {
+        let part = part.as_os_str();
+
+        let wildcard = match part.to_str().filter(|part| is_wildcard(part)) {
+            Some(wildcard) => wildcard_regex(wildcard),
+
+            None => {
+                paths = paths.into_iter().map(|path| path.join(part)).collect();
+                continue;
+            }
+        };
+
+        let mut matches = vec![];
+
+        for path in paths {
+            // Directories that can't be read have nothing to match
+            let entries = match fs::read_dir(&path) {
+                Ok(entries) => entries,
+                Err(_) => continue
+            };
+
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+
+                if name.to_str().is_some_and(|name| wildcard.is_match(name)) {
+                    matches.push(path.join(name));
+                }
+            }
+        }
+
+        matches.sort();
+        paths = matches;
+    }
+
+    paths.retain(|path| path.is_file());
+    Ok(paths)
+}
+
+/// Converts a part of a path containing wildcards into a regular expression matching it entirely
+fn wildcard_regex(wildcard: &str) -> Regex {
+    let pattern = regex::escape(wildcard).replace("\\*", ".*").replace("\\?", ".");
+
+    // Everything but the wildcards is escaped, so this is always a valid expression
+    Regex::new(&format!("^{}$", pattern)).unwrap()
+}
+
+fn canonical(file: &Path) -> PathBuf {
+    fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf())
+}
+
+fn in_file(file: &Path, err: ConfigError) -> ConfigError {
+    ConfigError::InFile(file.to_path_buf(), Box::new(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::config::loader::load_config;
+
+    /// Makes an empty directory for the files of a test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("mmpd-loader-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("apps")).unwrap();
+        dir
+    }
+
+    /// A list of one macro named `name`, calling the "greet" action group, indented by `indent`
+    fn macros_yaml(name: &str, indent: &str) -> String {
+        let lines = [
+            format!("- name: {}", name),
+            "  matching_events:".to_string(),
+            "    - type: midi".to_string(),
+            "      data:".to_string(),
+            "        message_type: note_on".to_string(),
+            "  actions:".to_string(),
+            "    - type: call".to_string(),
+            "      data: greet".to_string()
+        ];
+
+        lines.iter().map(|line| format!("\n{}{}", indent, line)).collect()
+    }
+
+    #[test]
+    fn includes_macros_and_action_groups_from_other_files() {
+        let dir = test_dir("includes");
+
+        let main_yaml = "version: 1\ninclude: [\"*.yml\", \"apps/*.yml\"]\nglobal_macros:";
+        let main_yaml = format!("{}{}", main_yaml, macros_yaml("main", "  "));
+        fs::write(dir.join("main.yml"), main_yaml).unwrap();
+
+        fs::write(
+            dir.join("groups.yml"),
+            "action_groups:\n  greet:\n    - type: enter_text\n      data: hi\n"
+        ).unwrap();
+
+        let b_yaml = format!("global_macros:{}", macros_yaml("b", "  "));
+        fs::write(dir.join("apps/b.yml"), b_yaml).unwrap();
+
+        // Including a file that was already included is fine, it's only read once
+        let a_yaml = "include: ../groups.yml\nscopes:\n  - window_class:\n      is: a\n    macros:";
+        let a_yaml = format!("{}{}", a_yaml, macros_yaml("a", "      "));
+        fs::write(dir.join("apps/a.yml"), a_yaml).unwrap();
+
+        let config = load_config(&dir.join("main.yml")).ok().unwrap();
+        let names: Vec<Option<&str>> = config.macros.iter().map(|m| m.name()).collect();
+
+        assert_eq!(names, vec![Some("main"), Some("a"), Some("b")]);
+        assert_eq!(config.included_files, vec![
+            dir.join("groups.yml"),
+            dir.join("apps").join("a.yml"),
+            dir.join("apps").join("b.yml")
+        ]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn applies_defaults_and_templates_of_the_main_file_to_included_files() {
+        let dir = test_dir("version2");
+
+        fs::write(dir.join("main.yml"), "
+version: 2
+include: apps/*.yml
+defaults: { channel: 3 }
+macro_templates:
+  pad:
+    name: pad
+    matching_events:
+      - data: { message_type: note_on }
+    actions: [{ type: control, data: exit }]
+").unwrap();
+
+        fs::write(dir.join("apps/pads.yml"), "global_macros:\n  - extends: pad\n").unwrap();
+
+        let config = load_config(&dir.join("main.yml")).ok().unwrap();
+        let names: Vec<Option<&str>> = config.macros.iter().map(|m| m.name()).collect();
+
+        assert_eq!(names, vec![Some("pad")]);
+        assert!(format!("{:?}", config.macros).contains("channel_match: Some(Val(3))"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn says_which_included_file_an_error_is_in() {
+        let dir = test_dir("errors");
+
+        fs::write(dir.join("main.yml"), "version: 1\ninclude: apps/broken.yml\n").unwrap();
+        let broken_yaml = format!("global_macros:{}", macros_yaml("x", "  "));
+        fs::write(dir.join("apps/broken.yml"), broken_yaml).unwrap();
+
+        let description = load_config(&dir.join("main.yml")).err().unwrap().description();
+        assert!(description.contains("broken.yml"));
+        assert!(description.contains("greet"));
+
+        fs::write(dir.join("main.yml"), "version: 1\ninclude: apps/missing.yml\n").unwrap();
+        assert!(load_config(&dir.join("main.yml")).is_err());
+
+        fs::write(dir.join("main.yml"), "version: 1\ninclude: apps/other.yml\n").unwrap();
+        fs::write(dir.join("apps/other.yml"), "midi_device: keys\n").unwrap();
+
+        let description = load_config(&dir.join("main.yml")).err().unwrap().description();
+        assert!(description.contains("other.yml"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+use crate::config::ConfigError;
+use crate::config::raw_config::RawConfig;
+use crate::config::output_formats::yaml_config_serializer::YamlConfigOutput;
+use crate::config::output_formats::toml_config_serializer::TomlConfigOutput;
+use crate::config::output_formats::json_config_serializer::JsonConfigOutput;
+
+pub (crate) mod yaml_config_serializer;
+pub (crate) mod toml_config_serializer;
+pub (crate) mod json_config_serializer;
+
+/// The counterpart of `ConfigInputParser`: serializers that write `RawConfig` back out as text in
+/// a config file format, such that parsing the text with that format's parser gives the same
+/// `RawConfig` again.
+pub trait ConfigOutputSerializer {
+    /// Attempts serializing `raw_config` into the contents of a config file. If it has values that
+    /// can't be expressed in this format, returns a `ConfigError`.
+    fn serialize(&self, raw_config: &RawConfig) -> Result<String, ConfigError>;
+}
+
+pub fn get_serializer_for_extension(ext: &str) -> Option<Box<dyn ConfigOutputSerializer>> {
+    let ext = ext.to_lowercase();
+
+    match ext.as_ref() {
+        "yml" | "yaml" => Some(YamlConfigOutput::new()),
+        "toml" => Some(TomlConfigOutput::new()),
+        "json" => Some(JsonConfigOutput::new()),
+        _ => None
+    }
+}
+
+/// Converts a hash key to a string, for formats that only allow string keys. Keys are strings in
+/// practice, but integer and boolean ones are converted as they'd be written in YAML.
+pub (crate) fn string_key(key: &RawConfig) -> Result<String, ConfigError> {
+    match key {
+        RawConfig::String(key) => Ok(key.to_string()),
+        RawConfig::Integer(key) => Ok(key.to_string()),
+        RawConfig::Bool(key) => Ok(key.to_string()),
+
+        key => Err(ConfigError::InvalidConfig(
+            format!("Hash keys should be strings, found: {}", key)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::input_formats::get_parser_for_extension;
+    use crate::config::output_formats::get_serializer_for_extension;
+    use crate::config::raw_config::RawConfig;
+
+    const YAML_CONFIG: &str = r#"
+version: 1
+midi_device:
+  contains: KeyStep
+global_macros:
+  - name: "Volume: master"
+    matching_events:
+      - type: midi
+        data:
+          message_type: control_change
+          key: [12, 14, { min: 32, max: 44 }]
+          value: ~
+    actions:
+      - type: shell
+        data:
+          command: amixer
+          args: [set, Master, "50", 7]
+      - type: switch
+        data:
+          value: "%event.value%"
+          cases:
+            - value: 0
+              actions:
+                - type: enter_text
+                  data: "true"
+            - value: { min: 1 }
+              actions: []
+      - type: key_sequence
+        data: ""
+scopes:
+  - window_class:
+      is: "[terminal]"
+    macros: []
+"#;
+
+    fn parse(format: &str, text: &str) -> RawConfig {
+        get_parser_for_extension(format).unwrap().parse(text).ok().unwrap()
+    }
+
+    fn serialize(format: &str, raw_config: &RawConfig) -> String {
+        get_serializer_for_extension(format).unwrap().serialize(raw_config).ok().unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_every_format() {
+        let raw_config = parse("yml", YAML_CONFIG);
+
+        assert_eq!(parse("yml", &serialize("yml", &raw_config)), raw_config);
+        assert_eq!(parse("json", &serialize("json", &raw_config)), raw_config);
+
+        // TOML has no null, so the field that is null is left out instead
+        let toml = serialize("toml", &raw_config);
+        let without_null = parse("yml", &YAML_CONFIG.replace("          value: ~\n", ""));
+        assert_eq!(parse("toml", &toml), without_null);
+    }
+
+    #[test]
+    fn returns_an_error_for_what_toml_cannot_express() {
+        let serializer = get_serializer_for_extension("toml").unwrap();
+
+        let list = RawConfig::Array(vec![]);
+        assert!(serializer.serialize(&list).is_err());
+
+        let raw_config = parse("yml", "version: 1\nlist: [1, ~, 2]\n");
+        let description = serializer.serialize(&raw_config).err().unwrap().description();
+        assert!(description.contains("list[1]"));
+    }
+
+    #[test]
+    fn returns_no_serializer_for_unknown_extensions() {
+        assert!(get_serializer_for_extension("YAML").is_some());
+        assert!(get_serializer_for_extension("ini").is_none());
+    }
+}
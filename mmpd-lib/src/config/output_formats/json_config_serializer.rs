@@ -0,0 +1,52 @@
+//! Serializes a `RawConfig` tree into JSON text
+
+use serde_json::{Map, Value};
+
+use crate::config::ConfigError;
+use crate::config::output_formats::{ConfigOutputSerializer, string_key};
+use crate::config::raw_config::RawConfig;
+
+/// Config output serializer, writing `RawConfig` as JSON.
+pub struct JsonConfigOutput { }
+
+impl JsonConfigOutput {
+    /// Creates a new serializer instance
+    pub fn new() -> Box<dyn ConfigOutputSerializer> {
+        Box::new(JsonConfigOutput {})
+    }
+}
+
+impl ConfigOutputSerializer for JsonConfigOutput {
+    /// Serializes `raw_config` into JSON, indented by two spaces. Returns a `ConfigError` if a hash
+    /// has a list or hash as a key, since JSON keys can only be strings.
+    fn serialize(&self, raw_config: &RawConfig) -> Result<String, ConfigError> {
+        let json = serde_json::to_string_pretty(&raw_config_to_json(raw_config)?).map_err(|err| {
+            ConfigError::InvalidConfig(format!("Unable to write JSON: {}", err))
+        })?;
+
+        Ok(format!("{}\n", json))
+    }
+}
+
+fn raw_config_to_json(raw_config: &RawConfig) -> Result<Value, ConfigError> {
+    Ok(match raw_config {
+        RawConfig::Null => Value::Null,
+        RawConfig::Integer(i) => Value::from(*i),
+        RawConfig::String(s) => Value::String(s.to_string()),
+        RawConfig::Bool(b) => Value::Bool(*b),
+
+        RawConfig::Array(arr) => Value::Array(
+            arr
+                .iter()
+                .map(raw_config_to_json)
+                .collect::<Result<Vec<Value>, ConfigError>>()?
+        ),
+
+        RawConfig::Hash(hash) => Value::Object(
+            hash
+                .iter()
+                .map(|(key, value)| Ok((string_key(key)?, raw_config_to_json(value)?)))
+                .collect::<Result<Map<String, Value>, ConfigError>>()?
+        )
+    })
+}
@@ -0,0 +1,179 @@
+//! Serializes a `RawConfig` tree into TOML text
+
+use toml::Value;
+
+use crate::config::ConfigError;
+use crate::config::output_formats::{ConfigOutputSerializer, string_key};
+use crate::config::raw_config::{RawConfig, RCHash};
+
+/// Config output serializer, writing `RawConfig` as TOML.
+pub struct TomlConfigOutput { }
+
+impl TomlConfigOutput {
+    /// Creates a new serializer instance
+    pub fn new() -> Box<dyn ConfigOutputSerializer> {
+        Box::new(TomlConfigOutput {})
+    }
+}
+
+impl ConfigOutputSerializer for TomlConfigOutput {
+    /// Serializes `raw_config` into TOML. Lists of hashes, like macros and actions, are written as
+    /// arrays of tables, and hashes containing further hashes as tables. Any other hashes, like
+    /// string matchers, are written as inline tables.
+    ///
+    /// TOML has no null value, so hash fields that are null are left out, which reads the same.
+    /// Fields holding a table are written after the other fields of the same hash, since TOML
+    /// doesn't allow it the other way around.
+    ///
+    /// ## Errors
+    /// Returns a `ConfigError` if the top level of `raw_config` isn't a hash, or a list contains
+    /// null.
+    fn serialize(&self, raw_config: &RawConfig) -> Result<String, ConfigError> {
+        let hash = match raw_config {
+            RawConfig::Hash(hash) => hash,
+
+            _ => return Err(ConfigError::InvalidConfig(
+                format!("Top level of a TOML file should be Hash, found: {}", raw_config)
+            ))
+        };
+
+        let mut text = String::new();
+        write_table(&mut text, hash, &[])?;
+
+        Ok(text.trim_start().to_string())
+    }
+}
+
+/// Whether `raw_config` is written as a table of its own, rather than inline: a hash holding any
+/// further hashes, or lists of them
+fn is_table(raw_config: &RawConfig) -> bool {
+    match raw_config {
+        RawConfig::Hash(hash) => hash.values().any(|value| match value {
+            RawConfig::Hash(_) => true,
+            RawConfig::Array(arr) => arr.iter().any(|item| matches!(item, RawConfig::Hash(_))),
+            _ => false
+        }),
+
+        _ => false
+    }
+}
+
+/// Whether `raw_config` is written as an array of tables: a non-empty list of only hashes
+fn is_table_array(raw_config: &RawConfig) -> bool {
+    match raw_config {
+        RawConfig::Array(arr) => {
+            !arr.is_empty() && arr.iter().all(|item| matches!(item, RawConfig::Hash(_)))
+        }
+
+        _ => false
+    }
+}
+
+/// Writes the fields of `hash`, the table at `path`, followed by the tables within it
+fn write_table(text: &mut String, hash: &RCHash, path: &[String]) -> Result<(), ConfigError> {
+    let mut tables = vec![];
+
+    for (key, value) in hash {
+        let key = string_key(key)?;
+
+        if is_table(value) || is_table_array(value) {
+            tables.push((key, value));
+            continue;
+        }
+
+        let field_path = [path, &[key.to_string()]].concat();
+
+        if let Some(value) = inline_value(value, &field_path)? {
+            text.push_str(&format!("{} = {}\n", toml_key(&key), value));
+        }
+    }
+
+    for (key, value) in tables {
+        let table_path = [path, &[key]].concat();
+        let header = table_path.iter().map(|key| toml_key(key)).collect::<Vec<String>>().join(".");
+
+        match value {
+            RawConfig::Hash(hash) => {
+                text.push_str(&format!("\n[{}]\n", header));
+                write_table(text, hash, &table_path)?;
+            }
+
+            RawConfig::Array(arr) => {
+                for item in arr {
+                    if let RawConfig::Hash(hash) = item {
+                        text.push_str(&format!("\n[[{}]]\n", header));
+                        write_table(text, hash, &table_path)?;
+                    }
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes `raw_config`, found at `path`, as an inline value. Returns `None` for null, which is
+/// left out of hashes, and an error in lists.
+fn inline_value(raw_config: &RawConfig, path: &[String]) -> Result<Option<String>, ConfigError> {
+    Ok(Some(match raw_config {
+        RawConfig::Null => return Ok(None),
+        RawConfig::Integer(i) => Value::Integer(*i).to_string(),
+        RawConfig::String(s) => Value::String(s.to_string()).to_string(),
+        RawConfig::Bool(b) => Value::Boolean(*b).to_string(),
+
+        RawConfig::Array(arr) => {
+            let mut items = vec![];
+
+            for (index, item) in arr.iter().enumerate() {
+                let item_path = || {
+                    let (last, parents) = path.split_last().unwrap();
+                    [parents, &[format!("{}[{}]", last, index)]].concat()
+                };
+
+                items.push(inline_value(item, &item_path())?.ok_or_else(|| {
+                    ConfigError::InPart(item_path().join("."), None, Box::new(
+                        ConfigError::InvalidConfig(
+                            "Lists can't contain null values in TOML".to_string()
+                        )
+                    ))
+                })?);
+            }
+
+            format!("[{}]", items.join(", "))
+        }
+
+        RawConfig::Hash(hash) => {
+            let mut fields = vec![];
+
+            for (key, value) in hash {
+                let key = string_key(key)?;
+                let field_path = [path, &[key.to_string()]].concat();
+
+                if let Some(value) = inline_value(value, &field_path)? {
+                    fields.push(format!("{} = {}", toml_key(&key), value));
+                }
+            }
+
+            if fields.is_empty() {
+                "{}".to_string()
+            } else {
+                format!("{{ {} }}", fields.join(", "))
+            }
+        }
+    }))
+}
+
+/// Writes `key` as a bare key if it can be, or quoted otherwise
+fn toml_key(key: &str) -> String {
+    let is_bare = !key.is_empty() && key.chars().all(|c| {
+        c.is_ascii_alphanumeric() || c == '_' || c == '-'
+    });
+
+    if is_bare {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
@@ -0,0 +1,52 @@
+//! Serializes a `RawConfig` tree into YAML text
+
+use yaml_rust::{Yaml, YamlEmitter};
+use yaml_rust::yaml::Hash;
+
+use crate::config::ConfigError;
+use crate::config::output_formats::ConfigOutputSerializer;
+use crate::config::raw_config::RawConfig;
+
+/// Config output serializer, writing `RawConfig` as YAML.
+pub struct YamlConfigOutput { }
+
+impl YamlConfigOutput {
+    /// Creates a new serializer instance
+    pub fn new() -> Box<dyn ConfigOutputSerializer> {
+        Box::new(YamlConfigOutput {})
+    }
+}
+
+impl ConfigOutputSerializer for YamlConfigOutput {
+    /// Serializes `raw_config` into YAML, in block style, with strings only quoted where they'd
+    /// otherwise be read as something else.
+    fn serialize(&self, raw_config: &RawConfig) -> Result<String, ConfigError> {
+        let mut text = String::new();
+
+        YamlEmitter::new(&mut text).dump(&raw_config_to_yaml(raw_config)).map_err(|err| {
+            ConfigError::InvalidConfig(format!("Unable to write YAML: {:?}", err))
+        })?;
+
+        // The emitter starts with a document start marker, which a config file doesn't need
+        let text = text.strip_prefix("---\n").unwrap_or(&text);
+
+        Ok(format!("{}\n", text))
+    }
+}
+
+fn raw_config_to_yaml(raw_config: &RawConfig) -> Yaml {
+    match raw_config {
+        RawConfig::Null => Yaml::Null,
+        RawConfig::Integer(i) => Yaml::Integer(*i),
+        RawConfig::String(s) => Yaml::String(s.to_string()),
+        RawConfig::Bool(b) => Yaml::Boolean(*b),
+        RawConfig::Array(arr) => Yaml::Array(arr.iter().map(raw_config_to_yaml).collect()),
+
+        RawConfig::Hash(hash) => Yaml::Hash(
+            hash
+                .iter()
+                .map(|(key, value)| (raw_config_to_yaml(key), raw_config_to_yaml(value)))
+                .collect::<Hash>()
+        )
+    }
+}
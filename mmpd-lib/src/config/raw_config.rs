@@ -1,19 +1,114 @@
 //! Intermediary config value format and tools to parse `RawConfig` into full formed `Config`
 
+use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
 
-use linked_hash_map::LinkedHashMap;
+use linked_hash_map::{self, LinkedHashMap};
 
-use crate::config::{Config, ConfigError, versions};
+use crate::config::{Config, ConfigError, Loc, versions};
 
-/// The type `LinkedHashMap<RawConfig, RawConfig>` occurs a lot throughout the parsing code, so the
-/// type alias makes it less clunky to work with.
-pub (crate) type RCHash = LinkedHashMap<RawConfig, RawConfig>;
+/// A `LinkedHashMap<RawConfig, RawConfig>`, which occurs a lot throughout the parsing code, along
+/// with where it and its keys start in the config file it was parsed from, if known. It derefs to
+/// the map, so it can be worked with as one.
+///
+/// The locations are only there to point errors at the right place in the file; they don't count
+/// towards comparing or hashing, so parsed hashes still equal ones built in code.
+#[derive(Debug, Clone, Default)]
+pub struct RCHash {
+    entries: LinkedHashMap<RawConfig, RawConfig>,
+    loc: Option<Loc>,
+    key_locs: HashMap<RawConfig, Loc>
+}
+
+impl RCHash {
+    /// Creates an empty hash without a location
+    pub fn new() -> RCHash {
+        RCHash::default()
+    }
+
+    /// Creates an empty hash that starts at `loc` in the config file
+    pub fn at(loc: Option<Loc>) -> RCHash {
+        RCHash { entries: LinkedHashMap::new(), loc, key_locs: HashMap::new() }
+    }
+
+    /// Where this hash starts in the config file, if known
+    pub fn loc(&self) -> Option<Loc> {
+        self.loc
+    }
+
+    pub (crate) fn set_loc(&mut self, loc: Option<Loc>) {
+        self.loc = loc;
+    }
+
+    /// Where `key` is written in the config file, if known
+    pub fn key_loc(&self, key: &RawConfig) -> Option<Loc> {
+        self.key_locs.get(key).copied()
+    }
+
+    pub (crate) fn set_key_loc(&mut self, key: RawConfig, loc: Loc) {
+        self.key_locs.insert(key, loc);
+    }
+}
+
+impl Deref for RCHash {
+    type Target = LinkedHashMap<RawConfig, RawConfig>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl DerefMut for RCHash {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.entries
+    }
+}
+
+impl PartialEq for RCHash {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl Eq for RCHash {}
+
+impl Hash for RCHash {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.entries.hash(state)
+    }
+}
+
+impl FromIterator<(RawConfig, RawConfig)> for RCHash {
+    fn from_iter<I: IntoIterator<Item = (RawConfig, RawConfig)>>(iter: I) -> Self {
+        RCHash { entries: iter.into_iter().collect(), ..RCHash::default() }
+    }
+}
+
+impl IntoIterator for RCHash {
+    type Item = (RawConfig, RawConfig);
+    type IntoIter = linked_hash_map::IntoIter<RawConfig, RawConfig>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a RCHash {
+    type Item = (&'a RawConfig, &'a RawConfig);
+    type IntoIter = linked_hash_map::Iter<'a, RawConfig, RawConfig>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
 
 /// Intermediary type containing raw values from config files. File format parses such as
 /// YamlConfigParser parse the configuration file into this format, and from there it is
 /// further parsed into Config according to the specified configuration structure.
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum RawConfig {
     /// Represents the lack of a value, or some kind of value that isn't supported
     Null,
@@ -125,6 +220,14 @@ impl AccessHelpers for RCHash {
 }
 
 impl RawConfig {
+    /// Where this value starts in the config file, if known. Only hashes keep track of this.
+    pub fn loc(&self) -> Option<Loc> {
+        match self {
+            RawConfig::Hash(hash) => hash.loc(),
+            _ => None
+        }
+    }
+
     /// Processes the raw config into a `Config` instance.
     /// This method relies on the this value being a `RawConfig::Hash`, otherwise a `ConfigError` is
     /// returned. The `RCHash` contained in it must contain an integer "version" field, as this will
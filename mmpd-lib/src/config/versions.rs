@@ -1,8 +1,10 @@
 mod version1;
+pub (crate) mod version2;
 
-use crate::config::{Config, ConfigError};
+use crate::config::{Config, ConfigError, Loc};
 use crate::config::raw_config::RCHash;
 use crate::config::versions::version1::Version1Processor;
+use crate::config::versions::version2::Version2Processor;
 
 /// A `ConfigVersionProcessor` uses its own internal rules to parse an `RCHash` instance into an
 /// instance of `Config`.
@@ -10,6 +12,35 @@ use crate::config::versions::version1::Version1Processor;
 /// Returns `Err(ConfigError)` if there is any error in parsing the data.
 pub trait ConfigVersionProcessor {
     fn process(&self, raw_config: RCHash) -> Result<Config, ConfigError>;
+
+    /// Lists any fields in `raw_config` that aren't part of this version of the format, and would
+    /// be ignored by `process`.
+    fn find_unknown_fields(&self, raw_config: &RCHash) -> Vec<UnknownField>;
+
+    /// Rewrites every part of `raw_config` that has a shorthand form into `form`, without
+    /// changing what it means. Invalid parts are left alone, for `process` to report.
+    fn canonicalize(&self, raw_config: &mut RCHash, form: Form);
+}
+
+/// How to write parts of a config that can be written in a shorter form, such as an `enter_text`
+/// action's `data` being just the text, rather than a hash with a `text` field
+/// A field that isn't part of the config format, found by `find_unknown_fields`
+#[derive(Debug, PartialEq)]
+pub struct UnknownField {
+    /// Path to the field, like `scopes[0].window_class.start_with`
+    pub path: String,
+
+    /// Where the field's key is written in the config file, if known
+    pub loc: Option<Loc>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Form {
+    /// Written out in full, as hashes with named fields
+    Explicit,
+
+    /// Written in the shorthand form wherever that means the same
+    Minimal
 }
 
 /// Given a config format version number, returns a config processor implementation to parse
@@ -18,6 +49,7 @@ pub trait ConfigVersionProcessor {
 pub fn get_processor(version: i64) -> Option<Box<dyn ConfigVersionProcessor>> {
     match version {
         1 => Some(Version1Processor::new()),
+        2 => Some(Version2Processor::new()),
         _ => None
     }
 }
@@ -4,13 +4,28 @@ mod macros;
 mod event_matchers;
 mod precondition;
 mod actions;
+mod templates;
+mod midi_device_options;
+mod midi_device;
+mod press_timing;
+mod state_file;
+mod value_map;
+pub (crate) mod action_groups;
+pub (crate) mod known_fields;
+pub (crate) mod canonical;
 
-use crate::config::versions::ConfigVersionProcessor;
-use crate::config::raw_config::{RCHash, AccessHelpers, RawConfig};
+use crate::config::versions::{ConfigVersionProcessor, Form, UnknownField};
+use crate::config::raw_config::{RCHash, AccessHelpers, RawConfig, k};
 use crate::config::{ConfigError, Config};
 use crate::config::versions::version1::scope::build_scope;
 use crate::config::versions::version1::macros::build_scope_macros;
-use crate::config::versions::version1::primitive_matchers::build_string_matcher;
+use crate::config::versions::version1::midi_device_options::build_midi_device_options;
+use crate::config::versions::version1::midi_device::build_midi_devices;
+use crate::config::versions::version1::press_timing::build_press_timing;
+use crate::config::versions::version1::state_file::build_state_file_options;
+use crate::config::versions::version1::action_groups::ActionGroups;
+use crate::state::PressTiming;
+use crate::macros::executor::DEFAULT_MAX_WORKERS;
 
 pub (crate) struct Version1Processor {
     // Ideas:
@@ -34,7 +49,18 @@ impl ConfigVersionProcessor for Version1Processor {
     ///
     /// ## Notes on the version 1 format
     ///
-    /// At the top level, there are 3 possible expected fields:
+    /// At the top level, there are 10 possible expected fields:
+    /// - `midi_device`: Which MIDI device(s) to listen to, see `build_midi_devices`.
+    /// - `watch`: Optional boolean, whether to reload the config file automatically when it
+    ///   changes.
+    /// - `midi_device_options`: Per-device message processing, see `build_midi_device_options`.
+    /// - `press_timing`: Thresholds for tap and long press events, see `build_press_timing`.
+    /// - `state_file`: Whether and where to keep state across restarts, see
+    ///   `build_state_file_options`.
+    /// - `max_workers`: Optional number, 1 or more, of actions that take a while, like running a
+    ///   program, that can be performed at once. Defaults to `DEFAULT_MAX_WORKERS`.
+    /// - `action_groups`: Named lists of actions that `call` actions in macros can run, see
+    ///   `ActionGroups`.
     /// - `scopes`:
     ///     Contains window class/name matching, as well as a list of macros that apply to that
     ///     scope. Note that in the parsed Config struct, this is organised differently; there is
@@ -53,22 +79,107 @@ impl ConfigVersionProcessor for Version1Processor {
         const SCOPES_FIELD: &str = "scopes";
         const MACROS_FIELD: &str = "macros";
         const GLOBAL_MACROS_FIELD: &str = "global_macros";
+        const MIDI_DEVICE_OPTIONS_FIELD: &str = "midi_device_options";
+        const WATCH_FIELD: &str = "watch";
+        const PRESS_TIMING_FIELD: &str = "press_timing";
+        const STATE_FILE_FIELD: &str = "state_file";
+        const MAX_WORKERS_FIELD: &str = "max_workers";
+        const ACTION_GROUPS_FIELD: &str = "action_groups";
 
         let mut config = Config {
-            midi_device_matcher: None,
-            macros: vec![]
+            midi_devices: vec![],
+            macros: vec![],
+            midi_device_options: vec![],
+            press_timing: PressTiming::default(),
+            watch: false,
+            state_file: None,
+            max_workers: DEFAULT_MAX_WORKERS,
+            included_files: vec![]
         };
 
-        if let Some(raw_midi_device_matcher) = raw_config.get_hash(MIDI_DEVICE_FIELD) {
-            let midi_matcher = build_string_matcher(Some(raw_midi_device_matcher))?;
+        match raw_config.get(&k(WATCH_FIELD)) {
+            None => {}
+            Some(RawConfig::Bool(watch)) => config.watch = *watch,
 
-            config.midi_device_matcher = midi_matcher;
+            Some(_) => {
+                return Err(ConfigError::InvalidConfig(
+                    format!("'{}' should be true or false", WATCH_FIELD)
+                ));
+            }
+        }
+
+        if let Some(raw_midi_devices) = raw_config.get(&k(MIDI_DEVICE_FIELD)) {
+            config.midi_devices = build_midi_devices(raw_midi_devices).map_err(|err| {
+                err.within(MIDI_DEVICE_FIELD, raw_midi_devices.loc())
+            })?;
+        }
+
+        if let Some(raw_device_options) = raw_config.get_array(MIDI_DEVICE_OPTIONS_FIELD) {
+            for (index, raw_options) in raw_device_options.iter().enumerate() {
+                if let RawConfig::Hash(raw_options) = raw_options {
+                    config.midi_device_options.push(
+                        build_midi_device_options(raw_options).map_err(|err| {
+                            err.within_item(MIDI_DEVICE_OPTIONS_FIELD, index, raw_options.loc())
+                        })?
+                    );
+                }
+            }
+        }
+
+        match raw_config.get(&k(PRESS_TIMING_FIELD)) {
+            None => {}
+
+            Some(RawConfig::Hash(raw_timing)) => {
+                config.press_timing = build_press_timing(raw_timing).map_err(|err| {
+                    err.within(PRESS_TIMING_FIELD, raw_timing.loc())
+                })?;
+            }
+
+            Some(_) => {
+                return Err(ConfigError::InvalidConfig(
+                    format!("'{}' should be a hash", PRESS_TIMING_FIELD)
+                ));
+            }
+        }
+
+        if let Some(raw_options) = raw_config.get(&k(STATE_FILE_FIELD)) {
+            config.state_file = build_state_file_options(raw_options).map_err(|err| {
+                err.within(STATE_FILE_FIELD, raw_options.loc())
+            })?;
+        }
+
+        match raw_config.get(&k(MAX_WORKERS_FIELD)) {
+            None => {}
+            Some(RawConfig::Integer(max)) if *max >= 1 => config.max_workers = *max as usize,
+
+            Some(_) => {
+                return Err(ConfigError::InvalidConfig(
+                    format!("'{}' should be a number, 1 or more", MAX_WORKERS_FIELD)
+                ));
+            }
         }
 
+        let groups = match raw_config.get(&k(ACTION_GROUPS_FIELD)) {
+            None => ActionGroups::new(None),
+            Some(RawConfig::Hash(raw_groups)) => ActionGroups::new(Some(raw_groups)),
+
+            Some(_) => {
+                return Err(ConfigError::InvalidConfig(
+                    format!("'{}' should be a hash", ACTION_GROUPS_FIELD)
+                ));
+            }
+        };
+
+        groups.check()?;
+
         if let Some(raw_scopes) = raw_config.get_array(SCOPES_FIELD) {
-            for raw_scope in raw_scopes {
+            for (index, raw_scope) in raw_scopes.iter().enumerate() {
                 if let RawConfig::Hash(raw_scope) = raw_scope {
-                    let scope = build_scope(raw_scope)?;
+                    let in_scope = |err: ConfigError| {
+                        err.within_item(SCOPES_FIELD, index, raw_scope.loc())
+                    };
+
+                    let scope = build_scope(raw_scope).map_err(in_scope)?;
                     if let None = scope { continue; }
 
                     let raw_macros = raw_scope.get_array(MACROS_FIELD);
@@ -77,17 +188,29 @@ impl ConfigVersionProcessor for Version1Processor {
                     config.macros.extend(
                         build_scope_macros(
                             raw_macros.unwrap(),
-                            Some(scope.unwrap())
-                        )?
+                            MACROS_FIELD,
+                            Some(scope.unwrap()),
+                            &groups
+                        ).map_err(in_scope)?
                     );
                 }
             }
         }
 
         if let Some(raw_macros) = raw_config.get_array(GLOBAL_MACROS_FIELD) {
-            config.macros.extend(build_scope_macros(raw_macros, None)?);
+            config.macros.extend(
+                build_scope_macros(raw_macros, GLOBAL_MACROS_FIELD, None, &groups)?
+            );
         }
 
         Ok(config)
     }
+
+    fn find_unknown_fields(&self, raw_config: &RCHash) -> Vec<UnknownField> {
+        known_fields::find_unknown_fields(raw_config)
+    }
+
+    fn canonicalize(&self, raw_config: &mut RCHash, form: Form) {
+        canonical::canonicalize(raw_config, form)
+    }
 }
@@ -0,0 +1,615 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+use crate::config::ConfigError;
+use crate::config::versions::version1::actions::build_action_list;
+use crate::macros::actions::Action;
+
+/// Start of a parameter reference in the strings of an action group, as in `%params.url%`
+const PARAMS_PREFIX: &str = "params.";
+
+/// Character marking the start and end of a parameter reference, same as for template variables
+const DELIMITER: char = '%';
+
+const ACTION_GROUPS_FIELD: &str = "action_groups";
+const PARAMS_FIELD: &str = "params";
+const ACTIONS_FIELD: &str = "actions";
+
+/// Fields of actions whose strings are parsed as templates (see `build_template`), by action type
+const TEMPLATE_FIELDS: [(&str, &str); 7] = [
+    ("key_sequence", "sequence"),
+    ("key_sequence", "count"),
+    ("enter_text", "text"),
+    ("enter_text", "count"),
+    ("shell", "args"),
+    ("shell", "env_vars"),
+    ("switch", "value")
+];
+
+/// Action types whose `data` can be just a string that's parsed as a template
+const TEMPLATE_DATA_ACTIONS: [&str; 2] = ["key_sequence", "enter_text"];
+
+/// Where a value within an action group is, which decides whether its strings are templates
+#[derive(Clone, Copy)]
+enum Within<'t> {
+    /// The `data` of an action of this type
+    ActionData(&'t str),
+
+    /// Anywhere else, along with whether strings there are parsed as templates
+    Value { template: bool }
+}
+
+/// The named action groups from the `action_groups` section of a config file, which `call` actions
+/// refer to.
+///
+/// Action groups are built where they're called, since the parameters they're called with are
+/// filled in before building their actions. Each is built once for the same parameters, however
+/// many places call it that way. The calls between groups are checked up front by `check`, and
+/// to catch a group called by a parameter's name that ends up calling itself, this also keeps
+/// track of which groups are being built.
+pub (crate) struct ActionGroups<'a> {
+    raw_groups: Option<&'a RCHash>,
+
+    /// Names of the groups being built, from the outermost call inwards
+    calling: Vec<String>,
+
+    /// Groups built so far, shared with the `ActionGroups` of nested calls
+    built: Rc<RefCell<Vec<BuiltGroup>>>
+}
+
+/// The actions of the group `name`, as built when called with `params`
+struct BuiltGroup {
+    name: String,
+    params: Option<RCHash>,
+    actions: Vec<Action>
+}
+
+impl<'a> ActionGroups<'a> {
+    /// Sets up action groups from the `action_groups` section, `raw_groups`. Without one, any
+    /// `call` action fails to build.
+    pub (crate) fn new(raw_groups: Option<&'a RCHash>) -> ActionGroups<'a> {
+        ActionGroups { raw_groups, calling: vec![], built: Rc::new(RefCell::new(vec![])) }
+    }
+
+    /// Checks the calls between all groups, whether or not they're called themselves: each
+    /// `call` within a group should name a group that exists, and no group should end up calling
+    /// itself. Calls that take the group's name from a parameter are left to `build`.
+    ///
+    /// ## Errors
+    /// Returns a `ConfigError` within `action_groups.NAME` for the first group that breaks either
+    /// rule, or that is neither a list nor a hash with an `actions` list.
+    pub (crate) fn check(&self) -> Result<(), ConfigError> {
+        let raw_groups = match self.raw_groups {
+            Some(raw_groups) => raw_groups,
+            None => return Ok(())
+        };
+
+        let mut calls: Vec<(String, Vec<String>)> = vec![];
+
+        for (key, raw_group) in raw_groups {
+            let name = match key {
+                RawConfig::String(name) => name,
+                _ => continue
+            };
+
+            let in_group = |err: ConfigError| {
+                err.within(&format!("{}.{}", ACTION_GROUPS_FIELD, name), None)
+            };
+
+            let (raw_actions, _) = split_group(name, raw_group).map_err(in_group)?;
+
+            let mut called = vec![];
+            find_calls(raw_actions, &mut called);
+
+            let missing = called.iter().find(|called| !raw_groups.contains_key(&k(called)));
+
+            if let Some(missing) = missing {
+                return Err(in_group(ConfigError::InvalidConfig(
+                    format!("Action call: there is no action group named '{}'", missing)
+                )));
+            }
+
+            calls.push((name.clone(), called));
+        }
+
+        let mut checked = vec![];
+
+        for (name, _) in &calls {
+            check_cycles(name, &calls, &mut vec![], &mut checked).map_err(|err| {
+                err.within(&format!("{}.{}", ACTION_GROUPS_FIELD, name), None)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Builds the actions of the group named `name`, with parameters filled in from `params`.
+    ///
+    /// An action group is either a list of actions, or a hash structured as follows:
+    ///
+    /// ```yaml
+    /// params:
+    ///     url: "https://example.com" # Default value for the "url" parameter
+    /// actions:
+    ///     - # (one or more actions)
+    /// ```
+    ///
+    /// `params` is optional, and holds default values of parameters that calls may leave out.
+    ///
+    /// `actions` is required, and is a list of actions in the same structure as a macro's (see
+    /// `build_action`). Within its strings, `%params.NAME%` is replaced with the value of
+    /// parameter `NAME`. A string consisting of nothing but a parameter reference is replaced by
+    /// the value as is, so parameters can be used for numbers as well. Values are always taken
+    /// literally: in strings that are parsed as templates, any `%` in them is escaped.
+    ///
+    /// ## Errors
+    /// This function will return `ConfigError` in any of these conditions:
+    ///
+    /// - There is no group named `name`
+    /// - The group is already being built, meaning it ends up calling itself (see `check`)
+    /// - The group is neither a list nor a hash with an `actions` list
+    /// - A parameter is referenced that has no value, or a parameter used within a string has a
+    ///   value other than a string, integer or boolean
+    /// - Down the stream, one of the group's actions fails to be built. Its error is placed within
+    ///   `action_groups.NAME`, without a location, since the group may be from an included file.
+    pub (crate) fn build(
+        &self,
+        name: &str,
+        params: Option<&RCHash>
+    ) -> Result<Vec<Action>, ConfigError> {
+        let is_built = |built: &&BuiltGroup| built.name == name && built.params.as_ref() == params;
+
+        if let Some(built) = self.built.borrow().iter().find(is_built) {
+            return Ok(built.actions.clone());
+        }
+
+        let raw_group = self.raw_groups
+            .and_then(|raw_groups| raw_groups.get(&k(name)))
+            .ok_or_else(|| ConfigError::InvalidConfig(
+                format!("Action call: there is no action group named '{}'", name)
+            ))?;
+
+        if self.calling.iter().any(|calling| calling == name) {
+            return Err(ConfigError::InvalidConfig(format!(
+                "Action group '{}' calls itself: {} -> {}",
+                name, self.calling.join(" -> "), name
+            )));
+        }
+
+        let (raw_actions, defaults) = split_group(name, raw_group)?;
+
+        let param = |param_name: &str| {
+            params
+                .and_then(|params| params.get(&k(param_name)))
+                .or_else(|| defaults.and_then(|defaults| defaults.get(&k(param_name))))
+                .ok_or_else(|| ConfigError::InvalidConfig(format!(
+                    "Action group '{}': no value for parameter '{}'",
+                    name, param_name
+                )))
+        };
+
+        let raw_actions = fill_in_params(raw_actions, &param, Within::Value { template: false })?;
+
+        let mut calling = self.calling.clone();
+        calling.push(name.to_string());
+
+        let groups = ActionGroups {
+            raw_groups: self.raw_groups,
+            calling,
+            built: self.built.clone()
+        };
+
+        let actions = build_action_list(
+            Some(&raw_actions),
+            &groups,
+            &format!("group '{}'", name),
+            ACTIONS_FIELD
+        ).map_err(|err| err.within(&format!("{}.{}", ACTION_GROUPS_FIELD, name), None))?;
+
+        self.built.borrow_mut().push(BuiltGroup {
+            name: name.to_string(),
+            params: params.cloned(),
+            actions: actions.clone()
+        });
+
+        Ok(actions)
+    }
+}
+
+/// Splits `raw_group`, the group named `name`, into its actions and its parameters' defaults
+fn split_group<'g>(
+    name: &str,
+    raw_group: &'g RawConfig
+) -> Result<(&'g RawConfig, Option<&'g RCHash>), ConfigError> {
+    match raw_group {
+        RawConfig::Array(_) => Ok((raw_group, None)),
+
+        RawConfig::Hash(hash) => Ok((
+            hash.get(&k(ACTIONS_FIELD)).unwrap_or(&RawConfig::Null),
+            hash.get_hash(PARAMS_FIELD)
+        )),
+
+        _ => Err(ConfigError::InvalidConfig(format!(
+            "Action group '{}' should be a list of actions, or a hash with an '{}' list",
+            name, ACTIONS_FIELD
+        )))
+    }
+}
+
+/// Adds the names of the groups that `call` actions anywhere in `raw` call to `called`, leaving
+/// out names that come from parameters
+fn find_calls(raw: &RawConfig, called: &mut Vec<String>) {
+    const GROUP_FIELD: &str = "group";
+
+    match raw {
+        RawConfig::Array(items) => {
+            for item in items {
+                find_calls(item, called);
+            }
+        }
+
+        RawConfig::Hash(hash) => {
+            if hash.get_string("type") == Some("call") {
+                let group = match hash.get(&k("data")) {
+                    Some(RawConfig::String(group)) => Some(group.as_str()),
+                    Some(RawConfig::Hash(data)) => data.get_string(GROUP_FIELD),
+                    _ => None
+                };
+
+                let from_param = |group: &str| {
+                    group.contains(&format!("{}{}", DELIMITER, PARAMS_PREFIX))
+                };
+
+                if let Some(group) = group.filter(|group| !from_param(group)) {
+                    if !called.iter().any(|existing| existing == group) {
+                        called.push(group.to_string());
+                    }
+                }
+            }
+
+            for (_, value) in hash {
+                find_calls(value, called);
+            }
+        }
+
+        _ => {}
+    }
+}
+
+/// Follows the calls from the group `name` through `calls`, the groups each group calls,
+/// returning an error if it gets back to a group in `calling`. Groups in `checked` are known not
+/// to, and the groups checked here are added to it.
+fn check_cycles(
+    name: &str,
+    calls: &[(String, Vec<String>)],
+    calling: &mut Vec<String>,
+    checked: &mut Vec<String>
+) -> Result<(), ConfigError> {
+    if calling.iter().any(|calling| calling == name) {
+        return Err(ConfigError::InvalidConfig(format!(
+            "Action group '{}' calls itself: {} -> {}",
+            name, calling.join(" -> "), name
+        )));
+    }
+
+    if checked.iter().any(|checked| checked == name) {
+        return Ok(());
+    }
+
+    calling.push(name.to_string());
+
+    for (_, called) in calls.iter().filter(|(caller, _)| caller == name) {
+        for called in called {
+            check_cycles(called, calls, calling, checked)?;
+        }
+    }
+
+    calling.pop();
+    checked.push(name.to_string());
+
+    Ok(())
+}
+
+/// Copies `raw`, found `within` an action group, replacing parameter references in any strings in
+/// it with values from `param`
+fn fill_in_params<'a, F>(
+    raw: &RawConfig,
+    param: &F,
+    within: Within
+) -> Result<RawConfig, ConfigError>
+    where F: Fn(&str) -> Result<&'a RawConfig, ConfigError>
+{
+    let template = match within {
+        Within::ActionData(action_type) => TEMPLATE_DATA_ACTIONS.contains(&action_type),
+        Within::Value { template } => template
+    };
+
+    Ok(match raw {
+        RawConfig::String(s) => {
+            // A lone reference keeps the value's type
+            match lone_param(s) {
+                Some(name) => match param(name)? {
+                    RawConfig::String(value) if template => RawConfig::String(escape(value)),
+                    value => value.clone()
+                },
+
+                None => RawConfig::String(fill_in_string_params(s, param, template)?)
+            }
+        }
+
+        RawConfig::Array(items) => RawConfig::Array(
+            items
+                .iter()
+                .map(|item| fill_in_params(item, param, Within::Value { template }))
+                .collect::<Result<Vec<RawConfig>, ConfigError>>()?
+        ),
+
+        RawConfig::Hash(hash) => {
+            let action_type = hash.get_string("type");
+            let mut filled_in = RCHash::new();
+
+            for (key, value) in hash {
+                let within = match (within, action_type) {
+                    (Within::ActionData(action_type), _) => Within::Value {
+                        template: TEMPLATE_FIELDS.iter().any(|(template_type, field)| {
+                            *template_type == action_type && *key == k(field)
+                        })
+                    },
+
+                    // Nested actions, like those of `if` or `switch`
+                    (_, Some(action_type)) if *key == k("data") => {
+                        Within::ActionData(action_type)
+                    }
+
+                    _ => Within::Value { template }
+                };
+
+                filled_in.insert(key.clone(), fill_in_params(value, param, within)?);
+            }
+
+            RawConfig::Hash(filled_in)
+        }
+
+        other => other.clone()
+    })
+}
+
+/// Escapes `%` in `value`, so a template takes it literally
+fn escape(value: &str) -> String {
+    value.replace(DELIMITER, &format!("{}{}", DELIMITER, DELIMITER))
+}
+
+/// The name of the parameter `s` refers to, if `s` consists of nothing but a parameter reference,
+/// like `%params.url%`. Such a string is replaced by the parameter's value as is.
+pub (crate) fn lone_param(s: &str) -> Option<&str> {
+    s.strip_prefix(DELIMITER)
+        .and_then(|s| s.strip_suffix(DELIMITER))
+        .and_then(|s| s.strip_prefix(PARAMS_PREFIX))
+        .filter(|name| !name.contains(DELIMITER))
+}
+
+/// Replaces parameter references in `s` with values from `param`, leaving any other variables,
+/// and escaped `%%` delimiters, to be parsed as a template later on. If it will be, as `template`
+/// says, string values are escaped.
+fn fill_in_string_params<'a, F>(s: &str, param: &F, template: bool) -> Result<String, ConfigError>
+    where F: Fn(&str) -> Result<&'a RawConfig, ConfigError>
+{
+    let mut filled_in = String::new();
+    let mut rest = s;
+
+    while let Some(start) = rest.find(DELIMITER) {
+        filled_in.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+
+        let end = match rest.find(DELIMITER) {
+            Some(end) => end,
+
+            // Unclosed; left for the template parser to complain about
+            None => {
+                filled_in.push(DELIMITER);
+                break;
+            }
+        };
+
+        let variable = &rest[..end];
+        rest = &rest[end + 1..];
+
+        match variable.strip_prefix(PARAMS_PREFIX) {
+            Some(name) => match param(name)? {
+                RawConfig::String(value) if template => filled_in.push_str(&escape(value)),
+                RawConfig::String(value) => filled_in.push_str(value),
+                RawConfig::Integer(value) => filled_in.push_str(&value.to_string()),
+                RawConfig::Bool(value) => filled_in.push_str(&value.to_string()),
+
+                _ => return Err(ConfigError::InvalidConfig(format!(
+                    "Parameter '{}' is used in text, so it should be a string, integer or boolean",
+                    name
+                )))
+            },
+
+            // Including an empty variable, which is an escaped delimiter
+            None => {
+                filled_in.push(DELIMITER);
+                filled_in.push_str(variable);
+                filled_in.push(DELIMITER);
+            }
+        }
+    }
+
+    filled_in.push_str(rest);
+    Ok(filled_in)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::raw_config::{RawConfig, RCHash, RCHashBuilder, k};
+    use crate::config::versions::version1::action_groups::ActionGroups;
+    use crate::macros::actions::{Action, Count, VariableAction};
+    use crate::macros::actions::template::{Template, TemplatePart, Variable, EventField};
+
+    fn raw_action(action_type: &str, data: RawConfig) -> RawConfig {
+        RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("type"), k(action_type))
+                .insert(k("data"), data)
+                .build()
+        )
+    }
+
+    fn call(group: &str) -> RawConfig {
+        raw_action("call", k(group))
+    }
+
+    #[test]
+    fn builds_groups_with_params_filled_in() {
+        let key_sequence = raw_action(
+            "key_sequence",
+            RawConfig::Hash(
+                RCHashBuilder::new()
+                    .insert(k("sequence"), k("%params.keys%"))
+                    .insert(k("count"), k("%params.count%"))
+                    .build()
+            )
+        );
+
+        let raw_groups = RCHashBuilder::new()
+            .insert(k("search"), RawConfig::Hash(
+                RCHashBuilder::new()
+                    .insert(k("params"), RawConfig::Hash(
+                        RCHashBuilder::new().insert(k("count"), RawConfig::Integer(2)).build()
+                    ))
+                    .insert(k("actions"), RawConfig::Array(vec![
+                        key_sequence,
+                        raw_action("enter_text", k("%params.query%, 100%% %event.value%"))
+                    ]))
+                    .build()
+            ))
+            .build();
+
+        let groups = ActionGroups::new(Some(&raw_groups));
+        let params = RCHashBuilder::new()
+            .insert(k("keys"), k("ctrl+f"))
+            .insert(k("query"), k("mmpd"))
+            .build();
+
+        assert_eq!(
+            groups.build("search", Some(&params)).ok().unwrap(),
+            vec![
+                Action::KeySequence {
+                    sequence: Template::literal("ctrl+f"),
+                    count: Count::Fixed(2),
+                    delay: None
+                },
+                Action::EnterText {
+                    text: Template::new(vec![
+                        TemplatePart::Literal("mmpd, 100% ".to_string()),
+                        TemplatePart::Variable(Variable::Event(EventField::Value))
+                    ]),
+                    count: Count::Fixed(1),
+                    delay: None
+                }
+            ]
+        );
+
+        // Without a value for "keys"
+        assert!(groups.build("search", None).is_err());
+    }
+
+    #[test]
+    fn takes_param_values_literally_in_templates() {
+        let raw_groups = RCHashBuilder::new()
+            .insert(k("greet"), RawConfig::Array(vec![
+                raw_action("enter_text", k("Hello %params.name%")),
+                raw_action("enter_text", k("%params.name%")),
+                raw_action("toggle_var", k("%params.name%"))
+            ]))
+            .build();
+
+        let groups = ActionGroups::new(Some(&raw_groups));
+
+        let build = |name: &str| {
+            let params = RCHashBuilder::new().insert(k("name"), k(name)).build();
+            groups.build("greet", Some(&params)).ok().unwrap()
+        };
+
+        let text = |text: &str| Action::EnterText {
+            text: Template::literal(text),
+            count: Count::Fixed(1),
+            delay: None
+        };
+
+        let actions = build("100%");
+        assert_eq!(actions[0], text("Hello 100%"));
+        assert_eq!(actions[1], text("100%"));
+
+        // A value that looks like a variable isn't one
+        let actions = build("%event.key%");
+        assert_eq!(actions[0], text("Hello %event.key%"));
+        assert_eq!(actions[1], text("%event.key%"));
+
+        // Outside of templates, values are used as they are
+        assert_eq!(
+            actions[2],
+            Action::Variable(VariableAction::Toggle { name: "%event.key%".to_string() })
+        );
+    }
+
+    #[test]
+    fn checks_calls_between_groups_that_are_never_called() {
+        let check = |raw_groups: RCHash| ActionGroups::new(Some(&raw_groups)).check();
+
+        let cycle = RCHashBuilder::new()
+            .insert(k("fine"), RawConfig::Array(vec![call("loopa")]))
+            .insert(k("loopa"), RawConfig::Array(vec![call("loopb")]))
+            .insert(k("loopb"), RawConfig::Array(vec![call("loopa")]))
+            .build();
+
+        assert_eq!(
+            check(cycle).err().unwrap().description(),
+            "action_groups.fine: Action group 'loopa' calls itself: fine -> loopa -> loopb -> loopa"
+        );
+
+        let unknown = RCHashBuilder::new()
+            .insert(k("lost"), RawConfig::Array(vec![raw_action("if", RawConfig::Hash(
+                RCHashBuilder::new()
+                    .insert(k("then"), RawConfig::Array(vec![call("missing")]))
+                    .build()
+            ))]))
+            .build();
+
+        assert_eq!(
+            check(unknown).err().unwrap().description(),
+            "action_groups.lost: Action call: there is no action group named 'missing'"
+        );
+
+        let fine = RCHashBuilder::new()
+            .insert(k("fine"), RawConfig::Array(vec![raw_action("enter_text", k("fine"))]))
+            .insert(k("twice"), RawConfig::Array(vec![call("fine"), call("fine")]))
+            .insert(k("any"), RawConfig::Array(vec![call("%params.group%")]))
+            .build();
+
+        assert!(check(fine).is_ok());
+        assert!(ActionGroups::new(None).check().is_ok());
+    }
+
+    #[test]
+    fn returns_an_error_for_unknown_groups_and_cycles() {
+        let raw_groups = RCHashBuilder::new()
+            .insert(k("ping"), RawConfig::Array(vec![call("pong")]))
+            .insert(k("pong"), RawConfig::Array(vec![call("ping")]))
+            .insert(k("lost"), RawConfig::Array(vec![call("missing")]))
+            .insert(k("fine"), RawConfig::Array(vec![raw_action("enter_text", k("fine"))]))
+            .insert(k("also_fine"), RawConfig::Array(vec![call("fine"), call("fine")]))
+            .build();
+
+        let groups = ActionGroups::new(Some(&raw_groups));
+
+        assert!(groups.build("ping", None).is_err());
+        assert!(groups.build("lost", None).is_err());
+        assert!(groups.build("missing", None).is_err());
+        assert!(groups.build("also_fine", None).is_ok());
+        assert!(ActionGroups::new(None).build("fine", None).is_err());
+        assert!(ActionGroups::new(Some(&RCHash::new())).build("fine", None).is_err());
+    }
+}
@@ -3,15 +3,33 @@ mod enter_text;
 mod shell;
 mod wait;
 mod control;
+mod midi_send;
+mod variable;
+mod repeat_while;
+mod cancel_macro;
+mod branching;
+mod call;
 
-use crate::config::raw_config::{RCHash, AccessHelpers, k};
+use crate::config::raw_config::{RCHash, RawConfig, AccessHelpers, k};
 use crate::macros::actions::Action;
+use crate::macros::preconditions::Precondition;
+use crate::config::versions::version1::precondition::build_precondition;
+use crate::config::versions::version1::action_groups::ActionGroups;
 use crate::config::ConfigError;
 use key_sequence::build_action_key_sequence;
 use enter_text::build_action_enter_text;
 use shell::build_action_shell;
 use wait::build_action_wait;
 use control::build_action_control;
+use midi_send::build_action_midi_send;
+use variable::{
+    build_action_set_var, build_action_toggle_var, build_action_increment_var,
+    build_action_cycle_var
+};
+use repeat_while::build_action_repeat_while;
+use cancel_macro::build_action_cancel_macro;
+use branching::{build_action_if, build_action_switch};
+use call::build_action_call;
 
 /// Constructs an `Action` from a `raw_action` `RCHash`.
 ///
@@ -28,6 +46,18 @@ use control::build_action_control;
 ///     - `key_sequence` (see `build_action_key_sequence`)
 ///     - `enter_text` (see `build_action_enter_text`)
 ///     - `shell` (see `build_action_shell`)
+///     - `wait` (see `build_action_wait`)
+///     - `control` (see `build_action_control`)
+///     - `midi_send` (see `build_action_midi_send`)
+///     - `set_var` (see `build_action_set_var`)
+///     - `toggle_var` (see `build_action_toggle_var`)
+///     - `increment_var` (see `build_action_increment_var`)
+///     - `cycle_var` (see `build_action_cycle_var`)
+///     - `repeat_while` (see `build_action_repeat_while`)
+///     - `cancel_macro` (see `build_action_cancel_macro`)
+///     - `if` (see `build_action_if`)
+///     - `switch` (see `build_action_switch`)
+///     - `call` (see `build_action_call`)
 ///
 /// `data` is not strictly required, nor are their restrictions on what type of data it should
 /// represent. Most often it will be a hash to specify one or more fields, but depending on the
@@ -40,7 +70,7 @@ use control::build_action_control;
 /// - The `type` field is missing
 /// - The `type` value doesn't match any of the implemented values
 /// - Down the stream, we fail to build the specific Action value from given data for any reason
-pub fn build_action(raw_action: &RCHash) -> Result<Action, ConfigError> {
+pub fn build_action(raw_action: &RCHash, groups: &ActionGroups) -> Result<Action, ConfigError> {
     const TYPE_FIELD: &str = "type";
     const DATA_FIELD: &str = "data";
 
@@ -49,6 +79,16 @@ pub fn build_action(raw_action: &RCHash) -> Result<Action, ConfigError> {
     const SHELL_TYPE: &str = "shell";
     const WAIT_TYPE: &str = "wait";
     const CONTROL_TYPE: &str = "control";
+    const MIDI_SEND_TYPE: &str = "midi_send";
+    const SET_VAR_TYPE: &str = "set_var";
+    const TOGGLE_VAR_TYPE: &str = "toggle_var";
+    const INCREMENT_VAR_TYPE: &str = "increment_var";
+    const CYCLE_VAR_TYPE: &str = "cycle_var";
+    const REPEAT_WHILE_TYPE: &str = "repeat_while";
+    const CANCEL_MACRO_TYPE: &str = "cancel_macro";
+    const IF_TYPE: &str = "if";
+    const SWITCH_TYPE: &str = "switch";
+    const CALL_TYPE: &str = "call";
 
     let data_hash = raw_action.get(&k(DATA_FIELD));
 
@@ -58,25 +98,105 @@ pub fn build_action(raw_action: &RCHash) -> Result<Action, ConfigError> {
         )
     })?;
 
-    Ok(match action_type {
-        KEY_SEQUENCE_TYPE => build_action_key_sequence(data_hash)?,
-        ENTER_TEXT_TYPE => build_action_enter_text(data_hash)?,
-        SHELL_TYPE => build_action_shell(data_hash)?,
-        WAIT_TYPE => build_action_wait(data_hash)?,
-        CONTROL_TYPE => build_action_control(data_hash)?,
+    match action_type {
+        KEY_SEQUENCE_TYPE => build_action_key_sequence(data_hash),
+        ENTER_TEXT_TYPE => build_action_enter_text(data_hash),
+        SHELL_TYPE => build_action_shell(data_hash),
+        WAIT_TYPE => build_action_wait(data_hash),
+        CONTROL_TYPE => build_action_control(data_hash),
+        MIDI_SEND_TYPE => build_action_midi_send(data_hash),
+        SET_VAR_TYPE => build_action_set_var(data_hash),
+        TOGGLE_VAR_TYPE => build_action_toggle_var(data_hash),
+        INCREMENT_VAR_TYPE => build_action_increment_var(data_hash),
+        CYCLE_VAR_TYPE => build_action_cycle_var(data_hash),
+        REPEAT_WHILE_TYPE => build_action_repeat_while(data_hash, groups),
+        CANCEL_MACRO_TYPE => build_action_cancel_macro(data_hash),
+        IF_TYPE => build_action_if(data_hash, groups),
+        SWITCH_TYPE => build_action_switch(data_hash, groups),
+        CALL_TYPE => build_action_call(data_hash, groups),
 
         _ => {
             return Err(ConfigError::InvalidConfig(
                 format!("Unknown action type '{}'", action_type)
             ));
         }
-    })
+    }.map_err(|err| err.within(DATA_FIELD, data_hash.and_then(RawConfig::loc)))
+}
+
+/// Constructs a list of actions contained in another action, from `raw_actions`, skipping over
+/// any items that aren't hashes. `action_type` and `field` are used in error messages.
+///
+/// ## Errors
+/// This function will return `ConfigError` if `raw_actions` is `None`, isn't a
+/// `RawConfig::Array`, or is empty, or down the stream, one of the actions fails to be built.
+pub (crate) fn build_action_list(
+    raw_actions: Option<&RawConfig>,
+    groups: &ActionGroups,
+    action_type: &str,
+    field: &str
+) -> Result<Vec<Action>, ConfigError> {
+    let raw_actions = match raw_actions {
+        Some(RawConfig::Array(raw_actions)) if !raw_actions.is_empty() => raw_actions,
+
+        _ => return Err(ConfigError::InvalidConfig(format!(
+            "Action {}: '{}' should be a list with at least one action",
+            action_type, field
+        )))
+    };
+
+    let mut actions = vec![];
+
+    for (index, raw_action) in raw_actions.iter().enumerate() {
+        if let RawConfig::Hash(raw_action) = raw_action {
+            actions.push(build_action(raw_action, groups).map_err(|err| {
+                err.within_item(field, index, raw_action.loc())
+            })?);
+        }
+    }
+
+    Ok(actions)
+}
+
+/// Constructs a list of preconditions an action checks, from `raw_preconditions`, skipping over
+/// any items that aren't hashes. `action_type` and `field` are used in error messages.
+///
+/// ## Errors
+/// This function will return `ConfigError` if `raw_preconditions` is `None`, isn't a
+/// `RawConfig::Array`, or is empty, or down the stream, one of the preconditions fails to be built.
+pub (crate) fn build_precondition_list(
+    raw_preconditions: Option<&RawConfig>,
+    action_type: &str,
+    field: &str
+) -> Result<Vec<Precondition>, ConfigError> {
+    let raw_preconditions = match raw_preconditions {
+        Some(RawConfig::Array(raw_preconditions)) if !raw_preconditions.is_empty() => {
+            raw_preconditions
+        }
+
+        _ => return Err(ConfigError::InvalidConfig(format!(
+            "Action {}: '{}' should be a list with at least one precondition",
+            action_type, field
+        )))
+    };
+
+    let mut preconditions = vec![];
+
+    for (index, raw_precondition) in raw_preconditions.iter().enumerate() {
+        if let RawConfig::Hash(raw_precondition) = raw_precondition {
+            preconditions.push(build_precondition(raw_precondition).map_err(|err| {
+                err.within_item(field, index, raw_precondition.loc())
+            })?);
+        }
+    }
+
+    Ok(preconditions)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::config::raw_config::{RCHash, k, RawConfig};
     use crate::config::versions::version1::actions::build_action;
+    use crate::config::versions::version1::action_groups::ActionGroups;
     use crate::macros::actions::{Action, ControlAction};
 
     #[test]
@@ -84,7 +204,7 @@ mod tests {
         let mut hash = RCHash::new();
         hash.insert(k("data"), k("Hello world"));
 
-        let action = build_action(&hash);
+        let action = build_action(&hash, &ActionGroups::new(None));
         assert!(action.is_err());
     }
 
@@ -94,7 +214,7 @@ mod tests {
         hash.insert(k("type"), k("not-a-real-action"));
         hash.insert(k("data"), k("Hello world"));
 
-        let action = build_action(&hash);
+        let action = build_action(&hash, &ActionGroups::new(None));
         assert!(action.is_err());
     }
 
@@ -106,7 +226,7 @@ mod tests {
         // build_enter_text_action
         hash.insert(k("data"), k("Hello world"));
 
-        let action = build_action(&hash).ok().unwrap();
+        let action = build_action(&hash, &ActionGroups::new(None)).ok().unwrap();
 
         assert_eq!(
             action,
@@ -122,7 +242,7 @@ mod tests {
         // build_key_sequence_action
         hash.insert(k("data"), k("ctrl+shift+t"));
 
-        let action = build_action(&hash).ok().unwrap();
+        let action = build_action(&hash, &ActionGroups::new(None)).ok().unwrap();
 
         assert_eq!(
             action,
@@ -138,7 +258,7 @@ mod tests {
         // build_shell_action
         hash.insert(k("data"), k("cmd"));
 
-        let action = build_action(&hash).ok().unwrap();
+        let action = build_action(&hash, &ActionGroups::new(None)).ok().unwrap();
 
         assert_eq!(
             action,
@@ -156,7 +276,7 @@ mod tests {
         hash.insert(k("type"), k("wait"));
         hash.insert(k("data"), RawConfig::Integer(20));
 
-        let action = build_action(&hash).ok().unwrap();
+        let action = build_action(&hash, &ActionGroups::new(None)).ok().unwrap();
 
         assert_eq!(
             action,
@@ -170,7 +290,7 @@ mod tests {
         hash.insert(k("type"), k("control"));
         hash.insert(k("data"), k("exit"));
 
-        let action = build_action(&hash).ok().unwrap();
+        let action = build_action(&hash, &ActionGroups::new(None)).ok().unwrap();
 
         assert_eq!(
             action,
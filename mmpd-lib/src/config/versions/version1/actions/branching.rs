@@ -0,0 +1,275 @@
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+use crate::config::ConfigError;
+use crate::config::versions::version1::actions::{build_action_list, build_precondition_list};
+use crate::config::versions::version1::action_groups::ActionGroups;
+use crate::config::versions::version1::primitive_matchers::build_number_matcher;
+use crate::config::versions::version1::templates::build_template;
+use crate::macros::actions::{Action, SwitchCase};
+
+/// Constructs an `Action::If` from `raw_data` structured as follows:
+///
+/// ```yaml
+/// required_preconditions:
+///     - # (one or more preconditions)
+/// then:
+///     - # (one or more actions)
+/// else:
+///     - # (zero or more actions)
+/// ```
+///
+/// `required_preconditions` is required, and must be a list of at least one precondition, in the
+/// same structure as a macro's `required_preconditions` (see `build_precondition`).
+///
+/// `then` is required, and is the list of actions to run if all of the preconditions are satisfied
+/// when the action is reached. Its actions have the same structure as a macro's (see
+/// `build_action`).
+///
+/// `else` is optional, and is the list of actions to run otherwise.
+///
+/// ## Errors
+/// The function returns a `ConfigError` if `raw_data` isn't a hash, `required_preconditions` or
+/// `then` are missing or empty, `else` isn't a list, or down the stream, one of the preconditions
+/// or actions fails to be built.
+pub fn build_action_if(
+    raw_data: Option<&RawConfig>,
+    groups: &ActionGroups
+) -> Result<Action, ConfigError> {
+    const ACTION: &str = "if";
+    const REQUIRED_PRECONDITIONS_FIELD: &str = "required_preconditions";
+    const THEN_FIELD: &str = "then";
+    const ELSE_FIELD: &str = "else";
+
+    let hash = get_hash(ACTION, raw_data)?;
+
+    let required_preconditions = build_precondition_list(
+        hash.get(&k(REQUIRED_PRECONDITIONS_FIELD)),
+        ACTION,
+        REQUIRED_PRECONDITIONS_FIELD
+    )?;
+
+    let then = build_action_list(hash.get(&k(THEN_FIELD)), groups, ACTION, THEN_FIELD)?;
+    let otherwise = build_optional_action_list(hash, groups, ACTION, ELSE_FIELD)?;
+
+    Ok(Action::If { required_preconditions, then, otherwise })
+}
+
+/// Constructs an `Action::Switch` from `raw_data` structured as follows:
+///
+/// ```yaml
+/// value: "%event.value%"
+/// cases:
+///     - value:
+///         min: 0
+///         max: 63
+///       actions:
+///         - # (one or more actions)
+/// default:
+///     - # (zero or more actions)
+/// ```
+///
+/// `value` is required, and is a string that may contain variables (see `build_template`). It's
+/// resolved when the action is reached, and the first case it matches is run.
+///
+/// `cases` is required, and is a list of at least one case. Each case has a `value` number matcher
+/// (see `build_number_matcher`), and a list of `actions` in the same structure as a macro's (see
+/// `build_action`).
+///
+/// `default` is optional, and is the list of actions to run if no case matches, or `value` doesn't
+/// resolve to a whole number of 0 or more.
+///
+/// ## Errors
+/// The function returns a `ConfigError` if `raw_data` isn't a hash, `value` is missing or contains
+/// an invalid variable, `cases` is missing or empty, a case is missing its `value` or `actions`,
+/// `default` isn't a list, or down the stream, one of the actions fails to be built.
+pub fn build_action_switch(
+    raw_data: Option<&RawConfig>,
+    groups: &ActionGroups
+) -> Result<Action, ConfigError> {
+    const ACTION: &str = "switch";
+    const VALUE_FIELD: &str = "value";
+    const CASES_FIELD: &str = "cases";
+    const ACTIONS_FIELD: &str = "actions";
+    const DEFAULT_FIELD: &str = "default";
+
+    let hash = get_hash(ACTION, raw_data)?;
+
+    let value = hash.get_string(VALUE_FIELD).ok_or_else(|| ConfigError::InvalidConfig(format!(
+        "Action {}: missing string '{}' field",
+        ACTION, VALUE_FIELD
+    )))?;
+
+    let raw_cases = hash.get_array(CASES_FIELD)
+        .filter(|raw_cases| !raw_cases.is_empty())
+        .ok_or_else(|| ConfigError::InvalidConfig(format!(
+            "Action {}: '{}' should be a list with at least one case",
+            ACTION, CASES_FIELD
+        )))?;
+
+    let mut cases = vec![];
+
+    for (index, raw_case) in raw_cases.iter().enumerate() {
+        let raw_case = match raw_case {
+            RawConfig::Hash(raw_case) => raw_case,
+            _ => continue
+        };
+
+        let in_case = |err: ConfigError| err.within_item(CASES_FIELD, index, raw_case.loc());
+
+        let value_match = build_number_matcher(raw_case.get(&k(VALUE_FIELD)))
+            .and_then(|value_match| value_match.ok_or_else(|| {
+                ConfigError::InvalidConfig(format!(
+                    "Action {}: each case should have a number matcher '{}' field",
+                    ACTION, VALUE_FIELD
+                ))
+            }))
+            .map_err(in_case)?;
+
+        let actions = build_action_list(
+            raw_case.get(&k(ACTIONS_FIELD)),
+            groups,
+            ACTION,
+            ACTIONS_FIELD
+        ).map_err(in_case)?;
+
+        cases.push(SwitchCase { value_match, actions });
+    }
+
+    Ok(Action::Switch {
+        value: build_template(value)?,
+        cases,
+        default: build_optional_action_list(hash, groups, ACTION, DEFAULT_FIELD)?
+    })
+}
+
+fn get_hash<'a>(action: &str, raw_data: Option<&'a RawConfig>) -> Result<&'a RCHash, ConfigError> {
+    match raw_data {
+        Some(RawConfig::Hash(hash)) => Ok(hash),
+
+        _ => Err(ConfigError::InvalidConfig(
+            format!("Action {}: data field should be a hash", action)
+        ))
+    }
+}
+
+/// Builds the list of actions in `field` of `hash`, which may be left out or empty
+fn build_optional_action_list(
+    hash: &RCHash,
+    groups: &ActionGroups,
+    action: &str,
+    field: &str
+) -> Result<Vec<Action>, ConfigError> {
+    match hash.get(&k(field)) {
+        None => Ok(vec![]),
+        Some(RawConfig::Array(raw_actions)) if raw_actions.is_empty() => Ok(vec![]),
+        raw_actions => build_action_list(raw_actions, groups, action, field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::raw_config::{RawConfig, RCHashBuilder, k};
+    use crate::config::versions::version1::actions::branching::{
+        build_action_if, build_action_switch
+    };
+    use crate::config::versions::version1::action_groups::ActionGroups;
+    use crate::macros::actions::{Action, SwitchCase};
+    use crate::macros::actions::template::{Template, TemplatePart, Variable, EventField};
+    use crate::macros::preconditions::{Precondition, PreconditionType};
+    use crate::match_checker::NumberMatcher;
+
+    fn raw_actions(text: &str) -> RawConfig {
+        RawConfig::Array(vec![RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("type"), k("enter_text"))
+                .insert(k("data"), k(text))
+                .build()
+        )])
+    }
+
+    #[test]
+    fn builds_if_action() {
+        let precondition = RawConfig::Hash(
+            RCHashBuilder::new().insert(k("type"), k("other")).build()
+        );
+
+        let data = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("required_preconditions"), RawConfig::Array(vec![precondition]))
+                .insert(k("then"), raw_actions("then"))
+                .insert(k("else"), raw_actions("else"))
+                .build()
+        );
+
+        assert_eq!(
+            build_action_if(Some(&data), &ActionGroups::new(None)).ok().unwrap(),
+            Action::If {
+                required_preconditions: vec![Precondition {
+                    invert: false,
+                    condition: PreconditionType::Other
+                }],
+                then: vec![Action::enter_text("then")],
+                otherwise: vec![Action::enter_text("else")]
+            }
+        );
+
+        let without_preconditions = RawConfig::Hash(
+            RCHashBuilder::new().insert(k("then"), raw_actions("then")).build()
+        );
+
+        assert!(build_action_if(Some(&without_preconditions), &ActionGroups::new(None)).is_err());
+        assert!(build_action_if(None, &ActionGroups::new(None)).is_err());
+    }
+
+    #[test]
+    fn builds_switch_action() {
+        let case = |value: RawConfig, text: &str| RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("value"), value)
+                .insert(k("actions"), raw_actions(text))
+                .build()
+        );
+
+        let low = RawConfig::Hash(
+            RCHashBuilder::new().insert(k("max"), RawConfig::Integer(63)).build()
+        );
+
+        let data = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("value"), k("%event.value%"))
+                .insert(k("cases"), RawConfig::Array(vec![
+                    case(low, "low"),
+                    case(RawConfig::Integer(127), "max")
+                ]))
+                .build()
+        );
+
+        assert_eq!(
+            build_action_switch(Some(&data), &ActionGroups::new(None)).ok().unwrap(),
+            Action::Switch {
+                value: Template::new(vec![
+                    TemplatePart::Variable(Variable::Event(EventField::Value))
+                ]),
+                cases: vec![
+                    SwitchCase {
+                        value_match: NumberMatcher::Range { min: None, max: Some(63) },
+                        actions: vec![Action::enter_text("low")]
+                    },
+                    SwitchCase {
+                        value_match: NumberMatcher::Val(127),
+                        actions: vec![Action::enter_text("max")]
+                    }
+                ],
+                default: vec![]
+            }
+        );
+
+        let case_without_value = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("value"), k("%event.value%"))
+                .insert(k("cases"), RawConfig::Array(vec![case(RawConfig::Null, "low")]))
+                .build()
+        );
+
+        assert!(build_action_switch(Some(&case_without_value), &ActionGroups::new(None)).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::config::raw_config::{RawConfig, AccessHelpers};
+use crate::config::ConfigError;
+use crate::config::versions::version1::action_groups::ActionGroups;
+use crate::macros::actions::Action;
+
+/// Constructs an `Action::Call` running the actions of an action group, from `raw_data`
+/// structured as follows:
+///
+/// ```yaml
+/// group: open_url
+/// params:
+///     url: "https://example.com"
+/// ```
+///
+/// `group` is required, and is the name of a group in `groups`.
+///
+/// `params` is optional, and holds the values of the group's parameters (see
+/// `ActionGroups::build`).
+///
+/// Alternatively, `raw_data` can be just the name of the group, to call it without parameters.
+///
+/// ## Errors
+/// The function returns a `ConfigError` if no group name is found in `raw_data`, or down the
+/// stream, the group's actions fail to be built, for example because there is no such group.
+pub fn build_action_call(
+    raw_data: Option<&RawConfig>,
+    groups: &ActionGroups
+) -> Result<Action, ConfigError> {
+    const GROUP_FIELD: &str = "group";
+    const PARAMS_FIELD: &str = "params";
+
+    let (group, params) = match raw_data {
+        Some(RawConfig::String(group)) => (Some(group.as_str()), None),
+        Some(RawConfig::Hash(hash)) => (hash.get_string(GROUP_FIELD), hash.get_hash(PARAMS_FIELD)),
+        _ => (None, None)
+    };
+
+    let group = group.ok_or_else(|| ConfigError::InvalidConfig(format!(
+        "Action call: data field should be a group name, or a hash with a string '{}' field",
+        GROUP_FIELD
+    )))?;
+
+    Ok(Action::Call {
+        group: group.to_string(),
+        actions: groups.build(group, params)?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::raw_config::{RawConfig, RCHashBuilder, k};
+    use crate::config::versions::version1::action_groups::ActionGroups;
+    use crate::config::versions::version1::actions::call::build_action_call;
+    use crate::macros::actions::Action;
+
+    #[test]
+    fn builds_call_action() {
+        let raw_groups = RCHashBuilder::new()
+            .insert(k("greet"), RawConfig::Array(vec![RawConfig::Hash(
+                RCHashBuilder::new()
+                    .insert(k("type"), k("enter_text"))
+                    .insert(k("data"), k("Hello %params.name%"))
+                    .build()
+            )]))
+            .build();
+
+        let groups = ActionGroups::new(Some(&raw_groups));
+
+        let data = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("group"), k("greet"))
+                .insert(k("params"), RawConfig::Hash(
+                    RCHashBuilder::new().insert(k("name"), k("world")).build()
+                ))
+                .build()
+        );
+
+        assert_eq!(
+            build_action_call(Some(&data), &groups).ok().unwrap(),
+            Action::Call {
+                group: "greet".to_string(),
+                actions: vec![Action::enter_text("Hello world")]
+            }
+        );
+
+        // The name alone leaves out the parameter the group needs
+        assert!(build_action_call(Some(&k("greet")), &groups).is_err());
+        assert!(build_action_call(None, &groups).is_err());
+    }
+}
@@ -0,0 +1,45 @@
+use crate::config::raw_config::{RawConfig, AccessHelpers};
+use crate::config::ConfigError;
+use crate::macros::actions::Action;
+
+/// Constructs an `Action::CancelMacro` from `raw_data`, which is either the name of the macro to
+/// cancel, or a hash with a `name` field:
+///
+/// ```yaml
+/// name: Scroll down
+/// ```
+///
+/// ## Errors
+/// The function returns a `ConfigError` if no name is found in `raw_data`.
+pub fn build_action_cancel_macro(raw_data: Option<&RawConfig>) -> Result<Action, ConfigError> {
+    const NAME_FIELD: &str = "name";
+
+    let name = match raw_data {
+        Some(RawConfig::String(name)) => Some(name.as_str()),
+        Some(RawConfig::Hash(hash)) => hash.get_string(NAME_FIELD),
+        _ => None
+    }.ok_or_else(|| ConfigError::InvalidConfig(format!(
+        "Action cancel_macro: data field should be a macro name, or a hash with a string '{}' field",
+        NAME_FIELD
+    )))?;
+
+    Ok(Action::CancelMacro { name: name.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::raw_config::{RawConfig, RCHashBuilder, k};
+    use crate::config::versions::version1::actions::cancel_macro::build_action_cancel_macro;
+    use crate::macros::actions::Action;
+
+    #[test]
+    fn builds_cancel_macro_action() {
+        let expected = Action::CancelMacro { name: "Scroll down".to_string() };
+        let hash = RawConfig::Hash(RCHashBuilder::new().insert(k("name"), k("Scroll down")).build());
+
+        assert_eq!(build_action_cancel_macro(Some(&k("Scroll down"))).ok().unwrap(), expected);
+        assert_eq!(build_action_cancel_macro(Some(&hash)).ok().unwrap(), expected);
+        assert!(build_action_cancel_macro(Some(&RawConfig::Integer(1))).is_err());
+        assert!(build_action_cancel_macro(None).is_err());
+    }
+}
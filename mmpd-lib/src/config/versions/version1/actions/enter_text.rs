@@ -1,6 +1,7 @@
-use crate::config::raw_config::{RawConfig, AccessHelpers};
-use crate::macros::actions::Action;
+use crate::config::raw_config::{RawConfig, AccessHelpers, k};
+use crate::macros::actions::{Action, Count};
 use crate::config::ConfigError;
+use crate::config::versions::version1::templates::{build_template, build_count};
 
 /// Constructs an `Action::EnterText` from `raw_data` `RawConfig`.
 ///
@@ -15,10 +16,13 @@ use crate::config::ConfigError;
 ///   delay_ms: 30 # Alternatively, delay in milliseconds
 ///   ```
 ///
-///   `text` is required and should be a String, like "Hello world!"
+///   `text` is required and should be a String, like "Hello world!". It may contain variables,
+///   see `build_template`.
 ///
 ///   `count` is optional and should be a positive integer; this is how many times the text
 ///   is to be repeated. It also defaults to 1 if anything that isn't an integer is given.
+///   `count` may also be a string with variables, like `"%event.magnitude%"`, which is resolved
+///   when the action is run; see `build_count`.
 ///
 ///   `delay` is optional and should be a positive integer; this is how long to wait between
 ///   key presses, allowing the focused application time to process it. This is expressed in
@@ -39,6 +43,7 @@ use crate::config::ConfigError;
 /// - `raw_data` is a `RawConfig::Hash` but is missing a `text` field that is a
 ///   `RawConfig::String`
 /// - `raw_data` is a `RawConfig::Hash` but `count` is a negative integer
+/// - The text or `count` contains an invalid variable
 pub fn build_action_enter_text(raw_data: Option<&RawConfig>) -> Result<Action, ConfigError> {
     const TEXT_FIELD: &str = "text";
     const COUNT_FIELD: &str = "count";
@@ -52,7 +57,11 @@ pub fn build_action_enter_text(raw_data: Option<&RawConfig>) -> Result<Action, C
     })?;
 
     match raw_data {
-        RawConfig::String(text) => Ok(Action::enter_text(text)),
+        RawConfig::String(text) => Ok(Action::EnterText {
+            text: build_template(text)?,
+            count: Count::Fixed(1),
+            delay: None
+        }),
 
         RawConfig::Hash(hash) => {
             let text = hash.get_string(TEXT_FIELD).ok_or_else(|| {
@@ -62,7 +71,7 @@ pub fn build_action_enter_text(raw_data: Option<&RawConfig>) -> Result<Action, C
                 ))
             })?;
 
-            let count = hash.get_integer(COUNT_FIELD).unwrap_or(1);
+            let count = build_count(hash.get(&k(COUNT_FIELD)), "enter_text")?;
 
             let delay = hash.get_integer(DELAY_FIELD)
                 .map(|d| {
@@ -82,17 +91,11 @@ pub fn build_action_enter_text(raw_data: Option<&RawConfig>) -> Result<Action, C
                    })
                 }).flatten();
 
-            if count < 0 {
-                Err(ConfigError::InvalidConfig(
-                    format!("Action enter_text: count should be 0 or more, found {}", count)
-                ))
-            } else {
-                Ok(Action::EnterText {
-                    text: text.to_string(),
-                    count: count as usize,
-                    delay
-                })
-            }
+            Ok(Action::EnterText {
+                text: build_template(text)?,
+                count,
+                delay
+            })
         }
 
         _ => Err(ConfigError::InvalidConfig(
@@ -107,7 +110,8 @@ pub fn build_action_enter_text(raw_data: Option<&RawConfig>) -> Result<Action, C
 mod tests {
     use crate::config::versions::version1::actions::enter_text::build_action_enter_text;
     use crate::config::raw_config::{RawConfig, RCHash, k};
-    use crate::macros::actions::Action;
+    use crate::macros::actions::{Action, Count};
+    use crate::macros::actions::template::{Template, TemplatePart, Variable, EventField};
 
     #[test]
     fn returns_an_error_if_no_data_is_provided() {
@@ -133,8 +137,8 @@ mod tests {
             .ok().unwrap();
 
         assert_eq!(action, Action::EnterText {
-            text: "Hello world".to_string(),
-            count: 3,
+            text: Template::literal("Hello world"),
+            count: Count::Fixed(3),
             delay: None
         });
     }
@@ -149,8 +153,8 @@ mod tests {
             .ok().unwrap();
 
         assert_eq!(action, Action::EnterText {
-            text: "Hello world".to_string(),
-            count: 1,
+            text: Template::literal("Hello world"),
+            count: Count::Fixed(1),
             delay: Some(2500)
         });
     }
@@ -165,8 +169,8 @@ mod tests {
             .ok().unwrap();
 
         assert_eq!(action, Action::EnterText {
-            text: "Hello world".to_string(),
-            count: 1,
+            text: Template::literal("Hello world"),
+            count: Count::Fixed(1),
             delay: None
         });
     }
@@ -181,8 +185,8 @@ mod tests {
             .ok().unwrap();
 
         assert_eq!(action, Action::EnterText {
-            text: "Hello world".to_string(),
-            count: 1,
+            text: Template::literal("Hello world"),
+            count: Count::Fixed(1),
             delay: Some(20_000)
         })
     }
@@ -198,8 +202,8 @@ mod tests {
             .ok().unwrap();
 
         assert_eq!(action, Action::EnterText {
-            text: "Hello world".to_string(),
-            count: 1,
+            text: Template::literal("Hello world"),
+            count: Count::Fixed(1),
             delay: Some(33)
         })
     }
@@ -231,4 +235,28 @@ mod tests {
         let action = build_action_enter_text(Some(&RawConfig::Null));
         assert!(action.is_err());
     }
+
+    #[test]
+    fn builds_enter_text_action_with_variables() {
+        let mut data_hash = RCHash::new();
+        data_hash.insert(k("text"), k("Value: %event.value%"));
+
+        let action = build_action_enter_text(Some(&RawConfig::Hash(data_hash)))
+            .ok().unwrap();
+
+        assert_eq!(action, Action::EnterText {
+            text: Template::new(vec![
+                TemplatePart::Literal("Value: ".to_string()),
+                TemplatePart::Variable(Variable::Event(EventField::Value))
+            ]),
+            count: Count::Fixed(1),
+            delay: None
+        });
+    }
+
+    #[test]
+    fn returns_an_error_if_text_contains_an_unclosed_variable() {
+        let action = build_action_enter_text(Some(&k("100% sure")));
+        assert!(action.is_err());
+    }
 }
@@ -1,6 +1,7 @@
-use crate::config::raw_config::{RawConfig, AccessHelpers};
-use crate::macros::actions::Action;
+use crate::config::raw_config::{RawConfig, AccessHelpers, k};
+use crate::macros::actions::{Action, Count};
 use crate::config::ConfigError;
+use crate::config::versions::version1::templates::{build_template, build_count};
 
 /// Constructs an `Action::KeySequence` from `raw_data` `RawConfig`.
 ///
@@ -15,10 +16,13 @@ use crate::config::ConfigError;
 ///   delay_ms: 30 # Alternatively, delay in milliseconds
 ///   ```
 ///
-///   `sequence` is required and should be a String, like "ctrl+shift+t"
+///   `sequence` is required and should be a String, like "ctrl+shift+t". It may contain variables,
+///   see `build_template`.
 ///
 ///   `count` is optional and should be a positive integer; this is how many times the key sequence
 ///   is to be repeated. It also defaults to 1 if anything that isn't an integer is given
+///   `count` may also be a string with variables, like `"%event.magnitude%"`, which is resolved
+///   when the action is run; see `build_count`.
 ///
 ///   `delay` is optional and should be a positive integer; this is how long to wait between
 ///   key presses, allowing the focused application time to process it. This is expressed in
@@ -39,6 +43,7 @@ use crate::config::ConfigError;
 /// - `raw_data` is a `RawConfig::Hash` but is missing a `sequence` field that is a
 ///   `RawConfig::String`
 /// - `raw_data` is a `RawConfig::Hash` but `count` is a negative integer
+/// - The sequence or `count` contains an invalid variable
 pub fn build_action_key_sequence(raw_data: Option<&RawConfig>) -> Result<Action, ConfigError> {
     const SEQUENCE_FIELD: &str = "sequence";
     const COUNT_FIELD: &str = "count";
@@ -52,7 +57,11 @@ pub fn build_action_key_sequence(raw_data: Option<&RawConfig>) -> Result<Action,
     })?;
 
     match raw_data {
-        RawConfig::String(sequence) => Ok(Action::key_sequence(sequence)),
+        RawConfig::String(sequence) => Ok(Action::KeySequence {
+            sequence: build_template(sequence)?,
+            count: Count::Fixed(1),
+            delay: None
+        }),
 
         RawConfig::Hash(hash) => {
             let sequence = hash.get_string(SEQUENCE_FIELD).ok_or_else(|| {
@@ -62,7 +71,7 @@ pub fn build_action_key_sequence(raw_data: Option<&RawConfig>) -> Result<Action,
                 ))
             })?;
 
-            let count = hash.get_integer(COUNT_FIELD).unwrap_or(1);
+            let count = build_count(hash.get(&k(COUNT_FIELD)), "key_sequence")?;
 
             let delay = hash.get_integer(DELAY_FIELD)
                 .map(|d| {
@@ -82,17 +91,11 @@ pub fn build_action_key_sequence(raw_data: Option<&RawConfig>) -> Result<Action,
                     })
                 }).flatten();
 
-            if count < 0 {
-                Err(ConfigError::InvalidConfig(
-                    format!("Action key_sequence: count should be 0 or more, found {}", count)
-                ))
-            } else {
-                Ok(Action::KeySequence {
-                    sequence: sequence.to_string(),
-                    count: count as usize,
-                    delay
-                })
-            }
+            Ok(Action::KeySequence {
+                sequence: build_template(sequence)?,
+                count,
+                delay
+            })
         }
 
         _ => Err(ConfigError::InvalidConfig(format!(
@@ -104,7 +107,8 @@ pub fn build_action_key_sequence(raw_data: Option<&RawConfig>) -> Result<Action,
 #[cfg(test)]
 mod tests {
     use crate::config::raw_config::{RawConfig, RCHash, k};
-    use crate::macros::actions::Action;
+    use crate::macros::actions::{Action, Count};
+    use crate::macros::actions::template::{Template, TemplatePart, Variable, EventField};
     use crate::config::versions::version1::actions::key_sequence::build_action_key_sequence;
 
     #[test]
@@ -132,8 +136,8 @@ mod tests {
             .ok().unwrap();
 
         assert_eq!(action, Action::KeySequence {
-            sequence: "ctrl+shift+t".to_string(),
-            count: 3,
+            sequence: Template::literal("ctrl+shift+t"),
+            count: Count::Fixed(3),
             delay: None
         });
     }
@@ -148,8 +152,8 @@ mod tests {
             .ok().unwrap();
 
         assert_eq!(action, Action::KeySequence {
-            sequence: "ctrl+shift+t".to_string(),
-            count: 1,
+            sequence: Template::literal("ctrl+shift+t"),
+            count: Count::Fixed(1),
             delay: Some(2500)
         });
     }
@@ -164,8 +168,8 @@ mod tests {
             .ok().unwrap();
 
         assert_eq!(action, Action::KeySequence {
-            sequence: "ctrl+shift+t".to_string(),
-            count: 1,
+            sequence: Template::literal("ctrl+shift+t"),
+            count: Count::Fixed(1),
             delay: None
         });
     }
@@ -180,8 +184,8 @@ mod tests {
             .ok().unwrap();
 
         assert_eq!(action, Action::KeySequence {
-            sequence: "ctrl+shift+t".to_string(),
-            count: 1,
+            sequence: Template::literal("ctrl+shift+t"),
+            count: Count::Fixed(1),
             delay: Some(20_000)
         })
     }
@@ -197,8 +201,8 @@ mod tests {
             .ok().unwrap();
 
         assert_eq!(action, Action::KeySequence {
-            sequence: "ctrl+shift+t".to_string(),
-            count: 1,
+            sequence: Template::literal("ctrl+shift+t"),
+            count: Count::Fixed(1),
             delay: Some(33)
         })
     }
@@ -230,4 +234,43 @@ mod tests {
         let action = build_action_key_sequence(Some(&RawConfig::Null));
         assert!(action.is_err());
     }
+
+    #[test]
+    fn builds_key_sequence_action_with_variables() {
+        let action = build_action_key_sequence(Some(&k("ctrl+%event.key%")))
+            .ok().unwrap();
+
+        assert_eq!(action, Action::KeySequence {
+            sequence: Template::new(vec![
+                TemplatePart::Literal("ctrl+".to_string()),
+                TemplatePart::Variable(Variable::Event(EventField::Key))
+            ]),
+            count: Count::Fixed(1),
+            delay: None
+        });
+    }
+
+    #[test]
+    fn builds_key_sequence_action_with_variable_count() {
+        let mut data_hash = RCHash::new();
+        data_hash.insert(k("sequence"), k("Right"));
+        data_hash.insert(k("count"), k("%event.magnitude%"));
+
+        let action = build_action_key_sequence(Some(&RawConfig::Hash(data_hash)))
+            .ok().unwrap();
+
+        assert_eq!(action, Action::KeySequence {
+            sequence: Template::literal("Right"),
+            count: Count::Template(Template::new(vec![
+                TemplatePart::Variable(Variable::Event(EventField::Magnitude))
+            ])),
+            delay: None
+        });
+    }
+
+    #[test]
+    fn returns_an_error_if_sequence_contains_an_invalid_variable() {
+        let action = build_action_key_sequence(Some(&k("ctrl+%event.nope%")));
+        assert!(action.is_err());
+    }
 }
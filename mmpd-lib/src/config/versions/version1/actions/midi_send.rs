@@ -0,0 +1,355 @@
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+use crate::macros::actions::Action;
+use crate::midi::{MidiMessage, parse_keys_from_str};
+use crate::config::ConfigError;
+
+/// Constructs an `Action::MidiSend` from `raw_data` `RawConfig`.
+///
+/// `raw_data` must be a `RawConfig::Hash` of this form:
+///
+/// ```yaml
+/// port: "Launchpad"
+/// message_type: note_on
+/// channel: 0
+/// key: 36
+/// velocity: 127
+/// ```
+///
+/// `port` is required, and should be a String. The message is sent to the first MIDI output port
+/// whose name contains this string.
+///
+/// `message_type` is required, and must be one of the types below. Any additional fields depend on
+/// the message type:
+///
+/// - `note_on`: `channel`, `key`, `velocity`
+/// - `note_off`: `channel`, `key`, `velocity`
+/// - `control_change`: `channel`, `control`, `value`
+/// - `program_change`: `channel`, `program`
+/// - `sys_ex`: `bytes`
+///
+/// `channel` is optional, defaults to 0, and must be an integer in range 0-15.
+///
+/// `key` is required, either an integer in range 0-127, or a note name including octave, like
+/// "C3" (see `parse_keys_from_str`).
+///
+/// `velocity` is optional, and must be an integer in range 0-127. It defaults to 127 for
+/// `note_on` and to 0 for `note_off`.
+///
+/// `control`, `value`, and `program` are required integers in range 0-127.
+///
+/// `bytes` is a required list of integers in range 0-127, being the content of the system
+/// exclusive message, without the start (0xF0) and end (0xF7) bytes, which are added
+/// automatically.
+///
+/// ## Errors
+/// The function returns `ConfigError` under any of the following conditions:
+///
+/// - `raw_data` is `None`
+/// - `raw_data` is not a `RawConfig::Hash`
+/// - `port` or `message_type` are missing or aren't strings
+/// - `message_type` is not one of the supported values
+/// - A required field for the message type is missing
+/// - Any of the fields has a value of the wrong type or out of range
+pub fn build_action_midi_send(raw_data: Option<&RawConfig>) -> Result<Action, ConfigError> {
+    const PORT_FIELD: &str = "port";
+    const MESSAGE_TYPE_FIELD: &str = "message_type";
+    const CHANNEL_FIELD: &str = "channel";
+    const KEY_FIELD: &str = "key";
+    const VELOCITY_FIELD: &str = "velocity";
+    const CONTROL_FIELD: &str = "control";
+    const VALUE_FIELD: &str = "value";
+    const PROGRAM_FIELD: &str = "program";
+    const BYTES_FIELD: &str = "bytes";
+
+    const NOTE_ON_MESSAGE: &str = "note_on";
+    const NOTE_OFF_MESSAGE: &str = "note_off";
+    const CONTROL_CHANGE_MESSAGE: &str = "control_change";
+    const PROGRAM_CHANGE_MESSAGE: &str = "program_change";
+    const SYS_EX_MESSAGE: &str = "sys_ex";
+
+    const MAX_CHANNEL: i64 = 15;
+    const MAX_DATA_VALUE: i64 = 127;
+
+    let hash = match raw_data {
+        Some(RawConfig::Hash(hash)) => hash,
+
+        Some(_) => return Err(ConfigError::InvalidConfig(
+            "Action midi_send: data field should be a hash".to_string()
+        )),
+
+        None => return Err(ConfigError::InvalidConfig(
+            "Action midi_send: missing data field".to_string()
+        ))
+    };
+
+    let port = hash.get_string(PORT_FIELD).ok_or_else(|| {
+        ConfigError::InvalidConfig(format!(
+            "Action midi_send: data field doesn't contain a '{}' field",
+            PORT_FIELD
+        ))
+    })?;
+
+    let message_type = hash.get_string(MESSAGE_TYPE_FIELD).ok_or_else(|| {
+        ConfigError::InvalidConfig(format!(
+            "Action midi_send: data field doesn't contain a '{}' field",
+            MESSAGE_TYPE_FIELD
+        ))
+    })?;
+
+    let channel = get_data_byte(hash, CHANNEL_FIELD, MAX_CHANNEL)?.unwrap_or(0);
+
+    let required = |field: &str| -> Result<u8, ConfigError> {
+        get_data_byte(hash, field, MAX_DATA_VALUE)?.ok_or_else(|| {
+            ConfigError::InvalidConfig(format!(
+                "Action midi_send: {} message requires a '{}' field",
+                message_type, field
+            ))
+        })
+    };
+
+    let message = match message_type {
+        NOTE_ON_MESSAGE => MidiMessage::NoteOn {
+            channel,
+            key: get_key(hash, KEY_FIELD)?,
+            velocity: get_data_byte(hash, VELOCITY_FIELD, MAX_DATA_VALUE)?.unwrap_or(127)
+        },
+
+        NOTE_OFF_MESSAGE => MidiMessage::NoteOff {
+            channel,
+            key: get_key(hash, KEY_FIELD)?,
+            velocity: get_data_byte(hash, VELOCITY_FIELD, MAX_DATA_VALUE)?.unwrap_or(0)
+        },
+
+        CONTROL_CHANGE_MESSAGE => MidiMessage::ControlChange {
+            channel,
+            control: required(CONTROL_FIELD)?,
+            value: required(VALUE_FIELD)?
+        },
+
+        PROGRAM_CHANGE_MESSAGE => MidiMessage::ProgramChange {
+            channel,
+            program: required(PROGRAM_FIELD)?
+        },
+
+        SYS_EX_MESSAGE => {
+            let raw_bytes = hash.get_array(BYTES_FIELD).ok_or_else(|| {
+                ConfigError::InvalidConfig(format!(
+                    "Action midi_send: {} message requires a '{}' list",
+                    message_type, BYTES_FIELD
+                ))
+            })?;
+
+            let data = raw_bytes
+                .iter()
+                .map(|b| {
+                    match b {
+                        RawConfig::Integer(b) if (0..=MAX_DATA_VALUE).contains(b) => Ok(*b as u8),
+                        _ => Err(ConfigError::InvalidConfig(format!(
+                            "Action midi_send: items in '{}' must be integers in range 0-{}",
+                            BYTES_FIELD, MAX_DATA_VALUE
+                        )))
+                    }
+                })
+                .collect::<Result<Vec<u8>, ConfigError>>()?;
+
+            MidiMessage::SysEx { data }
+        }
+
+        _ => return Err(ConfigError::InvalidConfig(format!(
+            "Action midi_send: unsupported {} '{}'",
+            MESSAGE_TYPE_FIELD, message_type
+        )))
+    };
+
+    Ok(Action::MidiSend { port: port.to_string(), message })
+}
+
+/// Retrieves an optional integer field from `hash`, checking it's in range 0-`max`.
+fn get_data_byte(hash: &RCHash, field: &str, max: i64) -> Result<Option<u8>, ConfigError> {
+    match hash.get(&k(field)) {
+        None => Ok(None),
+
+        Some(RawConfig::Integer(i)) if (0..=max).contains(i) => Ok(Some(*i as u8)),
+
+        Some(_) => Err(ConfigError::InvalidConfig(format!(
+            "Action midi_send: '{}' must be an integer in range 0-{}",
+            field, max
+        )))
+    }
+}
+
+/// Retrieves a required key field from `hash`, given either as note number or as a note name with
+/// octave.
+fn get_key(hash: &RCHash, field: &str) -> Result<u8, ConfigError> {
+    if let Some(key_str) = hash.get_string(field) {
+        let keys = parse_keys_from_str(key_str);
+
+        return if keys.len() == 1 {
+            Ok(keys[0])
+        } else {
+            Err(ConfigError::InvalidConfig(format!(
+                "Action midi_send: '{}' must be a single note including octave, found '{}'",
+                field, key_str
+            )))
+        }
+    }
+
+    get_data_byte(hash, field, 127)?.ok_or_else(|| {
+        ConfigError::InvalidConfig(format!(
+            "Action midi_send: note messages require a '{}' field",
+            field
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::versions::version1::actions::midi_send::build_action_midi_send;
+    use crate::config::raw_config::{RawConfig, RCHash, k};
+    use crate::macros::actions::Action;
+    use crate::midi::MidiMessage;
+
+    fn data(fields: Vec<(&str, RawConfig)>) -> RawConfig {
+        let mut hash = RCHash::new();
+        hash.insert(k("port"), k("Launchpad"));
+
+        for (key, value) in fields {
+            hash.insert(k(key), value);
+        }
+
+        RawConfig::Hash(hash)
+    }
+
+    #[test]
+    fn returns_error_if_data_is_missing_or_not_a_hash() {
+        assert!(build_action_midi_send(None).is_err());
+        assert!(build_action_midi_send(Some(&k("Launchpad"))).is_err());
+    }
+
+    #[test]
+    fn returns_error_if_port_is_missing() {
+        let mut hash = RCHash::new();
+        hash.insert(k("message_type"), k("program_change"));
+        hash.insert(k("program"), RawConfig::Integer(1));
+
+        assert!(build_action_midi_send(Some(&RawConfig::Hash(hash))).is_err());
+    }
+
+    #[test]
+    fn builds_note_messages() {
+        let action = build_action_midi_send(Some(&data(vec![
+            ("message_type", k("note_on")),
+            ("channel", RawConfig::Integer(2)),
+            ("key", RawConfig::Integer(36)),
+            ("velocity", RawConfig::Integer(5))
+        ]))).ok().unwrap();
+
+        assert_eq!(action, Action::MidiSend {
+            port: "Launchpad".to_string(),
+            message: MidiMessage::NoteOn { channel: 2, key: 36, velocity: 5 }
+        });
+
+        let action = build_action_midi_send(Some(&data(vec![
+            ("message_type", k("note_off")),
+            ("key", k("C3"))
+        ]))).ok().unwrap();
+
+        assert_eq!(action, Action::MidiSend {
+            port: "Launchpad".to_string(),
+            message: MidiMessage::NoteOff { channel: 0, key: 48, velocity: 0 }
+        });
+    }
+
+    #[test]
+    fn defaults_note_on_velocity_to_max() {
+        let action = build_action_midi_send(Some(&data(vec![
+            ("message_type", k("note_on")),
+            ("key", RawConfig::Integer(36))
+        ]))).ok().unwrap();
+
+        assert_eq!(action, Action::MidiSend {
+            port: "Launchpad".to_string(),
+            message: MidiMessage::NoteOn { channel: 0, key: 36, velocity: 127 }
+        });
+    }
+
+    #[test]
+    fn builds_control_change_and_program_change_messages() {
+        let action = build_action_midi_send(Some(&data(vec![
+            ("message_type", k("control_change")),
+            ("channel", RawConfig::Integer(15)),
+            ("control", RawConfig::Integer(7)),
+            ("value", RawConfig::Integer(100))
+        ]))).ok().unwrap();
+
+        assert_eq!(action, Action::MidiSend {
+            port: "Launchpad".to_string(),
+            message: MidiMessage::ControlChange { channel: 15, control: 7, value: 100 }
+        });
+
+        let action = build_action_midi_send(Some(&data(vec![
+            ("message_type", k("program_change")),
+            ("program", RawConfig::Integer(3))
+        ]))).ok().unwrap();
+
+        assert_eq!(action, Action::MidiSend {
+            port: "Launchpad".to_string(),
+            message: MidiMessage::ProgramChange { channel: 0, program: 3 }
+        });
+    }
+
+    #[test]
+    fn builds_sysex_messages() {
+        let action = build_action_midi_send(Some(&data(vec![
+            ("message_type", k("sys_ex")),
+            ("bytes", RawConfig::Array(vec![
+                RawConfig::Integer(0x00),
+                RawConfig::Integer(0x20),
+                RawConfig::Integer(0x29)
+            ]))
+        ]))).ok().unwrap();
+
+        assert_eq!(action, Action::MidiSend {
+            port: "Launchpad".to_string(),
+            message: MidiMessage::SysEx { data: vec![0x00, 0x20, 0x29] }
+        });
+    }
+
+    #[test]
+    fn returns_error_for_missing_or_invalid_fields() {
+        // Unknown message type
+        assert!(build_action_midi_send(Some(&data(vec![
+            ("message_type", k("pitch_bend_change"))
+        ]))).is_err());
+
+        // Missing key
+        assert!(build_action_midi_send(Some(&data(vec![
+            ("message_type", k("note_on"))
+        ]))).is_err());
+
+        // Key that isn't a single note
+        assert!(build_action_midi_send(Some(&data(vec![
+            ("message_type", k("note_on")),
+            ("key", k("C"))
+        ]))).is_err());
+
+        // Channel out of range
+        assert!(build_action_midi_send(Some(&data(vec![
+            ("message_type", k("program_change")),
+            ("channel", RawConfig::Integer(16)),
+            ("program", RawConfig::Integer(1))
+        ]))).is_err());
+
+        // Missing value
+        assert!(build_action_midi_send(Some(&data(vec![
+            ("message_type", k("control_change")),
+            ("control", RawConfig::Integer(1))
+        ]))).is_err());
+
+        // SysEx byte out of range
+        assert!(build_action_midi_send(Some(&data(vec![
+            ("message_type", k("sys_ex")),
+            ("bytes", RawConfig::Array(vec![RawConfig::Integer(0xF7)]))
+        ]))).is_err());
+    }
+}
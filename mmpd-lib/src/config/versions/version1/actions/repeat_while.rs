@@ -0,0 +1,136 @@
+use std::time::Duration;
+use crate::config::raw_config::{RawConfig, AccessHelpers, k};
+use crate::config::ConfigError;
+use crate::config::versions::version1::actions::{build_action_list, build_precondition_list};
+use crate::config::versions::version1::action_groups::ActionGroups;
+use crate::macros::actions::Action;
+
+/// Constructs an `Action::RepeatWhile` from `raw_data` structured as follows:
+///
+/// ```yaml
+/// required_preconditions:
+///     - # (one or more preconditions)
+/// interval_ms: 100
+/// actions:
+///     - # (one or more actions to repeat)
+/// ```
+///
+/// `required_preconditions` is required, and must be a list of at least one precondition, in the
+/// same structure as a macro's `required_preconditions` (see `build_precondition`). The actions
+/// are repeated for as long as all of these are satisfied.
+///
+/// `interval` is the time to wait between repetitions, in microseconds. Alternatively,
+/// `interval_ms` specifies it in milliseconds. One of the two is required; if both are specified,
+/// `interval` is used.
+///
+/// `actions` is required, and must be a list of at least one action, in the same structure as a
+/// macro's `actions` (see `build_action`).
+///
+/// ## Errors
+/// The function returns a `ConfigError` if `raw_data` isn't a hash, `required_preconditions` or
+/// `actions` are missing or empty, neither `interval` nor `interval_ms` is a positive integer, or
+/// down the stream, one of the preconditions or actions fails to be built.
+pub fn build_action_repeat_while(
+    raw_data: Option<&RawConfig>,
+    groups: &ActionGroups
+) -> Result<Action, ConfigError> {
+    const ACTION: &str = "repeat_while";
+    const REQUIRED_PRECONDITIONS_FIELD: &str = "required_preconditions";
+    const INTERVAL_FIELD: &str = "interval";
+    const INTERVAL_MS_FIELD: &str = "interval_ms";
+    const ACTIONS_FIELD: &str = "actions";
+
+    let hash = match raw_data {
+        Some(RawConfig::Hash(hash)) => hash,
+
+        _ => return Err(ConfigError::InvalidConfig(
+            "Action repeat_while: data field should be a hash".to_string()
+        ))
+    };
+
+    let interval = hash.get_integer(INTERVAL_FIELD)
+        .filter(|i| *i > 0)
+        .map(|i| Duration::from_micros(i as u64))
+        .or_else(|| {
+            hash.get_integer(INTERVAL_MS_FIELD)
+                .filter(|i| *i > 0)
+                .map(|i| Duration::from_millis(i as u64))
+        })
+        .ok_or_else(|| ConfigError::InvalidConfig(format!(
+            "Action repeat_while: no positive {} or {} specified",
+            INTERVAL_FIELD, INTERVAL_MS_FIELD
+        )))?;
+
+    let required_preconditions = build_precondition_list(
+        hash.get(&k(REQUIRED_PRECONDITIONS_FIELD)),
+        ACTION,
+        REQUIRED_PRECONDITIONS_FIELD
+    )?;
+
+    let actions = build_action_list(hash.get(&k(ACTIONS_FIELD)), groups, ACTION, ACTIONS_FIELD)?;
+
+    Ok(Action::RepeatWhile { required_preconditions, interval, actions })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::config::raw_config::{RawConfig, RCHashBuilder, k};
+    use crate::config::versions::version1::actions::repeat_while::build_action_repeat_while;
+    use crate::config::versions::version1::action_groups::ActionGroups;
+    use crate::macros::actions::Action;
+    use crate::macros::preconditions::{Precondition, PreconditionType};
+
+    fn data(interval_field: &str, with_preconditions: bool) -> RawConfig {
+        let precondition = RawConfig::Hash(
+            RCHashBuilder::new().insert(k("type"), k("other")).build()
+        );
+        let action = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("type"), k("wait"))
+                .insert(k("data"), RawConfig::Integer(20))
+                .build()
+        );
+
+        let preconditions = if with_preconditions { vec![precondition] } else { vec![] };
+
+        RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("required_preconditions"), RawConfig::Array(preconditions))
+                .insert(k(interval_field), RawConfig::Integer(100))
+                .insert(k("actions"), RawConfig::Array(vec![action]))
+                .build()
+        )
+    }
+
+    #[test]
+    fn builds_repeat_while_action() {
+        let groups = ActionGroups::new(None);
+
+        assert_eq!(
+            build_action_repeat_while(Some(&data("interval_ms", true)), &groups).ok().unwrap(),
+            Action::RepeatWhile {
+                required_preconditions: vec![Precondition {
+                    invert: false,
+                    condition: PreconditionType::Other
+                }],
+                interval: Duration::from_millis(100),
+                actions: vec![Action::Wait { duration: 20 }]
+            }
+        );
+
+        assert!(matches!(
+            build_action_repeat_while(Some(&data("interval", true)), &groups).ok().unwrap(),
+            Action::RepeatWhile { interval, .. } if interval == Duration::from_micros(100)
+        ));
+    }
+
+    #[test]
+    fn returns_an_error_if_fields_are_missing() {
+        let groups = ActionGroups::new(None);
+
+        assert!(build_action_repeat_while(None, &groups).is_err());
+        assert!(build_action_repeat_while(Some(&data("interval_ms", false)), &groups).is_err());
+        assert!(build_action_repeat_while(Some(&data("not_interval", true)), &groups).is_err());
+    }
+}
@@ -1,6 +1,8 @@
 use crate::config::raw_config::{RawConfig, AccessHelpers};
 use crate::macros::actions::Action;
 use crate::config::ConfigError;
+use crate::config::versions::version1::templates::build_template;
+use crate::macros::actions::template::Template;
 
 /// Constructs an `Action:Shell` from `raw_data` `RawConfig`.
 ///
@@ -26,6 +28,8 @@ use crate::config::ConfigError;
 ///
 ///    `env_vars` is optional, a hash of string/int key / string/int value pairs
 ///
+///    String `args` and `env_vars` values may contain variables, see `build_template`.
+///
 /// ## Errors
 /// The function returns ConfigError under any of the following conditions:
 ///
@@ -34,6 +38,7 @@ use crate::config::ConfigError;
 /// - `raw_data` is `RawConfig::Hash` but is missing a `RawConfig::String` `command` field
 /// - Any of the items in `args` is neither `RawConfig::String` or `RawConfig::Int`
 /// - Any of the keys or values in `env_vars` is neither `RawConfig::String` or `RawConfig::Int`
+/// - Any of the items in `args` or values in `env_vars` contains an invalid variable
 pub fn build_action_shell(raw_data: Option<&RawConfig>) -> Result<Action, ConfigError> {
     const COMMAND_FIELD: &str = "command";
     const ARGS_FIELD: &str = "args";
@@ -71,16 +76,18 @@ pub fn build_action_shell(raw_data: Option<&RawConfig>) -> Result<Action, Config
                             format!("Action: shell: invalid argument type")
                         ))
                     } else {
-                        let out_args: Vec<String> = raw_args
+                        let out_args: Vec<Template> = raw_args
                             .iter()
                             .filter_map(|a| {
                                 match a {
-                                    RawConfig::Integer(i) => Some(i.to_string()),
-                                    RawConfig::String(s) => Some(s.to_string()),
+                                    RawConfig::Integer(i) => Some(
+                                        Ok(Template::literal(&i.to_string()))
+                                    ),
+                                    RawConfig::String(s) => Some(build_template(s)),
                                     _ => None
                                 }
                             })
-                            .collect();
+                            .collect::<Result<Vec<Template>, ConfigError>>()?;
 
                         Ok(if out_args.is_empty() {
                             None
@@ -113,7 +120,7 @@ pub fn build_action_shell(raw_data: Option<&RawConfig>) -> Result<Action, Config
                             format!("Action: shell: invalid env var key and/or value type")
                         ))
                     } else {
-                        let out_env_vars: Vec<(String, String)> = raw_env_vars
+                        let out_env_vars: Vec<(String, Template)> = raw_env_vars
                             .iter()
                             .filter_map(|(k, v)| {
                                 let k = match k {
@@ -123,18 +130,20 @@ pub fn build_action_shell(raw_data: Option<&RawConfig>) -> Result<Action, Config
                                 };
 
                                 let v = match v {
-                                    RawConfig::Integer(i) => Some(i.to_string()),
-                                    RawConfig::String(s) => Some(s.to_string()),
+                                    RawConfig::Integer(i) => Some(
+                                        Ok(Template::literal(&i.to_string()))
+                                    ),
+                                    RawConfig::String(s) => Some(build_template(s)),
                                     _ => None
                                 };
 
                                 if k.is_none() || v.is_none() {
                                     None
                                 } else {
-                                    Some((k.unwrap(), v.unwrap()))
+                                    Some(v.unwrap().map(|v| (k.unwrap(), v)))
                                 }
                             })
-                            .collect();
+                            .collect::<Result<Vec<(String, Template)>, ConfigError>>()?;
 
                         Ok(if out_env_vars.is_empty() {
                             None
@@ -159,6 +168,7 @@ mod tests {
     use crate::config::versions::version1::actions::shell::build_action_shell;
     use crate::config::raw_config::{RawConfig, RCHash, k};
     use crate::macros::actions::Action;
+    use crate::macros::actions::template::{Template, TemplatePart, Variable, EventField};
 
     #[test]
     fn returns_error_if_no_data_is_provided() {
@@ -227,7 +237,11 @@ mod tests {
             action,
             Action::Shell {
                 command: "cmd".to_string(),
-                args: Some(vec!["arg1".to_string(), 7.to_string(), "arg3".to_string()]),
+                args: Some(vec![
+                    Template::literal("arg1"),
+                    Template::literal("7"),
+                    Template::literal("arg3")
+                ]),
                 env_vars: None,
             }
         );
@@ -267,8 +281,8 @@ mod tests {
                 command: "cmd".to_string(),
                 args: None,
                 env_vars: Some(vec![
-                    ("env1".to_string(), "val1".to_string()),
-                    ("env2".to_string(), "val2".to_string())
+                    ("env1".to_string(), Template::literal("val1")),
+                    ("env2".to_string(), Template::literal("val2"))
                 ])
             }
         );
@@ -322,12 +336,63 @@ mod tests {
             action,
             Action::Shell {
                 command: "cmd".to_string(),
-                args: Some(vec!["arg1".to_string(), 4.to_string()]),
+                args: Some(vec![Template::literal("arg1"), Template::literal("4")]),
+                env_vars: Some(vec![
+                    ("env1".to_string(), Template::literal("val1")),
+                    ("env2".to_string(), Template::literal("7"))
+                ])
+            }
+        );
+    }
+
+    #[test]
+    fn builds_shell_action_with_variables_in_args_and_env_vars() {
+        let mut hash = RCHash::new();
+        hash.insert(k("command"), k("cmd"));
+        hash.insert(k("args"), RawConfig::Array(vec![k("--key=%event.key%")]));
+
+        let mut env_vars_hash = RCHash::new();
+        env_vars_hash.insert(k("VALUE"), k("%event.value%"));
+        hash.insert(k("env_vars"), RawConfig::Hash(env_vars_hash));
+
+        let action = build_action_shell(Some(&RawConfig::Hash(hash)))
+            .ok().unwrap();
+
+        assert_eq!(
+            action,
+            Action::Shell {
+                command: "cmd".to_string(),
+                args: Some(vec![Template::new(vec![
+                    TemplatePart::Literal("--key=".to_string()),
+                    TemplatePart::Variable(Variable::Event(EventField::Key))
+                ])]),
                 env_vars: Some(vec![
-                    ("env1".to_string(), "val1".to_string()),
-                    ("env2".to_string(), 7.to_string())
+                    (
+                        "VALUE".to_string(),
+                        Template::new(vec![
+                            TemplatePart::Variable(Variable::Event(EventField::Value))
+                        ])
+                    )
                 ])
             }
         );
     }
+
+    #[test]
+    fn returns_error_if_args_or_env_vars_contain_invalid_variables() {
+        let mut hash = RCHash::new();
+        hash.insert(k("command"), k("cmd"));
+        hash.insert(k("args"), RawConfig::Array(vec![k("%event.nope%")]));
+
+        assert!(build_action_shell(Some(&RawConfig::Hash(hash))).is_err());
+
+        let mut env_vars_hash = RCHash::new();
+        env_vars_hash.insert(k("VALUE"), k("50%"));
+
+        let mut hash = RCHash::new();
+        hash.insert(k("command"), k("cmd"));
+        hash.insert(k("env_vars"), RawConfig::Hash(env_vars_hash));
+
+        assert!(build_action_shell(Some(&RawConfig::Hash(hash))).is_err());
+    }
 }
@@ -0,0 +1,278 @@
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+use crate::config::ConfigError;
+use crate::macros::actions::{Action, VariableAction};
+use crate::state::Value;
+
+const NAME_FIELD: &str = "name";
+
+/// Constructs an `Action::Variable` setting a variable, from `raw_data` structured as follows:
+///
+/// ```yaml
+/// name: layer
+/// value: editing
+/// ```
+///
+/// `name` is required, and is the name of the variable to set.
+///
+/// `value` is required, and can be a string, an integer, or a boolean.
+///
+/// ## Errors
+/// The function returns a `ConfigError` if `raw_data` isn't a hash, `name` is missing, or `value`
+/// is missing or isn't a string, integer or boolean.
+pub fn build_action_set_var(raw_data: Option<&RawConfig>) -> Result<Action, ConfigError> {
+    const ACTION: &str = "set_var";
+    const VALUE_FIELD: &str = "value";
+
+    let hash = get_hash(ACTION, raw_data)?;
+    let name = get_name(ACTION, hash)?;
+
+    let value = hash
+        .get(&k(VALUE_FIELD))
+        .and_then(build_value)
+        .ok_or_else(|| ConfigError::InvalidConfig(format!(
+            "Action {}: '{}' should be a string, integer or boolean",
+            ACTION, VALUE_FIELD
+        )))?;
+
+    Ok(Action::Variable(VariableAction::Set { name, value }))
+}
+
+/// Constructs an `Action::Variable` flipping a boolean variable. `raw_data` is either the name of
+/// the variable, or a hash with a `name` field.
+///
+/// ## Errors
+/// The function returns a `ConfigError` if no name is found in `raw_data`.
+pub fn build_action_toggle_var(raw_data: Option<&RawConfig>) -> Result<Action, ConfigError> {
+    const ACTION: &str = "toggle_var";
+
+    let name = match raw_data {
+        Some(RawConfig::String(name)) => name.to_string(),
+        _ => get_name(ACTION, get_hash(ACTION, raw_data)?)?
+    };
+
+    Ok(Action::Variable(VariableAction::Toggle { name }))
+}
+
+/// Constructs an `Action::Variable` adding to an integer variable, from `raw_data` structured as
+/// follows:
+///
+/// ```yaml
+/// name: page
+/// by: -1
+/// min: 0
+/// max: 7
+/// wrap: true
+/// ```
+///
+/// `name` is required, and is the name of the variable to change.
+///
+/// `by` is optional, and is the (possibly negative) amount to add. Defaults to 1.
+///
+/// `min` and `max` are optional, and limit the range of the variable. Values past either end are
+/// clamped, unless `wrap` is `true`, in which case they wrap around to the other end. `wrap`
+/// requires both `min` and `max`.
+///
+/// ## Errors
+/// The function returns a `ConfigError` if `raw_data` isn't a hash, `name` is missing, `min` is
+/// greater than `max`, or `wrap` is set without both `min` and `max`.
+pub fn build_action_increment_var(raw_data: Option<&RawConfig>) -> Result<Action, ConfigError> {
+    const ACTION: &str = "increment_var";
+    const BY_FIELD: &str = "by";
+    const MIN_FIELD: &str = "min";
+    const MAX_FIELD: &str = "max";
+    const WRAP_FIELD: &str = "wrap";
+
+    let hash = get_hash(ACTION, raw_data)?;
+    let name = get_name(ACTION, hash)?;
+
+    let by = hash.get_integer(BY_FIELD).unwrap_or(1);
+    let min = hash.get_integer(MIN_FIELD);
+    let max = hash.get_integer(MAX_FIELD);
+    let wrap = hash.get_bool(WRAP_FIELD).unwrap_or(false);
+
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return Err(ConfigError::InvalidConfig(format!(
+                "Action {}: '{}' ({}) is greater than '{}' ({})",
+                ACTION, MIN_FIELD, min, MAX_FIELD, max
+            )));
+        }
+    }
+
+    if wrap && (min.is_none() || max.is_none()) {
+        return Err(ConfigError::InvalidConfig(format!(
+            "Action {}: '{}' requires both '{}' and '{}'",
+            ACTION, WRAP_FIELD, MIN_FIELD, MAX_FIELD
+        )));
+    }
+
+    Ok(Action::Variable(VariableAction::Increment { name, by, min, max, wrap }))
+}
+
+/// Constructs an `Action::Variable` stepping a variable through a list of values, from `raw_data`
+/// structured as follows:
+///
+/// ```yaml
+/// name: layer
+/// values: [editing, browsing, media]
+/// ```
+///
+/// `name` is required, and is the name of the variable to change.
+///
+/// `values` is required, and is a list of at least one string, integer or boolean.
+///
+/// ## Errors
+/// The function returns a `ConfigError` if `raw_data` isn't a hash, `name` is missing, or `values`
+/// is missing, empty, or contains anything other than strings, integers and booleans.
+pub fn build_action_cycle_var(raw_data: Option<&RawConfig>) -> Result<Action, ConfigError> {
+    const ACTION: &str = "cycle_var";
+    const VALUES_FIELD: &str = "values";
+
+    let hash = get_hash(ACTION, raw_data)?;
+    let name = get_name(ACTION, hash)?;
+
+    let invalid_values = || ConfigError::InvalidConfig(format!(
+        "Action {}: '{}' should be a list of strings, integers or booleans",
+        ACTION, VALUES_FIELD
+    ));
+
+    let values = hash
+        .get_array(VALUES_FIELD)
+        .ok_or_else(invalid_values)?
+        .iter()
+        .map(|raw_value| build_value(raw_value).ok_or_else(invalid_values))
+        .collect::<Result<Vec<Value>, ConfigError>>()?;
+
+    if values.is_empty() {
+        return Err(ConfigError::InvalidConfig(format!(
+            "Action {}: '{}' should contain at least one value",
+            ACTION, VALUES_FIELD
+        )));
+    }
+
+    Ok(Action::Variable(VariableAction::Cycle { name, values }))
+}
+
+/// Converts a string, integer or boolean `raw_value` into a variable `Value`
+pub (crate) fn build_value(raw_value: &RawConfig) -> Option<Value> {
+    match raw_value {
+        RawConfig::String(s) => Some(Value::String(s.to_string())),
+        RawConfig::Integer(i) => Some(Value::Integer(*i)),
+        RawConfig::Bool(b) => Some(Value::Bool(*b)),
+        _ => None
+    }
+}
+
+fn get_hash<'a>(action: &str, raw_data: Option<&'a RawConfig>) -> Result<&'a RCHash, ConfigError> {
+    match raw_data {
+        Some(RawConfig::Hash(hash)) => Ok(hash),
+
+        _ => Err(ConfigError::InvalidConfig(
+            format!("Action {}: data field should be a hash", action)
+        ))
+    }
+}
+
+fn get_name(action: &str, hash: &RCHash) -> Result<String, ConfigError> {
+    hash.get_string(NAME_FIELD)
+        .map(|name| name.to_string())
+        .ok_or_else(|| ConfigError::InvalidConfig(format!(
+            "Action {}: missing string '{}' field",
+            action, NAME_FIELD
+        )))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::raw_config::{RawConfig, RCHashBuilder, k};
+    use crate::config::versions::version1::actions::variable::{
+        build_action_set_var, build_action_toggle_var, build_action_increment_var,
+        build_action_cycle_var
+    };
+    use crate::macros::actions::{Action, VariableAction};
+    use crate::state::Value;
+
+    #[test]
+    fn builds_set_and_toggle_var_actions() {
+        let data = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("name"), k("layer"))
+                .insert(k("value"), k("editing"))
+                .build()
+        );
+
+        assert_eq!(
+            build_action_set_var(Some(&data)).ok().unwrap(),
+            Action::Variable(VariableAction::Set {
+                name: "layer".to_string(),
+                value: Value::String("editing".to_string())
+            })
+        );
+
+        assert_eq!(
+            build_action_toggle_var(Some(&k("shift"))).ok().unwrap(),
+            Action::Variable(VariableAction::Toggle { name: "shift".to_string() })
+        );
+
+        let no_value = RawConfig::Hash(RCHashBuilder::new().insert(k("name"), k("layer")).build());
+        assert!(build_action_set_var(Some(&no_value)).is_err());
+        assert!(build_action_toggle_var(None).is_err());
+    }
+
+    #[test]
+    fn builds_increment_var_actions() {
+        let data = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("name"), k("page"))
+                .insert(k("by"), RawConfig::Integer(-1))
+                .insert(k("min"), RawConfig::Integer(0))
+                .insert(k("max"), RawConfig::Integer(7))
+                .insert(k("wrap"), RawConfig::Bool(true))
+                .build()
+        );
+
+        assert_eq!(
+            build_action_increment_var(Some(&data)).ok().unwrap(),
+            Action::Variable(VariableAction::Increment {
+                name: "page".to_string(),
+                by: -1,
+                min: Some(0),
+                max: Some(7),
+                wrap: true
+            })
+        );
+
+        // Wrapping needs both ends of the range
+        let data = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("name"), k("page"))
+                .insert(k("max"), RawConfig::Integer(7))
+                .insert(k("wrap"), RawConfig::Bool(true))
+                .build()
+        );
+
+        assert!(build_action_increment_var(Some(&data)).is_err());
+    }
+
+    #[test]
+    fn builds_cycle_var_actions() {
+        let values = |values: Vec<RawConfig>| RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("name"), k("layer"))
+                .insert(k("values"), RawConfig::Array(values))
+                .build()
+        );
+
+        assert_eq!(
+            build_action_cycle_var(Some(&values(vec![k("editing"), RawConfig::Integer(2)])))
+                .ok().unwrap(),
+            Action::Variable(VariableAction::Cycle {
+                name: "layer".to_string(),
+                values: vec![Value::String("editing".to_string()), Value::Integer(2)]
+            })
+        );
+
+        assert!(build_action_cycle_var(Some(&values(vec![]))).is_err());
+        assert!(build_action_cycle_var(Some(&values(vec![RawConfig::Null]))).is_err());
+    }
+}
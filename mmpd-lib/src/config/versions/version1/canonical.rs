@@ -0,0 +1,325 @@
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+use crate::config::versions::Form;
+use crate::config::versions::version1::action_groups::lone_param;
+
+const TYPE_FIELD: &str = "type";
+const DATA_FIELD: &str = "data";
+const ACTIONS_FIELD: &str = "actions";
+const REQUIRED_PRECONDITIONS_FIELD: &str = "required_preconditions";
+
+/// Action types whose `data` can be just the value of one of its fields, along with that field
+const SHORTHAND_ACTIONS: [(&str, &str); 8] = [
+    ("key_sequence", "sequence"),
+    ("enter_text", "text"),
+    ("shell", "command"),
+    ("wait", "duration"),
+    ("control", "action"),
+    ("toggle_var", "name"),
+    ("cancel_macro", "name"),
+    ("call", "group")
+];
+
+/// Rewrites the shorthand forms in `raw_config` into `form`. These are:
+///
+/// - The `data` of the actions in `SHORTHAND_ACTIONS`, which is either a hash with only the field
+///   listed there, like `{ text: "Hello" }` for `enter_text`, or just that field's value
+/// - The `string` matcher of variable preconditions, which is either `{ is: "value" }`, or just
+///   the value
+///
+/// Only the structure is followed here, like in `find_unknown_fields`; parts that aren't valid are
+/// left as they are. Values that are nothing but an action group parameter reference are left
+/// alone too, as their parameter could hold a hash.
+pub (crate) fn canonicalize(raw_config: &mut RCHash, form: Form) {
+    if let Some(RawConfig::Hash(groups)) = raw_config.get_mut(&k("action_groups")) {
+        for (_, group) in groups.iter_mut() {
+            match group {
+                RawConfig::Hash(group) => {
+                    canonicalize_actions(group.get_mut(&k(ACTIONS_FIELD)), form);
+                }
+
+                group => canonicalize_actions(Some(group), form)
+            }
+        }
+    }
+
+    for scope in hashes_mut(raw_config.get_mut(&k("scopes"))) {
+        for raw_macro in hashes_mut(scope.get_mut(&k("macros"))) {
+            canonicalize_macro(raw_macro, form);
+        }
+    }
+
+    for raw_macro in hashes_mut(raw_config.get_mut(&k("global_macros"))) {
+        canonicalize_macro(raw_macro, form);
+    }
+}
+
+pub (crate) fn canonicalize_macro(raw_macro: &mut RCHash, form: Form) {
+    for event in hashes_mut(raw_macro.get_mut(&k("matching_events"))) {
+        canonicalize_preconditions(event.get_mut(&k(REQUIRED_PRECONDITIONS_FIELD)), form);
+    }
+
+    canonicalize_preconditions(raw_macro.get_mut(&k(REQUIRED_PRECONDITIONS_FIELD)), form);
+    canonicalize_actions(raw_macro.get_mut(&k(ACTIONS_FIELD)), form);
+}
+
+fn canonicalize_preconditions(raw_preconditions: Option<&mut RawConfig>, form: Form) {
+    for precondition in hashes_mut(raw_preconditions) {
+        if precondition.get_string(TYPE_FIELD) != Some("variable") {
+            continue;
+        }
+
+        if let Some(RawConfig::Hash(data)) = precondition.get_mut(&k(DATA_FIELD)) {
+            if let Some(matcher) = data.get_mut(&k("string")) {
+                canonicalize_shorthand(matcher, "is", form);
+            }
+        }
+    }
+}
+
+fn canonicalize_actions(raw_actions: Option<&mut RawConfig>, form: Form) {
+    for action in hashes_mut(raw_actions) {
+        let action_type = action.get_string(TYPE_FIELD).map(|t| t.to_string());
+
+        let data = match action.get_mut(&k(DATA_FIELD)) {
+            Some(data) => data,
+            None => continue
+        };
+
+        if let RawConfig::Hash(data) = data {
+            match action_type.as_deref() {
+                Some("repeat_while") | Some("if") => {
+                    let preconditions = data.get_mut(&k(REQUIRED_PRECONDITIONS_FIELD));
+                    canonicalize_preconditions(preconditions, form);
+
+                    for actions_field in [ACTIONS_FIELD, "then", "else"].iter() {
+                        canonicalize_actions(data.get_mut(&k(actions_field)), form);
+                    }
+                }
+
+                Some("switch") => {
+                    for case in hashes_mut(data.get_mut(&k("cases"))) {
+                        canonicalize_actions(case.get_mut(&k(ACTIONS_FIELD)), form);
+                    }
+
+                    canonicalize_actions(data.get_mut(&k("default")), form);
+                }
+
+                _ => {}
+            }
+        }
+
+        let shorthand = SHORTHAND_ACTIONS
+            .iter()
+            .find(|(shorthand_type, _)| action_type.as_deref() == Some(*shorthand_type));
+
+        if let Some((_, field)) = shorthand {
+            canonicalize_shorthand(data, field, form);
+        }
+    }
+}
+
+/// Rewrites `raw`, which can either be a hash with only `field`, or just that field's value, into
+/// `form`
+fn canonicalize_shorthand(raw: &mut RawConfig, field: &str, form: Form) {
+    let is_plain_value = |value: &RawConfig| match value {
+        RawConfig::Integer(_) => true,
+        RawConfig::String(s) => lone_param(s).is_none(),
+        _ => false
+    };
+
+    let rewritten = match (form, &*raw) {
+        (Form::Explicit, value) if is_plain_value(value) => {
+            let mut hash = RCHash::new();
+            hash.insert(k(field), value.clone());
+            RawConfig::Hash(hash)
+        }
+
+        (Form::Minimal, RawConfig::Hash(hash)) if hash.len() == 1 => match hash.get(&k(field)) {
+            Some(value) if is_plain_value(value) => value.clone(),
+            _ => return
+        },
+
+        _ => return
+    };
+
+    *raw = rewritten;
+}
+
+/// The hashes in `raw_list`, if it's a list
+pub (crate) fn hashes_mut(raw_list: Option<&mut RawConfig>) -> Vec<&mut RCHash> {
+    match raw_list {
+        Some(RawConfig::Array(items)) => items
+            .iter_mut()
+            .filter_map(|raw_item| match raw_item {
+                RawConfig::Hash(hash) => Some(hash),
+                _ => None
+            })
+            .collect(),
+
+        _ => vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::input_formats::get_parser_for_extension;
+    use crate::config::raw_config::{RawConfig, RCHash};
+    use crate::config::versions::Form;
+    use crate::config::versions::version1::canonical::canonicalize;
+
+    const MINIMAL_CONFIG: &str = r#"
+version: 1
+action_groups:
+  pause:
+    params: { ms: 10 }
+    actions:
+      - type: wait
+        data: "%params.ms%"
+      - type: shell
+        data: { command: ls, args: [-l] }
+scopes:
+  - window_class:
+      is: Gimp
+    macros:
+      - matching_events:
+          - type: chord
+            data:
+              events:
+                - type: midi
+                  data: { message_type: note_on, key: 36 }
+                - type: midi
+                  data: { message_type: note_on, key: 40 }
+            required_preconditions:
+              - type: variable
+                data: { name: layer, string: edit }
+        actions:
+          - type: enter_text
+            data: Hello
+global_macros:
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on }
+    actions:
+      - type: if
+        data:
+          required_preconditions:
+            - type: variable
+              data: { name: layer, string: { contains: edit } }
+          then:
+            - type: key_sequence
+              data: ctrl+c
+          else:
+            - type: call
+              data: pause
+      - type: switch
+        data:
+          value: "%event.value%"
+          cases:
+            - value: 0
+              actions:
+                - type: control
+                  data: exit
+          default:
+            - type: wait
+              data: 2000
+            - type: toggle_var
+              data: muted
+"#;
+
+    const EXPLICIT_CONFIG: &str = r#"
+version: 1
+action_groups:
+  pause:
+    params: { ms: 10 }
+    actions:
+      - type: wait
+        data: "%params.ms%"
+      - type: shell
+        data: { command: ls, args: [-l] }
+scopes:
+  - window_class:
+      is: Gimp
+    macros:
+      - matching_events:
+          - type: chord
+            data:
+              events:
+                - type: midi
+                  data: { message_type: note_on, key: 36 }
+                - type: midi
+                  data: { message_type: note_on, key: 40 }
+            required_preconditions:
+              - type: variable
+                data: { name: layer, string: { is: edit } }
+        actions:
+          - type: enter_text
+            data: { text: Hello }
+global_macros:
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on }
+    actions:
+      - type: if
+        data:
+          required_preconditions:
+            - type: variable
+              data: { name: layer, string: { contains: edit } }
+          then:
+            - type: key_sequence
+              data: { sequence: ctrl+c }
+          else:
+            - type: call
+              data: { group: pause }
+      - type: switch
+        data:
+          value: "%event.value%"
+          cases:
+            - value: 0
+              actions:
+                - type: control
+                  data: { action: exit }
+          default:
+            - type: wait
+              data: { duration: 2000 }
+            - type: toggle_var
+              data: { name: muted }
+"#;
+
+    fn parse(yaml: &str) -> RCHash {
+        match get_parser_for_extension("yml").unwrap().parse(yaml).ok().unwrap() {
+            RawConfig::Hash(hash) => hash,
+            _ => panic!("Expected a hash")
+        }
+    }
+
+    #[test]
+    fn rewrites_shorthand_forms_into_explicit_form_and_back() {
+        let mut raw_config = parse(MINIMAL_CONFIG);
+
+        canonicalize(&mut raw_config, Form::Explicit);
+        assert_eq!(raw_config, parse(EXPLICIT_CONFIG));
+
+        canonicalize(&mut raw_config, Form::Minimal);
+        assert_eq!(raw_config, parse(MINIMAL_CONFIG));
+
+        // Both forms mean the same, and the rewritten config still processes
+        canonicalize(&mut raw_config, Form::Explicit);
+        assert!(RawConfig::Hash(raw_config).process().is_ok());
+    }
+
+    #[test]
+    fn leaves_hashes_with_more_fields_as_they_are() {
+        let yaml = "
+global_macros:
+  - actions:
+      - type: enter_text
+        data: { text: Hi, count: 2 }
+      - type: call
+        data: { group: greet, params: { name: you } }
+";
+
+        let mut raw_config = parse(yaml);
+        canonicalize(&mut raw_config, Form::Minimal);
+        assert_eq!(raw_config, parse(yaml));
+    }
+}
@@ -1,4 +1,6 @@
 mod midi;
+mod device;
+mod gesture;
 
 use crate::config::raw_config::{RCHash, AccessHelpers, RawConfig};
 use crate::macros::event_matching::{EventMatcher, MatcherType};
@@ -6,6 +8,8 @@ use crate::config::ConfigError;
 use crate::macros::preconditions::Precondition;
 use crate::config::versions::version1::precondition::build_precondition;
 use midi::build_midi_event_matcher;
+use device::build_device_event_matcher;
+use gesture::{build_chord_matcher, build_sequence_matcher};
 
 /// Constructs an `EventMatcher` instance from a Raw `raw_event_matcher`
 /// `RCHash`'s fields.
@@ -24,6 +28,10 @@ use midi::build_midi_event_matcher;
 ///
 /// `type` is required. Its value must be one of the implemented event types. Currently, these are:
 ///     - midi
+///     - device_connected
+///     - device_disconnected
+///     - chord
+///     - sequence
 ///
 /// `data` is meant to be a hash, but is not strictly required. Depending on the event type, it
 /// may be required, but this function does not enforce it.
@@ -48,6 +56,10 @@ pub (crate) fn build_event_matcher(raw_event_matcher: &RCHash) -> Result<EventMa
     const REQUIRED_PRECONDITIONS_FIELD: &str = "required_preconditions";
 
     const TYPE_MIDI: &str = "midi";
+    const TYPE_DEVICE_CONNECTED: &str = "device_connected";
+    const TYPE_DEVICE_DISCONNECTED: &str = "device_disconnected";
+    const TYPE_CHORD: &str = "chord";
+    const TYPE_SEQUENCE: &str = "sequence";
 
     let event_type = raw_event_matcher.get_string(TYPE_FIELD).ok_or_else(|| {
         ConfigError::InvalidConfig(
@@ -59,24 +71,37 @@ pub (crate) fn build_event_matcher(raw_event_matcher: &RCHash) -> Result<EventMa
     let mut preconditions: Vec<Precondition> = vec![];
 
     if let Some(raw_preconditions) = raw_preconditions {
-        for precondition_hash in raw_preconditions {
+        for (index, precondition_hash) in raw_preconditions.iter().enumerate() {
             if let RawConfig::Hash(precondition_hash) = precondition_hash {
-                preconditions.push(build_precondition(precondition_hash)?);
+                preconditions.push(build_precondition(precondition_hash).map_err(|err| {
+                    err.within_item(REQUIRED_PRECONDITIONS_FIELD, index, precondition_hash.loc())
+                })?);
             }
         }
     }
 
     let data = raw_event_matcher.get_hash(DATA_FIELD);
 
-    let matcher_type: MatcherType = match event_type {
-        TYPE_MIDI => MatcherType::Midi(build_midi_event_matcher(data)?),
+    let matcher_type = match event_type {
+        TYPE_MIDI => build_midi_event_matcher(data).map(MatcherType::Midi),
+
+        TYPE_DEVICE_CONNECTED => {
+            build_device_event_matcher(true, data).map(|m| MatcherType::Device(Box::new(m)))
+        }
+
+        TYPE_DEVICE_DISCONNECTED => {
+            build_device_event_matcher(false, data).map(|m| MatcherType::Device(Box::new(m)))
+        }
+
+        TYPE_CHORD => build_chord_matcher(data).map(|m| MatcherType::Gesture(Box::new(m))),
+        TYPE_SEQUENCE => build_sequence_matcher(data).map(|m| MatcherType::Gesture(Box::new(m))),
 
         _ => {
             return Err(ConfigError::InvalidConfig(
                 format!("Unknown event matcher type '{}'", event_type)
             ));
         }
-    };
+    }.map_err(|err| err.within(DATA_FIELD, data.and_then(RCHash::loc)))?;
 
     Ok(EventMatcher::new(
             matcher_type,
@@ -91,6 +116,7 @@ mod tests {
     use crate::config::versions::version1::event_matchers::build_event_matcher;
     use crate::macros::event_matching::{EventMatcher, MatcherType};
     use crate::macros::event_matching::midi::MidiEventMatcher;
+    use crate::macros::event_matching::device::DeviceEventMatcher;
     use crate::macros::preconditions::{Precondition, PreconditionType};
 
     #[test]
@@ -142,6 +168,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn builds_device_event_matcher() {
+        let hash = RCHashBuilder::new()
+            .insert(k("type"), k("device_disconnected"))
+            .build();
+
+        let matcher = build_event_matcher(&hash).ok().unwrap();
+
+        assert_eq!(
+            matcher,
+            EventMatcher {
+                matcher: MatcherType::Device(Box::new(
+                    DeviceEventMatcher::Disconnected { device_match: None }
+                )),
+
+                required_preconditions: None
+            }
+        );
+    }
+
     #[test]
     fn returns_an_error_if_data_for_underlying_matcher_is_invalid() {
         let mut data_hash = RCHash::new();
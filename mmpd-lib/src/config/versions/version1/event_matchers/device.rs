@@ -0,0 +1,90 @@
+use crate::config::raw_config::{RCHash, RawConfig, k};
+use crate::config::ConfigError;
+use crate::config::versions::version1::primitive_matchers::build_string_matcher;
+use crate::macros::event_matching::device::DeviceEventMatcher;
+
+/// Constructs a `DeviceEventMatcher` from an optional `data` `RCHash`, matching devices being
+/// connected if `connected` is true, or being disconnected otherwise.
+///
+/// `data` is optional, and if specified, should be structured as follows:
+/// ```yaml
+/// device:
+///     # (string matcher)
+/// ```
+///
+/// `device` is a string matcher for the name of the device, see `build_string_matcher` for its
+/// structure. If omitted, any device matches.
+///
+/// ## Errors
+/// The function returns `ConfigError` if `device` is specified but isn't a valid string matcher.
+pub fn build_device_event_matcher(
+    connected: bool,
+    data: Option<&RCHash>
+) -> Result<DeviceEventMatcher, ConfigError> {
+    const DEVICE_FIELD: &str = "device";
+
+    let device_match = match data.and_then(|data| data.get(&k(DEVICE_FIELD))) {
+        None => None,
+
+        Some(RawConfig::Hash(raw_matcher)) => {
+            Some(build_string_matcher(Some(raw_matcher))?.ok_or_else(|| {
+                ConfigError::InvalidConfig(format!(
+                    "Invalid string matcher for {} field in device event data",
+                    DEVICE_FIELD
+                ))
+            })?)
+        }
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "{} field in device event data should be a string matcher",
+                DEVICE_FIELD
+            )));
+        }
+    };
+
+    Ok(if connected {
+        DeviceEventMatcher::Connected { device_match }
+    } else {
+        DeviceEventMatcher::Disconnected { device_match }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::raw_config::{k, RawConfig, RCHashBuilder};
+    use crate::config::versions::version1::event_matchers::device::build_device_event_matcher;
+    use crate::macros::event_matching::device::DeviceEventMatcher;
+    use crate::match_checker::StringMatcher;
+
+    #[test]
+    fn builds_device_event_matchers() {
+        let matcher = build_device_event_matcher(true, None).ok().unwrap();
+        assert_eq!(matcher, DeviceEventMatcher::Connected { device_match: None });
+
+        let data = RCHashBuilder::new()
+            .insert(
+                k("device"),
+                RawConfig::Hash(RCHashBuilder::new().insert(k("is"), k("pads")).build())
+            )
+            .build();
+
+        let matcher = build_device_event_matcher(false, Some(&data)).ok().unwrap();
+
+        assert_eq!(
+            matcher,
+            DeviceEventMatcher::Disconnected {
+                device_match: Some(StringMatcher::Is("pads".to_string()))
+            }
+        );
+    }
+
+    #[test]
+    fn returns_an_error_if_device_is_invalid() {
+        let data = RCHashBuilder::new()
+            .insert(k("device"), k("pads"))
+            .build();
+
+        assert!(build_device_event_matcher(true, Some(&data)).is_err());
+    }
+}
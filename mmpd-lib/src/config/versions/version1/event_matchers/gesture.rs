@@ -0,0 +1,297 @@
+use std::time::Duration;
+use crate::config::raw_config::{RCHash, RawConfig, AccessHelpers, k};
+use crate::config::ConfigError;
+use crate::config::versions::version1::event_matchers::build_event_matcher;
+use crate::macros::event_matching::{MatcherType, EventMatcher};
+use crate::macros::event_matching::gesture::GestureMatcher;
+
+/// Default for how close together the events in a chord have to be, in milliseconds
+const DEFAULT_CHORD_WITHIN_MS: i64 = 50;
+
+/// Default for how long a sequence may take from first to last step, in milliseconds
+const DEFAULT_SEQUENCE_TIMEOUT_MS: i64 = 1000;
+
+/// Constructs a `GestureMatcher::Chord` from a `data` `RCHash`, structured as follows:
+///
+/// ```yaml
+/// within_ms: 100
+/// events:
+///     - # (event matcher)
+///     - # (event matcher)
+/// ```
+///
+/// `within_ms` is optional, defaulting to 50. It is how many milliseconds apart the events of the
+/// chord may be at most.
+///
+/// `events` is required, and is a list of at least one event matcher, structured as described in
+/// `build_event_matcher`. They can be in any order.
+///
+/// ## Errors
+/// The function returns `ConfigError` if `data` is missing, or for any of the reasons listed in
+/// `build_gesture_events` and `build_gesture_duration`.
+pub fn build_chord_matcher(data: Option<&RCHash>) -> Result<GestureMatcher, ConfigError> {
+    const WITHIN_MS_FIELD: &str = "within_ms";
+    const EVENTS_FIELD: &str = "events";
+
+    let data = data.ok_or_else(|| {
+        ConfigError::InvalidConfig("chord event: missing data field".to_string())
+    })?;
+
+    Ok(GestureMatcher::Chord {
+        events: build_gesture_events("chord", EVENTS_FIELD, data)?,
+        within: build_gesture_duration("chord", WITHIN_MS_FIELD, DEFAULT_CHORD_WITHIN_MS, data)?
+    })
+}
+
+/// Constructs a `GestureMatcher::Sequence` from a `data` `RCHash`, structured as follows:
+///
+/// ```yaml
+/// timeout_ms: 1000
+/// steps:
+///     - # (event matcher)
+///     - # (event matcher)
+/// ```
+///
+/// `timeout_ms` is optional, defaulting to 1000. It is how many milliseconds may pass at most
+/// between the first and the last step.
+///
+/// `steps` is required, and is a list of at least one event matcher, structured as described in
+/// `build_event_matcher`. They have to happen in the order they are listed in.
+///
+/// ## Errors
+/// The function returns `ConfigError` if `data` is missing, or for any of the reasons listed in
+/// `build_gesture_events` and `build_gesture_duration`.
+pub fn build_sequence_matcher(data: Option<&RCHash>) -> Result<GestureMatcher, ConfigError> {
+    const TIMEOUT_MS_FIELD: &str = "timeout_ms";
+    const STEPS_FIELD: &str = "steps";
+
+    let data = data.ok_or_else(|| {
+        ConfigError::InvalidConfig("sequence event: missing data field".to_string())
+    })?;
+
+    Ok(GestureMatcher::Sequence {
+        steps: build_gesture_events("sequence", STEPS_FIELD, data)?,
+        timeout: build_gesture_duration(
+            "sequence",
+            TIMEOUT_MS_FIELD,
+            DEFAULT_SEQUENCE_TIMEOUT_MS,
+            data
+        )?
+    })
+}
+
+/// Builds the list of event matchers in the `field` field of a gesture's `data`.
+///
+/// ## Errors
+/// This function returns `ConfigError` if:
+///
+/// - `field` is missing, isn't a list, or is an empty list
+/// - Any of the items isn't a valid event matcher
+/// - Any of the items is a gesture itself, or has `required_preconditions`, neither of which are
+///   supported within gestures
+fn build_gesture_events(
+    gesture_type: &str,
+    field: &str,
+    data: &RCHash
+) -> Result<Vec<MatcherType>, ConfigError> {
+    let raw_events = data.get_array(field).ok_or_else(|| {
+        ConfigError::InvalidConfig(format!(
+            "{} event: '{}' should be a list of events",
+            gesture_type,
+            field
+        ))
+    })?;
+
+    if raw_events.is_empty() {
+        return Err(ConfigError::InvalidConfig(format!(
+            "{} event: '{}' should contain at least one event",
+            gesture_type,
+            field
+        )));
+    }
+
+    raw_events
+        .iter()
+        .enumerate()
+        .map(|(index, raw_event)| {
+            build_gesture_event(gesture_type, field, raw_event)
+                .map_err(|err| err.within_item(field, index, raw_event.loc()))
+        })
+        .collect()
+}
+
+/// Constructs the matcher for one of the events in the `field` list of a gesture of type
+/// `gesture_type`, from `raw_event`.
+fn build_gesture_event(
+    gesture_type: &str,
+    field: &str,
+    raw_event: &RawConfig
+) -> Result<MatcherType, ConfigError> {
+    let raw_event = match raw_event {
+        RawConfig::Hash(raw_event) => raw_event,
+
+        _ => return Err(ConfigError::InvalidConfig(format!(
+            "{} event: items in '{}' should be events",
+            gesture_type,
+            field
+        )))
+    };
+
+    match build_event_matcher(raw_event)? {
+        EventMatcher { matcher: MatcherType::Gesture(_), .. } => {
+            Err(ConfigError::InvalidConfig(format!(
+                "{} event: '{}' can't contain chords or sequences",
+                gesture_type,
+                field
+            )))
+        }
+
+        EventMatcher { required_preconditions: Some(_), .. } => {
+            Err(ConfigError::InvalidConfig(format!(
+                "{} event: events in '{}' can't have required_preconditions; \
+                add them to the {} event itself instead",
+                gesture_type,
+                field,
+                gesture_type
+            )))
+        }
+
+        EventMatcher { matcher, .. } => Ok(matcher)
+    }
+}
+
+/// Reads the duration in milliseconds from the `field` field of a gesture's `data`, falling back
+/// to `default_ms` if it is absent.
+///
+/// ## Errors
+/// This function returns `ConfigError` if `field` is present but isn't a positive integer.
+fn build_gesture_duration(
+    gesture_type: &str,
+    field: &str,
+    default_ms: i64,
+    data: &RCHash
+) -> Result<Duration, ConfigError> {
+    let ms = match data.get(&k(field)) {
+        None => default_ms,
+        Some(RawConfig::Integer(ms)) if *ms > 0 => *ms,
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "{} event: '{}' should be a positive number of milliseconds",
+                gesture_type,
+                field
+            )));
+        }
+    };
+
+    Ok(Duration::from_millis(ms as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::config::raw_config::{RCHashBuilder, RawConfig, RCHash, k};
+    use crate::config::versions::version1::event_matchers::gesture::{
+        build_chord_matcher,
+        build_sequence_matcher
+    };
+    use crate::macros::event_matching::MatcherType;
+    use crate::macros::event_matching::gesture::GestureMatcher;
+    use crate::macros::event_matching::midi::MidiEventMatcher;
+    use crate::match_checker::NumberMatcher;
+
+    fn raw_note_on(key: i64) -> RawConfig {
+        RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("type"), k("midi"))
+                .insert(
+                    k("data"),
+                    RawConfig::Hash(
+                        RCHashBuilder::new()
+                            .insert(k("message_type"), k("note_on"))
+                            .insert(k("key"), RawConfig::Integer(key))
+                            .build()
+                    )
+                )
+                .build()
+        )
+    }
+
+    fn note_on_matcher(key: u32) -> MatcherType {
+        MatcherType::Midi(MidiEventMatcher::NoteOn {
+            channel_match: None,
+            key_match: Some(NumberMatcher::Val(key)),
+            velocity_match: None
+        })
+    }
+
+    #[test]
+    fn builds_chord_matcher() {
+        let data = RCHashBuilder::new()
+            .insert(k("within_ms"), RawConfig::Integer(80))
+            .insert(k("events"), RawConfig::Array(vec![raw_note_on(60), raw_note_on(64)]))
+            .build();
+
+        assert_eq!(
+            build_chord_matcher(Some(&data)).ok().unwrap(),
+            GestureMatcher::Chord {
+                events: vec![note_on_matcher(60), note_on_matcher(64)],
+                within: Duration::from_millis(80)
+            }
+        );
+    }
+
+    #[test]
+    fn builds_sequence_matcher_with_default_timeout() {
+        let data = RCHashBuilder::new()
+            .insert(k("steps"), RawConfig::Array(vec![raw_note_on(36), raw_note_on(37)]))
+            .build();
+
+        assert_eq!(
+            build_sequence_matcher(Some(&data)).ok().unwrap(),
+            GestureMatcher::Sequence {
+                steps: vec![note_on_matcher(36), note_on_matcher(37)],
+                timeout: Duration::from_millis(1000)
+            }
+        );
+    }
+
+    #[test]
+    fn returns_an_error_for_invalid_gestures() {
+        assert!(build_chord_matcher(None).is_err());
+        assert!(build_sequence_matcher(Some(&RCHash::new())).is_err());
+
+        // No events
+        let data = RCHashBuilder::new().insert(k("events"), RawConfig::Array(vec![])).build();
+        assert!(build_chord_matcher(Some(&data)).is_err());
+
+        // Invalid duration
+        let data = RCHashBuilder::new()
+            .insert(k("steps"), RawConfig::Array(vec![raw_note_on(36)]))
+            .insert(k("timeout_ms"), RawConfig::Integer(0))
+            .build();
+
+        assert!(build_sequence_matcher(Some(&data)).is_err());
+
+        // Nested gesture
+        let nested = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("type"), k("sequence"))
+                .insert(
+                    k("data"),
+                    RawConfig::Hash(
+                        RCHashBuilder::new()
+                            .insert(k("steps"), RawConfig::Array(vec![raw_note_on(36)]))
+                            .build()
+                    )
+                )
+                .build()
+        );
+
+        let data = RCHashBuilder::new()
+            .insert(k("steps"), RawConfig::Array(vec![raw_note_on(36), nested]))
+            .build();
+
+        assert!(build_sequence_matcher(Some(&data)).is_err());
+    }
+}
@@ -1,7 +1,9 @@
-use crate::config::raw_config::{RCHash, AccessHelpers, k};
+use crate::config::raw_config::{RCHash, RawConfig, AccessHelpers, k};
 use crate::config::ConfigError;
-use crate::config::versions::version1::primitive_matchers::{build_number_matcher, build_musical_key_matcher};
-use crate::macros::event_matching::midi::MidiEventMatcher;
+use crate::config::versions::version1::primitive_matchers::{
+    build_number_matcher, build_musical_key_matcher, build_string_matcher
+};
+use crate::macros::event_matching::midi::{MidiEventMatcher, ButtonMatcher, EncoderDirection};
 
 /// Constructs a `MidiEventMatcher` from a `data` `RCHash`.
 ///
@@ -45,18 +47,76 @@ use crate::macros::event_matching::midi::MidiEventMatcher;
 /// - `pitch_bend_change` - Position of the pitch bender changes
 ///     - `channel`
 ///     - `value` - New pitch bend position (0-16383)
+/// - `control_change_14` - A high resolution control changed (only if enabled for the device, see
+///   `build_midi_device_options`)
+///     - `channel`
+///     - `control` - Control number of the most significant byte (0-31)
+///     - `value` - New value of the control (0-16383)
+/// - `nrpn` - A non-registered parameter changed (only if enabled for the device)
+///     - `channel`
+///     - `parameter` - Parameter number (0-16383)
+///     - `value` - New value of the parameter (0-16383)
+/// - `rpn` - A registered parameter changed (only if enabled for the device)
+///     - `channel`
+///     - `parameter` - Parameter number (0-16383)
+///     - `value` - New value of the parameter (0-16383)
+/// - `sys_ex` - System exclusive message (manufacturer-specific data)
+///     - `starts_with` - List of bytes (0-127) the message data must start with, not including
+///       the SysEx start byte (0xF0). Unlike other fields, this is not a number matcher.
+/// - `mtc_quarter_frame` - MIDI Time Code quarter frame
+///     - `piece` - Which piece of the time code (0-7)
+///     - `value` - Value of that piece (0-15)
+/// - `song_position` - Song position pointer
+///     - `position` - Position in MIDI beats since the start of the song (0-16383)
+/// - `song_select` - A song was selected
+///     - `song` - Song number (0-127)
+/// - `tune_request`, `timing_clock`, `start`, `continue`, `stop`, `active_sensing`, `reset` -
+///   System messages without any additional fields; these are not tied to a channel, so the
+///   `channel` field is ignored for these and the types above.
+/// - `tap` - A button was pressed and released quickly
+///     - `channel`
+///     - `key` - Which key, if the button is a key (0-127)
+///     - `control` - Which control, if the button is a control (0-127)
+/// - `double_tap` - A button was tapped twice in quick succession
+///     - `channel`, `key`, `control` - As for `tap`
+/// - `long_press` - A button has been held down for a while
+///     - `channel`, `key`, `control` - As for `tap`
+/// - `hold_repeat` - A button is still held after a long press, sent repeatedly
+///     - `channel`, `key`, `control` - As for `tap`
+///     - `count` - How many times it repeated so far, starting at 1
+/// - `release` - A button was released
+///     - `channel`, `key`, `control` - As for `tap`
+///     - `duration` - How long the button was held, in milliseconds
+/// - `encoder_turn` - An encoder was turned (only for controls set up as encoders, see
+///   `build_midi_device_options`)
+///     - `channel`
+///     - `control` - Control number of the encoder (0-127)
+///     - `direction` - Either `clockwise` or `counterclockwise`. Unlike other fields, this is not a
+///       number matcher. If omitted, either direction matches.
+///     - `magnitude` - How far the encoder was turned, regardless of direction (1-64)
+///
+/// For the button message types, only one of `key` and `control` may be specified. With `key`,
+/// only keys match, and with `control`, only controls that are set up as buttons; see
+/// `build_press_timing`. Without either, any button matches.
 ///
-/// For `note_on`, `note_off`, and `poly_aftertouch`'s `key` field, you can specify a string
+/// For `note_on`, `note_off`, `poly_aftertouch`, and the button message types' `key` field, you can specify a string
 /// describing a note, e.g.: "D#2", "A2", Bb1".
 /// You can also leave out the octave number, to create a number matcher matching that note on every
 /// octave, e.g.: "D#", "A", "Bb".
 ///
+/// Any message type can also have a `device` field, a string matcher for the name of the MIDI
+/// device the message must come from. Without it, messages from any device match.
+///
 /// ## Errors
 /// The function returns `ConfigError` in any of the following conditions:
 ///
 /// - No `data` is specified
 /// - No `message_type` string field is part of `data`
 /// - `message_type` value is not one of the supported values
+/// - `starts_with` is specified but isn't a list of integers in range 0-127
+/// - Both `key` and `control` are specified for a button message type
+/// - `direction` is specified but isn't `clockwise` or `counterclockwise`
+/// - `device` is specified but isn't a valid string matcher
 /// - Downstream there is an issue constructing a number matcher for any reason
 pub fn build_midi_event_matcher(
     data: Option<&RCHash>
@@ -69,6 +129,16 @@ pub fn build_midi_event_matcher(
     const VALUE_FIELD: &str = "value";
     const CONTROL_FIELD: &str = "control";
     const PROGRAM_FIELD: &str = "program";
+    const STARTS_WITH_FIELD: &str = "starts_with";
+    const PIECE_FIELD: &str = "piece";
+    const POSITION_FIELD: &str = "position";
+    const SONG_FIELD: &str = "song";
+    const PARAMETER_FIELD: &str = "parameter";
+    const DEVICE_FIELD: &str = "device";
+    const COUNT_FIELD: &str = "count";
+    const DURATION_FIELD: &str = "duration";
+    const DIRECTION_FIELD: &str = "direction";
+    const MAGNITUDE_FIELD: &str = "magnitude";
 
     const NOTE_ON_EVENT: &str = "note_on";
     const NOTE_OFF_EVENT: &str = "note_off";
@@ -77,6 +147,26 @@ pub fn build_midi_event_matcher(
     const PROGRAM_CHANGE_EVENT: &str = "program_change";
     const CHANNEL_AFTERTOUCH_EVENT: &str = "channel_aftertouch";
     const PITCH_BEND_CHANGE_EVENT: &str = "pitch_bend_change";
+    const CONTROL_CHANGE_14_EVENT: &str = "control_change_14";
+    const NRPN_EVENT: &str = "nrpn";
+    const RPN_EVENT: &str = "rpn";
+    const SYS_EX_EVENT: &str = "sys_ex";
+    const MTC_QUARTER_FRAME_EVENT: &str = "mtc_quarter_frame";
+    const SONG_POSITION_EVENT: &str = "song_position";
+    const SONG_SELECT_EVENT: &str = "song_select";
+    const TUNE_REQUEST_EVENT: &str = "tune_request";
+    const TIMING_CLOCK_EVENT: &str = "timing_clock";
+    const START_EVENT: &str = "start";
+    const CONTINUE_EVENT: &str = "continue";
+    const STOP_EVENT: &str = "stop";
+    const ACTIVE_SENSING_EVENT: &str = "active_sensing";
+    const RESET_EVENT: &str = "reset";
+    const TAP_EVENT: &str = "tap";
+    const DOUBLE_TAP_EVENT: &str = "double_tap";
+    const LONG_PRESS_EVENT: &str = "long_press";
+    const HOLD_REPEAT_EVENT: &str = "hold_repeat";
+    const RELEASE_EVENT: &str = "release";
+    const ENCODER_TURN_EVENT: &str = "encoder_turn";
 
     let data = data.ok_or_else(|| {
         ConfigError::InvalidConfig(format!(
@@ -94,7 +184,27 @@ pub fn build_midi_event_matcher(
     let raw_channel_matcher = data.get(&k(CHANNEL_FIELD));
     let channel_match = build_number_matcher(raw_channel_matcher)?;
 
-    Ok(match message_type {
+    let device_match = match data.get(&k(DEVICE_FIELD)) {
+        None => None,
+
+        Some(RawConfig::Hash(raw_matcher)) => {
+            Some(build_string_matcher(Some(raw_matcher))?.ok_or_else(|| {
+                ConfigError::InvalidConfig(format!(
+                    "Invalid string matcher for {} field in midi event data",
+                    DEVICE_FIELD
+                ))
+            })?)
+        }
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "{} field in midi event data should be a string matcher",
+                DEVICE_FIELD
+            )));
+        }
+    };
+
+    let matcher = match message_type {
         NOTE_ON_EVENT => {
             let raw_key_matcher = data.get(&k(KEY_FIELD));
             let raw_velocity_matcher = data.get(&k(VELOCITY_FIELD));
@@ -166,6 +276,162 @@ pub fn build_midi_event_matcher(
             }
         }
 
+        CONTROL_CHANGE_14_EVENT => {
+            let raw_control_matcher = data.get(&k(CONTROL_FIELD));
+            let raw_value_matcher = data.get(&k(VALUE_FIELD));
+
+            MidiEventMatcher::ControlChange14 {
+                channel_match,
+                control_match: build_number_matcher(raw_control_matcher)?,
+                value_match: build_number_matcher(raw_value_matcher)?
+            }
+        }
+
+        NRPN_EVENT => {
+            let raw_parameter_matcher = data.get(&k(PARAMETER_FIELD));
+            let raw_value_matcher = data.get(&k(VALUE_FIELD));
+
+            MidiEventMatcher::Nrpn {
+                channel_match,
+                parameter_match: build_number_matcher(raw_parameter_matcher)?,
+                value_match: build_number_matcher(raw_value_matcher)?
+            }
+        }
+
+        RPN_EVENT => {
+            let raw_parameter_matcher = data.get(&k(PARAMETER_FIELD));
+            let raw_value_matcher = data.get(&k(VALUE_FIELD));
+
+            MidiEventMatcher::Rpn {
+                channel_match,
+                parameter_match: build_number_matcher(raw_parameter_matcher)?,
+                value_match: build_number_matcher(raw_value_matcher)?
+            }
+        }
+
+        SYS_EX_EVENT => {
+            let starts_with = match data.get(&k(STARTS_WITH_FIELD)) {
+                None => vec![],
+
+                Some(RawConfig::Array(raw_bytes)) => raw_bytes
+                    .iter()
+                    .map(|b| match b {
+                        RawConfig::Integer(b) if (0..=127).contains(b) => Ok(*b as u8),
+                        _ => Err(ConfigError::InvalidConfig(format!(
+                            "Items in {} of midi event data must be integers in range 0-127",
+                            STARTS_WITH_FIELD
+                        )))
+                    })
+                    .collect::<Result<Vec<u8>, ConfigError>>()?,
+
+                Some(_) => {
+                    return Err(ConfigError::InvalidConfig(format!(
+                        "{} field in midi event data must be a list",
+                        STARTS_WITH_FIELD
+                    )));
+                }
+            };
+
+            MidiEventMatcher::SysEx { starts_with }
+        }
+
+        MTC_QUARTER_FRAME_EVENT => {
+            let raw_piece_matcher = data.get(&k(PIECE_FIELD));
+            let raw_value_matcher = data.get(&k(VALUE_FIELD));
+
+            MidiEventMatcher::MtcQuarterFrame {
+                piece_match: build_number_matcher(raw_piece_matcher)?,
+                value_match: build_number_matcher(raw_value_matcher)?
+            }
+        }
+
+        SONG_POSITION_EVENT => {
+            let raw_position_matcher = data.get(&k(POSITION_FIELD));
+
+            MidiEventMatcher::SongPosition {
+                position_match: build_number_matcher(raw_position_matcher)?
+            }
+        }
+
+        SONG_SELECT_EVENT => {
+            let raw_song_matcher = data.get(&k(SONG_FIELD));
+
+            MidiEventMatcher::SongSelect {
+                song_match: build_number_matcher(raw_song_matcher)?
+            }
+        }
+
+        TUNE_REQUEST_EVENT => MidiEventMatcher::TuneRequest,
+        TIMING_CLOCK_EVENT => MidiEventMatcher::TimingClock,
+        START_EVENT => MidiEventMatcher::Start,
+        CONTINUE_EVENT => MidiEventMatcher::Continue,
+        STOP_EVENT => MidiEventMatcher::Stop,
+        ACTIVE_SENSING_EVENT => MidiEventMatcher::ActiveSensing,
+        RESET_EVENT => MidiEventMatcher::Reset,
+
+        TAP_EVENT => MidiEventMatcher::Tap {
+            channel_match,
+            button_match: build_button_matcher(data, KEY_FIELD, CONTROL_FIELD)?
+        },
+
+        DOUBLE_TAP_EVENT => MidiEventMatcher::DoubleTap {
+            channel_match,
+            button_match: build_button_matcher(data, KEY_FIELD, CONTROL_FIELD)?
+        },
+
+        LONG_PRESS_EVENT => MidiEventMatcher::LongPress {
+            channel_match,
+            button_match: build_button_matcher(data, KEY_FIELD, CONTROL_FIELD)?
+        },
+
+        HOLD_REPEAT_EVENT => {
+            let raw_count_matcher = data.get(&k(COUNT_FIELD));
+
+            MidiEventMatcher::HoldRepeat {
+                channel_match,
+                button_match: build_button_matcher(data, KEY_FIELD, CONTROL_FIELD)?,
+                count_match: build_number_matcher(raw_count_matcher)?
+            }
+        }
+
+        RELEASE_EVENT => {
+            let raw_duration_matcher = data.get(&k(DURATION_FIELD));
+
+            MidiEventMatcher::Release {
+                channel_match,
+                button_match: build_button_matcher(data, KEY_FIELD, CONTROL_FIELD)?,
+                duration_match: build_number_matcher(raw_duration_matcher)?
+            }
+        }
+
+        ENCODER_TURN_EVENT => {
+            let raw_control_matcher = data.get(&k(CONTROL_FIELD));
+            let raw_magnitude_matcher = data.get(&k(MAGNITUDE_FIELD));
+
+            let direction_match = match data.get(&k(DIRECTION_FIELD)) {
+                None => None,
+                Some(RawConfig::String(d)) if d == "clockwise" => Some(EncoderDirection::Clockwise),
+
+                Some(RawConfig::String(d)) if d == "counterclockwise" => {
+                    Some(EncoderDirection::Counterclockwise)
+                }
+
+                Some(_) => {
+                    return Err(ConfigError::InvalidConfig(format!(
+                        "{} field in midi event data should be clockwise or counterclockwise",
+                        DIRECTION_FIELD
+                    )));
+                }
+            };
+
+            MidiEventMatcher::EncoderTurn {
+                channel_match,
+                control_match: build_number_matcher(raw_control_matcher)?,
+                direction_match,
+                magnitude_match: build_number_matcher(raw_magnitude_matcher)?
+            }
+        }
+
         _ => {
             return Err(ConfigError::InvalidConfig(
                 format!(
@@ -174,15 +440,51 @@ pub fn build_midi_event_matcher(
                 )
             ))
         }
+    };
+
+    Ok(match device_match {
+        Some(device_match) => MidiEventMatcher::Device {
+            device_match: Box::new(device_match),
+            matcher: Box::new(matcher)
+        },
+
+        None => matcher
     })
 }
 
+/// Constructs a `ButtonMatcher` from the `key_field` or `control_field` field of `data`, for
+/// button message types. `key_field` is a musical key matcher, and `control_field` a number
+/// matcher. If neither is present, any button matches.
+fn build_button_matcher(
+    data: &RCHash,
+    key_field: &str,
+    control_field: &str
+) -> Result<ButtonMatcher, ConfigError> {
+    match (data.get(&k(key_field)), data.get(&k(control_field))) {
+        (None, None) => Ok(ButtonMatcher::Any),
+
+        (Some(raw_key_matcher), None) => Ok(ButtonMatcher::Note {
+            key_match: build_musical_key_matcher(Some(raw_key_matcher))?
+        }),
+
+        (None, Some(raw_control_matcher)) => Ok(ButtonMatcher::Control {
+            control_match: build_number_matcher(Some(raw_control_matcher))?
+        }),
+
+        (Some(_), Some(_)) => Err(ConfigError::InvalidConfig(format!(
+            "A button can't have both a {} and a {} in midi event data",
+            key_field,
+            control_field
+        )))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::config::versions::version1::event_matchers::midi::build_midi_event_matcher;
     use crate::config::raw_config::{RCHash, k, RawConfig, RCHashBuilder};
-    use crate::macros::event_matching::midi::MidiEventMatcher;
-    use crate::match_checker::NumberMatcher;
+    use crate::macros::event_matching::midi::{MidiEventMatcher, ButtonMatcher, EncoderDirection};
+    use crate::match_checker::{NumberMatcher, StringMatcher};
 
     #[test]
     fn returns_an_error_if_no_data_is_given() {
@@ -508,4 +810,287 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn builds_high_resolution_control_matchers() {
+        let hash = RCHashBuilder::new()
+            .insert(k("message_type"), k("control_change_14"))
+            .insert(k("channel"), RawConfig::Integer(1))
+            .insert(k("control"), RawConfig::Integer(7))
+            .insert(k("value"), RawConfig::Integer(9000))
+            .build();
+
+        assert_eq!(
+            build_midi_event_matcher(Some(&hash)).ok().unwrap(),
+            MidiEventMatcher::ControlChange14 {
+                channel_match: Some(NumberMatcher::Val(1)),
+                control_match: Some(NumberMatcher::Val(7)),
+                value_match: Some(NumberMatcher::Val(9000))
+            }
+        );
+
+        let hash = RCHashBuilder::new()
+            .insert(k("message_type"), k("nrpn"))
+            .insert(k("parameter"), RawConfig::Integer(300))
+            .build();
+
+        assert_eq!(
+            build_midi_event_matcher(Some(&hash)).ok().unwrap(),
+            MidiEventMatcher::Nrpn {
+                channel_match: None,
+                parameter_match: Some(NumberMatcher::Val(300)),
+                value_match: None
+            }
+        );
+
+        let hash = RCHashBuilder::new()
+            .insert(k("message_type"), k("rpn"))
+            .insert(k("parameter"), RawConfig::Integer(0))
+            .insert(k("value"), RawConfig::Integer(256))
+            .build();
+
+        assert_eq!(
+            build_midi_event_matcher(Some(&hash)).ok().unwrap(),
+            MidiEventMatcher::Rpn {
+                channel_match: None,
+                parameter_match: Some(NumberMatcher::Val(0)),
+                value_match: Some(NumberMatcher::Val(256))
+            }
+        );
+    }
+
+    #[test]
+    fn builds_sysex_matcher() {
+        let hash = RCHashBuilder::new()
+            .insert(k("message_type"), k("sys_ex"))
+            .insert(k("starts_with"), RawConfig::Array(vec![
+                RawConfig::Integer(0x00),
+                RawConfig::Integer(0x20),
+                RawConfig::Integer(0x29)
+            ]))
+            .build();
+
+        let matcher = build_midi_event_matcher(Some(&hash)).ok().unwrap();
+
+        assert_eq!(
+            matcher,
+            MidiEventMatcher::SysEx { starts_with: vec![0x00, 0x20, 0x29] }
+        );
+
+        // Without any bytes
+        let mut hash = RCHash::new();
+        hash.insert(k("message_type"), k("sys_ex"));
+
+        let matcher = build_midi_event_matcher(Some(&hash)).ok().unwrap();
+
+        assert_eq!(matcher, MidiEventMatcher::SysEx { starts_with: vec![] });
+    }
+
+    #[test]
+    fn returns_an_error_if_sysex_starts_with_is_invalid() {
+        let hash = RCHashBuilder::new()
+            .insert(k("message_type"), k("sys_ex"))
+            .insert(k("starts_with"), RawConfig::Integer(0x00))
+            .build();
+
+        assert!(build_midi_event_matcher(Some(&hash)).is_err());
+
+        let hash = RCHashBuilder::new()
+            .insert(k("message_type"), k("sys_ex"))
+            .insert(k("starts_with"), RawConfig::Array(vec![RawConfig::Integer(0xF7)]))
+            .build();
+
+        assert!(build_midi_event_matcher(Some(&hash)).is_err());
+    }
+
+    #[test]
+    fn builds_system_common_matchers() {
+        let hash = RCHashBuilder::new()
+            .insert(k("message_type"), k("mtc_quarter_frame"))
+            .insert(k("piece"), RawConfig::Integer(7))
+            .insert(k("value"), RawConfig::Integer(2))
+            .build();
+
+        assert_eq!(
+            build_midi_event_matcher(Some(&hash)).ok().unwrap(),
+            MidiEventMatcher::MtcQuarterFrame {
+                piece_match: Some(NumberMatcher::Val(7)),
+                value_match: Some(NumberMatcher::Val(2))
+            }
+        );
+
+        let hash = RCHashBuilder::new()
+            .insert(k("message_type"), k("song_position"))
+            .insert(k("position"), RawConfig::Integer(1024))
+            .build();
+
+        assert_eq!(
+            build_midi_event_matcher(Some(&hash)).ok().unwrap(),
+            MidiEventMatcher::SongPosition { position_match: Some(NumberMatcher::Val(1024)) }
+        );
+
+        let hash = RCHashBuilder::new()
+            .insert(k("message_type"), k("song_select"))
+            .build();
+
+        assert_eq!(
+            build_midi_event_matcher(Some(&hash)).ok().unwrap(),
+            MidiEventMatcher::SongSelect { song_match: None }
+        );
+    }
+
+    #[test]
+    fn builds_matchers_for_messages_without_fields() {
+        let types = vec![
+            ("tune_request", MidiEventMatcher::TuneRequest),
+            ("timing_clock", MidiEventMatcher::TimingClock),
+            ("start", MidiEventMatcher::Start),
+            ("continue", MidiEventMatcher::Continue),
+            ("stop", MidiEventMatcher::Stop),
+            ("active_sensing", MidiEventMatcher::ActiveSensing),
+            ("reset", MidiEventMatcher::Reset),
+        ];
+
+        for (message_type, expected) in types {
+            let mut hash = RCHash::new();
+            hash.insert(k("message_type"), k(message_type));
+
+            assert_eq!(build_midi_event_matcher(Some(&hash)).ok().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn builds_device_matcher() {
+        let data = RCHashBuilder::new()
+            .insert(k("message_type"), k("start"))
+            .insert(
+                k("device"),
+                RawConfig::Hash(RCHashBuilder::new().insert(k("contains"), k("pads")).build())
+            )
+            .build();
+
+        let matcher = build_midi_event_matcher(Some(&data)).ok().unwrap();
+
+        assert_eq!(
+            matcher,
+            MidiEventMatcher::Device {
+                device_match: Box::new(StringMatcher::Contains("pads".to_string())),
+                matcher: Box::new(MidiEventMatcher::Start)
+            }
+        );
+    }
+
+    #[test]
+    fn returns_an_error_if_device_is_invalid() {
+        let data = RCHashBuilder::new()
+            .insert(k("message_type"), k("start"))
+            .insert(k("device"), k("pads"))
+            .build();
+
+        assert!(build_midi_event_matcher(Some(&data)).is_err());
+
+        let data = RCHashBuilder::new()
+            .insert(k("message_type"), k("start"))
+            .insert(
+                k("device"),
+                RawConfig::Hash(RCHashBuilder::new().insert(k("nope"), k("pads")).build())
+            )
+            .build();
+
+        assert!(build_midi_event_matcher(Some(&data)).is_err());
+    }
+
+    #[test]
+    fn builds_press_timing_matchers() {
+        let data = RCHashBuilder::new()
+            .insert(k("message_type"), k("tap"))
+            .insert(k("key"), k("C3"))
+            .build();
+
+        assert_eq!(
+            build_midi_event_matcher(Some(&data)).ok().unwrap(),
+            MidiEventMatcher::Tap {
+                channel_match: None,
+                button_match: ButtonMatcher::Note { key_match: Some(NumberMatcher::Val(48)) }
+            }
+        );
+
+        let data = RCHashBuilder::new()
+            .insert(k("message_type"), k("hold_repeat"))
+            .insert(k("control"), RawConfig::Integer(64))
+            .insert(k("count"), RawConfig::Integer(3))
+            .build();
+
+        assert_eq!(
+            build_midi_event_matcher(Some(&data)).ok().unwrap(),
+            MidiEventMatcher::HoldRepeat {
+                channel_match: None,
+                button_match: ButtonMatcher::Control { control_match: Some(NumberMatcher::Val(64)) },
+                count_match: Some(NumberMatcher::Val(3))
+            }
+        );
+
+        let data = RCHashBuilder::new()
+            .insert(k("message_type"), k("release"))
+            .insert(
+                k("duration"),
+                RawConfig::Hash(RCHashBuilder::new().insert(k("min"), RawConfig::Integer(500)).build())
+            )
+            .build();
+
+        assert_eq!(
+            build_midi_event_matcher(Some(&data)).ok().unwrap(),
+            MidiEventMatcher::Release {
+                channel_match: None,
+                button_match: ButtonMatcher::Any,
+                duration_match: Some(NumberMatcher::Range { min: Some(500), max: None })
+            }
+        );
+
+        for message_type in ["double_tap", "long_press"] {
+            let data = RCHashBuilder::new().insert(k("message_type"), k(message_type)).build();
+            assert!(build_midi_event_matcher(Some(&data)).is_ok());
+        }
+    }
+
+    #[test]
+    fn builds_encoder_turn_matcher() {
+        let data = RCHashBuilder::new()
+            .insert(k("message_type"), k("encoder_turn"))
+            .insert(k("control"), RawConfig::Integer(21))
+            .insert(k("direction"), k("counterclockwise"))
+            .insert(
+                k("magnitude"),
+                RawConfig::Hash(RCHashBuilder::new().insert(k("min"), RawConfig::Integer(2)).build())
+            )
+            .build();
+
+        assert_eq!(
+            build_midi_event_matcher(Some(&data)).ok().unwrap(),
+            MidiEventMatcher::EncoderTurn {
+                channel_match: None,
+                control_match: Some(NumberMatcher::Val(21)),
+                direction_match: Some(EncoderDirection::Counterclockwise),
+                magnitude_match: Some(NumberMatcher::Range { min: Some(2), max: None })
+            }
+        );
+
+        let data = RCHashBuilder::new()
+            .insert(k("message_type"), k("encoder_turn"))
+            .insert(k("direction"), k("left"))
+            .build();
+
+        assert!(build_midi_event_matcher(Some(&data)).is_err());
+    }
+
+    #[test]
+    fn returns_an_error_if_button_has_both_key_and_control() {
+        let data = RCHashBuilder::new()
+            .insert(k("message_type"), k("long_press"))
+            .insert(k("key"), RawConfig::Integer(36))
+            .insert(k("control"), RawConfig::Integer(64))
+            .build();
+
+        assert!(build_midi_event_matcher(Some(&data)).is_err());
+    }
 }
@@ -0,0 +1,483 @@
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+use crate::config::versions::UnknownField;
+
+const TYPE_FIELD: &str = "type";
+const DATA_FIELD: &str = "data";
+const ACTIONS_FIELD: &str = "actions";
+const REQUIRED_PRECONDITIONS_FIELD: &str = "required_preconditions";
+
+/// Finds the fields in `raw_config` that aren't part of the version 1 format, such as misspelled
+/// ones, which the builders would otherwise ignore without a word. Returns the path to each of
+/// them, like `scopes[0].window_class.start_with`, along with where its key is written.
+///
+/// Only the structure is followed here; whether values are valid is up to the builders. Parts with
+/// free-form keys, like action group parameters and shell environment variables, aren't checked.
+pub (crate) fn find_unknown_fields(raw_config: &RCHash) -> Vec<UnknownField> {
+    let mut unknown = vec![];
+
+    check_fields(raw_config, "", &[
+        "version", "include", "midi_device", "watch", "midi_device_options", "press_timing",
+        "state_file", "max_workers", "action_groups", "scopes", "global_macros"
+    ], &mut unknown);
+
+    match raw_config.get(&k("midi_device")) {
+        Some(RawConfig::Hash(device)) => check_midi_device(device, "midi_device", &mut unknown),
+
+        Some(RawConfig::Array(devices)) => {
+            for (i, device) in devices.iter().enumerate() {
+                if let RawConfig::Hash(device) = device {
+                    check_midi_device(device, &item("midi_device", i), &mut unknown);
+                }
+            }
+        }
+
+        _ => {}
+    }
+
+    let device_options = raw_config.get(&k("midi_device_options"));
+
+    for (path, options) in hashes(device_options, "midi_device_options") {
+        check_fields(options, &path, &[
+            "device", "control_change_14", "nrpn", "rpn", "encoders"
+        ], &mut unknown);
+
+        check_string_matcher(options.get(&k("device")), &field(&path, "device"), &mut unknown);
+
+        let encoders_path = field(&path, "encoders");
+
+        for (path, encoder) in hashes(options.get(&k("encoders")), &encoders_path) {
+            check_fields(encoder, &path, &["control", "channel", "mode"], &mut unknown);
+            let channel_path = field(&path, "channel");
+            check_number_matcher(encoder.get(&k("channel")), &channel_path, &mut unknown);
+        }
+    }
+
+    if let Some(timing) = raw_config.get_hash("press_timing") {
+        check_fields(timing, "press_timing", &[
+            "tap_ms", "double_tap_ms", "long_press_ms", "hold_repeat_ms", "button_controls"
+        ], &mut unknown);
+
+        check_number_matcher(
+            timing.get(&k("button_controls")),
+            "press_timing.button_controls",
+            &mut unknown
+        );
+    }
+
+    if let Some(state_file) = raw_config.get_hash("state_file") {
+        check_fields(state_file, "state_file", &["path", "save_interval_s"], &mut unknown);
+    }
+
+    if let Some(groups) = raw_config.get_hash("action_groups") {
+        for (name, group) in groups {
+            let path = field("action_groups", &key_name(name));
+
+            match group {
+                RawConfig::Hash(group) => {
+                    check_fields(group, &path, &["params", ACTIONS_FIELD], &mut unknown);
+                    let actions_path = field(&path, ACTIONS_FIELD);
+                    check_actions(group.get(&k(ACTIONS_FIELD)), &actions_path, &mut unknown);
+                }
+
+                group => check_actions(Some(group), &path, &mut unknown)
+            }
+        }
+    }
+
+    for (path, scope) in hashes(raw_config.get(&k("scopes")), "scopes") {
+        const MATCHER_FIELDS: [&str; 4] = [
+            "window_class", "window_name", "executable_path", "executable_basename"
+        ];
+
+        check_fields(scope, &path, &[&MATCHER_FIELDS[..], &["macros"][..]].concat(), &mut unknown);
+
+        for matcher_field in MATCHER_FIELDS.iter() {
+            let matcher_path = field(&path, matcher_field);
+            check_string_matcher(scope.get(&k(matcher_field)), &matcher_path, &mut unknown);
+        }
+
+        for (path, raw_macro) in hashes(scope.get(&k("macros")), &field(&path, "macros")) {
+            check_macro(raw_macro, &path, &mut unknown);
+        }
+    }
+
+    for (path, raw_macro) in hashes(raw_config.get(&k("global_macros")), "global_macros") {
+        check_macro(raw_macro, &path, &mut unknown);
+    }
+
+    unknown
+}
+
+fn check_midi_device(device: &RCHash, path: &str, unknown: &mut Vec<UnknownField>) {
+    if device.contains_key(&k("name")) || device.contains_key(&k("port")) {
+        check_fields(device, path, &["name", "port"], unknown);
+        check_string_matcher(device.get(&k("port")), &field(path, "port"), unknown);
+    } else {
+        check_string_matcher(Some(&RawConfig::Hash(device.clone())), path, unknown);
+    }
+}
+
+fn check_macro(raw_macro: &RCHash, path: &str, unknown: &mut Vec<UnknownField>) {
+    check_fields(raw_macro, path, &[
+        "name", "matching_events", REQUIRED_PRECONDITIONS_FIELD, ACTIONS_FIELD, "map", "concurrency"
+    ], unknown);
+
+    let events_path = field(path, "matching_events");
+
+    for (path, event) in hashes(raw_macro.get(&k("matching_events")), &events_path) {
+        check_event_matcher(event, &path, unknown);
+    }
+
+    check_preconditions(
+        raw_macro.get(&k(REQUIRED_PRECONDITIONS_FIELD)),
+        &field(path, REQUIRED_PRECONDITIONS_FIELD),
+        unknown
+    );
+
+    check_actions(raw_macro.get(&k(ACTIONS_FIELD)), &field(path, ACTIONS_FIELD), unknown);
+
+    if let Some(map) = raw_macro.get_hash("map") {
+        let path = field(path, "map");
+
+        check_fields(map, &path, &[
+            "variable", "from", "to", "curve", "interval_ms", "deduplicate"
+        ], unknown);
+
+        for range_field in ["from", "to"].iter() {
+            if let Some(range) = map.get_hash(range_field) {
+                check_fields(range, &field(&path, range_field), &["min", "max"], unknown);
+            }
+        }
+    }
+}
+
+fn check_event_matcher(event: &RCHash, path: &str, unknown: &mut Vec<UnknownField>) {
+    check_fields(event, path, &[TYPE_FIELD, DATA_FIELD, REQUIRED_PRECONDITIONS_FIELD], unknown);
+
+    check_preconditions(
+        event.get(&k(REQUIRED_PRECONDITIONS_FIELD)),
+        &field(path, REQUIRED_PRECONDITIONS_FIELD),
+        unknown
+    );
+
+    let data = match event.get_hash(DATA_FIELD) {
+        Some(data) => data,
+        None => return
+    };
+
+    let path = field(path, DATA_FIELD);
+
+    match event.get_string(TYPE_FIELD) {
+        Some("midi") => {
+            const NUMBER_FIELDS: [&str; 13] = [
+                "channel", "key", "velocity", "value", "control", "program", "piece", "position",
+                "song", "parameter", "count", "duration", "magnitude"
+            ];
+
+            check_fields(data, &path, &[
+                &NUMBER_FIELDS[..],
+                &["message_type", "starts_with", "device", "direction"][..]
+            ].concat(), unknown);
+
+            for number_field in NUMBER_FIELDS.iter() {
+                let number_path = field(&path, number_field);
+                check_number_matcher(data.get(&k(number_field)), &number_path, unknown);
+            }
+
+            check_string_matcher(data.get(&k("device")), &field(&path, "device"), unknown);
+        }
+
+        Some("device_connected") | Some("device_disconnected") => {
+            check_fields(data, &path, &["device"], unknown);
+            check_string_matcher(data.get(&k("device")), &field(&path, "device"), unknown);
+        }
+
+        Some("chord") => {
+            check_fields(data, &path, &["within_ms", "events"], unknown);
+
+            for (path, event) in hashes(data.get(&k("events")), &field(&path, "events")) {
+                check_event_matcher(event, &path, unknown);
+            }
+        }
+
+        Some("sequence") => {
+            check_fields(data, &path, &["timeout_ms", "steps"], unknown);
+
+            for (path, event) in hashes(data.get(&k("steps")), &field(&path, "steps")) {
+                check_event_matcher(event, &path, unknown);
+            }
+        }
+
+        // Unknown types are reported as errors by the builders
+        _ => {}
+    }
+}
+
+fn check_preconditions(
+    raw_preconditions: Option<&RawConfig>,
+    path: &str,
+    unknown: &mut Vec<UnknownField>
+) {
+    for (path, precondition) in hashes(raw_preconditions, path) {
+        check_fields(precondition, &path, &[TYPE_FIELD, "invert", DATA_FIELD], unknown);
+
+        let data = match precondition.get_hash(DATA_FIELD) {
+            Some(data) => data,
+            None => continue
+        };
+
+        let path = field(&path, DATA_FIELD);
+
+        match precondition.get_string(TYPE_FIELD) {
+            Some("midi") => {
+                const NUMBER_FIELDS: [&str; 6] = [
+                    "channel", "key", "control", "value", "program", "parameter"
+                ];
+
+                check_fields(data, &path, &[
+                    &NUMBER_FIELDS[..],
+                    &["condition_type", "device"][..]
+                ].concat(), unknown);
+
+                for number_field in NUMBER_FIELDS.iter() {
+                    let number_path = field(&path, number_field);
+                    check_number_matcher(data.get(&k(number_field)), &number_path, unknown);
+                }
+
+                check_string_matcher(data.get(&k("device")), &field(&path, "device"), unknown);
+            }
+
+            Some("variable") => {
+                check_fields(data, &path, &["name", "number", "string", "bool"], unknown);
+                check_number_matcher(data.get(&k("number")), &field(&path, "number"), unknown);
+                check_string_matcher(data.get(&k("string")), &field(&path, "string"), unknown);
+            }
+
+            _ => {}
+        }
+    }
+}
+
+fn check_actions(raw_actions: Option<&RawConfig>, path: &str, unknown: &mut Vec<UnknownField>) {
+    for (path, action) in hashes(raw_actions, path) {
+        check_fields(action, &path, &[TYPE_FIELD, DATA_FIELD], unknown);
+
+        let data = match action.get_hash(DATA_FIELD) {
+            Some(data) => data,
+            None => continue
+        };
+
+        let path = field(&path, DATA_FIELD);
+
+        let known: &[&str] = match action.get_string(TYPE_FIELD) {
+            Some("key_sequence") => &["sequence", "count", "delay", "delay_ms"],
+            Some("enter_text") => &["text", "count", "delay", "delay_ms"],
+            Some("shell") => &["command", "args", "env_vars"],
+            Some("wait") => &["duration", "duration_ms"],
+            Some("control") => &["action"],
+
+            Some("midi_send") => &[
+                "port", "message_type", "channel", "key", "velocity", "control", "value", "program",
+                "bytes"
+            ],
+
+            Some("set_var") => &["name", "value"],
+            Some("toggle_var") => &["name"],
+            Some("increment_var") => &["name", "by", "min", "max", "wrap"],
+            Some("cycle_var") => &["name", "values"],
+            Some("cancel_macro") => &["name"],
+            Some("call") => &["group", "params"],
+
+            Some("repeat_while") => {
+                check_preconditions(
+                    data.get(&k(REQUIRED_PRECONDITIONS_FIELD)),
+                    &field(&path, REQUIRED_PRECONDITIONS_FIELD),
+                    unknown
+                );
+
+                check_actions(data.get(&k(ACTIONS_FIELD)), &field(&path, ACTIONS_FIELD), unknown);
+                &[REQUIRED_PRECONDITIONS_FIELD, "interval", "interval_ms", ACTIONS_FIELD]
+            }
+
+            Some("if") => {
+                check_preconditions(
+                    data.get(&k(REQUIRED_PRECONDITIONS_FIELD)),
+                    &field(&path, REQUIRED_PRECONDITIONS_FIELD),
+                    unknown
+                );
+
+                check_actions(data.get(&k("then")), &field(&path, "then"), unknown);
+                check_actions(data.get(&k("else")), &field(&path, "else"), unknown);
+                &[REQUIRED_PRECONDITIONS_FIELD, "then", "else"]
+            }
+
+            Some("switch") => {
+                for (path, case) in hashes(data.get(&k("cases")), &field(&path, "cases")) {
+                    check_fields(case, &path, &["value", ACTIONS_FIELD], unknown);
+                    check_number_matcher(case.get(&k("value")), &field(&path, "value"), unknown);
+                    let actions_path = field(&path, ACTIONS_FIELD);
+                    check_actions(case.get(&k(ACTIONS_FIELD)), &actions_path, unknown);
+                }
+
+                check_actions(data.get(&k("default")), &field(&path, "default"), unknown);
+                &["value", "cases", "default"]
+            }
+
+            // Unknown types are reported as errors by the builders
+            _ => continue
+        };
+
+        check_fields(data, &path, known, unknown);
+    }
+}
+
+/// Checks the fields of a string matcher, if `raw_matcher` is in the hash form
+fn check_string_matcher(
+    raw_matcher: Option<&RawConfig>,
+    path: &str,
+    unknown: &mut Vec<UnknownField>
+) {
+    const MATCHER_TYPES: [&str; 5] = ["is", "contains", "starts_with", "ends_with", "regex"];
+
+    if let Some(RawConfig::Hash(matcher)) = raw_matcher {
+        for key in matcher.keys() {
+            // Matcher types aren't case sensitive
+            let is_known = match key {
+                RawConfig::String(key) => MATCHER_TYPES.contains(&key.to_lowercase().as_str()),
+                _ => false
+            };
+
+            if !is_known {
+                unknown.push(unknown_field(matcher, path, key));
+            }
+        }
+    }
+}
+
+/// Checks the fields of a number matcher, if `raw_matcher` is in the hash form, or a list of them
+fn check_number_matcher(
+    raw_matcher: Option<&RawConfig>,
+    path: &str,
+    unknown: &mut Vec<UnknownField>
+) {
+    match raw_matcher {
+        Some(RawConfig::Hash(matcher)) => check_fields(matcher, path, &["min", "max"], unknown),
+
+        Some(RawConfig::Array(matchers)) => {
+            for (i, matcher) in matchers.iter().enumerate() {
+                check_number_matcher(Some(matcher), &item(path, i), unknown);
+            }
+        }
+
+        _ => {}
+    }
+}
+
+/// Adds the path of any field of `hash` not listed in `known` to `unknown`
+fn check_fields(hash: &RCHash, path: &str, known: &[&str], unknown: &mut Vec<UnknownField>) {
+    for key in hash.keys() {
+        if !known.iter().any(|known| *key == k(known)) {
+            unknown.push(unknown_field(hash, path, key));
+        }
+    }
+}
+
+/// The field `key` of `hash`, which is at `path`
+fn unknown_field(hash: &RCHash, path: &str, key: &RawConfig) -> UnknownField {
+    UnknownField { path: field(path, &key_name(key)), loc: hash.key_loc(key) }
+}
+
+/// The hashes in `raw_list`, if it's a list, along with their paths
+fn hashes<'a>(raw_list: Option<&'a RawConfig>, path: &str) -> Vec<(String, &'a RCHash)> {
+    match raw_list {
+        Some(RawConfig::Array(items)) => items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, raw_item)| match raw_item {
+                RawConfig::Hash(hash) => Some((item(path, i), hash)),
+                _ => None
+            })
+            .collect(),
+
+        _ => vec![]
+    }
+}
+
+/// A hash key as it appears in a path, which is the key itself if it's a string
+pub (crate) fn key_name(key: &RawConfig) -> String {
+    match key {
+        RawConfig::String(key) => key.to_string(),
+        key => key.to_string()
+    }
+}
+
+fn field(path: &str, name: &str) -> String {
+    if path.is_empty() { name.to_string() } else { format!("{}.{}", path, name) }
+}
+
+fn item(path: &str, index: usize) -> String {
+    format!("{}[{}]", path, index)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::input_formats::get_parser_for_extension;
+    use crate::config::raw_config::RawConfig;
+    use crate::config::versions::version1::known_fields::find_unknown_fields;
+
+    #[test]
+    fn finds_fields_that_are_not_part_of_the_format() {
+        let yaml = "
+version: 1
+verbose: true
+scopes:
+  - window_class:
+      start_with: Firefox
+    macros:
+      - matching_events:
+          - type: midi
+            data:
+              message_type: note_on
+              key: { min: 10, maxx: 20 }
+        actions:
+          - type: if
+            data:
+              required_preconditions:
+                - type: variable
+                  data: { name: layer, bool: true }
+              then:
+                - type: wait
+                  data: { duration_ms: 10, ms: 10 }
+global_macros:
+  - name: Shell
+    concurrent: queue
+    matching_events:
+      - type: midi
+        data:
+          device:
+            Contains: pads
+    actions:
+      - type: shell
+        data:
+          command: ls
+          env_vars: { ANYTHING: goes }
+";
+
+        let raw_config = get_parser_for_extension("yml").unwrap().parse(yaml).ok().unwrap();
+
+        let raw_config = match raw_config {
+            RawConfig::Hash(hash) => hash,
+            _ => panic!("Expected a hash")
+        };
+
+        let unknown = find_unknown_fields(&raw_config);
+
+        assert_eq!(unknown.iter().map(|field| field.path.as_str()).collect::<Vec<_>>(), vec![
+            "verbose",
+            "scopes[0].window_class.start_with",
+            "scopes[0].macros[0].matching_events[0].data.key.maxx",
+            "scopes[0].macros[0].actions[0].data.then[0].data.ms",
+            "global_macros[0].concurrent"
+        ]);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::config::raw_config::{RCHash, AccessHelpers, RawConfig};
+use crate::config::raw_config::{RCHash, AccessHelpers, RawConfig, k};
 use crate::macros::{Scope, Macro, MacroBuilder};
 use crate::config::ConfigError;
 use crate::macros::event_matching::EventMatcher;
@@ -7,13 +7,20 @@ use crate::macros::actions::Action;
 use crate::config::versions::version1::event_matchers::build_event_matcher;
 use crate::config::versions::version1::precondition::build_precondition;
 use crate::config::versions::version1::actions::build_action;
+use crate::config::versions::version1::action_groups::ActionGroups;
+use crate::config::versions::version1::value_map::build_value_map;
+use crate::macros::executor::Concurrency;
 
 /// From a list of `RawConfig`s (expected to be `RawConfig::Hash`, otherwise skipped over), returns
-/// a list of `Macro` instances, attaching a copy of the provided `Scope`, if any.
-/// If any of the parsing into a macros goes wrong down the line, returns a `ConfigError` instead.
+/// a list of `Macro` instances, attaching a copy of the provided `Scope`, if any. `groups` are the
+/// action groups their actions can call.
+/// If any of the parsing into a macros goes wrong down the line, returns a `ConfigError` instead,
+/// placed within the list's field `field`.
 pub fn build_scope_macros(
     raw_macros: &Vec<RawConfig>,
-    scope: Option<Scope>
+    field: &str,
+    scope: Option<Scope>,
+    groups: &ActionGroups
 ) -> Result<Vec<Macro>, ConfigError> {
     // Note: a bit of magic happens here.
     // in the `map(...)` bit an iterator of Results is built.
@@ -22,17 +29,19 @@ pub fn build_scope_macros(
     // If I understand correctly, it will also not iterate beyond the first encountered error.
     raw_macros
         .iter()
-        .filter_map(|raw_macro| {
+        .enumerate()
+        .filter_map(|(index, raw_macro)| {
             // Filter out any values that aren't hashes
             if let RawConfig::Hash(hash) = raw_macro {
-                Some(hash)
+                Some((index, hash))
             } else {
                 None
             }
         })
-        .map(|raw_macro| {
+        .map(|(index, raw_macro)| {
             // Build an Ok(macro) or Err(ConfigError) for each item
-            Ok(build_macro(raw_macro, scope.clone())?)
+            build_macro(raw_macro, scope.clone(), groups)
+                .map_err(|err| err.within_item(field, index, raw_macro.loc()))
         })
         .collect()
 }
@@ -52,10 +61,15 @@ pub fn build_scope_macros(
 ///
 /// actions:
 ///     - # (one or more actions to be executed)
+///
+/// map:
+///     # (optional value map)
+///
+/// concurrency: queue
 /// ```
 ///
-/// `name` is optional. This name is only used for showing what is happening, but holds
-/// not operational significance otherwise.
+/// `name` is optional. This name is used for showing what is happening, and for stopping the
+/// macro's running actions with a `cancel_macro` action.
 ///
 /// `matching_events` is required, and must be a list of "event matchers". The structure
 /// of an event matcher is detailed in `build_event_matcher`. There must be at least one specified.
@@ -70,18 +84,34 @@ pub fn build_scope_macros(
 /// than one action is specified then they will all run in the order they are specified when the
 /// macro is run. The structure of an action is specified in `build_action`.
 ///
+/// `map` is optional. If specified, the value of the matched event, like a fader's position, is
+/// mapped into a variable the actions can use, and the macro only runs for events with such a
+/// value. Its structure is specified in `build_value_map`.
+///
+/// `concurrency` is optional, and determines what happens when the macro is triggered while its
+/// actions are still running: `queue` (the default) runs them again afterwards, `drop_if_running`
+/// ignores the trigger, `restart` stops the running actions and starts over, and `parallel` runs
+/// them again alongside the running ones. See `Concurrency`.
+///
 /// ## Errors
 /// This function will return `ConfigError` in any of these conditions:
 ///
 /// - The field `matching_events` is missing, is not a `RawConfig::Array`, or contains no items
 /// - The field `actions` is missing, is not a `RawConfig::Array`, or contains no items
-/// - Down the stream, an error occurs while trying to build one of the event matchers, actions, or
-///   preconditions
-fn build_macro(raw_macro: &RCHash, scope: Option<Scope>) -> Result<Macro, ConfigError> {
+/// - The field `concurrency` is not one of the above
+/// - Down the stream, an error occurs while trying to build one of the event matchers, actions,
+///   preconditions, or the value map
+fn build_macro(
+    raw_macro: &RCHash,
+    scope: Option<Scope>,
+    groups: &ActionGroups
+) -> Result<Macro, ConfigError> {
     const NAME_FIELD: &str = "name";
     const MATCHING_EVENTS_FIELD: &str = "matching_events";
     const REQUIRED_PRECONDITIONS_FIELD: &str = "required_preconditions";
     const ACTIONS_FIELD: &str = "actions";
+    const MAP_FIELD: &str = "map";
+    const CONCURRENCY_FIELD: &str = "concurrency";
 
     let raw_matching_events = raw_macro.get_array(MATCHING_EVENTS_FIELD).map_or_else(|| {
         Err(ConfigError::InvalidConfig(
@@ -119,22 +149,28 @@ fn build_macro(raw_macro: &RCHash, scope: Option<Scope>) -> Result<Macro, Config
     let mut required_preconditions: Vec<Precondition> = vec![];
     let mut actions: Vec<Action> = vec![];
 
-    for raw_event_matcher in raw_matching_events {
+    for (index, raw_event_matcher) in raw_matching_events.iter().enumerate() {
         if let RawConfig::Hash(raw_event_matcher) = raw_event_matcher {
-            matching_events.push(build_event_matcher(raw_event_matcher)?);
+            matching_events.push(build_event_matcher(raw_event_matcher).map_err(|err| {
+                err.within_item(MATCHING_EVENTS_FIELD, index, raw_event_matcher.loc())
+            })?);
         }
     }
 
-    for raw_action in raw_actions {
+    for (index, raw_action) in raw_actions.iter().enumerate() {
         if let RawConfig::Hash(raw_action) = raw_action {
-            actions.push(build_action(raw_action)?);
+            actions.push(build_action(raw_action, groups).map_err(|err| {
+                err.within_item(ACTIONS_FIELD, index, raw_action.loc())
+            })?);
         }
     }
 
     if let Some(raw_preconditions) = raw_preconditions {
-        for raw_preconditions in raw_preconditions {
+        for (index, raw_preconditions) in raw_preconditions.iter().enumerate() {
             if let RawConfig::Hash(raw_preconditions) = raw_preconditions {
-                required_preconditions.push(build_precondition(raw_preconditions)?);
+                required_preconditions.push(build_precondition(raw_preconditions).map_err(|err| {
+                    err.within_item(REQUIRED_PRECONDITIONS_FIELD, index, raw_preconditions.loc())
+                })?);
             }
         }
     }
@@ -159,6 +195,30 @@ fn build_macro(raw_macro: &RCHash, scope: Option<Scope>) -> Result<Macro, Config
         macro_builder = macro_builder.set_scope(scope);
     }
 
+    if let Some(raw_map) = raw_macro.get(&k(MAP_FIELD)) {
+        macro_builder = macro_builder.set_map(
+            build_value_map(raw_map).map_err(|err| err.within(MAP_FIELD, raw_map.loc()))?
+        );
+    }
+
+    if let Some(raw_concurrency) = raw_macro.get(&k(CONCURRENCY_FIELD)) {
+        let concurrency = match raw_concurrency {
+            RawConfig::String(c) if c == "queue" => Concurrency::Queue,
+            RawConfig::String(c) if c == "drop_if_running" => Concurrency::DropIfRunning,
+            RawConfig::String(c) if c == "restart" => Concurrency::Restart,
+            RawConfig::String(c) if c == "parallel" => Concurrency::Parallel,
+
+            _ => {
+                return Err(ConfigError::InvalidConfig(format!(
+                    "Macro '{}' should be one of queue, drop_if_running, restart, or parallel",
+                    CONCURRENCY_FIELD
+                )));
+            }
+        };
+
+        macro_builder = macro_builder.set_concurrency(concurrency);
+    }
+
     Ok(macro_builder.build())
 }
 
@@ -166,12 +226,14 @@ fn build_macro(raw_macro: &RCHash, scope: Option<Scope>) -> Result<Macro, Config
 mod tests {
     use crate::config::raw_config::{RCHash, k, RawConfig};
     use crate::config::versions::version1::macros::{build_macro, build_scope_macros};
+    use crate::config::versions::version1::action_groups::ActionGroups;
     use crate::macros::{Macro, Scope};
     use crate::macros::event_matching::{EventMatcher, MatcherType};
     use crate::macros::event_matching::midi::MidiEventMatcher;
     use crate::macros::actions::Action;
     use crate::config::versions::version1::scope::build_scope;
     use crate::match_checker::StringMatcher;
+    use crate::macros::executor::Concurrency;
 
     #[test]
     fn build_macro_returns_an_error_if_no_matching_events_are_specified() {
@@ -185,7 +247,7 @@ mod tests {
             RawConfig::Hash(enter_text_hash)
         ]));
 
-        let broken_macro = build_macro(&hash, None);
+        let broken_macro = build_macro(&hash, None, &ActionGroups::new(None));
         assert!(broken_macro.is_err());
 
         // Specified but empty array
@@ -200,7 +262,7 @@ mod tests {
             RawConfig::Hash(enter_text_hash)
         ]));
 
-        let broken_macro = build_macro(&hash, None);
+        let broken_macro = build_macro(&hash, None, &ActionGroups::new(None));
         assert!(broken_macro.is_err());
     }
 
@@ -220,7 +282,7 @@ mod tests {
             RawConfig::Hash(evt_hash)
         ]));
 
-        let broken_macro = build_macro(&hash, None);
+        let broken_macro = build_macro(&hash, None, &ActionGroups::new(None));
         assert!(broken_macro.is_err());
 
         // Specified but empty array
@@ -240,7 +302,7 @@ mod tests {
 
         hash.insert(k("actions"), RawConfig::Array(vec![]));
 
-        let broken_macro = build_macro(&hash, None);
+        let broken_macro = build_macro(&hash, None, &ActionGroups::new(None));
         assert!(broken_macro.is_err());
     }
 
@@ -263,7 +325,7 @@ mod tests {
             RawConfig::Hash(enter_text_hash)
         ]));
 
-        let broken_macro = build_macro(&hash, None);
+        let broken_macro = build_macro(&hash, None, &ActionGroups::new(None));
         assert!(broken_macro.is_err());
     }
 
@@ -288,7 +350,7 @@ mod tests {
             RawConfig::Hash(invalid_action_hash)
         ]));
 
-        let broken_macro = build_macro(&hash, None);
+        let broken_macro = build_macro(&hash, None, &ActionGroups::new(None));
         assert!(broken_macro.is_err());
     }
 
@@ -314,7 +376,7 @@ mod tests {
             RawConfig::Hash(action_hash)
         ]));
 
-        let simple_macro = build_macro(&hash, None)
+        let simple_macro = build_macro(&hash, None, &ActionGroups::new(None))
             .ok().unwrap();
 
         assert_eq!(
@@ -337,7 +399,9 @@ mod tests {
                 actions: vec![
                     Action::enter_text("Hello")
                 ],
-                scope: None
+                scope: None,
+                map: None,
+                concurrency: Concurrency::Queue
             }
         );
     }
@@ -365,6 +429,7 @@ mod tests {
         ]));
 
         hash.insert(k("name"), k("test macro"));
+        hash.insert(k("concurrency"), k("restart"));
 
         hash.insert(k("required_preconditions"), RawConfig::Array(vec![
             RawConfig::Null,
@@ -378,7 +443,7 @@ mod tests {
 
         let scope = build_scope(&scope_hash).ok().unwrap().unwrap();
 
-        let proper_macro = build_macro(&hash, Some(scope))
+        let proper_macro = build_macro(&hash, Some(scope), &ActionGroups::new(None))
             .ok().unwrap();
 
         assert_eq!(
@@ -407,7 +472,11 @@ mod tests {
                     window_name: Some(StringMatcher::Is("match".to_string())),
                     executable_path: None,
                     executable_basename: None,
-                })
+                }),
+
+                map: None,
+
+                concurrency: Concurrency::Restart
             }
         );
     }
@@ -465,7 +534,9 @@ mod tests {
 
         let macro_list = build_scope_macros(
             &vec![RawConfig::Hash(hash1), RawConfig::Hash(hash2)],
-            Some(scope)
+            "macros",
+            Some(scope),
+            &ActionGroups::new(None)
         ).ok().unwrap();
 
         assert_eq!(
@@ -498,7 +569,11 @@ mod tests {
                         window_name: Some(StringMatcher::Is("match".to_string())),
                         executable_path: None,
                         executable_basename: None,
-                    })
+                    }),
+
+                    map: None,
+
+                    concurrency: Concurrency::Queue
                 },
 
 
@@ -527,7 +602,11 @@ mod tests {
                         window_name: Some(StringMatcher::Is("match".to_string())),
                         executable_path: None,
                         executable_basename: None,
-                    })
+                    }),
+
+                    map: None,
+
+                    concurrency: Concurrency::Queue
                 },
             ]
         );
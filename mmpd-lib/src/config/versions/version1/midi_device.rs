@@ -0,0 +1,200 @@
+use crate::config::raw_config::{RCHash, RawConfig, AccessHelpers, k};
+use crate::config::{ConfigError, MidiDevice};
+use crate::config::versions::version1::primitive_matchers::build_string_matcher;
+
+/// Constructs a list of `MidiDevice`s from the top level `midi_device` field's value.
+///
+/// `raw_devices` can either be a single device, or a list of devices:
+///
+/// ```yml
+/// - name: pads
+///   port:
+///       contains: "Launchpad"
+/// - name: keys
+///   port:
+///       contains: "KeyStep"
+/// ```
+///
+/// See `build_midi_device` for the structure of each device.
+///
+/// ## Errors
+/// This function will return `ConfigError` if:
+///
+/// - `raw_devices` is neither a hash nor a list of hashes
+/// - Constructing any of the devices fails for any reason
+/// - More than one device has the same `name`
+pub (crate) fn build_midi_devices(raw_devices: &RawConfig) -> Result<Vec<MidiDevice>, ConfigError> {
+    let devices = match raw_devices {
+        RawConfig::Hash(raw_device) => vec![build_midi_device(raw_device)?],
+
+        RawConfig::Array(raw_devices) => raw_devices
+            .iter()
+            .map(|raw_device| match raw_device {
+                RawConfig::Hash(raw_device) => build_midi_device(raw_device),
+
+                _ => Err(ConfigError::InvalidConfig(
+                    "Items in midi_device should be devices or string matchers".to_string()
+                ))
+            })
+            .collect::<Result<Vec<MidiDevice>, ConfigError>>()?,
+
+        _ => {
+            return Err(ConfigError::InvalidConfig(
+                "midi_device should be a string matcher or a list of devices".to_string()
+            ));
+        }
+    };
+
+    for (i, device) in devices.iter().enumerate() {
+        if let Some(name) = &device.name {
+            if devices[..i].iter().any(|other| other.name.as_ref() == Some(name)) {
+                return Err(ConfigError::InvalidConfig(format!(
+                    "More than one MIDI device is named '{}'",
+                    name
+                )));
+            }
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Constructs a `MidiDevice` from a `raw_device` `RCHash`.
+///
+/// A device can be specified with a name:
+///
+/// ```yml
+/// name: pads
+/// port:
+///     # (string matcher)
+/// ```
+///
+/// `name` is optional. Events from the device are tagged with it, so event matchers and
+/// preconditions can tell devices apart. If omitted, the name of the MIDI port is used instead.
+///
+/// `port` is required, and is a string matcher for which MIDI port to listen to for this device.
+/// See `build_string_matcher` for its structure.
+///
+/// If neither `name` nor `port` is present, the whole of `raw_device` is interpreted as the `port`
+/// string matcher, so a plain string matcher is a device too.
+///
+/// ## Errors
+/// This function will return `ConfigError` if:
+///
+/// - `name` is specified but isn't a string
+/// - `port` (or `raw_device` itself, if there is no `port`) isn't a valid string matcher
+fn build_midi_device(raw_device: &RCHash) -> Result<MidiDevice, ConfigError> {
+    const NAME_FIELD: &str = "name";
+    const PORT_FIELD: &str = "port";
+
+    let is_named_device = raw_device.contains_key(&k(NAME_FIELD))
+        || raw_device.contains_key(&k(PORT_FIELD));
+
+    if !is_named_device {
+        let port_matcher = build_string_matcher(Some(raw_device))?.ok_or_else(|| {
+            ConfigError::InvalidConfig("Invalid string matcher for MIDI device".to_string())
+        })?;
+
+        return Ok(MidiDevice { name: None, port_matcher });
+    }
+
+    let name = match raw_device.get(&k(NAME_FIELD)) {
+        None => None,
+        Some(RawConfig::String(name)) => Some(name.to_string()),
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "MIDI device: '{}' should be a string",
+                NAME_FIELD
+            )));
+        }
+    };
+
+    let port_matcher = build_string_matcher(raw_device.get_hash(PORT_FIELD))?.ok_or_else(|| {
+        ConfigError::InvalidConfig(format!(
+            "MIDI device{}: missing or invalid '{}' string matcher",
+            name.as_ref().map_or_else(String::new, |name| format!(" '{}'", name)),
+            PORT_FIELD
+        ))
+    })?;
+
+    Ok(MidiDevice { name, port_matcher })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::raw_config::{RCHashBuilder, RawConfig, k};
+    use crate::config::versions::version1::midi_device::build_midi_devices;
+    use crate::config::MidiDevice;
+    use crate::match_checker::StringMatcher;
+
+    fn raw_device(name: &str, contains: &str) -> RawConfig {
+        RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("name"), k(name))
+                .insert(
+                    k("port"),
+                    RawConfig::Hash(RCHashBuilder::new().insert(k("contains"), k(contains)).build())
+                )
+                .build()
+        )
+    }
+
+    #[test]
+    fn builds_single_device_from_string_matcher() {
+        let raw = RawConfig::Hash(RCHashBuilder::new().insert(k("contains"), k("KeyStep")).build());
+
+        let devices = build_midi_devices(&raw).ok().unwrap();
+
+        assert_eq!(
+            devices,
+            vec![MidiDevice {
+                name: None,
+                port_matcher: StringMatcher::Contains("KeyStep".to_string())
+            }]
+        );
+    }
+
+    #[test]
+    fn builds_list_of_devices() {
+        let raw = RawConfig::Array(vec![
+            raw_device("pads", "Launchpad"),
+            RawConfig::Hash(RCHashBuilder::new().insert(k("is"), k("KeyStep 32")).build()),
+        ]);
+
+        let devices = build_midi_devices(&raw).ok().unwrap();
+
+        assert_eq!(
+            devices,
+            vec![
+                MidiDevice {
+                    name: Some("pads".to_string()),
+                    port_matcher: StringMatcher::Contains("Launchpad".to_string())
+                },
+                MidiDevice {
+                    name: None,
+                    port_matcher: StringMatcher::Is("KeyStep 32".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn returns_an_error_for_invalid_devices() {
+        // Duplicate names
+        let raw = RawConfig::Array(vec![
+            raw_device("pads", "Launchpad"),
+            raw_device("pads", "KeyStep"),
+        ]);
+
+        assert!(build_midi_devices(&raw).is_err());
+
+        // Named device without port
+        let raw = RawConfig::Hash(RCHashBuilder::new().insert(k("name"), k("pads")).build());
+        assert!(build_midi_devices(&raw).is_err());
+
+        // Not a hash or list
+        assert!(build_midi_devices(&k("KeyStep")).is_err());
+        assert!(build_midi_devices(&RawConfig::Array(vec![k("KeyStep")])).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+use crate::config::raw_config::{RCHash, RawConfig, k};
+use crate::config::{ConfigError, MidiDeviceOptions};
+use crate::config::versions::version1::primitive_matchers::{
+    build_string_matcher, build_number_matcher
+};
+use crate::midi::assembler::{AssemblerOptions, Encoder, EncoderMode};
+
+/// Constructs `MidiDeviceOptions` from a `raw_options` `RCHash`, describing extra processing for
+/// incoming messages of MIDI devices matching a string matcher.
+///
+/// Expects the `raw_options` hash to be structured as follows:
+///
+/// ```yml
+/// device:
+///     # (string matcher)
+/// control_change_14: true
+/// nrpn: true
+/// rpn: true
+/// encoders:
+///     - # (encoder)
+/// ```
+///
+/// `device` is optional. If omitted, the options apply to any MIDI device. See
+/// `build_string_matcher` for its structure.
+///
+/// `control_change_14`, `nrpn`, and `rpn` are optional booleans, defaulting to false. They enable
+/// assembling of high resolution control changes, non-registered parameter numbers, and registered
+/// parameter numbers respectively; see `AssemblerOptions`.
+///
+/// `encoders` is an optional list of controls that are endless encoders. See `build_encoder` for
+/// the structure of each.
+///
+/// ## Errors
+/// This function will return `ConfigError` if:
+///
+/// - Constructing the `device` `StringMatcher` fails for any reason
+/// - Any of `control_change_14`, `nrpn`, or `rpn` is specified, but isn't a boolean
+/// - `encoders` is specified, but isn't a list
+/// - Constructing any of the encoders fails for any reason
+pub (crate) fn build_midi_device_options(
+    raw_options: &RCHash
+) -> Result<MidiDeviceOptions, ConfigError> {
+    const DEVICE_FIELD: &str = "device";
+    const CONTROL_CHANGE_14_FIELD: &str = "control_change_14";
+    const NRPN_FIELD: &str = "nrpn";
+    const RPN_FIELD: &str = "rpn";
+    const ENCODERS_FIELD: &str = "encoders";
+
+    let device_matcher = match raw_options.get(&k(DEVICE_FIELD)) {
+        None => None,
+        Some(RawConfig::Hash(raw_matcher)) => build_string_matcher(Some(raw_matcher))?,
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "MIDI device options: '{}' should be a string matcher",
+                DEVICE_FIELD
+            )));
+        }
+    };
+
+    let get_flag = |field: &str| -> Result<bool, ConfigError> {
+        match raw_options.get(&k(field)) {
+            None => Ok(false),
+            Some(RawConfig::Bool(b)) => Ok(*b),
+
+            Some(_) => Err(ConfigError::InvalidConfig(format!(
+                "MIDI device options: '{}' should be true or false",
+                field
+            )))
+        }
+    };
+
+    let encoders = match raw_options.get(&k(ENCODERS_FIELD)) {
+        None => vec![],
+
+        Some(RawConfig::Array(raw_encoders)) => raw_encoders
+            .iter()
+            .enumerate()
+            .map(|(index, raw_encoder)| {
+                build_encoder(raw_encoder)
+                    .map_err(|err| err.within_item(ENCODERS_FIELD, index, raw_encoder.loc()))
+            })
+            .collect::<Result<Vec<Encoder>, ConfigError>>()?,
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "MIDI device options: '{}' should be a list of encoders",
+                ENCODERS_FIELD
+            )));
+        }
+    };
+
+    Ok(MidiDeviceOptions {
+        device_matcher,
+        assembler_options: AssemblerOptions {
+            control_change_14: get_flag(CONTROL_CHANGE_14_FIELD)?,
+            nrpn: get_flag(NRPN_FIELD)?,
+            rpn: get_flag(RPN_FIELD)?,
+            encoders
+        }
+    })
+}
+
+/// Constructs an `Encoder` from a `raw_encoder` `RawConfig`, declaring which controls are endless
+/// encoders and how they encode how far they were turned.
+///
+/// Expects `raw_encoder` to be a hash structured as follows:
+///
+/// ```yml
+/// control:
+///     # (number matcher)
+/// channel:
+///     # (number matcher)
+/// mode: twos_complement
+/// ```
+///
+/// `control` is required, and matches the control numbers the encoders send control changes on.
+/// `channel` is optional; if omitted, the encoders may send on any channel. See
+/// `build_number_matcher` for their structure.
+///
+/// `mode` is required, and is one of `twos_complement`, `binary_offset`, or `sign_magnitude`; see
+/// `EncoderMode`.
+///
+/// ## Errors
+/// This function will return `ConfigError` if:
+///
+/// - `raw_encoder` isn't a hash
+/// - `control` is missing, or isn't a valid number matcher
+/// - `channel` isn't a valid number matcher
+/// - `mode` is missing, or isn't one of the above
+fn build_encoder(raw_encoder: &RawConfig) -> Result<Encoder, ConfigError> {
+    const CONTROL_FIELD: &str = "control";
+    const CHANNEL_FIELD: &str = "channel";
+    const MODE_FIELD: &str = "mode";
+
+    let raw_encoder = match raw_encoder {
+        RawConfig::Hash(raw_encoder) => raw_encoder,
+
+        _ => {
+            return Err(ConfigError::InvalidConfig(
+                "MIDI device options: items in 'encoders' should be hashes".to_string()
+            ));
+        }
+    };
+
+    let control_match = build_number_matcher(raw_encoder.get(&k(CONTROL_FIELD)))?
+        .ok_or_else(|| {
+            ConfigError::InvalidConfig(format!(
+                "Encoder: missing or invalid '{}' number matcher",
+                CONTROL_FIELD
+            ))
+        })?;
+
+    let channel_match = build_number_matcher(raw_encoder.get(&k(CHANNEL_FIELD)))?;
+
+    let mode = match raw_encoder.get(&k(MODE_FIELD)) {
+        Some(RawConfig::String(mode)) if mode == "twos_complement" => EncoderMode::TwosComplement,
+        Some(RawConfig::String(mode)) if mode == "binary_offset" => EncoderMode::BinaryOffset,
+        Some(RawConfig::String(mode)) if mode == "sign_magnitude" => EncoderMode::SignMagnitude,
+
+        _ => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "Encoder: '{}' should be one of twos_complement, binary_offset, or sign_magnitude",
+                MODE_FIELD
+            )));
+        }
+    };
+
+    Ok(Encoder { channel_match, control_match, mode })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::raw_config::{RCHashBuilder, RawConfig, k};
+    use crate::config::versions::version1::midi_device_options::build_midi_device_options;
+    use crate::match_checker::{StringMatcher, NumberMatcher};
+    use crate::midi::assembler::{AssemblerOptions, Encoder, EncoderMode};
+
+    #[test]
+    fn builds_midi_device_options() {
+        let raw_options = RCHashBuilder::new()
+            .insert(
+                k("device"),
+                RawConfig::Hash(RCHashBuilder::new().insert(k("contains"), k("KeyStep")).build())
+            )
+            .insert(k("control_change_14"), RawConfig::Bool(true))
+            .insert(k("rpn"), RawConfig::Bool(true))
+            .build();
+
+        let options = build_midi_device_options(&raw_options).ok().unwrap();
+
+        assert_eq!(
+            options.device_matcher,
+            Some(StringMatcher::Contains("KeyStep".to_string()))
+        );
+
+        assert_eq!(
+            options.assembler_options,
+            AssemblerOptions { control_change_14: true, nrpn: false, rpn: true, encoders: vec![] }
+        );
+    }
+
+    #[test]
+    fn builds_midi_device_options_without_device() {
+        let raw_options = RCHashBuilder::new()
+            .insert(k("nrpn"), RawConfig::Bool(true))
+            .build();
+
+        let options = build_midi_device_options(&raw_options).ok().unwrap();
+
+        assert!(options.device_matcher.is_none());
+
+        assert_eq!(
+            options.assembler_options,
+            AssemblerOptions { control_change_14: false, nrpn: true, rpn: false, encoders: vec![] }
+        );
+    }
+
+    #[test]
+    fn builds_encoders() {
+        let raw_options = RCHashBuilder::new()
+            .insert(
+                k("encoders"),
+                RawConfig::Array(vec![
+                    RawConfig::Hash(
+                        RCHashBuilder::new()
+                            .insert(k("control"), RawConfig::Integer(21))
+                            .insert(k("mode"), k("binary_offset"))
+                            .build()
+                    ),
+                    RawConfig::Hash(
+                        RCHashBuilder::new()
+                            .insert(k("control"), RawConfig::Integer(22))
+                            .insert(k("channel"), RawConfig::Integer(1))
+                            .insert(k("mode"), k("sign_magnitude"))
+                            .build()
+                    ),
+                ])
+            )
+            .build();
+
+        let options = build_midi_device_options(&raw_options).ok().unwrap();
+
+        assert_eq!(
+            options.assembler_options.encoders,
+            vec![
+                Encoder {
+                    channel_match: None,
+                    control_match: NumberMatcher::Val(21),
+                    mode: EncoderMode::BinaryOffset
+                },
+                Encoder {
+                    channel_match: Some(NumberMatcher::Val(1)),
+                    control_match: NumberMatcher::Val(22),
+                    mode: EncoderMode::SignMagnitude
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn returns_an_error_for_invalid_fields() {
+        let raw_options = RCHashBuilder::new()
+            .insert(k("nrpn"), k("yes"))
+            .build();
+
+        assert!(build_midi_device_options(&raw_options).is_err());
+
+        let raw_options = RCHashBuilder::new()
+            .insert(k("device"), k("KeyStep"))
+            .build();
+
+        assert!(build_midi_device_options(&raw_options).is_err());
+
+        // Encoder without mode
+        let raw_options = RCHashBuilder::new()
+            .insert(
+                k("encoders"),
+                RawConfig::Array(vec![RawConfig::Hash(
+                    RCHashBuilder::new().insert(k("control"), RawConfig::Integer(21)).build()
+                )])
+            )
+            .build();
+
+        assert!(build_midi_device_options(&raw_options).is_err());
+    }
+}
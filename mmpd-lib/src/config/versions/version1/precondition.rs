@@ -1,9 +1,11 @@
 mod midi;
+mod variable;
 
 use crate::config::raw_config::{RCHash, AccessHelpers};
 use crate::macros::preconditions::{Precondition, PreconditionType};
 use crate::config::ConfigError;
 use crate::config::versions::version1::precondition::midi::build_midi_precondition;
+use crate::config::versions::version1::precondition::variable::build_variable_precondition;
 
 /// Constructs a `Precondition` instance from a raw `raw_precondition` `RCHash`'s fields.
 ///
@@ -19,6 +21,7 @@ use crate::config::versions::version1::precondition::midi::build_midi_preconditi
 /// `type` is required. Its value must be one of the implemented precondition types. Currently,
 /// there are:
 ///     - midi
+///     - variable
 ///
 /// `invert` is optional, and specifies whether the condition should be inverted; it essentially
 /// applies a logic "NOT" to the question "does this precondition match?" Defaults to `false`.
@@ -31,7 +34,7 @@ use crate::config::versions::version1::precondition::midi::build_midi_preconditi
 ///
 /// - `type` field is missing or is not a `RawConfig::String`
 /// - The value for the `type` field does not match any known precondition types; see above
-/// - Down the stream, a precondition type such as `MidiPrecondition`  fails to be constructed for
+/// - Down the stream, a precondition type such as `MidiPrecondition` fails to be constructed for
 ///   any reason
 pub (crate) fn build_precondition(raw_precondition: &RCHash) -> Result<Precondition, ConfigError> {
     const TYPE_FIELD: &str = "type";
@@ -39,6 +42,7 @@ pub (crate) fn build_precondition(raw_precondition: &RCHash) -> Result<Precondit
     const DATA_FIELD: &str = "data";
 
     const TYPE_MIDI: &str = "midi";
+    const TYPE_VARIABLE: &str = "variable";
 
     // Allows a building a simple do-nothing precondition in tests
     #[cfg(test)]
@@ -54,22 +58,22 @@ pub (crate) fn build_precondition(raw_precondition: &RCHash) -> Result<Precondit
 
     let data = raw_precondition.get_hash(DATA_FIELD);
 
-    Ok(Precondition {
-        invert,
-        condition: match condition_type {
-            TYPE_MIDI => PreconditionType::Midi(build_midi_precondition(data)?),
+    let condition = match condition_type {
+        TYPE_MIDI => build_midi_precondition(data).map(PreconditionType::Midi),
+        TYPE_VARIABLE => build_variable_precondition(data).map(PreconditionType::Variable),
 
-            // Allows building a simple do-nothing precondition in tests
-            #[cfg(test)]
-            TYPE_OTHER => PreconditionType::Other,
+        // Allows building a simple do-nothing precondition in tests
+        #[cfg(test)]
+        TYPE_OTHER => Ok(PreconditionType::Other),
 
-            _ => {
-                return Err(ConfigError::InvalidConfig(
-                    format!("Unknown precondition type '{}'", condition_type)
-                ));
-            }
+        _ => {
+            return Err(ConfigError::InvalidConfig(
+                format!("Unknown precondition type '{}'", condition_type)
+            ));
         }
-    })
+    }.map_err(|err| err.within(DATA_FIELD, data.and_then(RCHash::loc)))?;
+
+    Ok(Precondition { invert, condition })
 }
 
 #[cfg(test)]
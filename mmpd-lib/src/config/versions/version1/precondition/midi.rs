@@ -1,7 +1,9 @@
 use crate::macros::preconditions::midi::MidiPrecondition;
-use crate::config::raw_config::{RCHash, AccessHelpers, k};
+use crate::config::raw_config::{RCHash, AccessHelpers, RawConfig, k};
 use crate::config::ConfigError;
-use crate::config::versions::version1::primitive_matchers::{build_number_matcher, build_musical_key_matcher};
+use crate::config::versions::version1::primitive_matchers::{
+    build_number_matcher, build_musical_key_matcher, build_string_matcher
+};
 
 /// Constructs a `MidiPrecondition` from a `data` `RCHash`.
 ///
@@ -32,17 +34,32 @@ use crate::config::versions::version1::primitive_matchers::{build_number_matcher
 /// - `pitch_bend` - Position of the pitch bender, from "pitch_bend_change" messages
 ///     - `channel` - Which MIDI channel the pitch bend setting is on (0-15)
 ///     - `value` - What the last known pitch bend value is (0-16383)
+/// - `control_14` - A high resolution control value, from "control_change_14" messages
+///     - `channel` - Which MIDI channel the control is on (0-15)
+///     - `control` - Control identifier of the most significant byte (0-31)
+///     - `value` - Known value the control is set to (0-16383)
+/// - `nrpn` - A non-registered parameter value, from "nrpn" messages
+///     - `channel` - Which MIDI channel the parameter is on (0-15)
+///     - `parameter` - Parameter number (0-16383)
+///     - `value` - Known value the parameter is set to (0-16383)
+/// - `rpn` - A registered parameter value, from "rpn" messages
+///     - `channel`, `parameter`, `value` - Same as for `nrpn`
 ///
 /// For `note_on`'s `key` field, you can specify a string describing a note, e.g.: "D#2", "A2", Bb1".
 /// You can also leave out the octave number, to create a number matcher matching that note on every
 /// octave, e.g.: "D#", "A", "Bb".
 ///
+/// Any condition type can also have a `device` field, a string matcher for the name of the MIDI
+/// device whose state the condition is checked against. Without it, the condition is checked
+/// against the combined state of all devices.
+///
 /// ## Errors
 /// The function returns `ConfigError` in any of the following conditions:
 ///
 /// - No `data` is specified
 /// - No `condition_type` string field is found in `data`
 /// - `condition_type` is not one of the support values (see above)
+/// - `device` is specified but isn't a valid string matcher
 /// - Downstream, there is an issue constructing a number matcher for any reason
 pub fn build_midi_precondition(
     data: Option<&RCHash>
@@ -53,12 +70,17 @@ pub fn build_midi_precondition(
     const CONTROL_CONDITION: &str = "control";
     const PROGRAM_CONDITION: &str = "program";
     const PITCH_BEND_CONDITION: &str = "pitch_bend";
+    const CONTROL_14_CONDITION: &str = "control_14";
+    const NRPN_CONDITION: &str = "nrpn";
+    const RPN_CONDITION: &str = "rpn";
 
     const CHANNEL_FIELD: &str = "channel";
     const KEY_FIELD: &str = "key";
     const CONTROL_FIELD: &str = "control";
     const VALUE_FIELD: &str = "value";
     const PROGRAM_FIELD: &str = "program";
+    const PARAMETER_FIELD: &str = "parameter";
+    const DEVICE_FIELD: &str = "device";
 
     let data = data.ok_or_else(|| {
         ConfigError::InvalidConfig("Missing data for midi precondition".to_string())
@@ -74,7 +96,27 @@ pub fn build_midi_precondition(
     let raw_channel_matcher = data.get(&k(CHANNEL_FIELD));
     let channel_match = build_number_matcher(raw_channel_matcher)?;
 
-    Ok(match condition_type {
+    let device_match = match data.get(&k(DEVICE_FIELD)) {
+        None => None,
+
+        Some(RawConfig::Hash(raw_matcher)) => {
+            Some(build_string_matcher(Some(raw_matcher))?.ok_or_else(|| {
+                ConfigError::InvalidConfig(format!(
+                    "Invalid string matcher for {} field in midi precondition data",
+                    DEVICE_FIELD
+                ))
+            })?)
+        }
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "{} field in midi precondition data should be a string matcher",
+                DEVICE_FIELD
+            )));
+        }
+    };
+
+    let condition = match condition_type {
         NOTE_ON_CONDITION => MidiPrecondition::NoteOn {
             channel_match,
             key_match: build_musical_key_matcher(data.get(&k(KEY_FIELD)))?
@@ -96,6 +138,24 @@ pub fn build_midi_precondition(
             value_match: build_number_matcher(data.get(&k(VALUE_FIELD)))?
         },
 
+        CONTROL_14_CONDITION => MidiPrecondition::Control14 {
+            channel_match,
+            control_match: build_number_matcher(data.get(&k(CONTROL_FIELD)))?,
+            value_match: build_number_matcher(data.get(&k(VALUE_FIELD)))?
+        },
+
+        NRPN_CONDITION => MidiPrecondition::Nrpn {
+            channel_match,
+            parameter_match: build_number_matcher(data.get(&k(PARAMETER_FIELD)))?,
+            value_match: build_number_matcher(data.get(&k(VALUE_FIELD)))?
+        },
+
+        RPN_CONDITION => MidiPrecondition::Rpn {
+            channel_match,
+            parameter_match: build_number_matcher(data.get(&k(PARAMETER_FIELD)))?,
+            value_match: build_number_matcher(data.get(&k(VALUE_FIELD)))?
+        },
+
         _ => {
             return Err(ConfigError::InvalidConfig(
                format!(
@@ -104,6 +164,15 @@ pub fn build_midi_precondition(
                )
             ));
         }
+    };
+
+    Ok(match device_match {
+        Some(device_match) => MidiPrecondition::Device {
+            device_match: Box::new(device_match),
+            condition: Box::new(condition)
+        },
+
+        None => condition
     })
 }
 
@@ -112,7 +181,7 @@ mod tests {
     use crate::config::raw_config::{RCHash, k, RawConfig, RCHashBuilder};
     use crate::config::versions::version1::precondition::midi::build_midi_precondition;
     use crate::macros::preconditions::midi::MidiPrecondition;
-    use crate::match_checker::NumberMatcher;
+    use crate::match_checker::{NumberMatcher, StringMatcher};
 
     #[test]
     fn builds_note_on_precondition() {
@@ -357,4 +426,83 @@ mod tests {
         let condition = build_midi_precondition(Some(&hash));
         assert!(condition.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn builds_high_resolution_preconditions() {
+        let hash = RCHashBuilder::new()
+            .insert(k("condition_type"), k("control_14"))
+            .insert(k("control"), RawConfig::Integer(7))
+            .insert(k("value"), RawConfig::Integer(9000))
+            .build();
+
+        assert_eq!(
+            build_midi_precondition(Some(&hash)).ok().unwrap(),
+            MidiPrecondition::Control14 {
+                channel_match: None,
+                control_match: Some(NumberMatcher::Val(7)),
+                value_match: Some(NumberMatcher::Val(9000))
+            }
+        );
+
+        let hash = RCHashBuilder::new()
+            .insert(k("condition_type"), k("nrpn"))
+            .insert(k("channel"), RawConfig::Integer(1))
+            .insert(k("parameter"), RawConfig::Integer(300))
+            .build();
+
+        assert_eq!(
+            build_midi_precondition(Some(&hash)).ok().unwrap(),
+            MidiPrecondition::Nrpn {
+                channel_match: Some(NumberMatcher::Val(1)),
+                parameter_match: Some(NumberMatcher::Val(300)),
+                value_match: None
+            }
+        );
+
+        let hash = RCHashBuilder::new()
+            .insert(k("condition_type"), k("rpn"))
+            .insert(k("value"), RawConfig::Integer(2))
+            .build();
+
+        assert_eq!(
+            build_midi_precondition(Some(&hash)).ok().unwrap(),
+            MidiPrecondition::Rpn {
+                channel_match: None,
+                parameter_match: None,
+                value_match: Some(NumberMatcher::Val(2))
+            }
+        );
+    }
+
+    #[test]
+    fn builds_device_precondition() {
+        let hash = RCHashBuilder::new()
+            .insert(k("condition_type"), k("program"))
+            .insert(k("program"), RawConfig::Integer(3))
+            .insert(
+                k("device"),
+                RawConfig::Hash(RCHashBuilder::new().insert(k("is"), k("keys")).build())
+            )
+            .build();
+
+        let condition = build_midi_precondition(Some(&hash)).ok().unwrap();
+
+        assert_eq!(
+            condition,
+            MidiPrecondition::Device {
+                device_match: Box::new(StringMatcher::Is("keys".to_string())),
+                condition: Box::new(MidiPrecondition::Program {
+                    channel_match: None,
+                    program_match: Some(NumberMatcher::Val(3))
+                })
+            }
+        );
+
+        let hash = RCHashBuilder::new()
+            .insert(k("condition_type"), k("program"))
+            .insert(k("device"), k("keys"))
+            .build();
+
+        assert!(build_midi_precondition(Some(&hash)).is_err());
+    }
+}
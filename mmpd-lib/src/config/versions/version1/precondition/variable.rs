@@ -0,0 +1,159 @@
+use crate::macros::preconditions::variable::{VariablePrecondition, ValueMatcher};
+use crate::config::raw_config::{RCHash, AccessHelpers, RawConfig, k};
+use crate::config::ConfigError;
+use crate::config::versions::version1::primitive_matchers::{
+    build_number_matcher, build_string_matcher
+};
+use crate::match_checker::StringMatcher;
+
+/// Constructs a `VariablePrecondition` from a `data` `RCHash`.
+///
+/// `data` should be structured as follows:
+/// ```yaml
+/// name: layer
+/// string:
+///     is: editing
+/// ```
+///
+/// `name` is required, and is the name of the variable to check.
+///
+/// Exactly one of these fields is required, to match the variable's value:
+///
+/// - `number` - A number matcher, matching integer variables (see `build_number_matcher`)
+/// - `string` - A string matcher, matching the variable's value as text, whatever its type (see
+///   `build_string_matcher`). A plain string is short for `is: (that string)`.
+/// - `bool` - `true` or `false`, matching boolean variables with that value
+///
+/// A variable that hasn't been set yet doesn't match.
+///
+/// ## Errors
+/// The function returns `ConfigError` in any of the following conditions:
+///
+/// - No `data` is specified
+/// - `name` is missing or isn't a string
+/// - Not exactly one of `number`, `string` and `bool` is specified
+/// - The specified matcher isn't valid
+pub fn build_variable_precondition(
+    data: Option<&RCHash>
+) -> Result<VariablePrecondition, ConfigError> {
+    const NAME_FIELD: &str = "name";
+    const NUMBER_FIELD: &str = "number";
+    const STRING_FIELD: &str = "string";
+    const BOOL_FIELD: &str = "bool";
+
+    let data = data.ok_or_else(|| {
+        ConfigError::InvalidConfig("Variable precondition: missing data field".to_string())
+    })?;
+
+    let name = data.get_string(NAME_FIELD).ok_or_else(|| {
+        ConfigError::InvalidConfig(format!(
+            "Variable precondition: missing string '{}' field",
+            NAME_FIELD
+        ))
+    })?;
+
+    let matcher_fields = [NUMBER_FIELD, STRING_FIELD, BOOL_FIELD];
+    let mut specified = matcher_fields.iter().filter(|field| data.contains_key(&k(field)));
+
+    let (field, None) = (specified.next(), specified.next()) else {
+        return Err(ConfigError::InvalidConfig(format!(
+            "Variable precondition '{}': specify only one of '{}', '{}' or '{}'",
+            name, NUMBER_FIELD, STRING_FIELD, BOOL_FIELD
+        )));
+    };
+
+    let invalid = |field: &str| ConfigError::InvalidConfig(format!(
+        "Variable precondition '{}': invalid '{}' matcher",
+        name, field
+    ));
+
+    let value_match = match field {
+        Some(&NUMBER_FIELD) => ValueMatcher::Number(
+            build_number_matcher(data.get(&k(NUMBER_FIELD)))?
+                .ok_or_else(|| invalid(NUMBER_FIELD))?
+        ),
+
+        Some(&STRING_FIELD) => ValueMatcher::String(Box::new(match data.get(&k(STRING_FIELD))  {
+            Some(RawConfig::String(s)) => StringMatcher::Is(s.to_string()),
+
+            _ => build_string_matcher(data.get_hash(STRING_FIELD))?
+                .ok_or_else(|| invalid(STRING_FIELD))?
+        })),
+
+        Some(_) => ValueMatcher::Bool(
+            data.get_bool(BOOL_FIELD).ok_or_else(|| invalid(BOOL_FIELD))?
+        ),
+
+        None => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "Variable precondition '{}': missing one of '{}', '{}' or '{}'",
+                name, NUMBER_FIELD, STRING_FIELD, BOOL_FIELD
+            )));
+        }
+    };
+
+    Ok(VariablePrecondition { name: name.to_string(), value_match })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::raw_config::{RCHashBuilder, RawConfig, k};
+    use crate::config::versions::version1::precondition::variable::build_variable_precondition;
+    use crate::macros::preconditions::variable::{VariablePrecondition, ValueMatcher};
+    use crate::match_checker::{NumberMatcher, StringMatcher};
+
+    #[test]
+    fn builds_variable_preconditions() {
+        let build = |field: &str, value: RawConfig| build_variable_precondition(Some(
+            &RCHashBuilder::new()
+                .insert(k("name"), k("layer"))
+                .insert(k(field), value)
+                .build()
+        ));
+
+        let precondition = |value_match: ValueMatcher| VariablePrecondition {
+            name: "layer".to_string(),
+            value_match
+        };
+
+        assert_eq!(
+            build("string", k("media")).ok().unwrap(),
+            precondition(ValueMatcher::String(Box::new(StringMatcher::Is("media".to_string()))))
+        );
+
+        assert_eq!(
+            build(
+                "string",
+                RawConfig::Hash(RCHashBuilder::new().insert(k("starts_with"), k("med")).build())
+            ).ok().unwrap(),
+            precondition(ValueMatcher::String(
+                Box::new(StringMatcher::StartsWith("med".to_string()))
+            ))
+        );
+
+        assert_eq!(
+            build("number", RawConfig::Integer(2)).ok().unwrap(),
+            precondition(ValueMatcher::Number(NumberMatcher::Val(2)))
+        );
+
+        assert_eq!(
+            build("bool", RawConfig::Bool(false)).ok().unwrap(),
+            precondition(ValueMatcher::Bool(false))
+        );
+
+        assert!(build("bool", k("yes")).is_err());
+        assert!(build("other", k("media")).is_err());
+    }
+
+    #[test]
+    fn returns_an_error_for_more_than_one_matcher() {
+        let data = RCHashBuilder::new()
+            .insert(k("name"), k("layer"))
+            .insert(k("string"), k("2"))
+            .insert(k("number"), RawConfig::Integer(2))
+            .build();
+
+        assert!(build_variable_precondition(Some(&data)).is_err());
+        assert!(build_variable_precondition(None).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+use crate::config::raw_config::{RCHash, RawConfig, k};
+use crate::config::ConfigError;
+use crate::config::versions::version1::primitive_matchers::build_number_matcher;
+use crate::state::PressTiming;
+
+/// Constructs `PressTiming` from the top level `press_timing` field's `raw_timing` `RCHash`,
+/// setting the thresholds for tap, double tap, long press, and hold repeat events.
+///
+/// Expects the `raw_timing` hash to be structured as follows:
+///
+/// ```yml
+/// tap_ms: 250
+/// double_tap_ms: 300
+/// long_press_ms: 500
+/// hold_repeat_ms: 100
+/// button_controls:
+///     # (number matcher)
+/// ```
+///
+/// All fields are optional, the durations defaulting to the values above, in milliseconds:
+///
+/// - `tap_ms`: A button released within this time is tapped
+/// - `double_tap_ms`: A tap within this time of the previous tap is a double tap
+/// - `long_press_ms`: A button held for this long is long pressed
+/// - `hold_repeat_ms`: How often a long pressed button repeats while still held. 0 disables
+///   repeating.
+///
+/// `button_controls` is a number matcher for which controls are buttons, as opposed to knobs or
+/// faders. If omitted, no controls are buttons. See `build_number_matcher` for its structure.
+///
+/// ## Errors
+/// This function will return `ConfigError` if:
+///
+/// - Any of the durations isn't a number of 0 or more, or `tap_ms` or `long_press_ms` is 0
+/// - Constructing the `button_controls` number matcher fails for any reason
+pub (crate) fn build_press_timing(raw_timing: &RCHash) -> Result<PressTiming, ConfigError> {
+    const TAP_MS_FIELD: &str = "tap_ms";
+    const DOUBLE_TAP_MS_FIELD: &str = "double_tap_ms";
+    const LONG_PRESS_MS_FIELD: &str = "long_press_ms";
+    const HOLD_REPEAT_MS_FIELD: &str = "hold_repeat_ms";
+    const BUTTON_CONTROLS_FIELD: &str = "button_controls";
+
+    let defaults = PressTiming::default();
+
+    let get_duration = |field: &str, default: Duration, min_ms: i64| {
+        match raw_timing.get(&k(field)) {
+            None => Ok(default),
+            Some(RawConfig::Integer(ms)) if *ms >= min_ms => Ok(Duration::from_millis(*ms as u64)),
+
+            Some(_) => Err(ConfigError::InvalidConfig(format!(
+                "press_timing: '{}' should be a number of milliseconds, {} or more",
+                field,
+                min_ms
+            )))
+        }
+    };
+
+    Ok(PressTiming {
+        tap: get_duration(TAP_MS_FIELD, defaults.tap, 1)?,
+        double_tap: get_duration(DOUBLE_TAP_MS_FIELD, defaults.double_tap, 0)?,
+        long_press: get_duration(LONG_PRESS_MS_FIELD, defaults.long_press, 1)?,
+        hold_repeat: get_duration(HOLD_REPEAT_MS_FIELD, defaults.hold_repeat, 0)?,
+        button_controls: build_number_matcher(raw_timing.get(&k(BUTTON_CONTROLS_FIELD)))?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::config::raw_config::{RCHash, RCHashBuilder, RawConfig, k};
+    use crate::config::versions::version1::press_timing::build_press_timing;
+    use crate::match_checker::NumberMatcher;
+    use crate::state::PressTiming;
+
+    #[test]
+    fn builds_press_timing() {
+        assert_eq!(build_press_timing(&RCHash::new()).ok().unwrap(), PressTiming::default());
+
+        let raw_timing = RCHashBuilder::new()
+            .insert(k("long_press_ms"), RawConfig::Integer(800))
+            .insert(k("hold_repeat_ms"), RawConfig::Integer(0))
+            .insert(k("button_controls"), RawConfig::Integer(64))
+            .build();
+
+        assert_eq!(
+            build_press_timing(&raw_timing).ok().unwrap(),
+            PressTiming {
+                long_press: Duration::from_millis(800),
+                hold_repeat: Duration::from_millis(0),
+                button_controls: Some(NumberMatcher::Val(64)),
+                ..PressTiming::default()
+            }
+        );
+    }
+
+    #[test]
+    fn returns_an_error_for_invalid_durations() {
+        let raw_timing = RCHashBuilder::new().insert(k("tap_ms"), RawConfig::Integer(0)).build();
+        assert!(build_press_timing(&raw_timing).is_err());
+
+        let raw_timing = RCHashBuilder::new().insert(k("double_tap_ms"), k("fast")).build();
+        assert!(build_press_timing(&raw_timing).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::config::raw_config::{RawConfig, k};
+use crate::config::{ConfigError, StateFileOptions};
+
+/// Constructs `StateFileOptions` from the top level `state_file` field's `raw_options`, or `None`
+/// if state shouldn't be kept in a file.
+///
+/// `raw_options` is either a boolean, `true` enabling the state file with default options, or a
+/// hash structured as follows:
+///
+/// ```yml
+/// path: "/home/me/.mmpd-state.yml"
+/// save_interval_s: 30
+/// ```
+///
+/// Both fields are optional:
+///
+/// - `path`: Where to keep the state file. If omitted, it's kept in the default data directory.
+/// - `save_interval_s`: How often to write the state file while running, in seconds. Defaults
+///   to 30.
+///
+/// ## Errors
+/// This function will return `ConfigError` if:
+///
+/// - `raw_options` is neither a boolean nor a hash
+/// - `path` isn't a string
+/// - `save_interval_s` isn't a number of 1 or more
+pub (crate) fn build_state_file_options(
+    raw_options: &RawConfig
+) -> Result<Option<StateFileOptions>, ConfigError> {
+    const PATH_FIELD: &str = "path";
+    const SAVE_INTERVAL_S_FIELD: &str = "save_interval_s";
+
+    const DEFAULT_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+    let raw_options = match raw_options {
+        RawConfig::Bool(false) => return Ok(None),
+
+        RawConfig::Bool(true) => {
+            return Ok(Some(StateFileOptions { path: None, save_interval: DEFAULT_SAVE_INTERVAL }));
+        }
+
+        RawConfig::Hash(raw_options) => raw_options,
+
+        _ => {
+            return Err(ConfigError::InvalidConfig(
+                "state_file should be true, false, or a hash".to_string()
+            ));
+        }
+    };
+
+    let path = match raw_options.get(&k(PATH_FIELD)) {
+        None => None,
+        Some(RawConfig::String(path)) => Some(PathBuf::from(path)),
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(
+                format!("state_file: '{}' should be a string", PATH_FIELD)
+            ));
+        }
+    };
+
+    let save_interval = match raw_options.get(&k(SAVE_INTERVAL_S_FIELD)) {
+        None => DEFAULT_SAVE_INTERVAL,
+        Some(RawConfig::Integer(s)) if *s >= 1 => Duration::from_secs(*s as u64),
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "state_file: '{}' should be a number of seconds, 1 or more",
+                SAVE_INTERVAL_S_FIELD
+            )));
+        }
+    };
+
+    Ok(Some(StateFileOptions { path, save_interval }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use crate::config::raw_config::{RCHashBuilder, RawConfig, k};
+    use crate::config::versions::version1::state_file::build_state_file_options;
+    use crate::config::StateFileOptions;
+
+    #[test]
+    fn builds_state_file_options() {
+        assert_eq!(build_state_file_options(&RawConfig::Bool(false)).ok().unwrap(), None);
+
+        assert_eq!(
+            build_state_file_options(&RawConfig::Bool(true)).ok().unwrap(),
+            Some(StateFileOptions { path: None, save_interval: Duration::from_secs(30) })
+        );
+
+        let raw_options = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("path"), k("/tmp/mmpd-state.yml"))
+                .insert(k("save_interval_s"), RawConfig::Integer(5))
+                .build()
+        );
+
+        assert_eq!(
+            build_state_file_options(&raw_options).ok().unwrap(),
+            Some(StateFileOptions {
+                path: Some(PathBuf::from("/tmp/mmpd-state.yml")),
+                save_interval: Duration::from_secs(5)
+            })
+        );
+    }
+
+    #[test]
+    fn returns_an_error_for_invalid_options() {
+        assert!(build_state_file_options(&k("yes")).is_err());
+
+        let raw_options = RawConfig::Hash(
+            RCHashBuilder::new().insert(k("save_interval_s"), RawConfig::Integer(0)).build()
+        );
+
+        assert!(build_state_file_options(&raw_options).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use crate::config::ConfigError;
+use crate::config::raw_config::RawConfig;
+use crate::macros::actions::Count;
+use crate::macros::actions::template::{
+    Template, TemplatePart, Variable, EventField, ConditionVariable, MidiVariable
+};
+
+/// Character marking the start and end of a variable in a string
+const DELIMITER: char = '%';
+
+/// Parses a string used in an action into a `Template`, finding any variables in it.
+///
+/// Variables are written as `%variable.name%`. A literal `%` is written by doubling it: `%%`.
+///
+/// Available variables:
+///
+/// - `event.*`: fields of the event that triggered the macro:
+///   - `event.device`
+///   - `event.message_type`
+///   - `event.channel`
+///   - `event.key`
+///   - `event.velocity`
+///   - `event.control`
+///   - `event.value`
+///   - `event.program`
+///   - `event.magnitude`: how far an encoder was turned, regardless of direction
+/// - `conditions.midi.channels[c].*`: values from the MIDI state, for MIDI channel `c` (0-15):
+///   - `conditions.midi.channels[c].notes_on[k]`: "1" if key `k` (0-127) is held, "0" otherwise
+///   - `conditions.midi.channels[c].controls[n].value`: last known value of control `n` (0-127),
+///     which can also be written without `.value`
+///   - `conditions.midi.channels[c].program`: last known program
+///   - `conditions.midi.channels[c].pitch_bend`: last known pitch bend position
+/// - `conditions.variables.NAME`: value of the variable named `NAME`, if it has been set
+///
+/// ## Errors
+/// This function will return `ConfigError` in any of these conditions:
+///
+/// - A `%` is opened but never closed
+/// - A variable name is not one of the available variables listed above
+/// - A channel, key, or control index is out of range
+pub (crate) fn build_template(raw: &str) -> Result<Template, ConfigError> {
+    let mut parts: Vec<TemplatePart> = vec![];
+    let mut literal = String::new();
+    let mut chars = raw.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != DELIMITER {
+            literal.push(c);
+            continue;
+        }
+
+        // Doubled delimiter is an escaped literal delimiter
+        if chars.peek() == Some(&DELIMITER) {
+            chars.next();
+            literal.push(DELIMITER);
+            continue;
+        }
+
+        let mut name = String::new();
+        let mut closed = false;
+
+        for c in chars.by_ref() {
+            if c == DELIMITER {
+                closed = true;
+                break;
+            }
+
+            name.push(c);
+        }
+
+        if !closed {
+            return Err(ConfigError::InvalidConfig(format!(
+                "Variable '{}{}' in \"{}\" is missing a closing '{}'. \
+                 To use a literal '{}', write '{}{}'.",
+                DELIMITER, name, raw, DELIMITER, DELIMITER, DELIMITER, DELIMITER
+            )));
+        }
+
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(literal));
+            literal = String::new();
+        }
+
+        parts.push(TemplatePart::Variable(build_variable(&name)?));
+    }
+
+    if !literal.is_empty() {
+        parts.push(TemplatePart::Literal(literal));
+    }
+
+    Ok(Template::new(parts))
+}
+
+/// Parses a variable name (without the surrounding `%` characters) into a `Variable`.
+/// See `build_template` for the available variables.
+fn build_variable(name: &str) -> Result<Variable, ConfigError> {
+    const EVENT_NAMESPACE: &str = "event";
+    const CONDITIONS_NAMESPACE: &str = "conditions";
+
+    let unknown_variable = || {
+        ConfigError::InvalidConfig(format!("Unknown variable '{}'", name))
+    };
+
+    let segments = name
+        .split('.')
+        .map(|segment| parse_segment(segment).ok_or_else(unknown_variable))
+        .collect::<Result<Vec<(&str, Option<u32>)>, ConfigError>>()?;
+
+    match segments.as_slice() {
+        [(EVENT_NAMESPACE, None), (field, None)] => {
+            Ok(Variable::Event(match *field {
+                "device" => EventField::Device,
+                "message_type" => EventField::MessageType,
+                "channel" => EventField::Channel,
+                "key" => EventField::Key,
+                "velocity" => EventField::Velocity,
+                "control" => EventField::Control,
+                "value" => EventField::Value,
+                "program" => EventField::Program,
+                "magnitude" => EventField::Magnitude,
+                _ => return Err(unknown_variable())
+            }))
+        }
+
+        [(CONDITIONS_NAMESPACE, None), ("midi", None), ("channels", Some(channel)), rest @ ..] => {
+            let channel = check_range(name, "channel", *channel, 15)?;
+
+            Ok(Variable::Condition(ConditionVariable::Midi(match rest {
+                [("notes_on", Some(key))] => MidiVariable::NoteOn {
+                    channel,
+                    key: check_range(name, "key", *key, 127)?
+                },
+
+                [("controls", Some(control))]
+                | [("controls", Some(control)), ("value", None)] => MidiVariable::Control {
+                    channel,
+                    control: check_range(name, "control", *control, 127)?
+                },
+
+                [("program", None)] => MidiVariable::Program { channel },
+                [("pitch_bend", None)] => MidiVariable::PitchBend { channel },
+
+                _ => return Err(unknown_variable())
+            })))
+        }
+
+        [(CONDITIONS_NAMESPACE, None), ("variables", None), (variable_name, None)] => {
+            Ok(Variable::Condition(ConditionVariable::Variable(variable_name.to_string())))
+        }
+
+        _ => Err(unknown_variable())
+    }
+}
+
+/// Constructs a `Count` of how many times `action_type` is repeated, from the `count` field of its
+/// data.
+///
+/// An integer is a fixed count. A string is a template, resolved to a count when the action is
+/// run, like `"%event.magnitude%"`; see `Count::Template`. If `raw` is `None` or anything else, the
+/// count defaults to 1.
+///
+/// ## Errors
+/// This function will return `ConfigError` if:
+///
+/// - `raw` is a negative integer
+/// - `raw` is a string containing an invalid variable
+pub (crate) fn build_count(raw: Option<&RawConfig>, action_type: &str) -> Result<Count, ConfigError> {
+    match raw {
+        Some(RawConfig::Integer(count)) if *count < 0 => Err(ConfigError::InvalidConfig(format!(
+            "Action {}: count should be 0 or more, found {}",
+            action_type,
+            count
+        ))),
+
+        Some(RawConfig::Integer(count)) => Ok(Count::Fixed(*count as usize)),
+        Some(RawConfig::String(count)) => Ok(Count::Template(build_template(count)?)),
+        _ => Ok(Count::Fixed(1))
+    }
+}
+
+/// Splits a segment of a variable name like `controls[32]` into its name and index, or just
+/// a name if it has no index.
+/// Returns `None` if the segment is malformed.
+fn parse_segment(segment: &str) -> Option<(&str, Option<u32>)> {
+    match segment.find('[') {
+        Some(open) => {
+            let index = segment[open + 1..].strip_suffix(']')?.parse::<u32>().ok()?;
+            Some((&segment[..open], Some(index)))
+        }
+
+        None => {
+            if segment.is_empty() || segment.contains(']') {
+                None
+            } else {
+                Some((segment, None))
+            }
+        }
+    }
+}
+
+fn check_range(name: &str, what: &str, index: u32, max: u32) -> Result<u8, ConfigError> {
+    if index > max {
+        Err(ConfigError::InvalidConfig(format!(
+            "Variable '{}': {} should be in range 0-{}, found {}",
+            name, what, max, index
+        )))
+    } else {
+        Ok(index as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::versions::version1::templates::build_template;
+    use crate::macros::actions::template::{
+        Template, TemplatePart, Variable, EventField, ConditionVariable, MidiVariable
+    };
+
+    #[test]
+    fn builds_literal_template_from_string_without_variables() {
+        let template = build_template("Hello world").ok().unwrap();
+        assert_eq!(template, Template::literal("Hello world"));
+
+        let template = build_template("").ok().unwrap();
+        assert_eq!(template, Template::literal(""));
+    }
+
+    #[test]
+    fn unescapes_doubled_delimiter() {
+        let template = build_template("100%% sure, %%%%").ok().unwrap();
+        assert_eq!(template, Template::literal("100% sure, %%"));
+    }
+
+    #[test]
+    fn builds_template_with_event_variables() {
+        let template = build_template("key %event.key% at %event.velocity%%%").ok().unwrap();
+
+        assert_eq!(
+            template,
+            Template::new(vec![
+                TemplatePart::Literal("key ".to_string()),
+                TemplatePart::Variable(Variable::Event(EventField::Key)),
+                TemplatePart::Literal(" at ".to_string()),
+                TemplatePart::Variable(Variable::Event(EventField::Velocity)),
+                TemplatePart::Literal("%".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn builds_all_event_variables() {
+        let fields = vec![
+            ("device", EventField::Device),
+            ("message_type", EventField::MessageType),
+            ("channel", EventField::Channel),
+            ("key", EventField::Key),
+            ("velocity", EventField::Velocity),
+            ("control", EventField::Control),
+            ("value", EventField::Value),
+            ("program", EventField::Program),
+        ];
+
+        for (name, field) in fields {
+            let template = build_template(&format!("%event.{}%", name)).ok().unwrap();
+
+            assert_eq!(
+                template,
+                Template::new(vec![TemplatePart::Variable(Variable::Event(field))])
+            );
+        }
+    }
+
+    #[test]
+    fn builds_midi_condition_variables() {
+        let variables = vec![
+            (
+                "conditions.midi.channels[2].controls[32].value",
+                MidiVariable::Control { channel: 2, control: 32 }
+            ),
+            (
+                "conditions.midi.channels[2].controls[32]",
+                MidiVariable::Control { channel: 2, control: 32 }
+            ),
+            (
+                "conditions.midi.channels[0].notes_on[127]",
+                MidiVariable::NoteOn { channel: 0, key: 127 }
+            ),
+            ("conditions.midi.channels[15].program", MidiVariable::Program { channel: 15 }),
+            ("conditions.midi.channels[3].pitch_bend", MidiVariable::PitchBend { channel: 3 }),
+        ];
+
+        for (name, variable) in variables {
+            let template = build_template(&format!("%{}%", name)).ok().unwrap();
+
+            assert_eq!(
+                template,
+                Template::new(vec![
+                    TemplatePart::Variable(Variable::Condition(ConditionVariable::Midi(variable)))
+                ])
+            );
+        }
+    }
+
+    #[test]
+    fn builds_named_variables() {
+        let template = build_template("Layer: %conditions.variables.layer%").ok().unwrap();
+
+        assert_eq!(
+            template,
+            Template::new(vec![
+                TemplatePart::Literal("Layer: ".to_string()),
+                TemplatePart::Variable(Variable::Condition(
+                    ConditionVariable::Variable("layer".to_string())
+                ))
+            ])
+        );
+
+        assert!(build_template("%conditions.variables%").is_err());
+        assert!(build_template("%conditions.variables.layer.name%").is_err());
+    }
+
+    #[test]
+    fn returns_an_error_if_variable_is_not_closed() {
+        assert!(build_template("Hello %event.key").is_err());
+        assert!(build_template("100%").is_err());
+    }
+
+    #[test]
+    fn returns_an_error_for_unknown_variables() {
+        assert!(build_template("%event%").is_err());
+        assert!(build_template("%event.nope%").is_err());
+        assert!(build_template("%event.key.value%").is_err());
+        assert!(build_template("%nope.key%").is_err());
+        assert!(build_template("%conditions.midi.channels.program%").is_err());
+        assert!(build_template("%conditions.midi.channels[1].controls%").is_err());
+        assert!(build_template("%conditions.midi.channels[1].controls[2].program%").is_err());
+        assert!(build_template("%conditions.midi.channels[1].controls[a]%").is_err());
+        assert!(build_template("%conditions.midi.channels[1].program[2]%").is_err());
+    }
+
+    #[test]
+    fn returns_an_error_if_index_is_out_of_range() {
+        assert!(build_template("%conditions.midi.channels[16].program%").is_err());
+        assert!(build_template("%conditions.midi.channels[0].controls[128]%").is_err());
+        assert!(build_template("%conditions.midi.channels[0].notes_on[128]%").is_err());
+    }
+}
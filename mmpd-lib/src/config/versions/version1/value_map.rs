@@ -0,0 +1,259 @@
+use std::time::Duration;
+use crate::config::raw_config::{RawConfig, RCHash, k};
+use crate::config::ConfigError;
+use crate::macros::mapping::{ValueMap, Curve};
+
+/// Constructs a `ValueMap` from a macro's `map` field's `raw_map`, mapping the value of the event
+/// the macro matched into a variable for its actions to use.
+///
+/// Expects `raw_map` to be a hash structured as follows:
+///
+/// ```yml
+/// variable: volume
+/// from:
+///     min: 0
+///     max: 127
+/// to:
+///     min: 0
+///     max: 100
+/// curve: linear
+/// interval_ms: 50
+/// deduplicate: true
+/// ```
+///
+/// - `variable`: Required. Name of the variable to store the mapped value in.
+/// - `from`: Optional. Range of incoming values to map; values outside of it are clamped to it. If
+///   omitted, the full range of the event's value is used, like 0-127 for a control change.
+/// - `to`: Required. Range to map values into. `min` may be larger than `max` to invert it.
+/// - `curve`: Optional, `linear` or `logarithmic`, defaulting to `linear`. With `logarithmic`,
+///   both ends of `to` have to be more than 0.
+/// - `interval_ms`: Optional. Least time between runs of the macro, in milliseconds, defaulting
+///   to 50. 0 disables it.
+/// - `deduplicate`: Optional, defaulting to true. Whether to skip running the macro when the
+///   mapped value is the same as last time.
+///
+/// ## Errors
+/// This function will return `ConfigError` if:
+///
+/// - `raw_map` isn't a hash
+/// - `variable` is missing or isn't a string
+/// - `to` is missing, or either range doesn't have both a `min` and `max` integer
+/// - `from` has negative values, or its `min` isn't less than its `max`
+/// - `curve` is something other than `linear` or `logarithmic`
+/// - `curve` is `logarithmic`, but either end of `to` isn't more than 0
+/// - `interval_ms` is negative or isn't an integer
+/// - `deduplicate` isn't a boolean
+pub (crate) fn build_value_map(raw_map: &RawConfig) -> Result<ValueMap, ConfigError> {
+    const VARIABLE_FIELD: &str = "variable";
+    const FROM_FIELD: &str = "from";
+    const TO_FIELD: &str = "to";
+    const CURVE_FIELD: &str = "curve";
+    const INTERVAL_MS_FIELD: &str = "interval_ms";
+    const DEDUPLICATE_FIELD: &str = "deduplicate";
+
+    const DEFAULT_INTERVAL: Duration = Duration::from_millis(50);
+
+    let raw_map = match raw_map {
+        RawConfig::Hash(raw_map) => raw_map,
+        _ => return Err(ConfigError::InvalidConfig("Macro: 'map' should be a hash".to_string()))
+    };
+
+    let variable = match raw_map.get(&k(VARIABLE_FIELD)) {
+        Some(RawConfig::String(variable)) => variable.to_string(),
+
+        _ => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "Macro map: missing or invalid '{}' name",
+                VARIABLE_FIELD
+            )));
+        }
+    };
+
+    let from = match raw_map.get(&k(FROM_FIELD)) {
+        None => None,
+
+        Some(raw_range) => {
+            let (min, max) = build_range(raw_range, FROM_FIELD)?;
+
+            if min < 0 || min >= max || max > i64::from(u32::MAX) {
+                return Err(ConfigError::InvalidConfig(format!(
+                    "Macro map: '{}' should go from 0 or more up to a larger max, found {}-{}",
+                    FROM_FIELD,
+                    min,
+                    max
+                )));
+            }
+
+            Some((min as u32, max as u32))
+        }
+    };
+
+    let to = match raw_map.get(&k(TO_FIELD)) {
+        Some(raw_range) => build_range(raw_range, TO_FIELD)?,
+
+        None => {
+            return Err(ConfigError::InvalidConfig(
+                format!("Macro map: missing '{}' range", TO_FIELD)
+            ));
+        }
+    };
+
+    let curve = match raw_map.get(&k(CURVE_FIELD)) {
+        None => Curve::Linear,
+        Some(RawConfig::String(curve)) if curve == "linear" => Curve::Linear,
+        Some(RawConfig::String(curve)) if curve == "logarithmic" => Curve::Logarithmic,
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "Macro map: '{}' should be linear or logarithmic",
+                CURVE_FIELD
+            )));
+        }
+    };
+
+    if curve == Curve::Logarithmic && (to.0 <= 0 || to.1 <= 0) {
+        return Err(ConfigError::InvalidConfig(format!(
+            "Macro map: a logarithmic curve needs both ends of '{}' to be more than 0",
+            TO_FIELD
+        )));
+    }
+
+    let interval = match raw_map.get(&k(INTERVAL_MS_FIELD)) {
+        None => DEFAULT_INTERVAL,
+        Some(RawConfig::Integer(ms)) if *ms >= 0 => Duration::from_millis(*ms as u64),
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "Macro map: '{}' should be a number of milliseconds, 0 or more",
+                INTERVAL_MS_FIELD
+            )));
+        }
+    };
+
+    let deduplicate = match raw_map.get(&k(DEDUPLICATE_FIELD)) {
+        None => true,
+        Some(RawConfig::Bool(deduplicate)) => *deduplicate,
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(format!(
+                "Macro map: '{}' should be true or false",
+                DEDUPLICATE_FIELD
+            )));
+        }
+    };
+
+    Ok(ValueMap { variable, from, to, curve, interval, deduplicate })
+}
+
+/// Reads a `{ min, max }` hash from `raw_range`, the value of `field`, as `(min, max)`
+fn build_range(raw_range: &RawConfig, field: &str) -> Result<(i64, i64), ConfigError> {
+    let get = |raw_range: &RCHash, key: &str| match raw_range.get(&k(key)) {
+        Some(RawConfig::Integer(value)) => Some(*value),
+        _ => None
+    };
+
+    if let RawConfig::Hash(raw_range) = raw_range {
+        if let (Some(min), Some(max)) = (get(raw_range, "min"), get(raw_range, "max")) {
+            return Ok((min, max));
+        }
+    }
+
+    Err(ConfigError::InvalidConfig(format!(
+        "Macro map: '{}' should be a hash with integer 'min' and 'max' fields",
+        field
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::config::raw_config::{RawConfig, RCHashBuilder, k};
+    use crate::config::versions::version1::value_map::build_value_map;
+    use crate::macros::mapping::{ValueMap, Curve};
+
+    fn range(min: i64, max: i64) -> RawConfig {
+        RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("min"), RawConfig::Integer(min))
+                .insert(k("max"), RawConfig::Integer(max))
+                .build()
+        )
+    }
+
+    #[test]
+    fn builds_value_map() {
+        let raw_map = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("variable"), k("cutoff"))
+                .insert(k("from"), range(10, 100))
+                .insert(k("to"), range(20, 20000))
+                .insert(k("curve"), k("logarithmic"))
+                .insert(k("interval_ms"), RawConfig::Integer(0))
+                .insert(k("deduplicate"), RawConfig::Bool(false))
+                .build()
+        );
+
+        assert_eq!(
+            build_value_map(&raw_map).ok().unwrap(),
+            ValueMap {
+                variable: "cutoff".to_string(),
+                from: Some((10, 100)),
+                to: (20, 20000),
+                curve: Curve::Logarithmic,
+                interval: Duration::from_millis(0),
+                deduplicate: false
+            }
+        );
+    }
+
+    #[test]
+    fn builds_value_map_with_defaults() {
+        let raw_map = RawConfig::Hash(
+            RCHashBuilder::new()
+                .insert(k("variable"), k("volume"))
+                .insert(k("to"), range(0, 100))
+                .build()
+        );
+
+        assert_eq!(
+            build_value_map(&raw_map).ok().unwrap(),
+            ValueMap {
+                variable: "volume".to_string(),
+                from: None,
+                to: (0, 100),
+                curve: Curve::Linear,
+                interval: Duration::from_millis(50),
+                deduplicate: true
+            }
+        );
+    }
+
+    #[test]
+    fn returns_an_error_for_invalid_value_maps() {
+        let build = |builder: RCHashBuilder| build_value_map(&RawConfig::Hash(builder.build()));
+
+        // Missing variable
+        assert!(build(RCHashBuilder::new().insert(k("to"), range(0, 100))).is_err());
+
+        // Missing to
+        assert!(build(RCHashBuilder::new().insert(k("variable"), k("volume"))).is_err());
+
+        // Empty from range
+        assert!(build(
+            RCHashBuilder::new()
+                .insert(k("variable"), k("volume"))
+                .insert(k("from"), range(64, 64))
+                .insert(k("to"), range(0, 100))
+        ).is_err());
+
+        // Logarithmic curve through 0
+        assert!(build(
+            RCHashBuilder::new()
+                .insert(k("variable"), k("volume"))
+                .insert(k("to"), range(0, 100))
+                .insert(k("curve"), k("logarithmic"))
+        ).is_err());
+
+        assert!(build_value_map(&k("volume")).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+mod defaults;
+mod macro_templates;
+pub (crate) mod migrate;
+
+use crate::config::versions::{ConfigVersionProcessor, Form, UnknownField};
+use crate::config::versions::version1::Version1Processor;
+use crate::config::versions::version1::canonical::{canonicalize_macro, hashes_mut};
+use crate::config::versions::version1::known_fields::key_name;
+use crate::config::versions::version2::defaults::{apply_defaults, DEFAULTS_FIELDS};
+use crate::config::versions::version2::macro_templates::{resolve_templates, EXTENDS_FIELD};
+use crate::config::raw_config::{RCHash, AccessHelpers, RawConfig, k};
+use crate::config::{ConfigError, Config};
+
+const DEFAULTS_FIELD: &str = "defaults";
+const MACRO_TEMPLATES_FIELD: &str = "macro_templates";
+const SCOPES_FIELD: &str = "scopes";
+const MACROS_FIELD: &str = "macros";
+const GLOBAL_MACROS_FIELD: &str = "global_macros";
+
+/// Processes version 2 configs by spelling them out into version 1, see `process`
+pub (crate) struct Version2Processor { }
+
+impl Version2Processor {
+    /// Provides an instance of Version2Processor presented as "an implementation of
+    /// `ConfigVersionProcessor`"
+    pub (crate) fn new() -> Box<dyn ConfigVersionProcessor> {
+        Box::new(Version2Processor {})
+    }
+}
+
+impl ConfigVersionProcessor for Version2Processor {
+    /// Processes a top level RCHash into a Config instance, or returns a ConfigError if something
+    /// doesn't work out correctly.
+    ///
+    /// ## Notes on the version 2 format
+    ///
+    /// Version 2 is version 1 with less repetition; each part of it is spelled out into its
+    /// version 1 form, which is then processed like a version 1 config. The additions are:
+    /// - `type` may be left out of event matchers and preconditions, in which case it's `midi`.
+    /// - `defaults`: At the top level and in scopes, the `channel` and `device` for MIDI event
+    ///   matchers and preconditions that don't have their own, and the `delay` or `delay_ms` for
+    ///   `key_sequence` and `enter_text` actions. See `apply_defaults`.
+    /// - `macro_templates`: Named partial macros at the top level, which macros can take fields
+    ///   from with `extends`. See `resolve_templates`.
+    ///
+    /// Further documentation and examples on the format can be found in /docs/config.md
+    ///
+    /// ## Arguments
+    /// raw_config: Top level hash parsed from the config input file
+    fn process(&self, raw_config: RCHash) -> Result<Config, ConfigError> {
+        Version1Processor::new().process(expand(raw_config)?)
+    }
+
+    /// Checks the parts added in version 2 here, and leaves the rest to version 1. Macros are
+    /// checked without the fields they'd take from templates, and templates on their own, so
+    /// unknown fields are reported where they're written.
+    fn find_unknown_fields(&self, raw_config: &RCHash) -> Vec<UnknownField> {
+        let version1 = Version1Processor::new();
+        let mut v1_config = raw_config.clone();
+        let mut unknown = vec![];
+
+        for scope in hashes_mut(v1_config.get_mut(&k(SCOPES_FIELD))) {
+            for raw_macro in hashes_mut(scope.get_mut(&k(MACROS_FIELD))) {
+                raw_macro.remove(&k(EXTENDS_FIELD));
+            }
+        }
+
+        for raw_macro in hashes_mut(v1_config.get_mut(&k(GLOBAL_MACROS_FIELD))) {
+            raw_macro.remove(&k(EXTENDS_FIELD));
+        }
+
+        apply_defaults(&mut v1_config);
+        strip_version2_fields(&mut v1_config);
+        unknown.extend(version1.find_unknown_fields(&v1_config));
+
+        check_defaults(raw_config.get_hash(DEFAULTS_FIELD), DEFAULTS_FIELD, &mut unknown);
+
+        if let Some(raw_scopes) = raw_config.get_array(SCOPES_FIELD) {
+            for (index, raw_scope) in raw_scopes.iter().enumerate() {
+                if let RawConfig::Hash(raw_scope) = raw_scope {
+                    let path = format!("{}[{}].{}", SCOPES_FIELD, index, DEFAULTS_FIELD);
+                    check_defaults(raw_scope.get_hash(DEFAULTS_FIELD), &path, &mut unknown);
+                }
+            }
+        }
+
+        // Each template is checked as the only global macro of an otherwise empty config
+        for (name, template) in raw_config.get_hash(MACRO_TEMPLATES_FIELD).into_iter().flatten() {
+            if let RawConfig::Hash(template) = template {
+                let mut template = template.clone();
+                template.remove(&k(EXTENDS_FIELD));
+
+                let mut template_config = RCHash::new();
+                let macros = RawConfig::Array(vec![RawConfig::Hash(template)]);
+                template_config.insert(k(GLOBAL_MACROS_FIELD), macros);
+                apply_defaults(&mut template_config);
+
+                let macro_path = format!("{}[0]", GLOBAL_MACROS_FIELD);
+                let template_path = format!("{}.{}", MACRO_TEMPLATES_FIELD, key_name(name));
+
+                unknown.extend(
+                    version1
+                        .find_unknown_fields(&template_config)
+                        .into_iter()
+                        .map(|field| UnknownField {
+                            path: field.path.replacen(&macro_path, &template_path, 1),
+                            ..field
+                        })
+                );
+            }
+        }
+
+        unknown
+    }
+
+    fn canonicalize(&self, raw_config: &mut RCHash, form: Form) {
+        Version1Processor::new().canonicalize(raw_config, form);
+
+        if let Some(RawConfig::Hash(templates)) = raw_config.get_mut(&k(MACRO_TEMPLATES_FIELD)) {
+            for (_, template) in templates.iter_mut() {
+                if let RawConfig::Hash(template) = template {
+                    canonicalize_macro(template, form);
+                }
+            }
+        }
+    }
+}
+
+/// Spells out a version 2 config into its version 1 form: macros take the fields of the templates
+/// they extend first, after which the defaults are filled in.
+///
+/// ## Errors
+/// Returns a `ConfigError` if `defaults` isn't a hash, or a template can't be resolved.
+fn expand(mut raw_config: RCHash) -> Result<RCHash, ConfigError> {
+    let not_a_hash = || {
+        ConfigError::InvalidConfig(format!("'{}' should be a hash", DEFAULTS_FIELD))
+    };
+
+    if let Some(raw_defaults) = raw_config.get(&k(DEFAULTS_FIELD)) {
+        if !matches!(raw_defaults, RawConfig::Hash(_)) {
+            return Err(not_a_hash());
+        }
+    }
+
+    if let Some(raw_scopes) = raw_config.get_array(SCOPES_FIELD) {
+        for (index, raw_scope) in raw_scopes.iter().enumerate() {
+            if let RawConfig::Hash(raw_scope) = raw_scope {
+                if let Some(raw_defaults) = raw_scope.get(&k(DEFAULTS_FIELD)) {
+                    if !matches!(raw_defaults, RawConfig::Hash(_)) {
+                        let err = not_a_hash().within_item(SCOPES_FIELD, index, raw_scope.loc());
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+
+    resolve_templates(&mut raw_config)?;
+    apply_defaults(&mut raw_config);
+    strip_version2_fields(&mut raw_config);
+
+    Ok(raw_config)
+}
+
+/// Removes the fields version 1 doesn't know about once their contents have been applied
+fn strip_version2_fields(raw_config: &mut RCHash) {
+    raw_config.remove(&k(DEFAULTS_FIELD));
+    raw_config.remove(&k(MACRO_TEMPLATES_FIELD));
+
+    for scope in hashes_mut(raw_config.get_mut(&k(SCOPES_FIELD))) {
+        scope.remove(&k(DEFAULTS_FIELD));
+    }
+}
+
+/// Adds the fields of `raw_defaults`, found at `path`, that aren't defaults
+fn check_defaults(raw_defaults: Option<&RCHash>, path: &str, unknown: &mut Vec<UnknownField>) {
+    let raw_defaults = match raw_defaults {
+        Some(raw_defaults) => raw_defaults,
+        None => return
+    };
+
+    for key in raw_defaults.keys() {
+        if !DEFAULTS_FIELDS.iter().any(|field| *key == k(field)) {
+            unknown.push(UnknownField {
+                path: format!("{}.{}", path, key_name(key)),
+                loc: raw_defaults.key_loc(key)
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::input_formats::get_parser_for_extension;
+    use crate::config::raw_config::{RawConfig, RCHash};
+    use crate::config::versions::{get_processor, Form};
+
+    fn parse(yaml: &str) -> RCHash {
+        match get_parser_for_extension("yml").unwrap().parse(yaml).ok().unwrap() {
+            RawConfig::Hash(hash) => hash,
+            _ => panic!("Expected a hash")
+        }
+    }
+
+    #[test]
+    fn processes_the_same_as_the_spelled_out_version_1_config() {
+        let version2 = parse("
+version: 2
+defaults:
+  channel: 2
+  delay_ms: 5
+macro_templates:
+  pad:
+    matching_events:
+      - data: { message_type: note_on, key: 36 }
+  loud_pad:
+    extends: pad
+    name: Loud pad
+scopes:
+  - window_class: { is: Gimp }
+    defaults: { channel: 3 }
+    macros:
+      - extends: loud_pad
+        actions:
+          - type: key_sequence
+            data: ctrl+z
+global_macros:
+  - extends: pad
+    required_preconditions:
+      - data: { condition_type: control, control: 7, value: 0 }
+    actions:
+      - type: enter_text
+        data: { text: Hi, delay: 100 }
+  - matching_events:
+      - data: { message_type: note_off, channel: ~ }
+    actions: [{ type: control, data: exit }]
+");
+
+        let version1 = parse("
+version: 1
+scopes:
+  - window_class: { is: Gimp }
+    macros:
+      - name: Loud pad
+        matching_events:
+          - type: midi
+            data: { message_type: note_on, key: 36, channel: 3 }
+        actions:
+          - type: key_sequence
+            data: { sequence: ctrl+z, delay_ms: 5 }
+global_macros:
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on, key: 36, channel: 2 }
+    required_preconditions:
+      - type: midi
+        data: { condition_type: control, control: 7, value: 0, channel: 2 }
+    actions:
+      - type: enter_text
+        data: { text: Hi, delay: 100 }
+  - matching_events:
+      - type: midi
+        data: { message_type: note_off }
+    actions: [{ type: control, data: exit }]
+");
+
+        let processor = get_processor(2).unwrap();
+        assert!(processor.find_unknown_fields(&version2).is_empty());
+
+        assert_eq!(
+            format!("{:?}", processor.process(version2).ok().unwrap().macros),
+            format!("{:?}", RawConfig::Hash(version1).process().ok().unwrap().macros)
+        );
+    }
+
+    #[test]
+    fn returns_errors_for_templates_that_cannot_be_resolved() {
+        let processor = get_processor(2).unwrap();
+
+        let missing = parse("
+version: 2
+scopes:
+  - macros:
+      - extends: nothing
+");
+
+        let description = processor.process(missing).err().unwrap().description();
+        assert!(description.starts_with("scopes[0].macros[0] (line 5, column 9)"));
+        assert!(description.contains("There is no macro template named 'nothing'"));
+
+        let cycle = parse("
+version: 2
+macro_templates:
+  a: { extends: b }
+  b: { extends: a }
+global_macros:
+  - extends: a
+");
+
+        let description = processor.process(cycle).err().unwrap().description();
+        assert!(description.contains("Macro template 'a' extends itself: a -> b -> a"));
+    }
+
+    #[test]
+    fn finds_unknown_fields_where_they_are_written() {
+        let raw_config = parse("
+version: 2
+defaults: { chanel: 1 }
+macro_templates:
+  pad:
+    matching_events:
+      - data: { message_type: note_on, kye: 36 }
+scopes:
+  - defaults: { delay: 10, velocity: 3 }
+    macros:
+      - extends: pad
+        actoins: []
+");
+
+        let unknown = get_processor(2).unwrap().find_unknown_fields(&raw_config);
+
+        assert_eq!(unknown.iter().map(|field| field.path.as_str()).collect::<Vec<_>>(), vec![
+            "scopes[0].macros[0].actoins",
+            "defaults.chanel",
+            "scopes[0].defaults.velocity",
+            "macro_templates.pad.matching_events[0].data.kye"
+        ]);
+    }
+
+    #[test]
+    fn rewrites_shorthand_forms_in_templates_too() {
+        let mut raw_config = parse("
+version: 2
+macro_templates:
+  greet:
+    actions:
+      - type: enter_text
+        data: { text: Hi }
+");
+
+        get_processor(2).unwrap().canonicalize(&mut raw_config, Form::Minimal);
+
+        assert_eq!(raw_config, parse("
+version: 2
+macro_templates:
+  greet:
+    actions:
+      - type: enter_text
+        data: Hi
+"));
+    }
+}
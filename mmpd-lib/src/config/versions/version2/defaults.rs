@@ -0,0 +1,375 @@
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+use crate::config::versions::version1::action_groups::lone_param;
+use crate::config::versions::version1::canonical::hashes_mut;
+
+pub (crate) const TYPE_FIELD: &str = "type";
+pub (crate) const DATA_FIELD: &str = "data";
+pub (crate) const TYPE_MIDI: &str = "midi";
+
+pub (crate) const CHANNEL_FIELD: &str = "channel";
+pub (crate) const DEVICE_FIELD: &str = "device";
+pub (crate) const DELAY_FIELD: &str = "delay";
+pub (crate) const DELAY_MS_FIELD: &str = "delay_ms";
+
+/// Fields a `defaults` hash can have
+pub (crate) const DEFAULTS_FIELDS: [&str; 4] =
+    [CHANNEL_FIELD, DEVICE_FIELD, DELAY_FIELD, DELAY_MS_FIELD];
+
+/// Action types that type text or keys, and so have a delay between them, along with the field
+/// their `data` shortens to
+pub (crate) const DELAYED_ACTIONS: [(&str, &str); 2] =
+    [("key_sequence", "sequence"), ("enter_text", "text")];
+
+const ACTIONS_FIELD: &str = "actions";
+const REQUIRED_PRECONDITIONS_FIELD: &str = "required_preconditions";
+
+/// The parts of a config that `walk` visits
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub (crate) enum Part {
+    EventMatcher,
+    Precondition,
+    Action
+}
+
+/// Default values from a `defaults` hash, for the parts that don't specify their own
+#[derive(Clone, Default)]
+struct Defaults {
+    channel: Option<RawConfig>,
+    device: Option<RawConfig>,
+    delay: Option<RawConfig>,
+    delay_ms: Option<RawConfig>
+}
+
+impl Defaults {
+    /// Takes the defaults in `raw_defaults` over `self`. The delay is taken as a whole, so one
+    /// given in milliseconds replaces one that isn't.
+    fn overridden_by(&self, raw_defaults: Option<&RCHash>) -> Defaults {
+        let raw_defaults = match raw_defaults {
+            Some(raw_defaults) => raw_defaults,
+            None => return self.clone()
+        };
+
+        let get = |field| raw_defaults.get(&k(field)).cloned();
+        let has_delay = get(DELAY_FIELD).is_some() || get(DELAY_MS_FIELD).is_some();
+
+        Defaults {
+            channel: get(CHANNEL_FIELD).or_else(|| self.channel.clone()),
+            device: get(DEVICE_FIELD).or_else(|| self.device.clone()),
+            delay: if has_delay { get(DELAY_FIELD) } else { self.delay.clone() },
+            delay_ms: if has_delay { get(DELAY_MS_FIELD) } else { self.delay_ms.clone() }
+        }
+    }
+
+    /// Fills in the channel and device of the `data` of a MIDI event matcher or precondition
+    fn apply_to_midi_data(&self, data: &mut RCHash) {
+        fill_in(data, CHANNEL_FIELD, &self.channel);
+        fill_in(data, DEVICE_FIELD, &self.device);
+    }
+
+    /// Fills in the delay of the `data` of an action in `DELAYED_ACTIONS`, whose `data` shortens to
+    /// `field`
+    fn apply_to_delayed_action_data(&self, data: &mut RawConfig, field: &str) {
+        if self.delay.is_none() && self.delay_ms.is_none() {
+            return;
+        }
+
+        // Just the text or sequence is expanded into a hash, so that the delay can be added. An
+        // action group parameter reference could hold a hash already, so it's left alone.
+        if let RawConfig::String(value) = data {
+            if lone_param(value).is_some() {
+                return;
+            }
+
+            let mut hash = RCHash::new();
+            hash.insert(k(field), RawConfig::String(value.to_string()));
+            *data = RawConfig::Hash(hash);
+        }
+
+        if let RawConfig::Hash(data) = data {
+            if data.contains_key(&k(DELAY_FIELD)) || data.contains_key(&k(DELAY_MS_FIELD)) {
+                fill_in(data, DELAY_FIELD, &None);
+                fill_in(data, DELAY_MS_FIELD, &None);
+            } else {
+                fill_in(data, DELAY_FIELD, &self.delay);
+                fill_in(data, DELAY_MS_FIELD, &self.delay_ms);
+            }
+        }
+    }
+}
+
+/// Sets `field` of `data` to `default` if it doesn't have it, and removes it if it's null, which
+/// is how a part opts out of a default
+fn fill_in(data: &mut RCHash, field: &str, default: &Option<RawConfig>) {
+    match data.get(&k(field)) {
+        Some(RawConfig::Null) => { data.remove(&k(field)); }
+
+        None => if let Some(default) = default {
+            if *default != RawConfig::Null {
+                data.insert(k(field), default.clone());
+            }
+        },
+
+        Some(_) => {}
+    }
+}
+
+/// Fills in what the parts of `raw_config` leave out, as far as they're valid:
+///
+/// - Event matchers and preconditions without a `type` are of type `midi`.
+/// - The `data` of MIDI event matchers and preconditions, including the events of chords and
+///   sequences, gets the default `channel` and `device` if it doesn't have them.
+/// - The `data` of `key_sequence` and `enter_text` actions gets the default `delay` or `delay_ms`
+///   if it has neither. If its `data` is just the text or sequence, it's expanded into a hash.
+///
+/// The defaults are those of the top level `defaults` hash, with the fields of a scope's own
+/// `defaults` taking precedence for its macros. Action groups only use the top level defaults, as
+/// they can be called from any scope. Fields set to null opt out of the default, and are removed.
+pub (crate) fn apply_defaults(raw_config: &mut RCHash) {
+    let defaults = Defaults::default().overridden_by(raw_config.get_hash("defaults"));
+
+    let scope_defaults: Vec<Defaults> = match raw_config.get_array("scopes") {
+        Some(raw_scopes) => raw_scopes
+            .iter()
+            .map(|raw_scope| match raw_scope {
+                RawConfig::Hash(raw_scope) => {
+                    defaults.overridden_by(raw_scope.get_hash("defaults"))
+                }
+
+                _ => defaults.clone()
+            })
+            .collect(),
+
+        None => vec![]
+    };
+
+    walk(raw_config, &mut |scope, part, hash| {
+        let defaults = scope.map_or(&defaults, |index| &scope_defaults[index]);
+
+        if part != Part::Action && !hash.contains_key(&k(TYPE_FIELD)) {
+            hash.insert(k(TYPE_FIELD), k(TYPE_MIDI));
+        }
+
+        let hash_type = hash.get_string(TYPE_FIELD).map(|t| t.to_string());
+
+        match (part, hash.get_mut(&k(DATA_FIELD))) {
+            (Part::EventMatcher, Some(RawConfig::Hash(data)))
+            | (Part::Precondition, Some(RawConfig::Hash(data)))
+                if hash_type.as_deref() == Some(TYPE_MIDI) =>
+            {
+                defaults.apply_to_midi_data(data);
+            }
+
+            (Part::Action, Some(data)) => {
+                let delayed_action = DELAYED_ACTIONS
+                    .iter()
+                    .find(|(action_type, _)| hash_type.as_deref() == Some(*action_type));
+
+                if let Some((_, field)) = delayed_action {
+                    defaults.apply_to_delayed_action_data(data, field);
+                }
+            }
+
+            _ => {}
+        }
+    });
+}
+
+/// Calls `visit` with every event matcher, precondition and action hash in `raw_config`, along
+/// with the index of the scope it's in, or `None` for global macros and action groups. Parts are
+/// visited before the parts within them, which are found by their `type` after the visit.
+///
+/// Like `find_unknown_fields`, only the structure is followed; parts that aren't valid are
+/// skipped.
+pub (crate) fn walk<F>(raw_config: &mut RCHash, visit: &mut F)
+    where F: FnMut(Option<usize>, Part, &mut RCHash)
+{
+    if let Some(RawConfig::Hash(groups)) = raw_config.get_mut(&k("action_groups")) {
+        for (_, group) in groups.iter_mut() {
+            match group {
+                RawConfig::Hash(group) => {
+                    walk_actions(group.get_mut(&k(ACTIONS_FIELD)), None, visit);
+                }
+
+                group => walk_actions(Some(group), None, visit)
+            }
+        }
+    }
+
+    if let Some(RawConfig::Array(scopes)) = raw_config.get_mut(&k("scopes")) {
+        for (index, scope) in scopes.iter_mut().enumerate() {
+            if let RawConfig::Hash(scope) = scope {
+                for raw_macro in hashes_mut(scope.get_mut(&k("macros"))) {
+                    walk_macro(raw_macro, Some(index), visit);
+                }
+            }
+        }
+    }
+
+    for raw_macro in hashes_mut(raw_config.get_mut(&k("global_macros"))) {
+        walk_macro(raw_macro, None, visit);
+    }
+}
+
+fn walk_macro<F>(raw_macro: &mut RCHash, scope: Option<usize>, visit: &mut F)
+    where F: FnMut(Option<usize>, Part, &mut RCHash)
+{
+    for event in hashes_mut(raw_macro.get_mut(&k("matching_events"))) {
+        walk_event_matcher(event, scope, visit);
+    }
+
+    walk_preconditions(raw_macro.get_mut(&k(REQUIRED_PRECONDITIONS_FIELD)), scope, visit);
+    walk_actions(raw_macro.get_mut(&k(ACTIONS_FIELD)), scope, visit);
+}
+
+fn walk_event_matcher<F>(event: &mut RCHash, scope: Option<usize>, visit: &mut F)
+    where F: FnMut(Option<usize>, Part, &mut RCHash)
+{
+    visit(scope, Part::EventMatcher, event);
+    walk_preconditions(event.get_mut(&k(REQUIRED_PRECONDITIONS_FIELD)), scope, visit);
+
+    let events_field = match event.get_string(TYPE_FIELD) {
+        Some("chord") => "events",
+        Some("sequence") => "steps",
+        _ => return
+    };
+
+    if let Some(RawConfig::Hash(data)) = event.get_mut(&k(DATA_FIELD)) {
+        for event in hashes_mut(data.get_mut(&k(events_field))) {
+            walk_event_matcher(event, scope, visit);
+        }
+    }
+}
+
+fn walk_preconditions<F>(
+    raw_preconditions: Option<&mut RawConfig>,
+    scope: Option<usize>,
+    visit: &mut F
+)
+    where F: FnMut(Option<usize>, Part, &mut RCHash)
+{
+    for precondition in hashes_mut(raw_preconditions) {
+        visit(scope, Part::Precondition, precondition);
+    }
+}
+
+fn walk_actions<F>(raw_actions: Option<&mut RawConfig>, scope: Option<usize>, visit: &mut F)
+    where F: FnMut(Option<usize>, Part, &mut RCHash)
+{
+    for action in hashes_mut(raw_actions) {
+        visit(scope, Part::Action, action);
+
+        let action_type = action.get_string(TYPE_FIELD).map(|t| t.to_string());
+
+        let data = match action.get_mut(&k(DATA_FIELD)) {
+            Some(RawConfig::Hash(data)) => data,
+            _ => continue
+        };
+
+        match action_type.as_deref() {
+            Some("repeat_while") | Some("if") => {
+                let preconditions = data.get_mut(&k(REQUIRED_PRECONDITIONS_FIELD));
+                walk_preconditions(preconditions, scope, visit);
+
+                for actions_field in [ACTIONS_FIELD, "then", "else"].iter() {
+                    walk_actions(data.get_mut(&k(actions_field)), scope, visit);
+                }
+            }
+
+            Some("switch") => {
+                for case in hashes_mut(data.get_mut(&k("cases"))) {
+                    walk_actions(case.get_mut(&k(ACTIONS_FIELD)), scope, visit);
+                }
+
+                walk_actions(data.get_mut(&k("default")), scope, visit);
+            }
+
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::input_formats::get_parser_for_extension;
+    use crate::config::raw_config::{RawConfig, RCHash};
+    use crate::config::versions::version2::defaults::apply_defaults;
+
+    fn parse(yaml: &str) -> RCHash {
+        match get_parser_for_extension("yml").unwrap().parse(yaml).ok().unwrap() {
+            RawConfig::Hash(hash) => hash,
+            _ => panic!("Expected a hash")
+        }
+    }
+
+    #[test]
+    fn fills_in_defaults_where_they_are_left_out() {
+        let mut raw_config = parse("
+defaults: { channel: 1, device: { is: Pad }, delay_ms: 4 }
+action_groups:
+  greet:
+    - type: enter_text
+      data: Hi
+scopes:
+  - defaults: { channel: 2, delay: 300 }
+    macros:
+      - matching_events:
+          - type: chord
+            data:
+              events:
+                - data: { message_type: note_on, key: 36 }
+                - data: { message_type: note_on, key: 38, channel: ~ }
+        actions:
+          - type: if
+            data:
+              required_preconditions:
+                - data: { condition_type: note_on, key: 40 }
+              then:
+                - type: key_sequence
+                  data: { sequence: ctrl+s, delay_ms: 1 }
+global_macros:
+  - matching_events:
+      - type: device_connected
+        data: { device: { is: Pad } }
+    required_preconditions:
+      - type: variable
+        data: { name: mode, string: edit }
+");
+
+        apply_defaults(&mut raw_config);
+
+        assert_eq!(raw_config, parse("
+defaults: { channel: 1, device: { is: Pad }, delay_ms: 4 }
+action_groups:
+  greet:
+    - type: enter_text
+      data: { text: Hi, delay_ms: 4 }
+scopes:
+  - defaults: { channel: 2, delay: 300 }
+    macros:
+      - matching_events:
+          - type: chord
+            data:
+              events:
+                - data: { message_type: note_on, key: 36, channel: 2, device: { is: Pad } }
+                  type: midi
+                - data: { message_type: note_on, key: 38, device: { is: Pad } }
+                  type: midi
+        actions:
+          - type: if
+            data:
+              required_preconditions:
+                - data: { condition_type: note_on, key: 40, channel: 2, device: { is: Pad } }
+                  type: midi
+              then:
+                - type: key_sequence
+                  data: { sequence: ctrl+s, delay_ms: 1 }
+global_macros:
+  - matching_events:
+      - type: device_connected
+        data: { device: { is: Pad } }
+    required_preconditions:
+      - type: variable
+        data: { name: mode, string: edit }
+"));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::config::ConfigError;
+use crate::config::raw_config::{RawConfig, RCHash, k};
+use crate::config::versions::version1::canonical::hashes_mut;
+
+pub (crate) const EXTENDS_FIELD: &str = "extends";
+
+const MACRO_TEMPLATES_FIELD: &str = "macro_templates";
+const SCOPES_FIELD: &str = "scopes";
+const MACROS_FIELD: &str = "macros";
+const GLOBAL_MACROS_FIELD: &str = "global_macros";
+
+/// Fills in the fields of each macro in `raw_config` that has an `extends` field from the macro
+/// template it names, and removes `extends`.
+///
+/// Macro templates are partial macros in the top level `macro_templates` hash, by name. A macro
+/// takes each field it doesn't have itself from its template as a whole, so a macro with its own
+/// `actions` uses those instead of the template's. Templates can extend other templates in turn.
+///
+/// ```yaml
+/// macro_templates:
+///   pad:
+///     matching_events:
+///       - data: { message_type: note_on, key: 36 }
+///
+/// global_macros:
+///   - extends: pad
+///     actions:
+///       - type: enter_text
+///         data: Hello
+/// ```
+///
+/// ## Errors
+/// Returns a `ConfigError` if `macro_templates` isn't a hash, or a macro extends a template that
+/// doesn't exist, isn't a hash, or ends up extending itself.
+pub (crate) fn resolve_templates(raw_config: &mut RCHash) -> Result<(), ConfigError> {
+    let templates = match raw_config.get(&k(MACRO_TEMPLATES_FIELD)) {
+        None => RCHash::new(),
+        Some(RawConfig::Hash(templates)) => templates.clone(),
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(
+                format!("'{}' should be a hash", MACRO_TEMPLATES_FIELD)
+            ));
+        }
+    };
+
+    if let Some(RawConfig::Array(raw_scopes)) = raw_config.get_mut(&k(SCOPES_FIELD)) {
+        for (scope_index, raw_scope) in raw_scopes.iter_mut().enumerate() {
+            if let RawConfig::Hash(raw_scope) = raw_scope {
+                let scope_loc = raw_scope.loc();
+
+                let raw_macros = hashes_mut(raw_scope.get_mut(&k(MACROS_FIELD)));
+
+                for (index, raw_macro) in raw_macros.into_iter().enumerate() {
+                    extend_macro(raw_macro, &templates, &mut vec![]).map_err(|err| {
+                        err.within_item(MACROS_FIELD, index, raw_macro.loc())
+                            .within_item(SCOPES_FIELD, scope_index, scope_loc)
+                    })?;
+                }
+            }
+        }
+    }
+
+    let raw_macros = hashes_mut(raw_config.get_mut(&k(GLOBAL_MACROS_FIELD)));
+
+    for (index, raw_macro) in raw_macros.into_iter().enumerate() {
+        extend_macro(raw_macro, &templates, &mut vec![]).map_err(|err| {
+            err.within_item(GLOBAL_MACROS_FIELD, index, raw_macro.loc())
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Fills in the fields `raw_macro` doesn't have from the template it extends, if any, after that
+/// template has been filled in from the one it extends. `extending` holds the names of the
+/// templates being resolved, to catch templates extending themselves.
+fn extend_macro(
+    raw_macro: &mut RCHash,
+    templates: &RCHash,
+    extending: &mut Vec<String>
+) -> Result<(), ConfigError> {
+    let name = match raw_macro.remove(&k(EXTENDS_FIELD)) {
+        None => return Ok(()),
+        Some(RawConfig::String(name)) => name,
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(
+                format!("'{}' should be the name of a macro template", EXTENDS_FIELD)
+            ));
+        }
+    };
+
+    if extending.contains(&name) {
+        return Err(ConfigError::InvalidConfig(format!(
+            "Macro template '{}' extends itself: {} -> {}",
+            name, extending.join(" -> "), name
+        )));
+    }
+
+    let mut template = match templates.get(&k(&name)) {
+        Some(RawConfig::Hash(template)) => template.clone(),
+
+        Some(_) => {
+            return Err(ConfigError::InvalidConfig(
+                format!("Macro template '{}' should be a hash", name)
+            ));
+        }
+
+        None => {
+            return Err(ConfigError::InvalidConfig(
+                format!("There is no macro template named '{}'", name)
+            ));
+        }
+    };
+
+    extending.push(name);
+    extend_macro(&mut template, templates, extending)?;
+
+    for (key, value) in template {
+        if !raw_macro.contains_key(&key) {
+            raw_macro.insert(key, value);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::input_formats::get_parser_for_extension;
+    use crate::config::raw_config::{RawConfig, RCHash, k};
+    use crate::config::versions::version2::macro_templates::resolve_templates;
+
+    fn parse(yaml: &str) -> RCHash {
+        match get_parser_for_extension("yml").unwrap().parse(yaml).ok().unwrap() {
+            RawConfig::Hash(hash) => hash,
+            _ => panic!("Expected a hash")
+        }
+    }
+
+    #[test]
+    fn takes_the_fields_macros_leave_out_from_their_templates() {
+        let mut raw_config = parse("
+macro_templates:
+  base:
+    name: Base
+    actions: [{ type: control, data: reload }]
+  pad:
+    extends: base
+    matching_events: [{ data: { message_type: note_on } }]
+global_macros:
+  - extends: pad
+    name: Mine
+  - actions: []
+");
+
+        resolve_templates(&mut raw_config).ok().unwrap();
+
+        assert_eq!(raw_config.get(&k("global_macros")), parse("
+global_macros:
+  - name: Mine
+    matching_events: [{ data: { message_type: note_on } }]
+    actions: [{ type: control, data: reload }]
+  - actions: []
+").get(&k("global_macros")));
+    }
+}
@@ -0,0 +1,355 @@
+use std::mem;
+
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers, k};
+use crate::config::versions::version1::action_groups::lone_param;
+use crate::config::versions::version2::defaults::{
+    walk, Part, TYPE_FIELD, DATA_FIELD, TYPE_MIDI, CHANNEL_FIELD, DEVICE_FIELD, DELAY_FIELD,
+    DELAY_MS_FIELD, DELAYED_ACTIONS
+};
+
+const VERSION_FIELD: &str = "version";
+const INCLUDE_FIELD: &str = "include";
+const DEFAULTS_FIELD: &str = "defaults";
+const SCOPES_FIELD: &str = "scopes";
+const MACROS_FIELD: &str = "macros";
+
+/// What a part of the config has a default for
+#[derive(Clone, Copy, PartialEq)]
+enum Setting {
+    Channel,
+    Device,
+    Delay
+}
+
+const SETTINGS: [Setting; 3] = [Setting::Channel, Setting::Device, Setting::Delay];
+
+/// One part of the config a default could apply to, and its own value, if any. The value of a
+/// delay is a hash with its `delay` and `delay_ms` fields, as either can be used.
+struct Found {
+    scope: Option<usize>,
+    setting: Setting,
+    value: Option<RawConfig>
+}
+
+/// Rewrites `raw_config`, a version 1 config, into the equivalent version 2 config:
+///
+/// - `type: midi` is removed from event matchers and preconditions, as it's the default.
+/// - A channel, device or delay that every part it could apply to has, with the same value, is
+///   moved into the top level `defaults`. This is left out for configs that include other files,
+///   since the defaults would apply to them too, and for included files themselves, which can't
+///   have `defaults` at the top level; these are the files without a `version`. Otherwise, a
+///   value every such part of a scope has is moved into that scope's `defaults`. Either takes at
+///   least two parts having it.
+///
+/// Macro templates aren't made up, since which macros belong together isn't up to the format.
+/// Like `find_unknown_fields`, only the structure is followed; parts that aren't valid are left
+/// as they are.
+pub (crate) fn migrate(raw_config: &mut RCHash) {
+    let mut found = vec![];
+
+    walk(raw_config, &mut |scope, part, hash| {
+        for (setting, value) in settings_of(part, hash) {
+            found.push(Found { scope, setting, value });
+        }
+    });
+
+    let is_main_file = raw_config.contains_key(&k(VERSION_FIELD));
+
+    let top_defaults: Vec<(Setting, RawConfig)> = if !is_main_file
+        || raw_config.contains_key(&k(INCLUDE_FIELD))
+    {
+        vec![]
+    } else {
+        SETTINGS
+            .iter()
+            .filter_map(|setting| {
+                let values = found.iter().filter(|f| f.setting == *setting);
+                common_value(values).map(|value| (*setting, value))
+            })
+            .collect()
+    };
+
+    let scope_count = raw_config.get_array(SCOPES_FIELD).map_or(0, |scopes| scopes.len());
+
+    let scope_defaults: Vec<Vec<(Setting, RawConfig)>> = (0..scope_count)
+        .map(|index| {
+            SETTINGS
+                .iter()
+                .filter(|setting| top_defaults.iter().all(|(top, _)| top != *setting))
+                .filter_map(|setting| {
+                    let values = found
+                        .iter()
+                        .filter(|f| f.setting == *setting && f.scope == Some(index));
+
+                    common_value(values).map(|value| (*setting, value))
+                })
+                .collect()
+        })
+        .collect();
+
+    walk(raw_config, &mut |scope, part, hash| {
+        let is_default = |setting| {
+            top_defaults.iter().any(|(default, _)| *default == setting)
+                || scope.is_some_and(|index| {
+                    scope_defaults[index].iter().any(|(default, _)| *default == setting)
+                })
+        };
+
+        remove_defaults(part, hash, &is_default);
+    });
+
+    if let Some(RawConfig::Array(raw_scopes)) = raw_config.get_mut(&k(SCOPES_FIELD)) {
+        for (raw_scope, defaults) in raw_scopes.iter_mut().zip(scope_defaults) {
+            if let RawConfig::Hash(raw_scope) = raw_scope {
+                if !defaults.is_empty() {
+                    let position = key_position(raw_scope, MACROS_FIELD);
+                    insert_at(raw_scope, position, DEFAULTS_FIELD, defaults_hash(defaults));
+                }
+            }
+        }
+    }
+
+    if !top_defaults.is_empty() {
+        let position = key_position(raw_config, VERSION_FIELD).map(|position| position + 1);
+        insert_at(raw_config, position, DEFAULTS_FIELD, defaults_hash(top_defaults));
+    }
+
+    match raw_config.get_mut(&k(VERSION_FIELD)) {
+        Some(version) => *version = RawConfig::Integer(2),
+        None => insert_at(raw_config, Some(0), VERSION_FIELD, RawConfig::Integer(2))
+    }
+}
+
+/// The settings a default could apply to in `hash`, a part of type `part`, with its own values
+fn settings_of(part: Part, hash: &RCHash) -> Vec<(Setting, Option<RawConfig>)> {
+    match (part, hash.get(&k(DATA_FIELD))) {
+        (Part::EventMatcher, Some(RawConfig::Hash(data)))
+        | (Part::Precondition, Some(RawConfig::Hash(data))) => {
+            if hash.get_string(TYPE_FIELD) != Some(TYPE_MIDI) {
+                return vec![];
+            }
+
+            vec![
+                (Setting::Channel, data.get(&k(CHANNEL_FIELD)).cloned()),
+                (Setting::Device, data.get(&k(DEVICE_FIELD)).cloned())
+            ]
+        }
+
+        (Part::Action, Some(data)) if is_delayed_action(hash) => match data {
+            RawConfig::Hash(data) => {
+                let delay: RCHash = [DELAY_FIELD, DELAY_MS_FIELD]
+                    .iter()
+                    .filter_map(|field| data.get(&k(field)).map(|value| (k(field), value.clone())))
+                    .collect();
+
+                vec![(Setting::Delay, Some(delay).filter(|d| !d.is_empty()).map(RawConfig::Hash))]
+            }
+
+            RawConfig::String(s) if lone_param(s).is_none() => vec![(Setting::Delay, None)],
+            _ => vec![]
+        },
+
+        _ => vec![]
+    }
+}
+
+/// Removes `type: midi` from `hash` if it's an event matcher or precondition, and the settings
+/// `is_default` holds for from its data
+fn remove_defaults<F>(part: Part, hash: &mut RCHash, is_default: &F)
+    where F: Fn(Setting) -> bool
+{
+    let is_midi = part != Part::Action && hash.get_string(TYPE_FIELD) == Some(TYPE_MIDI);
+    let is_delayed_action = part == Part::Action && is_delayed_action(hash);
+
+    if is_midi {
+        hash.remove(&k(TYPE_FIELD));
+    }
+
+    if let Some(RawConfig::Hash(data)) = hash.get_mut(&k(DATA_FIELD)) {
+        if is_midi && is_default(Setting::Channel) {
+            data.remove(&k(CHANNEL_FIELD));
+        }
+
+        if is_midi && is_default(Setting::Device) {
+            data.remove(&k(DEVICE_FIELD));
+        }
+
+        if is_delayed_action && is_default(Setting::Delay) {
+            data.remove(&k(DELAY_FIELD));
+            data.remove(&k(DELAY_MS_FIELD));
+        }
+    }
+}
+
+fn is_delayed_action(hash: &RCHash) -> bool {
+    let action_type = hash.get_string(TYPE_FIELD);
+    DELAYED_ACTIONS.iter().any(|(delayed_type, _)| action_type == Some(*delayed_type))
+}
+
+/// The value all of `found` have, if there are at least two of them
+fn common_value<'a, I>(found: I) -> Option<RawConfig> where I: Iterator<Item = &'a Found> {
+    let values: Vec<&Option<RawConfig>> = found.map(|f| &f.value).collect();
+    let first = values.first()?.as_ref()?;
+
+    if values.len() >= 2 && values.iter().all(|value| value.as_ref() == Some(first)) {
+        Some(first.clone())
+    } else {
+        None
+    }
+}
+
+fn defaults_hash(defaults: Vec<(Setting, RawConfig)>) -> RawConfig {
+    let mut hash = RCHash::new();
+
+    for (setting, value) in defaults {
+        match (setting, value) {
+            (Setting::Channel, value) => { hash.insert(k(CHANNEL_FIELD), value); }
+            (Setting::Device, value) => { hash.insert(k(DEVICE_FIELD), value); }
+            (Setting::Delay, RawConfig::Hash(delay)) => hash.extend(delay),
+            _ => {}
+        }
+    }
+
+    RawConfig::Hash(hash)
+}
+
+fn key_position(hash: &RCHash, key: &str) -> Option<usize> {
+    hash.keys().position(|existing| *existing == k(key))
+}
+
+/// Inserts `key` into `hash` at `position`, or at the end if there's none, keeping the order of
+/// the other fields
+fn insert_at(hash: &mut RCHash, position: Option<usize>, key: &str, value: RawConfig) {
+    let position = position.unwrap_or_else(|| hash.len());
+    let fields = mem::replace(hash, RCHash::at(hash.loc()));
+
+    for (index, (existing_key, existing_value)) in fields.into_iter().enumerate() {
+        if index == position {
+            hash.insert(k(key), value.clone());
+        }
+
+        hash.insert(existing_key, existing_value);
+    }
+
+    if !hash.contains_key(&k(key)) {
+        hash.insert(k(key), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::input_formats::get_parser_for_extension;
+    use crate::config::raw_config::{RawConfig, RCHash};
+    use crate::config::versions::get_processor;
+    use crate::config::versions::version2::migrate::migrate;
+
+    fn parse(yaml: &str) -> RCHash {
+        match get_parser_for_extension("yml").unwrap().parse(yaml).ok().unwrap() {
+            RawConfig::Hash(hash) => hash,
+            _ => panic!("Expected a hash")
+        }
+    }
+
+    const VERSION_1_CONFIG: &str = "
+version: 1
+scopes:
+  - window_class: { is: Gimp }
+    macros:
+      - matching_events:
+          - type: midi
+            data: { message_type: note_on, channel: 1, device: { is: Pad }, key: 36 }
+          - type: midi
+            data: { message_type: note_off, channel: 1, device: { is: Pad }, key: 36 }
+        actions:
+          - type: key_sequence
+            data: { sequence: ctrl+z, delay: 50 }
+          - type: key_sequence
+            data: { sequence: ctrl+y, delay: 50 }
+  - window_class: { is: Inkscape }
+    macros:
+      - matching_events:
+          - type: midi
+            data: { message_type: note_on, channel: 1, device: { is: Pad }, key: 38 }
+        required_preconditions:
+          - type: midi
+            data: { condition_type: note_on, channel: 2, device: { is: Pad }, key: 40 }
+        actions: [{ type: control, data: exit }]
+global_macros:
+  - matching_events:
+      - type: device_connected
+        data: { device: { is: Pad } }
+    actions:
+      - type: enter_text
+        data: Hi
+";
+
+    #[test]
+    fn moves_shared_values_into_defaults() {
+        let mut raw_config = parse(VERSION_1_CONFIG);
+        migrate(&mut raw_config);
+
+        assert_eq!(raw_config, parse("
+version: 2
+defaults:
+  device: { is: Pad }
+scopes:
+  - window_class: { is: Gimp }
+    defaults:
+      channel: 1
+      delay: 50
+    macros:
+      - matching_events:
+          - data: { message_type: note_on, key: 36 }
+          - data: { message_type: note_off, key: 36 }
+        actions:
+          - type: key_sequence
+            data: { sequence: ctrl+z }
+          - type: key_sequence
+            data: { sequence: ctrl+y }
+  - window_class: { is: Inkscape }
+    macros:
+      - matching_events:
+          - data: { message_type: note_on, channel: 1, key: 38 }
+        required_preconditions:
+          - data: { condition_type: note_on, channel: 2, key: 40 }
+        actions: [{ type: control, data: exit }]
+global_macros:
+  - matching_events:
+      - type: device_connected
+        data: { device: { is: Pad } }
+    actions:
+      - type: enter_text
+        data: Hi
+"));
+
+        assert_eq!(
+            format!("{:?}", get_processor(2).unwrap().process(raw_config).ok().unwrap().macros),
+            format!("{:?}", RawConfig::Hash(parse(VERSION_1_CONFIG)).process().ok().unwrap().macros)
+        );
+    }
+
+    #[test]
+    fn keeps_top_level_defaults_out_of_configs_that_include_others() {
+        let mut raw_config = parse("
+version: 1
+include: apps/*.yml
+global_macros:
+  - matching_events:
+      - type: midi
+        data: { message_type: note_on, channel: 1 }
+      - type: midi
+        data: { message_type: note_off, channel: 1 }
+");
+
+        migrate(&mut raw_config);
+
+        assert_eq!(raw_config, parse("
+version: 2
+include: apps/*.yml
+global_macros:
+  - matching_events:
+      - data: { message_type: note_on, channel: 1 }
+      - data: { message_type: note_off, channel: 1 }
+"));
+    }
+}
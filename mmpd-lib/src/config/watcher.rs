@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Keeps track of modifications to a set of config files, by comparing their modification times
+/// every time it's asked about them.
+pub struct ConfigWatcher {
+    /// Watched files, each with the modification time it had when last checked. `None` if the
+    /// file didn't exist or its modification time couldn't be read.
+    files: Vec<(PathBuf, Option<SystemTime>)>
+}
+
+impl ConfigWatcher {
+    /// Starts watching `files`, as they are now.
+    pub fn new(files: Vec<PathBuf>) -> ConfigWatcher {
+        ConfigWatcher {
+            files: files
+                .into_iter()
+                .map(|file| {
+                    let modified = get_modified_time(&file);
+                    (file, modified)
+                })
+                .collect()
+        }
+    }
+
+    /// Checks whether any of the watched files was modified, created, or removed since the last
+    /// check (or since this watcher was created).
+    pub fn has_changed(&mut self) -> bool {
+        let mut changed = false;
+
+        for (file, last_modified) in self.files.iter_mut() {
+            let modified = get_modified_time(file);
+
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+fn get_modified_time(file: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(file).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+    use crate::config::watcher::ConfigWatcher;
+
+    #[test]
+    fn detects_changes_to_watched_files() {
+        let file = env::temp_dir().join(format!("mmpd-watcher-test-{}.yml", std::process::id()));
+        fs::write(&file, "version: 1").unwrap();
+
+        let mut watcher = ConfigWatcher::new(vec![file.to_path_buf()]);
+        assert!(!watcher.has_changed());
+
+        // Set the modification time explicitly, rather than relying on the file system's
+        // timestamp resolution being fine enough to notice a write right after the previous one.
+        let modified = SystemTime::now() + Duration::from_secs(10);
+        fs::File::options().write(true).open(&file).unwrap().set_modified(modified).unwrap();
+
+        assert!(watcher.has_changed());
+        assert!(!watcher.has_changed());
+
+        fs::remove_file(&file).unwrap();
+
+        assert!(watcher.has_changed());
+        assert!(!watcher.has_changed());
+    }
+}
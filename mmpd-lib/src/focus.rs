@@ -16,6 +16,9 @@ mod mac_os;
 #[cfg(target_os = "macos")]
 pub use mac_os::get_adapter;
 
+#[cfg(test)]
+use mockall::automock;
+
 /// Container struct for window info
 #[derive(Debug)]
 pub struct FocusedWindow {
@@ -26,6 +29,7 @@ pub struct FocusedWindow {
 }
 
 /// Adapters implementing this trait can be asked to provided data on the currently focused window.
+#[cfg_attr(test, automock)]
 pub trait FocusAdapter {
     /// Returns an instance of FocusedWindow with relevant focused window info (class, name) if
     /// available, None otherwise.
@@ -10,5 +10,6 @@ pub mod macros;
 mod shell;
 pub mod match_checker;
 pub mod state;
+pub mod clock;
 
 pub mod config;
@@ -1,12 +1,16 @@
 use crate::macros::actions::Action;
 use crate::macros::event_matching::{Event, EventMatcher};
 use crate::match_checker::StringMatcher;
-use crate::state::State;
+use crate::state::{State, Value};
 use crate::macros::preconditions::Precondition;
+use crate::macros::mapping::ValueMap;
+use crate::macros::executor::Concurrency;
 
 pub mod actions;
 pub mod event_matching;
 pub mod preconditions;
+pub mod mapping;
+pub mod executor;
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Scope {
@@ -50,7 +54,9 @@ pub struct MacroBuilder {
     match_events: Vec<EventMatcher>,
     required_preconditions: Option<Vec<Precondition>>,
     actions: Vec<Action>,
-    scope: Option<Scope>
+    scope: Option<Scope>,
+    map: Option<ValueMap>,
+    concurrency: Concurrency
 }
 
 impl <'a> MacroBuilder {
@@ -62,7 +68,9 @@ impl <'a> MacroBuilder {
             match_events: vec![event_matcher],
             required_preconditions: None,
             actions: vec![],
-            scope: None
+            scope: None,
+            map: None,
+            concurrency: Concurrency::default()
         }
     }
 
@@ -74,7 +82,9 @@ impl <'a> MacroBuilder {
             match_events: event_matchers,
             required_preconditions: None,
             actions: vec![],
-            scope: None
+            scope: None,
+            map: None,
+            concurrency: Concurrency::default()
         }
     }
 
@@ -128,13 +138,25 @@ impl <'a> MacroBuilder {
         self
     }
 
+    pub fn set_map(mut self, map: ValueMap) -> Self {
+        self.map = Some(map);
+        self
+    }
+
+    pub fn set_concurrency(mut self, concurrency: Concurrency) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
     pub fn build(self) -> Macro {
         Macro {
             name: self.name,
             match_events: self.match_events,
             required_preconditions: self.required_preconditions,
             actions: self.actions,
-            scope: self.scope
+            scope: self.scope,
+            map: self.map,
+            concurrency: self.concurrency
         }
     }
 }
@@ -145,7 +167,9 @@ pub struct Macro {
     pub(crate) match_events: Vec<EventMatcher>,
     pub(crate) required_preconditions: Option<Vec<Precondition>>,
     pub(crate) actions: Vec<Action>,
-    pub(crate) scope: Option<Scope>
+    pub(crate) scope: Option<Scope>,
+    pub(crate) map: Option<ValueMap>,
+    pub(crate) concurrency: Concurrency
 }
 
 impl Macro {
@@ -182,9 +206,152 @@ impl Macro {
         }
     }
 
+    /// For a macro that maps a value (see `ValueMap`), maps the value of `event`, which the macro
+    /// matched, into its variable. Returns whether the macro's actions should run: not if the
+    /// event has no value to map, if the macro ran too recently, or if the value didn't change.
+    ///
+    /// Macros without a `ValueMap` always run.
+    pub fn apply_map(&self, event: &Event, state: &mut dyn State) -> bool {
+        let map = match &self.map {
+            Some(map) => map,
+            None => return true
+        };
+
+        let value = match map.map(event) {
+            Some(value) => Value::Integer(value),
+            None => return false
+        };
+
+        // Checked first, so a value that's dropped doesn't count as a run for throttling. Any
+        // value held back before it is out of date now, as the variable has the latest value.
+        if map.deduplicate && state.get_variable(&map.variable).as_ref() == Some(&value) {
+            state.discard_throttled(&map.variable);
+            return false;
+        }
+
+        if !state.throttle(&map.variable, map.interval, event) {
+            return false;
+        }
+
+        state.set_variable(&map.variable, value);
+        true
+    }
+
+    /// Whether this macro maps values into the variable named `variable`
+    pub fn maps_into(&self, variable: &str) -> bool {
+        self.map.as_ref().is_some_and(|map| map.variable == variable)
+    }
+
     fn matches_event<'b>(&self, event: &Event, state: &'b Box<dyn State>) -> bool {
         self.match_events.iter().any(|event_matcher| {
             event_matcher.matches(event, state)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::clock::ManualClock;
+    use crate::focus::MockFocusAdapter;
+    use crate::macros::MacroBuilder;
+    use crate::macros::actions::Action;
+    use crate::macros::event_matching::{Event, EventMatcher, MatcherType};
+    use crate::macros::mapping::{ValueMap, Curve};
+    use crate::midi::{MidiEvent, MidiMessage};
+    use crate::state::{self, Value};
+
+    fn cc(value: u8) -> Event {
+        Event::Midi(MidiEvent::new(
+            "faders",
+            MidiMessage::ControlChange { channel: 0, control: 7, value }
+        ))
+    }
+
+    #[test]
+    fn maps_values_into_variables_at_most_once_per_interval() {
+        let clock = ManualClock::new();
+        let mut state = state::with_clock(
+            Box::new(MockFocusAdapter::new()),
+            Box::new(clock.clone())
+        );
+
+        let fader = EventMatcher::new(MatcherType::Other, None);
+
+        let volume_macro = MacroBuilder::from_event_matcher(fader)
+            .add_action(Action::enter_text("Volume changed"))
+            .set_map(ValueMap {
+                variable: "volume".to_string(),
+                from: None,
+                to: (0, 10),
+                curve: Curve::Linear,
+                interval: Duration::from_millis(50),
+                deduplicate: true
+            })
+            .build();
+
+        assert!(volume_macro.maps_into("volume"));
+        assert!(volume_macro.apply_map(&cc(127), state.as_mut()));
+        assert_eq!(state.get_variable("volume"), Some(Value::Integer(10)));
+
+        // Too soon; held back until the interval has passed
+        clock.advance(Duration::from_millis(10));
+        assert!(!volume_macro.apply_map(&cc(64), state.as_mut()));
+        assert_eq!(state.get_variable("volume"), Some(Value::Integer(10)));
+
+        clock.advance(Duration::from_millis(40));
+        let throttled = state.take_throttled_events();
+        assert_eq!(throttled, vec![("volume".to_string(), cc(64))]);
+        assert!(volume_macro.apply_map(&throttled[0].1, state.as_mut()));
+        assert_eq!(state.get_variable("volume"), Some(Value::Integer(5)));
+
+        // Same value as before
+        clock.advance(Duration::from_millis(50));
+        assert!(!volume_macro.apply_map(&cc(65), state.as_mut()));
+
+        // Not a value to map
+        clock.advance(Duration::from_millis(50));
+        assert!(!volume_macro.apply_map(&Event::Other, state.as_mut()));
+    }
+
+    #[test]
+    fn does_not_throttle_on_values_dropped_as_duplicates() {
+        let clock = ManualClock::new();
+        let mut state = state::with_clock(
+            Box::new(MockFocusAdapter::new()),
+            Box::new(clock.clone())
+        );
+
+        let volume_macro = MacroBuilder::from_event_matcher(
+            EventMatcher::new(MatcherType::Other, None)
+        )
+            .add_action(Action::enter_text("Volume changed"))
+            .set_map(ValueMap {
+                variable: "volume".to_string(),
+                from: None,
+                to: (0, 10),
+                curve: Curve::Linear,
+                interval: Duration::from_millis(50),
+                deduplicate: true
+            })
+            .build();
+
+        state.set_variable("volume", Value::Integer(5));
+
+        // The same value, then a changed one, within one interval
+        assert!(!volume_macro.apply_map(&cc(64), state.as_mut()));
+        clock.advance(Duration::from_millis(10));
+        assert!(volume_macro.apply_map(&cc(127), state.as_mut()));
+        assert_eq!(state.get_variable("volume"), Some(Value::Integer(10)));
+
+        // A value held back is dropped once the value goes back to what it is
+        clock.advance(Duration::from_millis(10));
+        assert!(!volume_macro.apply_map(&cc(64), state.as_mut()));
+        clock.advance(Duration::from_millis(10));
+        assert!(!volume_macro.apply_map(&cc(127), state.as_mut()));
+
+        clock.advance(Duration::from_millis(50));
+        assert!(state.take_throttled_events().is_empty());
+        assert_eq!(state.get_variable("volume"), Some(Value::Integer(10)));
+    }
+}
@@ -1,21 +1,31 @@
+pub mod template;
+
 use crate::keyboard_control::{self, KeyboardControlAdapter};
 use crate::shell::{Shell, ShellImpl};
+use crate::midi::{self, MidiMessage};
+use crate::midi::adapters::MidiOutputAdapter;
+use crate::macros::event_matching::Event;
+use crate::macros::preconditions::Precondition;
+use crate::match_checker::{MatchChecker, NumberMatcher};
+use crate::state::{State, Value};
 use std::{thread, time};
 use regex::Regex;
+use template::Template;
 
 /// Action run in response to a MIDI event
 /// Any Action value can be run through ActionRunner::run.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Action {
     /// Sends a key sequence 0 or more times
     /// Use this one for key combinations.
     KeySequence {
         /// Key sequence, on Linux and Windows according to X Keysym notation, on Mac OS not quite
         /// but close. (Uses "command", "control", "option" instead, otherwise largely the same)
-        sequence: String,
+        /// May contain variables, which are substituted before the sequence is sent.
+        sequence: Template,
 
         /// How many times this sequence should be entered
-        count: usize,
+        count: Count,
 
         /// Delay time in microseconds between keystrokes, not applicable on Mac OS
         delay: Option<u32>
@@ -25,10 +35,11 @@ pub enum Action {
     /// Use this one for text exactly as in the string provided.
     EnterText {
         /// Text to be entered as if typed on keyboard
-        text: String,
+        /// May contain variables, which are substituted before the text is entered.
+        text: Template,
 
         /// How many times this text should be repeated
-        count: usize,
+        count: Count,
 
         /// Delay time in microseconds between keystrokes, not applicable on Mac OS
         delay: Option<u32>
@@ -41,11 +52,12 @@ pub enum Action {
 
         /// A list of arguments provided to the command. These end up space-separated.
         /// If one item includes spaces, that item will be surrounded by quotes so it's treated as
-        /// one argument.
-        args: Option<Vec<String>>,
+        /// one argument. Arguments may contain variables.
+        args: Option<Vec<Template>>,
 
         /// A list of key/value pairs with environment variables to be provided to the program
-        env_vars: Option<Vec<(String, String)>>
+        /// Values may contain variables.
+        env_vars: Option<Vec<(String, Template)>>
     },
 
     /// Blocks the thread for a given amount of microseconds, to allow some previous action to be
@@ -56,11 +68,76 @@ pub enum Action {
     },
 
     /// Controls the application itself via a ControlAction sub-action.
-    Control(ControlAction)
+    Control(ControlAction),
+
+    /// Sends a MIDI message to a MIDI output port, for example to light up LEDs on a controller,
+    /// or to forward events to another application.
+    MidiSend {
+        /// Pattern to find the output port by; the first port whose name contains this is used
+        port: String,
+
+        /// Message to be sent
+        message: MidiMessage
+    },
+
+    /// Changes the value of a named variable in state, which preconditions and templates can use.
+    Variable(VariableAction),
+
+    /// Runs `actions` over and over, waiting `interval` in between, for as long as all of
+    /// `required_preconditions` are satisfied, for example while a note is held. The
+    /// preconditions are checked before every repetition, including the first.
+    ///
+    /// Since this takes time and depends on state changing in the meantime, it is run by
+    /// `Executor` rather than `ActionRunner`.
+    RepeatWhile {
+        required_preconditions: Vec<Precondition>,
+        interval: time::Duration,
+        actions: Vec<Action>
+    },
+
+    /// Stops the running actions of any macro named `name`, including runs waiting for an
+    /// earlier run to be done. Since this is about other runs of actions, it is run by `Executor`
+    /// rather than `ActionRunner`.
+    CancelMacro { name: String },
+
+    /// Runs `then` if all of `required_preconditions` are satisfied by the time this action is
+    /// reached, and `otherwise` if not. Like `RepeatWhile`, it's run by `Executor`.
+    If {
+        required_preconditions: Vec<Precondition>,
+        then: Vec<Action>,
+        otherwise: Vec<Action>
+    },
+
+    /// Runs the actions of the first of `cases` matching `value`, or `default` if none do. Like
+    /// `RepeatWhile`, it's run by `Executor`.
+    Switch {
+        /// Value to pick a case by, such as `%event.value%`. It's resolved when this action is
+        /// reached; if it isn't a whole number of 0 or more, no case matches.
+        value: Template,
+
+        cases: Vec<SwitchCase>,
+        default: Vec<Action>
+    },
+
+    /// Runs the actions of the action group named `group`, whose parameters were filled in when
+    /// the config was loaded. Like `RepeatWhile`, it's run by `Executor`.
+    Call {
+        group: String,
+        actions: Vec<Action>
+    }
 
     // This can be expanded upon
 }
 
+/// One of the lists of actions an `Action::Switch` can pick between
+#[derive(PartialEq, Debug, Clone)]
+pub struct SwitchCase {
+    /// Which values of the switch's `value` this case is picked for
+    pub value_match: NumberMatcher,
+
+    pub actions: Vec<Action>
+}
+
 /// Action that controls application execution, allowing application control through macro
 /// actions.
 #[derive(PartialEq, Debug, Clone)]
@@ -79,12 +156,86 @@ pub enum ControlAction {
     Exit
 }
 
+/// Action that changes the value of a named variable
+#[derive(PartialEq, Debug, Clone)]
+pub enum VariableAction {
+    /// Sets the variable to `value`
+    Set { name: String, value: Value },
+
+    /// Flips a boolean variable. A variable that isn't a boolean (or isn't set yet) becomes `true`.
+    Toggle { name: String },
+
+    /// Adds `by` to an integer variable. A variable that isn't an integer (or isn't set yet) is
+    /// treated as 0.
+    ///
+    /// If the result falls outside of `min` to `max`, it is clamped to that range, or, if `wrap`
+    /// is set, wraps around to the other end of it.
+    Increment { name: String, by: i64, min: Option<i64>, max: Option<i64>, wrap: bool },
+
+    /// Sets the variable to the item in `values` after the one it is currently set to, going
+    /// back to the first one after the last. A variable whose value isn't in the list (or isn't
+    /// set yet) is set to the first one.
+    Cycle { name: String, values: Vec<Value> }
+}
+
+impl VariableAction {
+    /// Name of the variable this action changes
+    pub fn name(&self) -> &str {
+        match self {
+            VariableAction::Set { name, .. } => name,
+            VariableAction::Toggle { name } => name,
+            VariableAction::Increment { name, .. } => name,
+            VariableAction::Cycle { name, .. } => name
+        }
+    }
+
+    /// Determines the new value of the variable, given its `current` value
+    pub fn apply(&self, current: Option<&Value>) -> Value {
+        match self {
+            VariableAction::Set { value, .. } => value.clone(),
+
+            VariableAction::Toggle { .. } => Value::Bool(match current {
+                Some(Value::Bool(b)) => !b,
+                _ => true
+            }),
+
+            VariableAction::Increment { by, min, max, wrap, .. } => {
+                let current = match current {
+                    Some(Value::Integer(i)) => *i,
+                    _ => 0
+                };
+
+                let new = current.saturating_add(*by);
+
+                Value::Integer(match (min, max) {
+                    (Some(min), Some(max)) if *wrap && (new < *min || new > *max) => {
+                        min + (new - min).rem_euclid(max - min + 1)
+                    }
+
+                    _ => {
+                        let new = min.map_or(new, |min| new.max(min));
+                        max.map_or(new, |max| new.min(max))
+                    }
+                })
+            }
+
+            VariableAction::Cycle { values, .. } => {
+                let next = current
+                    .and_then(|current| values.iter().position(|value| value == current))
+                    .map_or(0, |index| (index + 1) % values.len());
+
+                values[next].clone()
+            }
+        }
+    }
+}
+
 impl Action {
     /// Shorthand for creating the common simple form of a
     pub fn key_sequence(sequence: &str) -> Action {
         Action::KeySequence {
-            sequence: sequence.to_string(),
-            count: 1,
+            sequence: Template::literal(sequence),
+            count: Count::Fixed(1),
             delay: None
         }
     }
@@ -92,11 +243,109 @@ impl Action {
     /// Shorthand for creating the common simple form of Action::EnterText
     pub fn enter_text(text: &str) -> Action {
         Action::EnterText {
-            text: text.to_string(),
-            count: 1,
+            text: Template::literal(text),
+            count: Count::Fixed(1),
             delay: None
         }
     }
+
+    /// For `Action::If` and `Action::Switch`, picks which of their lists of actions to run, given
+    /// the event that triggered the macro and the current state. Returns `None` for other kinds of
+    /// actions.
+    pub fn branch(&self, event: &Event, state: &dyn State) -> Option<&Vec<Action>> {
+        match self {
+            Action::If { required_preconditions, then, otherwise } => {
+                let conditions = required_preconditions;
+
+                if conditions.iter().all(|condition| state.matches_precondition(condition)) {
+                    Some(then)
+                } else {
+                    Some(otherwise)
+                }
+            }
+
+            Action::Switch { value, cases, default } => {
+                let value = value.resolve(event, state).trim().parse::<u32>().ok();
+
+                let case = value.and_then(|value| {
+                    cases.iter().find(|case| case.value_match.matches(&value))
+                });
+
+                Some(case.map_or(default, |case| &case.actions))
+            }
+
+            _ => None
+        }
+    }
+}
+
+/// How many times an action is repeated
+#[derive(PartialEq, Debug, Clone)]
+pub enum Count {
+    /// The same number of times, every time the action is run
+    Fixed(usize),
+
+    /// A number determined when the action is run, for example how far an encoder was turned.
+    /// If the template doesn't resolve to a whole number of 0 or more, the action isn't run.
+    Template(Template)
+}
+
+impl Count {
+    /// Determines how many times to repeat the action, given the event that triggered the macro
+    /// and the current state
+    pub fn resolve(&self, event: &Event, state: &dyn State) -> usize {
+        match self {
+            Count::Fixed(count) => *count,
+            Count::Template(template) => template.resolve(event, state).trim().parse().unwrap_or(0)
+        }
+    }
+}
+
+/// The part of running an action that may take a while, like typing text or running a program,
+/// with everything it needs from the triggering event and state filled in already. Since it
+/// doesn't need state anymore, it can be performed on another thread (see `executor`).
+#[derive(PartialEq, Debug)]
+pub enum Task {
+    KeySequence { sequence: String, count: usize, delay: Option<u32> },
+    EnterText { text: String, count: usize, delay: Option<u32> },
+    Shell { command: String, args: Option<Vec<String>>, env_vars: Option<Vec<(String, String)>> }
+}
+
+impl Task {
+    /// Makes a task out of `action`, if it's a kind of action that may take a while, resolving
+    /// its variables and count with `event` and `state`. Returns `None` for other kinds of actions.
+    pub fn from_action(action: &Action, event: &Event, state: &dyn State) -> Option<Task> {
+        match action {
+            Action::KeySequence { sequence, count, delay } => Some(Task::KeySequence {
+                sequence: sequence.resolve(event, state),
+                count: count.resolve(event, state),
+                delay: *delay
+            }),
+
+            Action::EnterText { text, count, delay } => Some(Task::EnterText {
+                text: text.resolve(event, state),
+                count: count.resolve(event, state),
+                delay: *delay
+            }),
+
+            Action::Shell { command, args, env_vars } => Some(Task::Shell {
+                command: command.to_string(),
+
+                args: args.as_ref().map(|args| {
+                    args.iter().map(|arg| arg.resolve(event, state)).collect()
+                }),
+
+                env_vars: env_vars.as_ref().map(|env_vars| {
+                    env_vars
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), value.resolve(event, state)))
+                        .collect()
+                })
+            }),
+
+            _ => None
+        }
+    }
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -112,33 +361,41 @@ const DELAY_BETWEEN_KEYS_US: u32 = 0;
 pub struct ActionRunner {
     kb_adapter: Box<dyn KeyboardControlAdapter>,
     shell_adapter: Box<dyn Shell>,
+    midi_output_adapter: Box<dyn MidiOutputAdapter>,
 }
 
 impl ActionRunner {
-    /// Set up a new ActionRunner, relying on getting an adapter from keyboard_control.
-    /// If no keyboard_control adapter can be obtained, returns None.
+    /// Set up a new ActionRunner, relying on getting adapters from keyboard_control and midi.
+    /// If either adapter can't be obtained, returns None.
     pub fn new() -> Option<ActionRunner> {
         Some(ActionRunner {
             kb_adapter: keyboard_control::get_adapter()?,
-            shell_adapter: Box::new(ShellImpl::new())
+            shell_adapter: Box::new(ShellImpl::new()),
+            midi_output_adapter: midi::get_output_adapter()?
         })
     }
 
     /// Executes a given action based on action type
     /// If the action is an `Action:Control`, returns `Some(&control_action)`.
     /// In all other cases, returns `None`.
-    pub fn run(&self, action: &Action) -> Option<ControlAction> {
+    ///
+    /// `event` and `state` are used to substitute any variables used in the action's strings;
+    /// `event` being the event that triggered the macro this action is part of. `state` is also
+    /// where `Action::Variable` stores variables, so later actions see their new values.
+    ///
+    /// `Action::RepeatWhile`, `Action::CancelMacro`, `Action::If`, `Action::Switch` and
+    /// `Action::Call` are left to `Executor`, and do nothing here.
+    pub fn run(
+        &self,
+        action: &Action,
+        event: &Event,
+        state: &mut dyn State
+    ) -> Option<ControlAction> {
         match action {
-            Action::KeySequence { sequence, count, delay} => {
-                self.run_key_sequence(sequence, *count, *delay);
-            }
-
-            Action::EnterText { text, count, delay } => {
-                self.run_enter_text(text, *count, *delay)
-            }
-
-            Action::Shell { command, args, env_vars } => {
-                self.run_shell(command, args.clone(), env_vars.clone());
+            Action::KeySequence { .. } | Action::EnterText { .. } | Action::Shell { .. } => {
+                if let Some(task) = Task::from_action(action, event, state) {
+                    self.perform(&task);
+                }
             }
 
             Action::Wait { duration } => {
@@ -148,11 +405,43 @@ impl ActionRunner {
             Action::Control(control_action) => {
                 return Some(control_action.clone());
             }
+
+            Action::MidiSend { port, message } => {
+                self.run_midi_send(port, message);
+            }
+
+            Action::Variable(variable_action) => {
+                let name = variable_action.name();
+                let value = variable_action.apply(state.get_variable(name).as_ref());
+                state.set_variable(name, value);
+            }
+
+            Action::RepeatWhile { .. }
+            | Action::CancelMacro { .. }
+            | Action::If { .. }
+            | Action::Switch { .. }
+            | Action::Call { .. } => {}
         }
 
         return None;
     }
 
+    /// Performs `task`, made from an action with `Task::from_action`. Unlike `run`, this doesn't
+    /// need state, so it can be done on another thread.
+    pub fn perform(&self, task: &Task) {
+        match task {
+            Task::KeySequence { sequence, count, delay } => {
+                self.run_key_sequence(sequence, *count, *delay);
+            }
+
+            Task::EnterText { text, count, delay } => self.run_enter_text(text, *count, *delay),
+
+            Task::Shell { command, args, env_vars } => {
+                self.run_shell(command, args.clone(), env_vars.clone());
+            }
+        }
+    }
+
     fn run_key_sequence(&self, sequence: &str, count: usize, delay: Option<u32>) {
         let separator = Regex::new(r"\s+").expect("Invalid space regex");
         let sequences: Vec<&str> = separator.split(sequence).into_iter().collect();
@@ -188,25 +477,48 @@ impl ActionRunner {
         args: Option<Vec<String>>,
         env_vars: Option<Vec<(String, String)>>
     ) {
-        // TODO: it would be good to be able to substitute certain patterns in any of the strings
-        // used in these commands. Substitutable values would essentially include any parameter that
-        // was involved in leading to this action being run. That is, any parameters of the
-        // MidiMessage, and perhaps access to the whole of the Midi state being stored in memory.
-        // This needs further working out to get sensible var names.
-
         self.shell_adapter.execute(command, args, env_vars);
     }
 
+    /// Sets up an ActionRunner with the given adapters, for tests outside of this module
+    #[cfg(test)]
+    pub(crate) fn with_adapters(
+        kb_adapter: Box<dyn KeyboardControlAdapter>,
+        shell_adapter: Box<dyn Shell>,
+        midi_output_adapter: Box<dyn MidiOutputAdapter>
+    ) -> ActionRunner {
+        ActionRunner { kb_adapter, shell_adapter, midi_output_adapter }
+    }
+
     fn run_wait(&self, duration: u64) {
         thread::sleep(time::Duration::from_micros(duration));
     }
+
+    fn run_midi_send(&self, port: &str, message: &MidiMessage) {
+        if let Some(bytes) = message.to_bytes() {
+            // TODO: expose errors all the way up
+            if let Err(e) = self.midi_output_adapter.send(port, &bytes) {
+                eprintln!("{}", e);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::macros::actions::{ActionRunner, Action, DELAY_BETWEEN_KEYS_US, ControlAction};
+    use crate::macros::actions::{
+        ActionRunner, Action, DELAY_BETWEEN_KEYS_US, ControlAction, VariableAction, Count,
+        SwitchCase
+    };
+    use crate::macros::preconditions::{Precondition, PreconditionType};
+    use crate::match_checker::NumberMatcher;
+    use crate::macros::actions::template::{Template, TemplatePart, Variable, EventField};
+    use crate::macros::event_matching::Event;
+    use crate::midi::{MidiMessage, MidiEvent};
+    use crate::state::{MockState, Value};
     use crate::keyboard_control::MockKeyboardControlAdapter;
     use crate::shell::{Shell, MockShell};
+    use crate::midi::adapters::{MidiOutputAdapter, MockMidiOutputAdapter};
     use mockall::predicate::eq;
     use crate::keyboard_control::KeyboardControlAdapter;
 
@@ -215,14 +527,16 @@ mod tests {
     /// look into.
     struct ActionRunnerBuilder {
         kb_adapter: Option<Box<dyn KeyboardControlAdapter>>,
-        shell_adapter: Option<Box<dyn Shell>>
+        shell_adapter: Option<Box<dyn Shell>>,
+        midi_output_adapter: Option<Box<dyn MidiOutputAdapter>>
     }
 
     impl ActionRunnerBuilder {
         fn new() -> ActionRunnerBuilder {
             ActionRunnerBuilder {
                 kb_adapter: None,
-                shell_adapter: None
+                shell_adapter: None,
+                midi_output_adapter: None
             }
         }
 
@@ -236,10 +550,20 @@ mod tests {
             self
         }
 
+        fn set_midi_output_adapter(
+            mut self,
+            midi_output_adapter: Box<dyn MidiOutputAdapter>
+        ) -> Self {
+            self.midi_output_adapter = Some(midi_output_adapter);
+            self
+        }
+
         fn into_runner(self) -> ActionRunner {
             ActionRunner {
                 kb_adapter: self.kb_adapter.unwrap_or(Box::new(MockKeyboardControlAdapter::new())),
-                shell_adapter: self.shell_adapter.unwrap_or(Box::new(MockShell::new()))
+                shell_adapter: self.shell_adapter.unwrap_or(Box::new(MockShell::new())),
+                midi_output_adapter: self.midi_output_adapter
+                    .unwrap_or(Box::new(MockMidiOutputAdapter::new()))
             }
         }
     }
@@ -257,7 +581,7 @@ mod tests {
             .set_keyboard_adapter(Box::new(mock_keyb_adapter))
             .into_runner();
 
-        let result = runner.run(&Action::key_sequence("ctrl+alt+delete"));
+        let result = run_without_event(&runner, &Action::key_sequence("ctrl+alt+delete"));
 
         assert!(result.is_none());
     }
@@ -275,9 +599,9 @@ mod tests {
             .set_keyboard_adapter(Box::new(mock_keyb_adapter))
             .into_runner();
 
-        let result = runner.run(&Action::KeySequence {
-            sequence: "Tab".to_string(),
-            count: 3,
+        let result = run_without_event(&runner, &Action::KeySequence {
+            sequence: Template::literal("Tab"),
+            count: Count::Fixed(3),
             delay: None
         });
 
@@ -307,10 +631,10 @@ mod tests {
             .set_keyboard_adapter(Box::new(mock_keyb_adapter))
             .into_runner();
 
-        let result = runner.run(&Action::KeySequence {
+        let result = run_without_event(&runner, &Action::KeySequence {
             // Should deal with arbitrary amounts of space characters in between sequences
-            sequence: "ctrl+t Tab   Tab  Return".to_string(),
-            count: 1,
+            sequence: Template::literal("ctrl+t Tab   Tab  Return"),
+            count: Count::Fixed(1),
             delay: None
         });
 
@@ -340,10 +664,10 @@ mod tests {
             .set_keyboard_adapter(Box::new(mock_keyb_adapter))
             .into_runner();
 
-        let result = runner.run(&Action::KeySequence {
+        let result = run_without_event(&runner, &Action::KeySequence {
             // Should deal with arbitrary amounts of space characters in between sequences
-            sequence: "ctrl+t Tab   Tab  Return".to_string(),
-            count: 3,
+            sequence: Template::literal("ctrl+t Tab   Tab  Return"),
+            count: Count::Fixed(3),
             delay: None
         });
 
@@ -363,7 +687,7 @@ mod tests {
             .set_keyboard_adapter(Box::new(mock_keyb_adapter))
             .into_runner();
 
-        let result = runner.run(&Action::enter_text("hello"));
+        let result = run_without_event(&runner, &Action::enter_text("hello"));
 
         assert!(result.is_none());
     }
@@ -381,9 +705,9 @@ mod tests {
             .set_keyboard_adapter(Box::new(mock_keyb_adapter))
             .into_runner();
 
-        let result = runner.run(&Action::EnterText {
-            text: "hello".to_string(),
-            count: 3,
+        let result = run_without_event(&runner, &Action::EnterText {
+            text: Template::literal("hello"),
+            count: Count::Fixed(3),
             delay: None
         });
 
@@ -394,11 +718,6 @@ mod tests {
     fn runs_shell_actions() {
         let mut mock_shell = MockShell::new();
 
-        // TODO: Currently this checks only if parameters are passed through as they came.
-        // Later we will want to process some input event-related variables by doing string
-        // substitution in arguments / env vars. At that point a unit tests for this
-        // functionality becomes actually useful.
-
         // TODO: this format of test with Mockall does not show very useful
         // output when it fails; room for improvement.
         mock_shell.expect_execute()
@@ -421,18 +740,173 @@ mod tests {
             .set_shell_adapter(Box::new(mock_shell))
             .into_runner();
 
-        let result = runner.run(&Action::Shell {
+        let result = run_without_event(&runner, &Action::Shell {
             command: "test_cmd".to_string(),
-            args: Some(vec!["arg1".to_string(), "arg2".to_string()]),
+            args: Some(vec![Template::literal("arg1"), Template::literal("arg2")]),
             env_vars: Some(vec![
-                ("key1".to_string(), "val1".to_string()),
-                ("key2".to_string(), "val2".to_string())
+                ("key1".to_string(), Template::literal("val1")),
+                ("key2".to_string(), Template::literal("val2"))
             ])
         });
 
         assert!(result.is_none());
     }
 
+    #[test]
+    fn substitutes_variables_in_shell_args_and_env_vars() {
+        let mut mock_shell = MockShell::new();
+
+        mock_shell.expect_execute()
+            .withf(|cmd, args, env_vars| {
+                let expected_cmd = "test_cmd";
+                let expected_args = Some(vec!["--value=42".to_string()]);
+                let expected_env_vars = Some(vec![
+                    ("CONTROL".to_string(), "7".to_string())
+                ]);
+
+                cmd == expected_cmd
+                    && do_opt_vecs_match(args, &expected_args)
+                    && do_opt_vecs_match(env_vars, &expected_env_vars)
+            })
+            .times(1)
+            .return_const(());
+
+        let runner = ActionRunnerBuilder::new()
+            .set_shell_adapter(Box::new(mock_shell))
+            .into_runner();
+
+        let event = Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::ControlChange { channel: 0, control: 7, value: 42 }
+        ));
+
+        let result = runner.run(&Action::Shell {
+            command: "test_cmd".to_string(),
+            args: Some(vec![Template::new(vec![
+                TemplatePart::Literal("--value=".to_string()),
+                TemplatePart::Variable(Variable::Event(EventField::Value))
+            ])]),
+            env_vars: Some(vec![(
+                "CONTROL".to_string(),
+                Template::new(vec![TemplatePart::Variable(Variable::Event(EventField::Control))])
+            )])
+        }, &event, &mut MockState::new());
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn substitutes_variables_in_enter_text() {
+        let mut mock_keyb_adapter = MockKeyboardControlAdapter::new();
+
+        mock_keyb_adapter.expect_send_text()
+            .with(eq("Key: 60"), eq(DELAY_BETWEEN_KEYS_US))
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let runner = ActionRunnerBuilder::new()
+            .set_keyboard_adapter(Box::new(mock_keyb_adapter))
+            .into_runner();
+
+        let event = Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::NoteOn { channel: 0, key: 60, velocity: 100 }
+        ));
+
+        let result = runner.run(&Action::EnterText {
+            text: Template::new(vec![
+                TemplatePart::Literal("Key: ".to_string()),
+                TemplatePart::Variable(Variable::Event(EventField::Key))
+            ]),
+            count: Count::Fixed(1),
+            delay: None
+        }, &event, &mut MockState::new());
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn repeats_key_sequences_by_variable_count() {
+        let mut mock_keyb_adapter = MockKeyboardControlAdapter::new();
+
+        mock_keyb_adapter.expect_send_keysequence()
+            .with(eq("Left"), eq(DELAY_BETWEEN_KEYS_US))
+            .times(3)
+            .returning(|_, _| Ok(()));
+
+        let runner = ActionRunnerBuilder::new()
+            .set_keyboard_adapter(Box::new(mock_keyb_adapter))
+            .into_runner();
+
+        let action = Action::KeySequence {
+            sequence: Template::literal("Left"),
+            count: Count::Template(Template::new(vec![
+                TemplatePart::Variable(Variable::Event(EventField::Magnitude))
+            ])),
+            delay: None
+        };
+
+        let event = Event::Midi(MidiEvent::new(
+            "knobs",
+            MidiMessage::EncoderTurn { channel: 0, control: 21, delta: -3 }
+        ));
+
+        runner.run(&action, &event, &mut MockState::new());
+
+        // Without a magnitude, the count is 0
+        runner.run(&action, &Event::Other, &mut MockState::new());
+    }
+
+    #[test]
+    fn picks_branches_of_if_and_switch_actions() {
+        let actions = |text: &str| vec![Action::enter_text(text)];
+
+        let if_action = Action::If {
+            required_preconditions: vec![Precondition {
+                invert: false,
+                condition: PreconditionType::Other
+            }],
+            then: actions("then"),
+            otherwise: actions("otherwise")
+        };
+
+        let mut state = MockState::new();
+        state.expect_matches_precondition().times(1).return_const(true);
+        assert_eq!(if_action.branch(&Event::Other, &state), Some(&actions("then")));
+
+        let mut state = MockState::new();
+        state.expect_matches_precondition().times(1).return_const(false);
+        assert_eq!(if_action.branch(&Event::Other, &state), Some(&actions("otherwise")));
+
+        let switch_action = Action::Switch {
+            value: Template::new(vec![TemplatePart::Variable(Variable::Event(EventField::Value))]),
+            cases: vec![
+                SwitchCase {
+                    value_match: NumberMatcher::Range { min: None, max: Some(63) },
+                    actions: actions("low")
+                },
+                SwitchCase {
+                    value_match: NumberMatcher::Range { min: Some(64), max: Some(100) },
+                    actions: actions("high")
+                }
+            ],
+            default: actions("default")
+        };
+
+        let control = |value| Event::Midi(MidiEvent::new(
+            "knobs",
+            MidiMessage::ControlChange { channel: 0, control: 21, value }
+        ));
+
+        let state = MockState::new();
+        assert_eq!(switch_action.branch(&control(10), &state), Some(&actions("low")));
+        assert_eq!(switch_action.branch(&control(64), &state), Some(&actions("high")));
+        assert_eq!(switch_action.branch(&control(127), &state), Some(&actions("default")));
+        assert_eq!(switch_action.branch(&Event::Other, &state), Some(&actions("default")));
+
+        assert_eq!(Action::key_sequence("Left").branch(&Event::Other, &state), None);
+    }
+
     // TODO: way to test `Action::Wait`. It's a very straightforward one, but testing is good.
     // I don't know if there's a way to mock thread::sleep somehow without doing a whole adapter
     // thing for it again like Action::Shell.
@@ -443,11 +917,127 @@ mod tests {
 
         let runner = ActionRunnerBuilder::new().into_runner();
 
-        let result = runner.run(&action);
+        let result = run_without_event(&runner, &action);
 
         assert_eq!(result, Some(ControlAction::Exit));
     }
 
+    #[test]
+    fn sends_midi_messages() {
+        let mut mock_midi_output_adapter = MockMidiOutputAdapter::new();
+
+        mock_midi_output_adapter.expect_send()
+            .withf(|port, message| port == "Launchpad" && message == [0x91, 36, 127])
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let runner = ActionRunnerBuilder::new()
+            .set_midi_output_adapter(Box::new(mock_midi_output_adapter))
+            .into_runner();
+
+        let result = run_without_event(&runner, &Action::MidiSend {
+            port: "Launchpad".to_string(),
+            message: MidiMessage::NoteOn { channel: 1, key: 36, velocity: 127 }
+        });
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn sends_midi_sysex_messages() {
+        let mut mock_midi_output_adapter = MockMidiOutputAdapter::new();
+
+        mock_midi_output_adapter.expect_send()
+            .withf(|port, message| port == "DAW" && message == [0xF0, 0x7D, 0x01, 0xF7])
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let runner = ActionRunnerBuilder::new()
+            .set_midi_output_adapter(Box::new(mock_midi_output_adapter))
+            .into_runner();
+
+        let result = run_without_event(&runner, &Action::MidiSend {
+            port: "DAW".to_string(),
+            message: MidiMessage::SysEx { data: vec![0x7D, 0x01] }
+        });
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn changes_variables_in_state() {
+        let mut state = MockState::new();
+
+        state.expect_get_variable()
+            .with(eq("page"))
+            .times(1)
+            .return_const(Some(Value::Integer(3)));
+
+        state.expect_set_variable()
+            .with(eq("page"), eq(Value::Integer(4)))
+            .times(1)
+            .return_const(());
+
+        let runner = ActionRunnerBuilder::new().into_runner();
+
+        let result = runner.run(
+            &Action::Variable(VariableAction::Increment {
+                name: "page".to_string(),
+                by: 1,
+                min: None,
+                max: None,
+                wrap: false
+            }),
+            &Event::Other,
+            &mut state
+        );
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn computes_new_variable_values() {
+        let s = |s: &str| Value::String(s.to_string());
+        let name = || "x".to_string();
+
+        let set = VariableAction::Set { name: name(), value: s("media") };
+        assert_eq!(set.apply(Some(&Value::Integer(1))), s("media"));
+
+        let toggle = VariableAction::Toggle { name: name() };
+        assert_eq!(toggle.apply(None), Value::Bool(true));
+        assert_eq!(toggle.apply(Some(&Value::Bool(true))), Value::Bool(false));
+        assert_eq!(toggle.apply(Some(&s("on"))), Value::Bool(true));
+
+        let increment = |by: i64, wrap: bool| VariableAction::Increment {
+            name: name(),
+            by,
+            min: Some(0),
+            max: Some(3),
+            wrap
+        };
+
+        assert_eq!(increment(1, false).apply(None), Value::Integer(1));
+        assert_eq!(increment(1, false).apply(Some(&Value::Integer(3))), Value::Integer(3));
+        assert_eq!(increment(-2, false).apply(Some(&Value::Integer(1))), Value::Integer(0));
+        assert_eq!(increment(1, true).apply(Some(&Value::Integer(3))), Value::Integer(0));
+        assert_eq!(increment(-2, true).apply(Some(&Value::Integer(1))), Value::Integer(3));
+
+        let cycle = VariableAction::Cycle {
+            name: name(),
+            values: vec![s("editing"), s("browsing"), s("media")]
+        };
+
+        assert_eq!(cycle.apply(None), s("editing"));
+        assert_eq!(cycle.apply(Some(&s("editing"))), s("browsing"));
+        assert_eq!(cycle.apply(Some(&s("media"))), s("editing"));
+        assert_eq!(cycle.apply(Some(&s("unknown"))), s("editing"));
+    }
+
+    // Helper function to run an action for which no event or state data is relevant
+    fn run_without_event(runner: &ActionRunner, action: &Action) -> Option<ControlAction> {
+        runner.run(action, &Event::Other, &mut MockState::new())
+    }
+
     // Helper function to see if two vectors are identical
     // TODO: perhaps move to some test util module.
     fn do_vecs_match<T: PartialEq>(a: &Vec<T>, b: &Vec<T>) -> bool {
@@ -0,0 +1,417 @@
+use crate::macros::event_matching::Event;
+use crate::midi::{MidiMessage, Button};
+use crate::state::State;
+
+/// Text inserted in place of a variable whose value is not available, for example
+/// `%event.velocity%` for a control change event, or the value of a control that no message has
+/// been received for yet.
+pub const UNAVAILABLE_VALUE: &str = "none";
+
+/// A string used in an action that may contain variables, which get substituted with data from
+/// the event that triggered the macro, or from state kept in memory, at the time the action is run.
+///
+/// Templates are parsed once when the configuration is loaded, so running an action does not need
+/// to parse the string again.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Template {
+    parts: Vec<TemplatePart>
+}
+
+/// A part of a `Template`, either literal text or a variable to be substituted
+#[derive(PartialEq, Debug, Clone)]
+pub enum TemplatePart {
+    /// Text to be used exactly as-is
+    Literal(String),
+
+    /// Placeholder for a value that is determined when the action is run
+    Variable(Variable)
+}
+
+/// Data that can be inserted into a `Template`, grouped by namespace
+#[derive(PartialEq, Debug, Clone)]
+pub enum Variable {
+    /// A field of the event that triggered the macro (`event.*`)
+    Event(EventField),
+
+    /// A value from the state kept in memory (`conditions.*`)
+    Condition(ConditionVariable)
+}
+
+/// Fields of an incoming event that can be accessed as a variable
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum EventField {
+    Device,
+    MessageType,
+    Channel,
+    Key,
+    Velocity,
+    Control,
+    Value,
+    Program,
+
+    /// How far an encoder was turned, regardless of direction
+    Magnitude
+}
+
+/// Values from state that can be accessed as a variable, scoped by the same types as preconditions
+#[derive(PartialEq, Debug, Clone)]
+pub enum ConditionVariable {
+    Midi(MidiVariable),
+
+    /// Value of the named variable
+    Variable(String)
+}
+
+/// Values kept in MIDI state that can be accessed as a variable
+#[derive(PartialEq, Debug, Clone)]
+pub enum MidiVariable {
+    /// Whether a given key is currently held on a given channel; "1" if so, "0" otherwise
+    NoteOn { channel: u8, key: u8 },
+
+    /// Last known value of a control on a channel
+    Control { channel: u8, control: u8 },
+
+    /// Last known program selected on a channel
+    Program { channel: u8 },
+
+    /// Last known pitch bend position on a channel
+    PitchBend { channel: u8 }
+}
+
+impl Template {
+    /// Creates a template from a list of parts, as produced by a config parser
+    pub fn new(parts: Vec<TemplatePart>) -> Template {
+        Template { parts }
+    }
+
+    /// Creates a template containing only the given text, without any variables
+    pub fn literal(text: &str) -> Template {
+        Template {
+            parts: if text.is_empty() {
+                vec![]
+            } else {
+                vec![TemplatePart::Literal(text.to_string())]
+            }
+        }
+    }
+
+    /// Builds the final string by substituting every variable in the template with its value,
+    /// given the event that triggered the macro and the current state.
+    ///
+    /// Variables whose value is not available are substituted with `UNAVAILABLE_VALUE`.
+    pub fn resolve(&self, event: &Event, state: &dyn State) -> String {
+        self.parts
+            .iter()
+            .map(|part| {
+                match part {
+                    TemplatePart::Literal(text) => text.to_string(),
+
+                    TemplatePart::Variable(variable) => {
+                        let value = match variable {
+                            Variable::Event(field) => get_event_field(event, *field),
+                            Variable::Condition(variable) => state.get_condition_value(variable)
+                        };
+
+                        value.unwrap_or_else(|| UNAVAILABLE_VALUE.to_string())
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Retrieves the value of a given field from an event, if the event has such a field
+fn get_event_field(event: &Event, field: EventField) -> Option<String> {
+    match event {
+        Event::Midi(midi_event) => match field {
+            EventField::Device => Some(midi_event.device.to_string()),
+            _ => get_midi_message_field(&midi_event.message, field)
+        },
+
+        Event::DeviceConnected { device } | Event::DeviceDisconnected { device } => match field {
+            EventField::Device => Some(device.to_string()),
+            _ => None
+        },
+
+        Event::Other => None
+    }
+}
+
+fn get_midi_message_field(msg: &MidiMessage, field: EventField) -> Option<String> {
+    match field {
+        EventField::Device => None,
+        EventField::MessageType => midi_message_type_name(msg).map(|t| t.to_string()),
+
+        EventField::Channel => match msg {
+            MidiMessage::NoteOff { channel, .. }
+            | MidiMessage::NoteOn { channel, .. }
+            | MidiMessage::PolyAftertouch { channel, .. }
+            | MidiMessage::ControlChange { channel, .. }
+            | MidiMessage::ControlChange14 { channel, .. }
+            | MidiMessage::Nrpn { channel, .. }
+            | MidiMessage::Rpn { channel, .. }
+            | MidiMessage::ProgramChange { channel, .. }
+            | MidiMessage::ChannelAftertouch { channel, .. }
+            | MidiMessage::PitchBendChange { channel, .. }
+            | MidiMessage::Tap { channel, .. }
+            | MidiMessage::DoubleTap { channel, .. }
+            | MidiMessage::LongPress { channel, .. }
+            | MidiMessage::HoldRepeat { channel, .. }
+            | MidiMessage::Release { channel, .. }
+            | MidiMessage::EncoderTurn { channel, .. } => Some(channel.to_string()),
+            _ => None
+        },
+
+        EventField::Key => match msg {
+            MidiMessage::NoteOff { key, .. }
+            | MidiMessage::NoteOn { key, .. }
+            | MidiMessage::PolyAftertouch { key, .. } => Some(key.to_string()),
+            _ => match button_of(msg)? {
+                Button::Note { key } => Some(key.to_string()),
+                _ => None
+            }
+        },
+
+        EventField::Velocity => match msg {
+            MidiMessage::NoteOff { velocity, .. }
+            | MidiMessage::NoteOn { velocity, .. } => Some(velocity.to_string()),
+            _ => None
+        },
+
+        EventField::Control => match msg {
+            MidiMessage::ControlChange { control, .. } => Some(control.to_string()),
+            MidiMessage::ControlChange14 { control, .. } => Some(control.to_string()),
+            MidiMessage::EncoderTurn { control, .. } => Some(control.to_string()),
+            _ => match button_of(msg)? {
+                Button::Control { control } => Some(control.to_string()),
+                _ => None
+            }
+        },
+
+        EventField::Value => match msg {
+            MidiMessage::PolyAftertouch { value, .. }
+            | MidiMessage::ControlChange { value, .. }
+            | MidiMessage::ChannelAftertouch { value, .. } => Some(value.to_string()),
+            MidiMessage::PitchBendChange { value, .. }
+            | MidiMessage::ControlChange14 { value, .. }
+            | MidiMessage::Nrpn { value, .. }
+            | MidiMessage::Rpn { value, .. } => Some(value.to_string()),
+            MidiMessage::MtcQuarterFrame { value, .. } => Some(value.to_string()),
+            MidiMessage::HoldRepeat { count, .. } => Some(count.to_string()),
+            MidiMessage::Release { duration, .. } => Some(duration.to_string()),
+            MidiMessage::EncoderTurn { delta, .. } => Some(delta.to_string()),
+            _ => None
+        },
+
+        EventField::Magnitude => match msg {
+            MidiMessage::EncoderTurn { delta, .. } => Some(delta.unsigned_abs().to_string()),
+            _ => None
+        },
+
+        EventField::Program => match msg {
+            MidiMessage::ProgramChange { program, .. } => Some(program.to_string()),
+            _ => None
+        }
+    }
+}
+
+/// The button a message produced by timing presses is about, if it is such a message
+fn button_of(msg: &MidiMessage) -> Option<Button> {
+    match msg {
+        MidiMessage::Tap { button, .. }
+        | MidiMessage::DoubleTap { button, .. }
+        | MidiMessage::LongPress { button, .. }
+        | MidiMessage::HoldRepeat { button, .. }
+        | MidiMessage::Release { button, .. } => Some(*button),
+        _ => None
+    }
+}
+
+/// Name of the MIDI message type, as used for `message_type` in config files
+fn midi_message_type_name(msg: &MidiMessage) -> Option<&'static str> {
+    match msg {
+        MidiMessage::NoteOff { .. } => Some("note_off"),
+        MidiMessage::NoteOn { .. } => Some("note_on"),
+        MidiMessage::PolyAftertouch { .. } => Some("poly_aftertouch"),
+        MidiMessage::ControlChange { .. } => Some("control_change"),
+        MidiMessage::ProgramChange { .. } => Some("program_change"),
+        MidiMessage::ChannelAftertouch { .. } => Some("channel_aftertouch"),
+        MidiMessage::PitchBendChange { .. } => Some("pitch_bend_change"),
+        MidiMessage::ControlChange14 { .. } => Some("control_change_14"),
+        MidiMessage::Nrpn { .. } => Some("nrpn"),
+        MidiMessage::Rpn { .. } => Some("rpn"),
+        MidiMessage::SysEx { .. } => Some("sys_ex"),
+        MidiMessage::MtcQuarterFrame { .. } => Some("mtc_quarter_frame"),
+        MidiMessage::SongPosition { .. } => Some("song_position"),
+        MidiMessage::SongSelect { .. } => Some("song_select"),
+        MidiMessage::TuneRequest => Some("tune_request"),
+        MidiMessage::TimingClock => Some("timing_clock"),
+        MidiMessage::Start => Some("start"),
+        MidiMessage::Continue => Some("continue"),
+        MidiMessage::Stop => Some("stop"),
+        MidiMessage::ActiveSensing => Some("active_sensing"),
+        MidiMessage::Reset => Some("reset"),
+        MidiMessage::Tap { .. } => Some("tap"),
+        MidiMessage::DoubleTap { .. } => Some("double_tap"),
+        MidiMessage::LongPress { .. } => Some("long_press"),
+        MidiMessage::HoldRepeat { .. } => Some("hold_repeat"),
+        MidiMessage::Release { .. } => Some("release"),
+        MidiMessage::EncoderTurn { .. } => Some("encoder_turn"),
+        MidiMessage::Other => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::macros::actions::template::{
+        Template, TemplatePart, Variable, EventField, ConditionVariable, MidiVariable
+    };
+    use crate::macros::event_matching::Event;
+    use crate::midi::{MidiMessage, MidiEvent};
+    use crate::state::MockState;
+    use mockall::predicate::eq;
+
+    #[test]
+    fn resolves_literal_template() {
+        let state = MockState::new();
+
+        let template = Template::literal("Hello world");
+
+        assert_eq!(template.resolve(&Event::Other, &state), "Hello world");
+    }
+
+    #[test]
+    fn resolves_event_fields() {
+        let state = MockState::new();
+
+        let template = Template::new(vec![
+            TemplatePart::Literal("ch".to_string()),
+            TemplatePart::Variable(Variable::Event(EventField::Channel)),
+            TemplatePart::Literal(" key ".to_string()),
+            TemplatePart::Variable(Variable::Event(EventField::Key)),
+            TemplatePart::Literal(" vel ".to_string()),
+            TemplatePart::Variable(Variable::Event(EventField::Velocity)),
+            TemplatePart::Literal(" ".to_string()),
+            TemplatePart::Variable(Variable::Event(EventField::MessageType)),
+            TemplatePart::Literal(" from ".to_string()),
+            TemplatePart::Variable(Variable::Event(EventField::Device)),
+        ]);
+
+        let event = Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::NoteOn { channel: 3, key: 60, velocity: 100 }
+        ));
+
+        assert_eq!(template.resolve(&event, &state), "ch3 key 60 vel 100 note_on from pads");
+    }
+
+    #[test]
+    fn resolves_value_for_all_message_types_that_have_it() {
+        let state = MockState::new();
+
+        let template = Template::new(vec![
+            TemplatePart::Variable(Variable::Event(EventField::Value))
+        ]);
+
+        let events = vec![
+            (MidiMessage::PolyAftertouch { channel: 0, key: 1, value: 2 }, "2"),
+            (MidiMessage::ControlChange { channel: 0, control: 1, value: 3 }, "3"),
+            (MidiMessage::ChannelAftertouch { channel: 0, value: 4 }, "4"),
+            (MidiMessage::PitchBendChange { channel: 0, value: 16000 }, "16000"),
+        ];
+
+        for (msg, expected) in events {
+            assert_eq!(template.resolve(&Event::Midi(MidiEvent::new("pads", msg)), &state), expected);
+        }
+    }
+
+    #[test]
+    fn resolves_encoder_turn_fields() {
+        let state = MockState::new();
+
+        let template = Template::new(vec![
+            TemplatePart::Variable(Variable::Event(EventField::MessageType)),
+            TemplatePart::Literal(" ".to_string()),
+            TemplatePart::Variable(Variable::Event(EventField::Control)),
+            TemplatePart::Literal(" ".to_string()),
+            TemplatePart::Variable(Variable::Event(EventField::Value)),
+            TemplatePart::Literal(" ".to_string()),
+            TemplatePart::Variable(Variable::Event(EventField::Magnitude)),
+        ]);
+
+        let event = Event::Midi(MidiEvent::new(
+            "knobs",
+            MidiMessage::EncoderTurn { channel: 0, control: 21, delta: -3 }
+        ));
+
+        assert_eq!(template.resolve(&event, &state), "encoder_turn 21 -3 3");
+    }
+
+    #[test]
+    fn resolves_unavailable_event_fields_as_none() {
+        let state = MockState::new();
+
+        let template = Template::new(vec![
+            TemplatePart::Variable(Variable::Event(EventField::Velocity))
+        ]);
+
+        let event = Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::ControlChange { channel: 0, control: 1, value: 3 }
+        ));
+
+        assert_eq!(template.resolve(&event, &state), "none");
+        assert_eq!(template.resolve(&Event::Other, &state), "none");
+    }
+
+    #[test]
+    fn resolves_device_of_device_events() {
+        let state = MockState::new();
+
+        let template = Template::new(vec![
+            TemplatePart::Variable(Variable::Event(EventField::Device)),
+            TemplatePart::Literal(" ".to_string()),
+            TemplatePart::Variable(Variable::Event(EventField::MessageType)),
+        ]);
+
+        let event = Event::DeviceConnected { device: "pads".to_string() };
+
+        assert_eq!(template.resolve(&event, &state), "pads none");
+    }
+
+    #[test]
+    fn resolves_condition_variables_through_state() {
+        let variable = ConditionVariable::Midi(MidiVariable::Control { channel: 2, control: 32 });
+
+        let mut state = MockState::new();
+
+        state.expect_get_condition_value()
+            .with(eq(variable.clone()))
+            .times(1)
+            .returning(|_| Some("64".to_string()));
+
+        let template = Template::new(vec![
+            TemplatePart::Literal("value: ".to_string()),
+            TemplatePart::Variable(Variable::Condition(variable))
+        ]);
+
+        assert_eq!(template.resolve(&Event::Other, &state), "value: 64");
+    }
+
+    #[test]
+    fn resolves_unknown_condition_variables_as_none() {
+        let mut state = MockState::new();
+
+        state.expect_get_condition_value()
+            .times(1)
+            .returning(|_| None);
+
+        let template = Template::new(vec![
+            TemplatePart::Variable(Variable::Condition(
+                ConditionVariable::Midi(MidiVariable::Program { channel: 0 })
+            ))
+        ]);
+
+        assert_eq!(template.resolve(&Event::Other, &state), "none");
+    }
+}
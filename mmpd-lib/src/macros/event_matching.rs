@@ -1,12 +1,16 @@
 use crate::match_checker::MatchChecker;
-use crate::midi::MidiMessage;
+use crate::midi::MidiEvent;
 use crate::macros::preconditions::Precondition;
 use crate::state::State;
 use crate::macros::event_matching::midi::MidiEventMatcher;
+use crate::macros::event_matching::device::DeviceEventMatcher;
+use crate::macros::event_matching::gesture::GestureMatcher;
 use std::sync::mpsc::{SyncSender, Receiver};
 use std::sync::mpsc;
 
 pub mod midi;
+pub mod device;
+pub mod gesture;
 
 /// An eventMatcher includes a matcher to validate whether a given event
 /// matches that what is defined, as well as an optional list of preconditions that
@@ -36,7 +40,11 @@ impl EventMatcher {
         }
 
         // If we're here, all preconditions, if any, are satisfied
-        self.matcher.matches(event)
+        match &self.matcher {
+            // Gestures depend on earlier events as well, which are kept track of by state
+            MatcherType::Gesture(gesture) => state.matches_gesture(gesture, event),
+            matcher => matcher.matches(event)
+        }
     }
 
     pub fn get_preconditions(&self) -> Option<Vec<&Precondition>> {
@@ -56,6 +64,13 @@ pub enum MatcherType {
     /// Checks against Event::Midi events
     Midi(MidiEventMatcher),
 
+    /// Checks against Event::DeviceConnected and Event::DeviceDisconnected events
+    Device(Box<DeviceEventMatcher>),
+
+    /// Checks against a combination of recent events. Since this needs to know about more than
+    /// the current event, it never matches through `MatchChecker`; see `EventMatcher::matches`.
+    Gesture(Box<GestureMatcher>),
+
     /// Checks against Event::Other events
     Other
 }
@@ -64,15 +79,26 @@ impl <'a> MatchChecker<Event> for MatcherType {
     fn matches(&self, val: &Event) -> bool {
         match val {
             Event::Midi(data) => self.matches_midi(data),
+            Event::DeviceConnected { .. } | Event::DeviceDisconnected { .. } => {
+                self.matches_device(val)
+            }
             Event::Other => self.matches_other(),
         }
     }
 }
 
 impl MatcherType {
-    fn matches_midi(&self, midi_message: &MidiMessage) -> bool {
+    fn matches_midi(&self, midi_event: &MidiEvent) -> bool {
         if let MatcherType::Midi(match_checker) = self {
-            match_checker.matches(midi_message)
+            match_checker.matches(midi_event)
+        } else {
+            false
+        }
+    }
+
+    fn matches_device(&self, event: &Event) -> bool {
+        if let MatcherType::Device(match_checker) = self {
+            match_checker.matches(event)
         } else {
             false
         }
@@ -90,8 +116,15 @@ impl MatcherType {
 }
 
 /// Wrapping type enumerating all the kinds of events supported by EventMatcher.
+#[derive(Clone, PartialEq, Debug)]
 pub enum Event {
-    Midi(MidiMessage),
+    Midi(MidiEvent),
+
+    /// A configured MIDI device was found and is now being listened to
+    DeviceConnected { device: String },
+
+    /// A MIDI device that was being listened to is no longer available
+    DeviceDisconnected { device: String },
     Other
 }
 
@@ -104,7 +137,7 @@ mod tests {
     use crate::macros::event_matching::{MatcherType, Event, EventMatcher};
     use crate::macros::event_matching::midi::MidiEventMatcher;
     use crate::match_checker::NumberMatcher;
-    use crate::midi::MidiMessage;
+    use crate::midi::{MidiEvent, MidiMessage};
     use crate::state::{MockState, State};
 
     #[test]
@@ -123,11 +156,10 @@ mod tests {
             None
         );
 
-        let event = Event::Midi(MidiMessage::NoteOn {
-            channel: 1,
-            key: 20,
-            velocity: 100
-        });
+        let event = Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::NoteOn { channel: 1, key: 20, velocity: 100 }
+        ));
 
         assert!(event_matcher.matches(&event, &state_box));
     }
@@ -150,10 +182,10 @@ mod tests {
             None
         );
 
-        let event = Event::Midi(MidiMessage::ChannelAftertouch {
-            channel: 1,
-            value: 30
-        });
+        let event = Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::ChannelAftertouch { channel: 1, value: 30 }
+        ));
 
         assert!(!event_matcher.matches(&event, &state_box));
     }
@@ -168,11 +200,10 @@ mod tests {
             None
         );
 
-        let event = Event::Midi(MidiMessage::NoteOn {
-            channel: 4,
-            key: 43,
-            velocity: 100
-        });
+        let event = Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::NoteOn { channel: 4, key: 43, velocity: 100 }
+        ));
 
         assert!(!event_matcher.matches(&event, &state_box));
     }
@@ -0,0 +1,57 @@
+use crate::match_checker::{MatchChecker, StringMatcher};
+use crate::macros::event_matching::Event;
+
+/// Matches MIDI devices being connected or disconnected. If `device_match` is specified, only
+/// devices whose name matches it are matched.
+#[derive(PartialEq, Debug)]
+pub enum DeviceEventMatcher {
+    Connected { device_match: Option<StringMatcher> },
+    Disconnected { device_match: Option<StringMatcher> },
+}
+
+impl MatchChecker<Event> for DeviceEventMatcher {
+    fn matches(&self, val: &Event) -> bool {
+        let (device_match, device) = match (self, val) {
+            (
+                DeviceEventMatcher::Connected { device_match },
+                Event::DeviceConnected { device }
+            ) => (device_match, device),
+
+            (
+                DeviceEventMatcher::Disconnected { device_match },
+                Event::DeviceDisconnected { device }
+            ) => (device_match, device),
+
+            _ => return false
+        };
+
+        device_match
+            .as_ref()
+            .is_none_or(|device_match| device_match.matches(&device.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::macros::event_matching::device::DeviceEventMatcher;
+    use crate::macros::event_matching::Event;
+    use crate::match_checker::{MatchChecker, StringMatcher};
+
+    #[test]
+    fn matches_device_events() {
+        let matcher = DeviceEventMatcher::Connected {
+            device_match: Some(StringMatcher::Is("pads".to_string()))
+        };
+
+        assert!(matcher.matches(&Event::DeviceConnected { device: "pads".to_string() }));
+        assert!(!matcher.matches(&Event::DeviceConnected { device: "keys".to_string() }));
+        assert!(!matcher.matches(&Event::DeviceDisconnected { device: "pads".to_string() }));
+        assert!(!matcher.matches(&Event::Other));
+
+        let matcher = DeviceEventMatcher::Disconnected { device_match: None };
+
+        assert!(matcher.matches(&Event::DeviceDisconnected { device: "pads".to_string() }));
+        assert!(matcher.matches(&Event::DeviceDisconnected { device: "keys".to_string() }));
+        assert!(!matcher.matches(&Event::DeviceConnected { device: "pads".to_string() }));
+    }
+}
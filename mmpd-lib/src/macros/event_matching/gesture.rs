@@ -0,0 +1,235 @@
+use std::time::Duration;
+use crate::match_checker::MatchChecker;
+use crate::macros::event_matching::{Event, MatcherType};
+use crate::state::event_history::{EventHistory, RecordedEvent};
+
+/// Matches gestures made up of several events, rather than a single event.
+///
+/// A gesture matches on the event that completes it, looking back through the recently received
+/// events for the rest. Each event in the gesture is described by a regular (non-gesture)
+/// `MatcherType`.
+#[derive(PartialEq, Debug)]
+pub enum GestureMatcher {
+    /// All of `events` happen within `within` of each other, in any order, such as a set of notes
+    /// pressed together. Each of `events` has to be matched by a different event.
+    Chord { events: Vec<MatcherType>, within: Duration },
+
+    /// All of `steps` happen in order, the first and last step being no more than `timeout`
+    /// apart. Other events in between are ignored.
+    Sequence { steps: Vec<MatcherType>, timeout: Duration }
+}
+
+impl GestureMatcher {
+    /// Checks whether `event` completes this gesture, given the `history` of recent events.
+    ///
+    /// `event` has to be the latest event recorded in `history`; events that aren't recorded
+    /// can't be part of a gesture, so they never complete one.
+    pub(crate) fn matches(&self, event: &Event, history: &EventHistory) -> bool {
+        let latest = match history.latest() {
+            Some(latest) if latest.event == *event => latest,
+            _ => return false
+        };
+
+        // Only events recent enough to be part of the gesture are considered, most recent first,
+        // starting with `event` itself.
+        let window = match self {
+            GestureMatcher::Chord { within, .. } => *within,
+            GestureMatcher::Sequence { timeout, .. } => *timeout
+        };
+
+        let candidates: Vec<&RecordedEvent> = history
+            .events()
+            .iter()
+            .rev()
+            .take_while(|recorded| latest.time.duration_since(recorded.time) <= window)
+            .collect();
+
+        match self {
+            GestureMatcher::Chord { events, .. } => matches_chord(events, &candidates),
+            GestureMatcher::Sequence { steps, .. } => matches_sequence(steps, &candidates)
+        }
+    }
+}
+
+/// Checks whether each of `events` can be matched by a different one of `candidates`, where the
+/// first candidate (the event that was just received) is one of them.
+fn matches_chord(events: &[MatcherType], candidates: &[&RecordedEvent]) -> bool {
+    if events.is_empty() {
+        return false;
+    }
+
+    let mut used = vec![false; candidates.len()];
+    used[0] = true;
+
+    (0..events.len()).any(|i| {
+        if !events[i].matches(&candidates[0].event) {
+            return false;
+        }
+
+        let others: Vec<&MatcherType> = events
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, matcher)| matcher)
+            .collect();
+
+        assign_chord_events(&others, candidates, &mut used)
+    })
+}
+
+/// Tries to find an unused candidate for each of `events`, backtracking if an assignment doesn't
+/// work out. Chords are small, so this is cheap in practice.
+fn assign_chord_events(
+    events: &[&MatcherType],
+    candidates: &[&RecordedEvent],
+    used: &mut Vec<bool>
+) -> bool {
+    let (matcher, rest) = match events.split_first() {
+        Some(split) => split,
+        None => return true
+    };
+
+    for (i, candidate) in candidates.iter().enumerate() {
+        if used[i] || !matcher.matches(&candidate.event) {
+            continue;
+        }
+
+        used[i] = true;
+
+        if assign_chord_events(rest, candidates, used) {
+            return true;
+        }
+
+        used[i] = false;
+    }
+
+    false
+}
+
+/// Checks whether `steps` happened in order, with the last step matched by the first candidate
+/// (the event that was just received). `candidates` are most recent first.
+fn matches_sequence(steps: &[MatcherType], candidates: &[&RecordedEvent]) -> bool {
+    let (last_step, earlier_steps) = match steps.split_last() {
+        Some(split) => split,
+        None => return false
+    };
+
+    if !last_step.matches(&candidates[0].event) {
+        return false;
+    }
+
+    // Matching each earlier step to its most recent occurrence leaves the most room for the steps
+    // before it.
+    let mut remaining = candidates[1..].iter();
+
+    earlier_steps
+        .iter()
+        .rev()
+        .all(|step| remaining.any(|candidate| step.matches(&candidate.event)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use crate::macros::event_matching::{Event, MatcherType};
+    use crate::macros::event_matching::gesture::GestureMatcher;
+    use crate::macros::event_matching::midi::MidiEventMatcher;
+    use crate::match_checker::NumberMatcher;
+    use crate::midi::{MidiEvent, MidiMessage};
+    use crate::state::event_history::EventHistory;
+
+    fn note_on(key: u8) -> Event {
+        Event::Midi(MidiEvent::new("pads", MidiMessage::NoteOn { channel: 0, key, velocity: 100 }))
+    }
+
+    fn note_on_matcher(key: u8) -> MatcherType {
+        MatcherType::Midi(MidiEventMatcher::NoteOn {
+            channel_match: None,
+            key_match: Some(NumberMatcher::Val(key as u32)),
+            velocity_match: None
+        })
+    }
+
+    /// Records `keys` being pressed at the given number of milliseconds after the same start time
+    fn history_of(presses: &[(u8, u64)]) -> EventHistory {
+        let start = Instant::now();
+        let mut history = EventHistory::new();
+
+        for (key, ms) in presses {
+            history.record(start + Duration::from_millis(*ms), &note_on(*key));
+        }
+
+        history
+    }
+
+    #[test]
+    fn matches_chords_in_any_order_within_time() {
+        let chord = GestureMatcher::Chord {
+            events: vec![note_on_matcher(60), note_on_matcher(64), note_on_matcher(67)],
+            within: Duration::from_millis(100)
+        };
+
+        assert!(chord.matches(&note_on(60), &history_of(&[(64, 0), (67, 20), (60, 50)])));
+        assert!(chord.matches(&note_on(67), &history_of(&[(12, 0), (60, 10), (64, 20), (67, 100)])));
+
+        // Too slow
+        assert!(!chord.matches(&note_on(67), &history_of(&[(60, 0), (64, 20), (67, 101)])));
+
+        // Missing a note
+        assert!(!chord.matches(&note_on(67), &history_of(&[(60, 0), (60, 20), (67, 40)])));
+
+        // Not completed by the latest event
+        assert!(!chord.matches(&note_on(67), &history_of(&[(60, 0), (64, 20), (67, 40), (1, 50)])));
+    }
+
+    #[test]
+    fn matches_chords_with_overlapping_event_matchers() {
+        let any_note = MatcherType::Midi(MidiEventMatcher::NoteOn {
+            channel_match: None,
+            key_match: None,
+            velocity_match: None
+        });
+
+        // The first candidate tried for "any note" is needed for the specific note
+        let chord = GestureMatcher::Chord {
+            events: vec![any_note, note_on_matcher(60)],
+            within: Duration::from_millis(100)
+        };
+
+        assert!(chord.matches(&note_on(60), &history_of(&[(64, 0), (60, 10)])));
+        assert!(chord.matches(&note_on(64), &history_of(&[(60, 0), (64, 10)])));
+        assert!(!chord.matches(&note_on(60), &history_of(&[(60, 10)])));
+    }
+
+    #[test]
+    fn matches_sequences_in_order_within_timeout() {
+        let sequence = GestureMatcher::Sequence {
+            steps: vec![note_on_matcher(36), note_on_matcher(37), note_on_matcher(36)],
+            timeout: Duration::from_millis(1000)
+        };
+
+        assert!(sequence.matches(&note_on(36), &history_of(&[(36, 0), (37, 300), (36, 600)])));
+
+        // Other events in between are ignored
+        assert!(
+            sequence.matches(&note_on(36), &history_of(&[(36, 0), (50, 100), (37, 300), (36, 600)]))
+        );
+
+        // Wrong order
+        assert!(!sequence.matches(&note_on(36), &history_of(&[(37, 0), (36, 300), (36, 600)])));
+
+        // Too slow
+        assert!(!sequence.matches(&note_on(36), &history_of(&[(36, 0), (37, 300), (36, 1001)])));
+    }
+
+    #[test]
+    fn doesnt_match_events_missing_from_history() {
+        let sequence = GestureMatcher::Sequence {
+            steps: vec![note_on_matcher(36)],
+            timeout: Duration::from_millis(1000)
+        };
+
+        assert!(!sequence.matches(&note_on(36), &EventHistory::new()));
+        assert!(!sequence.matches(&note_on(36), &history_of(&[(37, 0)])));
+    }
+}
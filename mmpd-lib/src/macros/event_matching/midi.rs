@@ -1,5 +1,6 @@
-use crate::match_checker::{MatchChecker, NumMatch};
-use crate::midi::MidiMessage;
+use std::mem::discriminant;
+use crate::match_checker::{MatchChecker, Covers, NumMatch, StringMatcher};
+use crate::midi::{MidiMessage, MidiEvent, Button};
 
 #[derive(PartialEq, Debug)]
 pub enum MidiEventMatcher {
@@ -10,6 +11,255 @@ pub enum MidiEventMatcher {
     ProgramChange { channel_match: NumMatch, program_match: NumMatch },
     ChannelAftertouch { channel_match: NumMatch, value_match: NumMatch },
     PitchBendChange { channel_match: NumMatch, value_match: NumMatch },
+    ControlChange14 { channel_match: NumMatch, control_match: NumMatch, value_match: NumMatch },
+    Nrpn { channel_match: NumMatch, parameter_match: NumMatch, value_match: NumMatch },
+    Rpn { channel_match: NumMatch, parameter_match: NumMatch, value_match: NumMatch },
+
+    /// Matches system exclusive messages whose data starts with the bytes in `starts_with`.
+    /// An empty `starts_with` matches any system exclusive message.
+    SysEx { starts_with: Vec<u8> },
+
+    MtcQuarterFrame { piece_match: NumMatch, value_match: NumMatch },
+    SongPosition { position_match: NumMatch },
+    SongSelect { song_match: NumMatch },
+    TuneRequest,
+    TimingClock,
+    Start,
+    Continue,
+    Stop,
+    ActiveSensing,
+    Reset,
+
+    Tap { channel_match: NumMatch, button_match: ButtonMatcher },
+    DoubleTap { channel_match: NumMatch, button_match: ButtonMatcher },
+    LongPress { channel_match: NumMatch, button_match: ButtonMatcher },
+    HoldRepeat { channel_match: NumMatch, button_match: ButtonMatcher, count_match: NumMatch },
+    Release { channel_match: NumMatch, button_match: ButtonMatcher, duration_match: NumMatch },
+
+    /// Matches encoders being turned. If `direction_match` is `None`, either direction matches.
+    /// `magnitude_match` is about how far the encoder was turned, regardless of direction.
+    EncoderTurn {
+        channel_match: NumMatch,
+        control_match: NumMatch,
+        direction_match: Option<EncoderDirection>,
+        magnitude_match: NumMatch
+    },
+
+    /// Matches events from a device whose name matches `device_match`, which also match
+    /// `matcher`.
+    /// When checked against a bare `MidiMessage` rather than a `MidiEvent`, the device is unknown,
+    /// so only `matcher` is checked.
+    Device { device_match: Box<StringMatcher>, matcher: Box<MidiEventMatcher> },
+}
+
+const MAX_CHANNEL: u32 = 15;
+const MAX_7_BIT: u32 = 127;
+const MAX_14_BIT: u32 = 16383;
+
+impl MidiEventMatcher {
+    /// Lists the number matchers of this matcher that are about MIDI data with a limited range,
+    /// each with the name of its field and the highest value that data can have.
+    pub fn bounded_matchers(&self) -> Vec<(&'static str, &NumMatch, u32)> {
+        match self {
+            MidiEventMatcher::NoteOn { channel_match, key_match, velocity_match }
+            | MidiEventMatcher::NoteOff { channel_match, key_match, velocity_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("key", key_match, MAX_7_BIT),
+                ("velocity", velocity_match, MAX_7_BIT)
+            ],
+
+            MidiEventMatcher::PolyAftertouch { channel_match, key_match, value_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("key", key_match, MAX_7_BIT),
+                ("value", value_match, MAX_7_BIT)
+            ],
+
+            MidiEventMatcher::ControlChange { channel_match, control_match, value_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("control", control_match, MAX_7_BIT),
+                ("value", value_match, MAX_7_BIT)
+            ],
+
+            MidiEventMatcher::ProgramChange { channel_match, program_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("program", program_match, MAX_7_BIT)
+            ],
+
+            MidiEventMatcher::ChannelAftertouch { channel_match, value_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("value", value_match, MAX_7_BIT)
+            ],
+
+            MidiEventMatcher::PitchBendChange { channel_match, value_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("value", value_match, MAX_14_BIT)
+            ],
+
+            // Only the most significant byte's controls, 0-31, make up high resolution controls
+            MidiEventMatcher::ControlChange14 { channel_match, control_match, value_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("control", control_match, 31),
+                ("value", value_match, MAX_14_BIT)
+            ],
+
+            MidiEventMatcher::Nrpn { channel_match, parameter_match, value_match }
+            | MidiEventMatcher::Rpn { channel_match, parameter_match, value_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("parameter", parameter_match, MAX_14_BIT),
+                ("value", value_match, MAX_14_BIT)
+            ],
+
+            MidiEventMatcher::MtcQuarterFrame { piece_match, value_match } => vec![
+                ("piece", piece_match, 7),
+                ("value", value_match, 15)
+            ],
+
+            MidiEventMatcher::SongPosition { position_match } => {
+                vec![("position", position_match, MAX_14_BIT)]
+            }
+
+            MidiEventMatcher::SongSelect { song_match } => vec![("song", song_match, MAX_7_BIT)],
+
+            MidiEventMatcher::Tap { channel_match, button_match }
+            | MidiEventMatcher::DoubleTap { channel_match, button_match }
+            | MidiEventMatcher::LongPress { channel_match, button_match }
+            | MidiEventMatcher::HoldRepeat { channel_match, button_match, .. }
+            | MidiEventMatcher::Release { channel_match, button_match, .. } => {
+                let mut matchers = vec![("channel", channel_match, MAX_CHANNEL)];
+
+                match button_match {
+                    ButtonMatcher::Any => {}
+
+                    ButtonMatcher::Note { key_match } => {
+                        matchers.push(("key", key_match, MAX_7_BIT))
+                    }
+
+                    ButtonMatcher::Control { control_match } => {
+                        matchers.push(("control", control_match, MAX_7_BIT))
+                    }
+                }
+
+                matchers
+            }
+
+            MidiEventMatcher::EncoderTurn { channel_match, control_match, .. } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("control", control_match, MAX_7_BIT)
+            ],
+
+            MidiEventMatcher::Device { matcher, .. } => matcher.bounded_matchers(),
+
+            _ => vec![]
+        }
+    }
+}
+
+impl Covers for MidiEventMatcher {
+    /// Whether this matcher matches every message `other` matches, making `other` redundant
+    /// behind it. The message type must be the same, and so must the device, unless this matcher
+    /// matches messages from any device.
+    fn covers(&self, other: &MidiEventMatcher) -> bool {
+        match (self, other) {
+            (
+                MidiEventMatcher::Device { device_match, matcher },
+                MidiEventMatcher::Device { device_match: other_device, matcher: other_matcher }
+            ) => device_match == other_device && matcher.covers(other_matcher),
+
+            (MidiEventMatcher::Device { .. }, _) => false,
+            (_, MidiEventMatcher::Device { matcher, .. }) => self.covers(matcher),
+
+            (
+                MidiEventMatcher::SysEx { starts_with },
+                MidiEventMatcher::SysEx { starts_with: other_starts_with }
+            ) => other_starts_with.starts_with(starts_with),
+
+            (
+                MidiEventMatcher::Tap { channel_match, button_match },
+                MidiEventMatcher::Tap { channel_match: other_channel, button_match: other_button }
+            )
+            | (
+                MidiEventMatcher::DoubleTap { channel_match, button_match },
+                MidiEventMatcher::DoubleTap {
+                    channel_match: other_channel,
+                    button_match: other_button
+                }
+            )
+            | (
+                MidiEventMatcher::LongPress { channel_match, button_match },
+                MidiEventMatcher::LongPress {
+                    channel_match: other_channel,
+                    button_match: other_button
+                }
+            ) => channel_match.covers(other_channel) && button_match.covers(other_button),
+
+            (
+                MidiEventMatcher::HoldRepeat { channel_match, button_match, count_match },
+                MidiEventMatcher::HoldRepeat {
+                    channel_match: other_channel,
+                    button_match: other_button,
+                    count_match: other_count
+                }
+            ) => {
+                channel_match.covers(other_channel)
+                    && button_match.covers(other_button)
+                    && count_match.covers(other_count)
+            }
+
+            (
+                MidiEventMatcher::Release { channel_match, button_match, duration_match },
+                MidiEventMatcher::Release {
+                    channel_match: other_channel,
+                    button_match: other_button,
+                    duration_match: other_duration
+                }
+            ) => {
+                channel_match.covers(other_channel)
+                    && button_match.covers(other_button)
+                    && duration_match.covers(other_duration)
+            }
+
+            (
+                MidiEventMatcher::EncoderTurn {
+                    channel_match, control_match, direction_match, magnitude_match
+                },
+                MidiEventMatcher::EncoderTurn {
+                    channel_match: other_channel,
+                    control_match: other_control,
+                    direction_match: other_direction,
+                    magnitude_match: other_magnitude
+                }
+            ) => {
+                channel_match.covers(other_channel)
+                    && control_match.covers(other_control)
+                    && (direction_match.is_none() || direction_match == other_direction)
+                    && magnitude_match.covers(other_magnitude)
+            }
+
+            // The other message types only have number matchers, all of which are bounded
+            _ => {
+                discriminant(self) == discriminant(other)
+                    && self
+                        .bounded_matchers()
+                        .iter()
+                        .zip(other.bounded_matchers())
+                        .all(|((_, matcher, _), (_, other_matcher, _))| {
+                            matcher.covers(other_matcher)
+                        })
+            }
+        }
+    }
+}
+
+impl MatchChecker<MidiEvent> for MidiEventMatcher {
+    fn matches(&self, val: &MidiEvent) -> bool {
+        match self {
+            MidiEventMatcher::Device { device_match, matcher } => {
+                device_match.matches(&val.device.as_str()) && matcher.matches(val)
+            }
+
+            _ => self.matches(&val.message)
+        }
+    }
 }
 
 impl MatchChecker<MidiMessage> for MidiEventMatcher {
@@ -96,15 +346,225 @@ impl MatchChecker<MidiMessage> for MidiEventMatcher {
                     _ => false
                 }
             }
+
+            MidiEventMatcher::ControlChange14 {
+                channel_match, control_match, value_match
+            } => {
+                match val {
+                    MidiMessage::ControlChange14 { channel, control, value } => {
+                        channel_match.matches(&u32::from(*channel))
+                            && control_match.matches(&u32::from(*control))
+                            && value_match.matches(&u32::from(*value))
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::Nrpn { channel_match, parameter_match, value_match } => {
+                match val {
+                    MidiMessage::Nrpn { channel, parameter, value } => {
+                        channel_match.matches(&u32::from(*channel))
+                            && parameter_match.matches(&u32::from(*parameter))
+                            && value_match.matches(&u32::from(*value))
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::Rpn { channel_match, parameter_match, value_match } => {
+                match val {
+                    MidiMessage::Rpn { channel, parameter, value } => {
+                        channel_match.matches(&u32::from(*channel))
+                            && parameter_match.matches(&u32::from(*parameter))
+                            && value_match.matches(&u32::from(*value))
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::SysEx { starts_with } => {
+                match val {
+                    MidiMessage::SysEx { data } => data.starts_with(starts_with),
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::MtcQuarterFrame { piece_match, value_match } => {
+                match val {
+                    MidiMessage::MtcQuarterFrame { piece, value } => {
+                        piece_match.matches(&u32::from(*piece))
+                            && value_match.matches(&u32::from(*value))
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::SongPosition { position_match } => {
+                match val {
+                    MidiMessage::SongPosition { position } => {
+                        position_match.matches(&u32::from(*position))
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::SongSelect { song_match } => {
+                match val {
+                    MidiMessage::SongSelect { song } => song_match.matches(&u32::from(*song)),
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::TuneRequest => *val == MidiMessage::TuneRequest,
+            MidiEventMatcher::TimingClock => *val == MidiMessage::TimingClock,
+            MidiEventMatcher::Start => *val == MidiMessage::Start,
+            MidiEventMatcher::Continue => *val == MidiMessage::Continue,
+            MidiEventMatcher::Stop => *val == MidiMessage::Stop,
+            MidiEventMatcher::ActiveSensing => *val == MidiMessage::ActiveSensing,
+            MidiEventMatcher::Reset => *val == MidiMessage::Reset,
+
+            MidiEventMatcher::Tap { channel_match, button_match } => {
+                match val {
+                    MidiMessage::Tap { channel, button } => {
+                        channel_match.matches(&u32::from(*channel))
+                            && button_match.matches(button)
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::DoubleTap { channel_match, button_match } => {
+                match val {
+                    MidiMessage::DoubleTap { channel, button } => {
+                        channel_match.matches(&u32::from(*channel))
+                            && button_match.matches(button)
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::LongPress { channel_match, button_match } => {
+                match val {
+                    MidiMessage::LongPress { channel, button } => {
+                        channel_match.matches(&u32::from(*channel))
+                            && button_match.matches(button)
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::HoldRepeat { channel_match, button_match, count_match } => {
+                match val {
+                    MidiMessage::HoldRepeat { channel, button, count } => {
+                        channel_match.matches(&u32::from(*channel))
+                            && button_match.matches(button)
+                            && count_match.matches(count)
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::Release { channel_match, button_match, duration_match } => {
+                match val {
+                    MidiMessage::Release { channel, button, duration } => {
+                        channel_match.matches(&u32::from(*channel))
+                            && button_match.matches(button)
+                            && duration_match.matches(duration)
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::EncoderTurn {
+                channel_match,
+                control_match,
+                direction_match,
+                magnitude_match
+            } => {
+                match val {
+                    MidiMessage::EncoderTurn { channel, control, delta } => {
+                        channel_match.matches(&u32::from(*channel))
+                            && control_match.matches(&u32::from(*control))
+                            && direction_match.is_none_or(|direction| direction.matches(delta))
+                            && magnitude_match.matches(&u32::from(delta.unsigned_abs()))
+                    }
+                    _ => false
+                }
+            }
+
+            MidiEventMatcher::Device { matcher, .. } => matcher.matches(val),
+        }
+    }
+}
+
+/// Which way an encoder was turned
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum EncoderDirection {
+    /// Turned up; a positive delta
+    Clockwise,
+
+    /// Turned down; a negative delta
+    Counterclockwise
+}
+
+impl MatchChecker<i8> for EncoderDirection {
+    fn matches(&self, delta: &i8) -> bool {
+        match self {
+            EncoderDirection::Clockwise => *delta > 0,
+            EncoderDirection::Counterclockwise => *delta < 0
+        }
+    }
+}
+
+/// Matches which button a tap, long press, or other press timing message is about
+#[derive(PartialEq, Debug)]
+pub enum ButtonMatcher {
+    /// Matches any key or control
+    Any,
+
+    /// Matches keys whose number matches `key_match`
+    Note { key_match: NumMatch },
+
+    /// Matches controls whose number matches `control_match`
+    Control { control_match: NumMatch }
+}
+
+impl MatchChecker<Button> for ButtonMatcher {
+    fn matches(&self, val: &Button) -> bool {
+        match (self, val) {
+            (ButtonMatcher::Any, _) => true,
+            (ButtonMatcher::Note { key_match }, Button::Note { key }) => {
+                key_match.matches(&u32::from(*key))
+            }
+            (ButtonMatcher::Control { control_match }, Button::Control { control }) => {
+                control_match.matches(&u32::from(*control))
+            }
+            _ => false
+        }
+    }
+}
+
+impl Covers for ButtonMatcher {
+    fn covers(&self, other: &ButtonMatcher) -> bool {
+        match (self, other) {
+            (ButtonMatcher::Any, _) => true,
+            (ButtonMatcher::Note { key_match }, ButtonMatcher::Note { key_match: other_key }) => {
+                key_match.covers(other_key)
+            }
+            (
+                ButtonMatcher::Control { control_match },
+                ButtonMatcher::Control { control_match: other_control }
+            ) => control_match.covers(other_control),
+            _ => false
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::macros::event_matching::midi::MidiEventMatcher;
-    use crate::match_checker::{MatchChecker, NumberMatcher};
-    use crate::midi::MidiMessage;
+    use crate::macros::event_matching::midi::{MidiEventMatcher, ButtonMatcher, EncoderDirection};
+    use crate::match_checker::{MatchChecker, NumberMatcher, StringMatcher};
+    use crate::midi::{MidiMessage, MidiEvent, Button};
 
 
     #[test]
@@ -444,4 +904,224 @@ mod tests {
         let message = MidiMessage::Other;
         assert!(!matcher.matches(&message));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn midi_event_match_control_change_14() {
+        let matcher = MidiEventMatcher::ControlChange14 {
+            channel_match: Some(NumberMatcher::Val(0)),
+            control_match: Some(NumberMatcher::Val(7)),
+            value_match: Some(NumberMatcher::Range { min: Some(8192), max: None })
+        };
+
+        let message = MidiMessage::ControlChange14 { channel: 0, control: 7, value: 10000 };
+        assert!(matcher.matches(&message));
+
+        let message = MidiMessage::ControlChange14 { channel: 0, control: 7, value: 8191 };
+        assert!(!matcher.matches(&message));
+
+        let message = MidiMessage::ControlChange14 { channel: 0, control: 8, value: 10000 };
+        assert!(!matcher.matches(&message));
+
+        let message = MidiMessage::ControlChange { channel: 0, control: 7, value: 127 };
+        assert!(!matcher.matches(&message));
+    }
+
+    #[test]
+    fn midi_event_match_nrpn_and_rpn() {
+        let matcher = MidiEventMatcher::Nrpn {
+            channel_match: None,
+            parameter_match: Some(NumberMatcher::Val(300)),
+            value_match: None
+        };
+
+        assert!(matcher.matches(&MidiMessage::Nrpn { channel: 4, parameter: 300, value: 1 }));
+        assert!(!matcher.matches(&MidiMessage::Nrpn { channel: 4, parameter: 301, value: 1 }));
+        assert!(!matcher.matches(&MidiMessage::Rpn { channel: 4, parameter: 300, value: 1 }));
+
+        let matcher = MidiEventMatcher::Rpn {
+            channel_match: Some(NumberMatcher::Val(1)),
+            parameter_match: Some(NumberMatcher::Val(0)),
+            value_match: None
+        };
+
+        assert!(matcher.matches(&MidiMessage::Rpn { channel: 1, parameter: 0, value: 256 }));
+        assert!(!matcher.matches(&MidiMessage::Rpn { channel: 2, parameter: 0, value: 256 }));
+        assert!(!matcher.matches(&MidiMessage::Nrpn { channel: 1, parameter: 0, value: 256 }));
+    }
+
+    #[test]
+    fn midi_event_match_sysex() {
+        let matcher = MidiEventMatcher::SysEx { starts_with: vec![0x00, 0x20, 0x29] };
+
+        let message = MidiMessage::SysEx { data: vec![0x00, 0x20, 0x29, 0x02, 0x10] };
+        assert!(matcher.matches(&message));
+
+        let message = MidiMessage::SysEx { data: vec![0x00, 0x20, 0x29] };
+        assert!(matcher.matches(&message));
+
+        let message = MidiMessage::SysEx { data: vec![0x00, 0x20] };
+        assert!(!matcher.matches(&message));
+
+        let message = MidiMessage::SysEx { data: vec![0x7E, 0x20, 0x29] };
+        assert!(!matcher.matches(&message));
+
+        let message = MidiMessage::Other;
+        assert!(!matcher.matches(&message));
+
+        // Without any bytes to match, any SysEx message matches
+        let matcher = MidiEventMatcher::SysEx { starts_with: vec![] };
+
+        let message = MidiMessage::SysEx { data: vec![0x01] };
+        assert!(matcher.matches(&message));
+    }
+
+    #[test]
+    fn midi_event_match_system_common_messages() {
+        let matcher = MidiEventMatcher::MtcQuarterFrame {
+            piece_match: Some(NumberMatcher::Val(7)),
+            value_match: None
+        };
+
+        assert!(matcher.matches(&MidiMessage::MtcQuarterFrame { piece: 7, value: 3 }));
+        assert!(!matcher.matches(&MidiMessage::MtcQuarterFrame { piece: 6, value: 3 }));
+        assert!(!matcher.matches(&MidiMessage::SongSelect { song: 7 }));
+
+        let matcher = MidiEventMatcher::SongPosition {
+            position_match: Some(NumberMatcher::Range { min: Some(1000), max: None })
+        };
+
+        assert!(matcher.matches(&MidiMessage::SongPosition { position: 1000 }));
+        assert!(!matcher.matches(&MidiMessage::SongPosition { position: 999 }));
+        assert!(!matcher.matches(&MidiMessage::Start));
+
+        let matcher = MidiEventMatcher::SongSelect { song_match: Some(NumberMatcher::Val(2)) };
+
+        assert!(matcher.matches(&MidiMessage::SongSelect { song: 2 }));
+        assert!(!matcher.matches(&MidiMessage::SongSelect { song: 3 }));
+
+        let matcher = MidiEventMatcher::TuneRequest;
+
+        assert!(matcher.matches(&MidiMessage::TuneRequest));
+        assert!(!matcher.matches(&MidiMessage::Reset));
+    }
+
+    #[test]
+    fn midi_event_match_system_realtime_messages() {
+        let pairs = [
+            (MidiEventMatcher::TimingClock, MidiMessage::TimingClock),
+            (MidiEventMatcher::Start, MidiMessage::Start),
+            (MidiEventMatcher::Continue, MidiMessage::Continue),
+            (MidiEventMatcher::Stop, MidiMessage::Stop),
+            (MidiEventMatcher::ActiveSensing, MidiMessage::ActiveSensing),
+            (MidiEventMatcher::Reset, MidiMessage::Reset),
+        ];
+
+        for (matcher, message) in pairs.iter() {
+            assert!(matcher.matches(message));
+            assert!(!matcher.matches(&MidiMessage::Other));
+            assert!(!matcher.matches(&MidiMessage::TuneRequest));
+        }
+
+        assert!(!MidiEventMatcher::Start.matches(&MidiMessage::Continue));
+        assert!(!MidiEventMatcher::Stop.matches(&MidiMessage::Start));
+    }
+
+    #[test]
+    fn midi_event_match_device() {
+        let matcher = MidiEventMatcher::Device {
+            device_match: Box::new(StringMatcher::Is("pads".to_string())),
+            matcher: Box::new(MidiEventMatcher::NoteOn {
+                channel_match: None,
+                key_match: Some(NumberMatcher::Val(36)),
+                velocity_match: None
+            })
+        };
+
+        let message = MidiMessage::NoteOn { channel: 0, key: 36, velocity: 100 };
+        assert!(matcher.matches(&MidiEvent::new("pads", message)));
+
+        let message = MidiMessage::NoteOn { channel: 0, key: 36, velocity: 100 };
+        assert!(!matcher.matches(&MidiEvent::new("keys", message)));
+
+        let message = MidiMessage::NoteOn { channel: 0, key: 37, velocity: 100 };
+        assert!(!matcher.matches(&MidiEvent::new("pads", message)));
+
+        // Matchers without a device match events from any device
+        let matcher = MidiEventMatcher::Start;
+
+        assert!(matcher.matches(&MidiEvent::new("pads", MidiMessage::Start)));
+        assert!(matcher.matches(&MidiEvent::new("keys", MidiMessage::Start)));
+        assert!(!matcher.matches(&MidiEvent::new("keys", MidiMessage::Stop)));
+    }
+
+    #[test]
+    fn midi_event_match_press_timing() {
+        let pad = Button::Note { key: 36 };
+        let control = Button::Control { control: 36 };
+
+        let matcher = MidiEventMatcher::Tap {
+            channel_match: None,
+            button_match: ButtonMatcher::Note { key_match: Some(NumberMatcher::Val(36)) }
+        };
+
+        assert!(matcher.matches(&MidiMessage::Tap { channel: 0, button: pad }));
+        assert!(!matcher.matches(&MidiMessage::Tap { channel: 0, button: control }));
+        assert!(!matcher.matches(&MidiMessage::DoubleTap { channel: 0, button: pad }));
+
+        let matcher = MidiEventMatcher::LongPress {
+            channel_match: Some(NumberMatcher::Val(1)),
+            button_match: ButtonMatcher::Any
+        };
+
+        assert!(matcher.matches(&MidiMessage::LongPress { channel: 1, button: control }));
+        assert!(!matcher.matches(&MidiMessage::LongPress { channel: 2, button: control }));
+
+        let matcher = MidiEventMatcher::HoldRepeat {
+            channel_match: None,
+            button_match: ButtonMatcher::Control { control_match: None },
+            count_match: Some(NumberMatcher::Range { min: Some(5), max: None })
+        };
+
+        assert!(matcher.matches(&MidiMessage::HoldRepeat { channel: 0, button: control, count: 5 }));
+        assert!(!matcher.matches(&MidiMessage::HoldRepeat { channel: 0, button: control, count: 4 }));
+        assert!(!matcher.matches(&MidiMessage::HoldRepeat { channel: 0, button: pad, count: 5 }));
+
+        let matcher = MidiEventMatcher::Release {
+            channel_match: None,
+            button_match: ButtonMatcher::Any,
+            duration_match: Some(NumberMatcher::Range { min: Some(1000), max: None })
+        };
+
+        assert!(matcher.matches(&MidiMessage::Release { channel: 0, button: pad, duration: 1500 }));
+        assert!(!matcher.matches(&MidiMessage::Release { channel: 0, button: pad, duration: 200 }));
+    }
+
+    #[test]
+    fn matches_encoder_turns() {
+        let matcher = MidiEventMatcher::EncoderTurn {
+            channel_match: None,
+            control_match: Some(NumberMatcher::Val(21)),
+            direction_match: Some(EncoderDirection::Counterclockwise),
+            magnitude_match: Some(NumberMatcher::Range { min: Some(2), max: None })
+        };
+
+        let turn = |control, delta| MidiMessage::EncoderTurn { channel: 0, control, delta };
+
+        assert!(matcher.matches(&turn(21, -2)));
+        assert!(matcher.matches(&turn(21, -64)));
+        assert!(!matcher.matches(&turn(21, -1)));
+        assert!(!matcher.matches(&turn(21, 2)));
+        assert!(!matcher.matches(&turn(22, -2)));
+        assert!(!matcher.matches(&MidiMessage::ControlChange { channel: 0, control: 21, value: 126 }));
+
+        let matcher = MidiEventMatcher::EncoderTurn {
+            channel_match: None,
+            control_match: None,
+            direction_match: None,
+            magnitude_match: None
+        };
+
+        assert!(matcher.matches(&turn(21, -2)));
+        assert!(matcher.matches(&turn(22, 5)));
+    }
+}
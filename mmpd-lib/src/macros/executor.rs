@@ -0,0 +1,730 @@
+mod workers;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::clock::{Clock, SystemClock};
+use crate::macros::Macro;
+use crate::macros::actions::{Action, ActionRunner, ControlAction, Task};
+use crate::macros::event_matching::Event;
+use crate::macros::preconditions::Precondition;
+use crate::state::State;
+use workers::{Workers, RunId};
+
+/// How often to check on tasks being performed by workers, while there are any
+const TASK_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Most worker threads performing actions at once, unless set otherwise with `set_max_workers`
+pub const DEFAULT_MAX_WORKERS: usize = 8;
+
+/// What happens when a macro is triggered while its actions from an earlier trigger are still
+/// running
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum Concurrency {
+    /// Runs the actions again once the running ones are done, as many times as it was triggered
+    #[default]
+    Queue,
+
+    /// Ignores the trigger
+    DropIfRunning,
+
+    /// Stops the running actions, and starts over. An action that is underway, like a program
+    /// that is running, is left to finish, but no further actions of the stopped run are run.
+    Restart,
+
+    /// Runs the actions again alongside the running ones
+    Parallel
+}
+
+/// Runs macros' actions without holding up the handling of events.
+///
+/// Actions are run in order as before, but rather than waiting for an action that takes a while,
+/// the executor moves on to other macros' actions in the meantime: a `wait` action only holds up
+/// the macro it's in, and typing text or running a program is done by a worker thread. There are
+/// at most `DEFAULT_MAX_WORKERS` of those, or as many as set by `set_max_workers`; once they're all
+/// busy, a run's next such action waits for one to be free, in the order the runs started. Actions
+/// can be repeated while some condition holds, picked between by conditions, and a macro's
+/// running actions can be stopped by another macro (see `Action::RepeatWhile`, `Action::If`,
+/// `Action::Switch` and `Action::CancelMacro`).
+///
+/// State stays with the thread handling events: actions that need it, such as changing variables,
+/// are run there, by `advance`, as are variables in an action resolved before handing it to a
+/// worker. Control actions are returned by `advance` too, to be acted upon like before.
+pub struct Executor {
+    /// Runs quick actions, like sending MIDI messages
+    runner: ActionRunner,
+
+    /// Performs actions that may take a while
+    workers: Workers,
+
+    /// Macros whose actions are running or waiting to run, in the order they were triggered
+    runs: Vec<Run>,
+
+    next_run_id: RunId,
+
+    /// Source of the current time, for timing `wait` actions
+    clock: Box<dyn Clock>
+}
+
+/// One run of a macro's actions
+struct Run {
+    id: RunId,
+
+    /// Index of the macro in the config the run was started with. `None` once the config has
+    /// been reloaded, since indexes then refer to different macros.
+    macro_index: Option<usize>,
+
+    /// Name of the macro, for `Action::CancelMacro`
+    name: Option<String>,
+
+    concurrency: Concurrency,
+
+    /// Lists of actions being run: the macro's, and on top of that those of any actions containing
+    /// actions that are running. Once empty, the run is done.
+    frames: Vec<Frame>,
+
+    /// The event that triggered the macro, for variables in its actions
+    event: Event,
+
+    /// What has to happen before the next action can run
+    waiting_for: WaitingFor,
+
+    /// Whether the run was stopped by an `Action::CancelMacro`, to be removed
+    is_cancelled: bool
+}
+
+/// A list of actions being run
+struct Frame {
+    actions: Vec<Action>,
+
+    /// Index of the next action to run
+    next: usize,
+
+    /// For the actions of an `Action::RepeatWhile`, what keeps them repeating
+    repeat: Option<Repeat>
+}
+
+struct Repeat {
+    required_preconditions: Vec<Precondition>,
+    interval: Duration
+}
+
+impl Frame {
+    fn new(actions: Vec<Action>, repeat: Option<Repeat>) -> Frame {
+        Frame { actions, next: 0, repeat }
+    }
+}
+
+enum WaitingFor {
+    Nothing,
+
+    /// A `wait` action to be done
+    Time(Instant),
+
+    /// A worker to finish the task it's performing for the run
+    Worker
+}
+
+impl Executor {
+    /// Sets up an executor running quick actions through `runner`. Each worker thread sets up its
+    /// own action runner with `new_runner`.
+    pub fn new<F>(runner: ActionRunner, new_runner: F) -> Executor
+        where F: Fn() -> Option<ActionRunner> + Send + Sync + 'static
+    {
+        Executor::with_clock(runner, new_runner, Box::new(SystemClock))
+    }
+
+    /// Same as `new`, but timing `wait` actions by `clock` rather than the system clock
+    pub fn with_clock<F>(runner: ActionRunner, new_runner: F, clock: Box<dyn Clock>) -> Executor
+        where F: Fn() -> Option<ActionRunner> + Send + Sync + 'static
+    {
+        Executor {
+            runner,
+            workers: Workers::new(Arc::new(new_runner), DEFAULT_MAX_WORKERS),
+            runs: vec![],
+            next_run_id: 0,
+            clock
+        }
+    }
+
+    /// Sets the most worker threads that can perform actions at once, at least 1
+    pub fn set_max_workers(&mut self, max: usize) {
+        self.workers.set_max(max.max(1));
+    }
+
+    /// Starts running the actions of `macro_item`, the macro at `macro_index` in config, triggered
+    /// by `event`, as far as its concurrency allows. The actions themselves are run by `advance`.
+    ///
+    /// Returns `false` if the macro was still running and therefore ignored.
+    pub fn start(&mut self, macro_index: usize, macro_item: &Macro, event: &Event) -> bool {
+        let is_running = self.runs.iter().any(|run| run.macro_index == Some(macro_index));
+
+        match macro_item.concurrency {
+            Concurrency::DropIfRunning if is_running => return false,
+
+            // A stopped run's task a worker is busy with is left to finish, and then ignored
+            Concurrency::Restart => self.runs.retain(|run| run.macro_index != Some(macro_index)),
+
+            _ => {}
+        }
+
+        self.runs.push(Run {
+            id: self.next_run_id,
+            macro_index: Some(macro_index),
+            name: macro_item.name.clone(),
+            concurrency: macro_item.concurrency,
+            frames: vec![Frame::new(macro_item.actions.clone(), None)],
+            event: event.clone(),
+            waiting_for: WaitingFor::Nothing,
+            is_cancelled: false
+        });
+
+        self.next_run_id += 1;
+        true
+    }
+
+    /// Runs all actions that can run by now, handing actions that may take a while to workers.
+    /// Returns the control actions that were run, in order.
+    pub fn advance(&mut self, state: &mut dyn State) -> Vec<ControlAction> {
+        for run_id in self.workers.take_done() {
+            if let Some(run) = self.runs.iter_mut().find(|run| run.id == run_id) {
+                run.waiting_for = WaitingFor::Nothing;
+            }
+        }
+
+        let now = self.clock.now();
+        let mut control_actions = vec![];
+        let mut cancelled = vec![];
+        let mut i = 0;
+
+        while i < self.runs.len() {
+            let run = &self.runs[i];
+
+            if run.is_cancelled {
+                self.runs.remove(i);
+                continue;
+            }
+
+            let is_queued = run.concurrency == Concurrency::Queue
+                && run.macro_index.is_some()
+                && self.runs[..i].iter().any(|earlier| {
+                    !earlier.is_cancelled && earlier.macro_index == run.macro_index
+                });
+
+            let is_waiting = match run.waiting_for {
+                WaitingFor::Nothing => false,
+                WaitingFor::Time(until) => until > now,
+                WaitingFor::Worker => true
+            };
+
+            if is_queued || is_waiting {
+                i += 1;
+                continue;
+            }
+
+            let run = &mut self.runs[i];
+            run.waiting_for = WaitingFor::Nothing;
+
+            let is_done = run_actions(
+                run,
+                &self.runner,
+                &mut self.workers,
+                now,
+                state,
+                &mut control_actions,
+                &mut cancelled
+            );
+
+            for name in cancelled.drain(..) {
+                for run in self.runs.iter_mut().filter(|run| run.name.as_ref() == Some(&name)) {
+                    run.is_cancelled = true;
+                }
+            }
+
+            // Removing a run may let a queued run of the same macro, later in the list, go ahead
+            if is_done || self.runs[i].is_cancelled {
+                self.runs.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        // Runs cancelled after their turn in the loop above
+        self.runs.retain(|run| !run.is_cancelled);
+
+        control_actions
+    }
+
+    /// The next time `advance` may have actions to run, if any runs are waiting
+    pub fn next_deadline(&self) -> Option<Instant> {
+        // Workers finishing a task are checked for regularly
+        let worker_deadline = if self.workers.any_busy() {
+            Some(self.clock.now() + TASK_POLL_INTERVAL)
+        } else {
+            None
+        };
+
+        self.runs
+            .iter()
+            .filter_map(|run| match run.waiting_for {
+                WaitingFor::Time(until) => Some(until),
+                _ => None
+            })
+            .chain(worker_deadline)
+            .min()
+    }
+
+    /// Whether there are no actions running or waiting to run
+    pub fn is_idle(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Lets running actions finish without regard for the concurrency of their macros, for when
+    /// the config is reloaded, and the macros they were started for are gone.
+    pub fn forget_macros(&mut self) {
+        for run in self.runs.iter_mut() {
+            run.macro_index = None;
+        }
+    }
+}
+
+/// Runs the actions of `run` from where it left off, until one has to be waited for. Returns
+/// whether all of its actions have run.
+///
+/// Control actions are added to `control_actions`, and names of macros to cancel to `cancelled`.
+fn run_actions(
+    run: &mut Run,
+    runner: &ActionRunner,
+    workers: &mut Workers,
+    now: Instant,
+    state: &mut dyn State,
+    control_actions: &mut Vec<ControlAction>,
+    cancelled: &mut Vec<String>
+) -> bool {
+    loop {
+        let frame = match run.frames.last_mut() {
+            Some(frame) => frame,
+            None => return true
+        };
+
+        // Repeated actions stop once their preconditions aren't satisfied anymore
+        if let (0, Some(repeat)) = (frame.next, &frame.repeat) {
+            let conditions = &repeat.required_preconditions;
+
+            if !conditions.iter().all(|condition| state.matches_precondition(condition)) {
+                run.frames.pop();
+                continue;
+            }
+        }
+
+        let action = match frame.actions.get(frame.next) {
+            Some(action) => action,
+
+            None => {
+                if let Some(repeat) = &frame.repeat {
+                    frame.next = 0;
+                    run.waiting_for = WaitingFor::Time(now + repeat.interval);
+                    return false;
+                }
+
+                run.frames.pop();
+                continue;
+            }
+        };
+
+        frame.next += 1;
+
+        match action {
+            Action::Wait { duration } => {
+                run.waiting_for = WaitingFor::Time(now + Duration::from_micros(*duration));
+                return false;
+            }
+
+            Action::RepeatWhile { required_preconditions, interval, actions } => {
+                let repeat = Repeat {
+                    required_preconditions: required_preconditions.clone(),
+                    interval: *interval
+                };
+
+                let frame = Frame::new(actions.clone(), Some(repeat));
+                run.frames.push(frame);
+            }
+
+            Action::CancelMacro { name } => cancelled.push(name.to_string()),
+
+            Action::Call { actions, .. } => {
+                let frame = Frame::new(actions.clone(), None);
+                run.frames.push(frame);
+            }
+
+            Action::If { .. } | Action::Switch { .. } => {
+                if let Some(actions) = action.branch(&run.event, state) {
+                    let frame = Frame::new(actions.clone(), None);
+                    run.frames.push(frame);
+                }
+            }
+
+            _ => {
+                if let Some(task) = Task::from_action(action, &run.event, state) {
+                    // Tried again from the same action next time, resolving its variables anew
+                    if workers.is_full() {
+                        frame.next -= 1;
+                        return false;
+                    }
+
+                    workers.perform(run.id, task);
+                    run.waiting_for = WaitingFor::Worker;
+                    return false;
+                }
+
+                if let Some(control_action) = runner.run(action, &run.event, state) {
+                    control_actions.push(control_action);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use crate::clock::ManualClock;
+    use crate::focus::MockFocusAdapter;
+    use crate::keyboard_control::MockKeyboardControlAdapter;
+    use crate::macros::{Macro, MacroBuilder};
+    use crate::macros::actions::{Action, ActionRunner, ControlAction, VariableAction};
+    use crate::macros::actions::template::Template;
+    use crate::macros::event_matching::{Event, EventMatcher, MatcherType};
+    use crate::macros::executor::{Executor, Concurrency};
+    use crate::macros::preconditions::{Precondition, PreconditionType};
+    use crate::macros::preconditions::midi::MidiPrecondition;
+    use crate::macros::preconditions::variable::{VariablePrecondition, ValueMatcher};
+    use crate::match_checker::NumberMatcher;
+    use crate::midi::{MidiEvent, MidiMessage};
+    use crate::midi::adapters::MockMidiOutputAdapter;
+    use crate::shell::MockShell;
+    use crate::state::{self, State, Value};
+
+    fn runner_with_shell(shell: MockShell) -> ActionRunner {
+        ActionRunner::with_adapters(
+            Box::new(MockKeyboardControlAdapter::new()),
+            Box::new(shell),
+            Box::new(MockMidiOutputAdapter::new())
+        )
+    }
+
+    fn runner() -> ActionRunner {
+        runner_with_shell(MockShell::new())
+    }
+
+    fn increment(name: &str) -> Action {
+        Action::Variable(VariableAction::Increment {
+            name: name.to_string(),
+            by: 1,
+            min: None,
+            max: None,
+            wrap: false
+        })
+    }
+
+    /// Macro that counts how many times it got halfway and all the way through its actions
+    fn counting_macro(concurrency: Concurrency) -> Macro {
+        MacroBuilder::from_event_matcher(EventMatcher::new(MatcherType::Other, None))
+            .add_action(increment("started"))
+            .add_action(Action::Wait { duration: 100_000 })
+            .add_action(increment("finished"))
+            .set_concurrency(concurrency)
+            .build()
+    }
+
+    fn counts(state: &dyn State) -> (Option<Value>, Option<Value>) {
+        (state.get_variable("started"), state.get_variable("finished"))
+    }
+
+    fn count(started: i64, finished: i64) -> (Option<Value>, Option<Value>) {
+        let value = |count| if count > 0 { Some(Value::Integer(count)) } else { None };
+        (value(started), value(finished))
+    }
+
+    /// Starts `macro_item` twice, then advances time until its runs are done, returning the
+    /// counts after each step
+    fn trigger_twice(macro_item: &Macro) -> Vec<(Option<Value>, Option<Value>)> {
+        let clock = ManualClock::new();
+        let mut state = state::new(Box::new(MockFocusAdapter::new()));
+        let mut executor = Executor::with_clock(runner(), || None, Box::new(clock.clone()));
+        let mut steps = vec![];
+
+        executor.start(0, macro_item, &Event::Other);
+        executor.advance(state.as_mut());
+        clock.advance(Duration::from_millis(50));
+
+        executor.start(0, macro_item, &Event::Other);
+        executor.advance(state.as_mut());
+        steps.push(counts(state.as_ref()));
+
+        while !executor.is_idle() {
+            clock.advance(Duration::from_millis(50));
+            executor.advance(state.as_mut());
+            steps.push(counts(state.as_ref()));
+        }
+
+        steps
+    }
+
+    #[test]
+    fn queues_runs_of_the_same_macro() {
+        assert_eq!(
+            trigger_twice(&counting_macro(Concurrency::Queue)),
+            vec![count(1, 0), count(2, 1), count(2, 1), count(2, 2)]
+        );
+    }
+
+    #[test]
+    fn drops_runs_of_a_macro_that_is_running() {
+        assert_eq!(
+            trigger_twice(&counting_macro(Concurrency::DropIfRunning)),
+            vec![count(1, 0), count(1, 1)]
+        );
+    }
+
+    #[test]
+    fn restarts_runs_of_a_macro_that_is_running() {
+        assert_eq!(
+            trigger_twice(&counting_macro(Concurrency::Restart)),
+            vec![count(2, 0), count(2, 0), count(2, 1)]
+        );
+    }
+
+    #[test]
+    fn runs_runs_of_the_same_macro_in_parallel() {
+        assert_eq!(
+            trigger_twice(&counting_macro(Concurrency::Parallel)),
+            vec![count(2, 0), count(2, 1), count(2, 2)]
+        );
+    }
+
+    #[test]
+    fn repeats_actions_while_preconditions_are_satisfied() {
+        let clock = ManualClock::new();
+        let mut state = state::new(Box::new(MockFocusAdapter::new()));
+        let mut executor = Executor::with_clock(runner(), || None, Box::new(clock.clone()));
+        let note = |message| Event::Midi(MidiEvent::new("pads", message));
+
+        let held = Precondition {
+            invert: false,
+            condition: PreconditionType::Midi(MidiPrecondition::NoteOn {
+                channel_match: None,
+                key_match: Some(NumberMatcher::Val(36))
+            })
+        };
+
+        let scroll_macro = MacroBuilder::from_event_matcher(
+            EventMatcher::new(MatcherType::Other, None)
+        )
+            .add_action(Action::RepeatWhile {
+                required_preconditions: vec![held],
+                interval: Duration::from_millis(100),
+                actions: vec![increment("scrolled")]
+            })
+            .add_action(increment("finished"))
+            .build();
+
+        let note_on = note(MidiMessage::NoteOn { channel: 0, key: 36, velocity: 100 });
+        state.process_event(&note_on);
+        executor.start(0, &scroll_macro, &note_on);
+        executor.advance(state.as_mut());
+        assert_eq!(state.get_variable("scrolled"), Some(Value::Integer(1)));
+
+        clock.advance(Duration::from_millis(50));
+        executor.advance(state.as_mut());
+        assert_eq!(state.get_variable("scrolled"), Some(Value::Integer(1)));
+
+        clock.advance(Duration::from_millis(50));
+        executor.advance(state.as_mut());
+        assert_eq!(state.get_variable("scrolled"), Some(Value::Integer(2)));
+
+        state.process_event(&note(MidiMessage::NoteOff { channel: 0, key: 36, velocity: 0 }));
+        clock.advance(Duration::from_millis(100));
+        executor.advance(state.as_mut());
+        assert_eq!(state.get_variable("scrolled"), Some(Value::Integer(2)));
+        assert_eq!(state.get_variable("finished"), Some(Value::Integer(1)));
+        assert!(executor.is_idle());
+    }
+
+    #[test]
+    fn runs_the_actions_picked_by_if_actions() {
+        let mut state = state::new(Box::new(MockFocusAdapter::new()));
+        let mut executor = Executor::new(runner(), || None);
+
+        let is_shifted = Precondition {
+            invert: false,
+            condition: PreconditionType::Variable(VariablePrecondition {
+                name: "shift".to_string(),
+                value_match: ValueMatcher::Bool(true)
+            })
+        };
+
+        let branching_macro = MacroBuilder::from_event_matcher(
+            EventMatcher::new(MatcherType::Other, None)
+        )
+            .add_action(Action::If {
+                required_preconditions: vec![is_shifted],
+                then: vec![increment("shifted")],
+                otherwise: vec![increment("unshifted")]
+            })
+            .add_action(increment("finished"))
+            .build();
+
+        executor.start(0, &branching_macro, &Event::Other);
+        executor.advance(state.as_mut());
+
+        state.set_variable("shift", Value::Bool(true));
+        executor.start(0, &branching_macro, &Event::Other);
+        executor.advance(state.as_mut());
+
+        assert_eq!(state.get_variable("unshifted"), Some(Value::Integer(1)));
+        assert_eq!(state.get_variable("shifted"), Some(Value::Integer(1)));
+        assert_eq!(state.get_variable("finished"), Some(Value::Integer(2)));
+        assert!(executor.is_idle());
+    }
+
+    #[test]
+    fn cancels_running_macros_by_name() {
+        let clock = ManualClock::new();
+        let mut state = state::new(Box::new(MockFocusAdapter::new()));
+        let mut executor = Executor::with_clock(runner(), || None, Box::new(clock.clone()));
+
+        let counting_macro = MacroBuilder::from_event_matcher(
+            EventMatcher::new(MatcherType::Other, None)
+        )
+            .add_action(Action::Wait { duration: 100_000 })
+            .add_action(increment("finished"))
+            .set_name("counting".to_string())
+            .build();
+
+        let cancelling_macro = MacroBuilder::from_event_matcher(
+            EventMatcher::new(MatcherType::Other, None)
+        )
+            .add_action(Action::CancelMacro { name: "counting".to_string() })
+            .build();
+
+        // One running, one queued
+        executor.start(0, &counting_macro, &Event::Other);
+        executor.start(0, &counting_macro, &Event::Other);
+        executor.advance(state.as_mut());
+
+        executor.start(1, &cancelling_macro, &Event::Other);
+        executor.advance(state.as_mut());
+        assert!(executor.is_idle());
+
+        clock.advance(Duration::from_millis(100));
+        executor.advance(state.as_mut());
+        assert_eq!(state.get_variable("finished"), None);
+    }
+
+    #[test]
+    fn performs_slow_actions_on_workers_and_returns_control_actions() {
+        let commands = Arc::new(Mutex::new(vec![]));
+        let worker_commands = Arc::clone(&commands);
+
+        let mut executor = Executor::new(runner(), move || {
+            let commands = Arc::clone(&worker_commands);
+            let mut shell = MockShell::new();
+
+            shell.expect_execute().returning(move |command, _, _| {
+                commands.lock().unwrap().push(command.to_string());
+            });
+
+            Some(runner_with_shell(shell))
+        });
+
+        let macro_item = MacroBuilder::from_event_matcher(
+            EventMatcher::new(MatcherType::Other, None)
+        )
+            .add_action(Action::Shell { command: "first".to_string(), args: None, env_vars: None })
+            .add_action(Action::Shell {
+                command: "second".to_string(),
+                args: Some(vec![Template::literal("--flag")]),
+                env_vars: None
+            })
+            .add_action(Action::Control(ControlAction::Exit))
+            .build();
+
+        let mut state = state::new(Box::new(MockFocusAdapter::new()));
+        let mut control_actions = vec![];
+
+        assert!(executor.start(0, &macro_item, &Event::Other));
+
+        for _ in 0..1000 {
+            control_actions.extend(executor.advance(state.as_mut()));
+
+            if executor.is_idle() {
+                break;
+            }
+
+            assert!(executor.next_deadline().is_some());
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(executor.is_idle());
+        assert_eq!(control_actions, vec![ControlAction::Exit]);
+        assert_eq!(*commands.lock().unwrap(), vec!["first".to_string(), "second".to_string()]);
+    }
+
+    #[test]
+    fn waits_for_a_free_worker_once_all_are_busy() {
+        // Commands being performed at the moment, and the most there were at once
+        let performing = Arc::new(Mutex::new((0, 0)));
+        let worker_performing = Arc::clone(&performing);
+
+        let mut executor = Executor::new(runner(), move || {
+            let performing = Arc::clone(&worker_performing);
+            let mut shell = MockShell::new();
+
+            shell.expect_execute().returning(move |_, _, _| {
+                {
+                    let (now, most) = &mut *performing.lock().unwrap();
+                    *now += 1;
+                    *most = (*most).max(*now);
+                }
+
+                thread::sleep(Duration::from_millis(20));
+                performing.lock().unwrap().0 -= 1;
+            });
+
+            Some(runner_with_shell(shell))
+        });
+
+        executor.set_max_workers(2);
+
+        let macro_item = MacroBuilder::from_event_matcher(
+            EventMatcher::new(MatcherType::Other, None)
+        )
+            .add_action(Action::Shell { command: "slow".to_string(), args: None, env_vars: None })
+            .add_action(increment("finished"))
+            .set_concurrency(Concurrency::Parallel)
+            .build();
+
+        let mut state = state::new(Box::new(MockFocusAdapter::new()));
+
+        for _ in 0..5 {
+            executor.start(0, &macro_item, &Event::Other);
+        }
+
+        for _ in 0..1000 {
+            executor.advance(state.as_mut());
+
+            if executor.is_idle() {
+                break;
+            }
+
+            assert!(executor.next_deadline().is_some());
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(executor.is_idle());
+        assert_eq!(state.get_variable("finished"), Some(Value::Integer(5)));
+        assert_eq!(performing.lock().unwrap().1, 2);
+    }
+}
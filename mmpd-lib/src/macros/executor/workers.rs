@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use crate::macros::actions::{ActionRunner, Task};
+
+/// Identifies one run of a macro's actions
+pub(crate) type RunId = u64;
+
+/// Sets up an `ActionRunner` for a worker. Since action runners can't be moved between threads,
+/// every worker sets up its own.
+pub(crate) type RunnerFactory = Arc<dyn Fn() -> Option<ActionRunner> + Send + Sync>;
+
+/// Threads performing tasks that may take a while, such as running a program, so they don't hold
+/// up handling events, or other macros.
+///
+/// Workers are started as they're needed, up to `max` of them, and reused once they're done with
+/// their task. Each performs one task at a time, on behalf of one run.
+pub(crate) struct Workers {
+    new_runner: RunnerFactory,
+
+    /// Most workers there can be at once
+    max: usize,
+
+    /// Workers not currently performing a task
+    idle: Vec<Worker>,
+
+    /// Workers performing a task, keyed by the run they're performing it for
+    busy: HashMap<RunId, Worker>,
+
+    /// Sending end of `done`, handed to each worker
+    done_tx: Sender<RunId>,
+
+    /// Runs whose task a worker finished
+    done: Receiver<RunId>
+}
+
+struct Worker {
+    tasks: Sender<(RunId, Task)>
+}
+
+impl Workers {
+    pub(crate) fn new(new_runner: RunnerFactory, max: usize) -> Workers {
+        let (done_tx, done) = mpsc::channel();
+
+        Workers {
+            new_runner,
+            max,
+            idle: vec![],
+            busy: HashMap::new(),
+            done_tx,
+            done
+        }
+    }
+
+    /// Sets the most workers there can be at once. If there are more already, the ones over the
+    /// limit are let go as they become idle.
+    pub(crate) fn set_max(&mut self, max: usize) {
+        self.max = max;
+        self.idle.truncate(max.saturating_sub(self.busy.len()));
+    }
+
+    /// Whether there's no idle worker, and no new one can be started, so `perform` has to wait
+    pub(crate) fn is_full(&self) -> bool {
+        self.idle.is_empty() && self.busy.len() >= self.max
+    }
+
+    /// Hands `task` to an idle worker, or to a new one if none are idle, on behalf of `run_id`.
+    /// Check `is_full` first; this doesn't keep to `max` by itself.
+    pub(crate) fn perform(&mut self, run_id: RunId, task: Task) {
+        let worker = self.idle.pop().unwrap_or_else(|| self.spawn());
+
+        // If the worker's thread somehow died, the task is lost, but the run shouldn't hang
+        // waiting for it.
+        if worker.tasks.send((run_id, task)).is_err() {
+            let _ = self.done_tx.send(run_id);
+            return;
+        }
+
+        self.busy.insert(run_id, worker);
+    }
+
+    /// Takes the runs whose task has been performed since the last call, making their workers
+    /// available for other tasks.
+    pub(crate) fn take_done(&mut self) -> Vec<RunId> {
+        let done: Vec<RunId> = self.done.try_iter().collect();
+
+        for run_id in &done {
+            if let Some(worker) = self.busy.remove(run_id) {
+                // Dropping a worker over the limit ends its thread
+                if self.idle.len() + self.busy.len() < self.max {
+                    self.idle.push(worker);
+                }
+            }
+        }
+
+        done
+    }
+
+    /// Whether any worker is performing a task
+    pub(crate) fn any_busy(&self) -> bool {
+        !self.busy.is_empty()
+    }
+
+    fn spawn(&self) -> Worker {
+        let (tasks, task_rx) = mpsc::channel::<(RunId, Task)>();
+        let new_runner = Arc::clone(&self.new_runner);
+        let done_tx = self.done_tx.clone();
+
+        // The thread ends once `tasks` is dropped along with `Workers`, after finishing the task
+        // at hand, if any.
+        thread::spawn(move || {
+            let runner = new_runner();
+
+            if runner.is_none() {
+                eprintln!("Unable to get an action runner for a worker; its actions are skipped.");
+            }
+
+            for (run_id, task) in task_rx {
+                if let Some(runner) = &runner {
+                    runner.perform(&task);
+                }
+
+                if done_tx.send(run_id).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Worker { tasks }
+    }
+}
@@ -0,0 +1,170 @@
+use std::time::Duration;
+use crate::macros::event_matching::Event;
+use crate::midi::MidiMessage;
+
+/// Highest value of 7-bit messages, like control changes and aftertouch
+const MAX_7_BIT: u32 = 127;
+
+/// Highest value of 14-bit messages, like pitch bend
+const MAX_14_BIT: u32 = 16383;
+
+/// Maps the value of a continuous control, such as a fader, into a range of numbers, and stores
+/// it in a variable, so a macro's actions can use a value proportional to the control's position.
+///
+/// Since moving a control quickly sends many messages, a macro with a `ValueMap` runs at most once
+/// every `interval` (see `State::throttle`), and, if `deduplicate` is set, only when the mapped
+/// value changed.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ValueMap {
+    /// Name of the variable the mapped value is stored in
+    pub variable: String,
+
+    /// Range of incoming values to map, as `(min, max)`. Values outside of it are clamped to it.
+    /// If `None`, the full range of the message's value is used.
+    pub from: Option<(u32, u32)>,
+
+    /// Range to map values into, as `(min, max)`. `min` may be larger than `max` to invert the
+    /// mapping.
+    pub to: (i64, i64),
+
+    /// How values are spread over the `to` range
+    pub curve: Curve,
+
+    /// Least time between runs of the macro
+    pub interval: Duration,
+
+    /// Whether to skip running the macro if the mapped value is the same as the variable's
+    pub deduplicate: bool
+}
+
+/// How a `ValueMap` spreads values over its target range
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Curve {
+    /// Equal steps in the incoming value are equal steps in the mapped value
+    Linear,
+
+    /// Equal steps in the incoming value multiply the mapped value by an equal factor, like the
+    /// frequency of a filter knob. Both ends of the target range have to be more than 0.
+    Logarithmic
+}
+
+impl ValueMap {
+    /// Maps the value of `event` into the target range.
+    /// Returns `None` if `event` doesn't have a value that can be mapped, which is the case for
+    /// anything but control changes, pitch bend, aftertouch, NRPN, and RPN.
+    pub fn map(&self, event: &Event) -> Option<i64> {
+        let (value, max) = match event {
+            Event::Midi(midi_event) => source_value(&midi_event.message)?,
+            _ => return None
+        };
+
+        let (from_min, from_max) = self.from.unwrap_or((0, max));
+        let (to_min, to_max) = (self.to.0 as f64, self.to.1 as f64);
+
+        let position = if from_max > from_min {
+            let value = value.clamp(from_min, from_max);
+            f64::from(value - from_min) / f64::from(from_max - from_min)
+        } else {
+            0.0
+        };
+
+        let mapped = match self.curve {
+            Curve::Linear => to_min + position * (to_max - to_min),
+            Curve::Logarithmic => to_min * (to_max / to_min).powf(position)
+        };
+
+        Some(mapped.round() as i64)
+    }
+}
+
+/// The value of `msg` along with the highest value it can have, if it has a value worth mapping
+fn source_value(msg: &MidiMessage) -> Option<(u32, u32)> {
+    match msg {
+        MidiMessage::ControlChange { value, .. }
+        | MidiMessage::ChannelAftertouch { value, .. }
+        | MidiMessage::PolyAftertouch { value, .. } => Some((u32::from(*value), MAX_7_BIT)),
+
+        MidiMessage::PitchBendChange { value, .. }
+        | MidiMessage::ControlChange14 { value, .. }
+        | MidiMessage::Nrpn { value, .. }
+        | MidiMessage::Rpn { value, .. } => Some((u32::from(*value), MAX_14_BIT)),
+
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::macros::event_matching::Event;
+    use crate::macros::mapping::{ValueMap, Curve};
+    use crate::midi::{MidiEvent, MidiMessage};
+
+    fn value_map(from: Option<(u32, u32)>, to: (i64, i64), curve: Curve) -> ValueMap {
+        ValueMap {
+            variable: "volume".to_string(),
+            from,
+            to,
+            curve,
+            interval: Duration::from_millis(50),
+            deduplicate: true
+        }
+    }
+
+    fn cc(value: u8) -> Event {
+        Event::Midi(MidiEvent::new(
+            "faders",
+            MidiMessage::ControlChange { channel: 0, control: 7, value }
+        ))
+    }
+
+    #[test]
+    fn maps_values_linearly() {
+        let map = value_map(None, (0, 100), Curve::Linear);
+
+        assert_eq!(map.map(&cc(0)), Some(0));
+        assert_eq!(map.map(&cc(64)), Some(50));
+        assert_eq!(map.map(&cc(127)), Some(100));
+
+        let pitch_bend = Event::Midi(MidiEvent::new(
+            "keys",
+            MidiMessage::PitchBendChange { channel: 0, value: 8192 }
+        ));
+
+        assert_eq!(value_map(None, (-12, 12), Curve::Linear).map(&pitch_bend), Some(0));
+
+        // Inverted
+        assert_eq!(value_map(None, (100, 0), Curve::Linear).map(&cc(127)), Some(0));
+    }
+
+    #[test]
+    fn clamps_values_outside_of_the_source_range() {
+        let map = value_map(Some((10, 110)), (0, 10), Curve::Linear);
+
+        assert_eq!(map.map(&cc(0)), Some(0));
+        assert_eq!(map.map(&cc(60)), Some(5));
+        assert_eq!(map.map(&cc(127)), Some(10));
+    }
+
+    #[test]
+    fn maps_values_logarithmically() {
+        let map = value_map(Some((0, 100)), (20, 20000), Curve::Logarithmic);
+
+        assert_eq!(map.map(&cc(0)), Some(20));
+        assert_eq!(map.map(&cc(50)), Some(632));
+        assert_eq!(map.map(&cc(100)), Some(20000));
+    }
+
+    #[test]
+    fn only_maps_events_with_values() {
+        let map = value_map(None, (0, 100), Curve::Linear);
+
+        let note_on = Event::Midi(MidiEvent::new(
+            "keys",
+            MidiMessage::NoteOn { channel: 0, key: 60, velocity: 100 }
+        ));
+
+        assert_eq!(map.map(&note_on), None);
+        assert_eq!(map.map(&Event::Other), None);
+    }
+}
@@ -1,15 +1,18 @@
 pub mod midi;
+pub mod variable;
 
 use midi::MidiPrecondition;
+use variable::VariablePrecondition;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct Precondition {
     pub invert: bool,
     pub condition: PreconditionType
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum PreconditionType {
     Midi(MidiPrecondition),
+    Variable(VariablePrecondition),
     Other // Placeholder
 }
\ No newline at end of file
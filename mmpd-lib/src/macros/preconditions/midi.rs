@@ -1,7 +1,11 @@
-use crate::match_checker::NumMatch;
+use crate::match_checker::{NumMatch, StringMatcher};
+
+const MAX_CHANNEL: u32 = 15;
+const MAX_7_BIT: u32 = 127;
+const MAX_14_BIT: u32 = 16383;
 
 /// Precondition to be checked against MidiState
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum MidiPrecondition {
     /// A note with a channel matching the channel matcher and the key matcher is currently held
     NoteOn { channel_match: NumMatch, key_match: NumMatch },
@@ -15,4 +19,65 @@ pub enum MidiPrecondition {
 
     /// A channel matching the channel matcher has a pitch bend value matching the value matcher
     PitchBend { channel_match: NumMatch, value_match: NumMatch },
-}
\ No newline at end of file
+
+    /// A high resolution control matching the channel and control matchers matches the value
+    /// matcher
+    Control14 { channel_match: NumMatch, control_match: NumMatch, value_match: NumMatch },
+
+    /// A non-registered parameter matching the channel and parameter matchers matches the value
+    /// matcher
+    Nrpn { channel_match: NumMatch, parameter_match: NumMatch, value_match: NumMatch },
+
+    /// A registered parameter matching the channel and parameter matchers matches the value
+    /// matcher
+    Rpn { channel_match: NumMatch, parameter_match: NumMatch, value_match: NumMatch },
+
+    /// The wrapped `condition` is satisfied by the state of a device whose name matches
+    /// `device_match`. Without this, a condition is checked against the combined state of all
+    /// devices.
+    Device { device_match: Box<StringMatcher>, condition: Box<MidiPrecondition> },
+}
+
+impl MidiPrecondition {
+    /// Lists the number matchers of this precondition that are about MIDI data with a limited
+    /// range, each with the name of its field and the highest value that data can have.
+    pub fn bounded_matchers(&self) -> Vec<(&'static str, &NumMatch, u32)> {
+        match self {
+            MidiPrecondition::NoteOn { channel_match, key_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("key", key_match, MAX_7_BIT)
+            ],
+
+            MidiPrecondition::Control { channel_match, control_match, value_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("control", control_match, MAX_7_BIT),
+                ("value", value_match, MAX_7_BIT)
+            ],
+
+            MidiPrecondition::Program { channel_match, program_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("program", program_match, MAX_7_BIT)
+            ],
+
+            MidiPrecondition::PitchBend { channel_match, value_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("value", value_match, MAX_14_BIT)
+            ],
+
+            MidiPrecondition::Control14 { channel_match, control_match, value_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("control", control_match, 31),
+                ("value", value_match, MAX_14_BIT)
+            ],
+
+            MidiPrecondition::Nrpn { channel_match, parameter_match, value_match }
+            | MidiPrecondition::Rpn { channel_match, parameter_match, value_match } => vec![
+                ("channel", channel_match, MAX_CHANNEL),
+                ("parameter", parameter_match, MAX_14_BIT),
+                ("value", value_match, MAX_14_BIT)
+            ],
+
+            MidiPrecondition::Device { condition, .. } => condition.bounded_matchers()
+        }
+    }
+}
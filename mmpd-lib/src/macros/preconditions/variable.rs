@@ -0,0 +1,71 @@
+use std::convert::TryFrom;
+use crate::match_checker::{MatchChecker, NumberMatcher, StringMatcher};
+use crate::state::Value;
+
+/// Precondition to be checked against a named variable in state. A variable that hasn't been set
+/// never matches.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VariablePrecondition {
+    pub name: String,
+    pub value_match: ValueMatcher
+}
+
+/// Matches the value of a variable
+#[derive(Debug, PartialEq, Clone)]
+pub enum ValueMatcher {
+    /// Matches integer values. Negative values never match, since number matchers can't express
+    /// them.
+    Number(NumberMatcher),
+
+    /// Matches the value as it would be formatted in a template, so it works on any type of value
+    String(Box<StringMatcher>),
+
+    /// Matches boolean values equal to this one
+    Bool(bool)
+}
+
+impl MatchChecker<Value> for ValueMatcher {
+    fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (ValueMatcher::Number(matcher), Value::Integer(i)) => {
+                u32::try_from(*i).is_ok_and(|i| matcher.matches(&i))
+            }
+
+            (ValueMatcher::String(matcher), value) => {
+                matcher.matches(&value.to_string().as_str())
+            }
+
+            (ValueMatcher::Bool(expected), Value::Bool(b)) => expected == b,
+
+            _ => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::macros::preconditions::variable::ValueMatcher;
+    use crate::match_checker::{MatchChecker, NumberMatcher, StringMatcher};
+    use crate::state::Value;
+
+    #[test]
+    fn matches_values_of_the_matching_type() {
+        let number = ValueMatcher::Number(NumberMatcher::Range { min: Some(1), max: Some(3) });
+        assert!(number.matches(&Value::Integer(2)));
+        assert!(!number.matches(&Value::Integer(4)));
+        assert!(!number.matches(&Value::Integer(-1)));
+        assert!(!number.matches(&Value::String("2".to_string())));
+
+        let string = ValueMatcher::String(Box::new(StringMatcher::Is("editing".to_string())));
+        assert!(string.matches(&Value::String("editing".to_string())));
+        assert!(!string.matches(&Value::String("media".to_string())));
+
+        let string = ValueMatcher::String(Box::new(StringMatcher::Is("3".to_string())));
+        assert!(string.matches(&Value::Integer(3)));
+
+        let bool = ValueMatcher::Bool(true);
+        assert!(bool.matches(&Value::Bool(true)));
+        assert!(!bool.matches(&Value::Bool(false)));
+        assert!(!bool.matches(&Value::Integer(1)));
+    }
+}
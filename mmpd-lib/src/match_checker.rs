@@ -4,6 +4,11 @@ pub trait MatchChecker<T> {
     fn matches(&self, val: &T) -> bool;
 }
 
+/// Implemented by matchers that can tell whether they match everything another matcher does
+pub trait Covers<T = Self> {
+    fn covers(&self, other: &T) -> bool;
+}
+
 #[derive(Clone, Debug)]
 pub enum StringMatcher {
     Is(String),
@@ -77,7 +82,7 @@ impl MatchChecker<&str> for StringMatcher {
 
 // For the time being this is hardcoded with u32, but could potentially be made more flexible with
 // a type parameter constrained to the PartialOrd trait.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum NumberMatcher {
     Any,
     Range { min: Option<u32>, max: Option<u32> },
@@ -117,6 +122,55 @@ impl MatchChecker<u32> for NumberMatcher {
     }
 }
 
+impl NumberMatcher {
+    /// Whether any number this matcher mentions is greater than `max`, such as a MIDI key number
+    /// above 127, meaning it can't match what it was meant to.
+    pub fn exceeds(&self, max: u32) -> bool {
+        match self {
+            NumberMatcher::Any => false,
+
+            NumberMatcher::Range { min, max: range_max } => {
+                min.iter().chain(range_max.iter()).any(|bound| *bound > max)
+            }
+
+            NumberMatcher::Val(a) => *a > max,
+            NumberMatcher::List(matchers) => matchers.iter().any(|m| m.exceeds(max))
+        }
+    }
+}
+
+impl NumberMatcher {
+    /// Lowest and highest number this matcher matches, if it matches a single range of numbers
+    fn bounds(&self) -> Option<(u32, u32)> {
+        match self {
+            NumberMatcher::Any => Some((u32::MIN, u32::MAX)),
+            NumberMatcher::Range { min, max } => {
+                Some((min.unwrap_or(u32::MIN), max.unwrap_or(u32::MAX)))
+            }
+            NumberMatcher::Val(a) => Some((*a, *a)),
+            NumberMatcher::List(_) => None
+        }
+    }
+}
+
+impl Covers for NumberMatcher {
+    /// A list is covered if all its items are, and covers what any of its items cover. Apart
+    /// from that, numbers are covered if they fall within the range this matcher matches.
+    fn covers(&self, other: &NumberMatcher) -> bool {
+        match (self, other) {
+            (_, NumberMatcher::List(others)) => others.iter().all(|o| self.covers(o)),
+            (NumberMatcher::List(matchers), _) => matchers.iter().any(|m| m.covers(other)),
+
+            _ => match (self.bounds(), other.bounds()) {
+                (Some((min, max)), Some((other_min, other_max))) => {
+                    min <= other_min && other_max <= max
+                }
+                _ => false
+            }
+        }
+    }
+}
+
 /// Convenience type for where a matcher is optional, allowing it to be used just like NumberMatcher
 pub type NumMatch = Option<NumberMatcher>;
 
@@ -130,9 +184,19 @@ impl MatchChecker<u32> for NumMatch {
     }
 }
 
+impl Covers for NumMatch {
+    fn covers(&self, other: &NumMatch) -> bool {
+        match (self, other) {
+            (None, _) => true,
+            (Some(matcher), None) => matcher.covers(&NumberMatcher::Any),
+            (Some(matcher), Some(other)) => matcher.covers(other)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::match_checker::{NumberMatcher, MatchChecker};
+    use crate::match_checker::{NumberMatcher, MatchChecker, Covers};
 
     #[test]
     fn number_matcher_any() {
@@ -222,10 +286,45 @@ mod tests {
         assert!(matcher.matches(&424242));
         assert!(matcher.matches(&u32::MAX));
     }
-}
-
-
-
-
 
+    #[test]
+    fn number_matcher_exceeds() {
+        assert!(!NumberMatcher::Any.exceeds(127));
+        assert!(!NumberMatcher::Val(127).exceeds(127));
+        assert!(NumberMatcher::Val(128).exceeds(127));
+        assert!(!NumberMatcher::Range { min: Some(100), max: None }.exceeds(127));
+        assert!(NumberMatcher::Range { min: Some(128), max: None }.exceeds(127));
+        assert!(NumberMatcher::Range { min: None, max: Some(200) }.exceeds(127));
+
+        assert!(NumberMatcher::List(vec![
+            NumberMatcher::Val(1),
+            NumberMatcher::Range { min: Some(10), max: Some(130) }
+        ]).exceeds(127));
+    }
 
+    #[test]
+    fn number_matcher_covers() {
+        let range = NumberMatcher::Range { min: Some(10), max: Some(20) };
+
+        assert!(NumberMatcher::Any.covers(&range));
+        assert!(!range.covers(&NumberMatcher::Any));
+        assert!(range.covers(&NumberMatcher::Val(10)));
+        assert!(!range.covers(&NumberMatcher::Val(21)));
+        assert!(range.covers(&NumberMatcher::Range { min: Some(12), max: Some(20) }));
+        assert!(!range.covers(&NumberMatcher::Range { min: Some(12), max: None }));
+        assert!(NumberMatcher::Range { min: None, max: None }.covers(&NumberMatcher::Any));
+        assert!(NumberMatcher::Val(3).covers(&NumberMatcher::Val(3)));
+        assert!(!NumberMatcher::Val(3).covers(&NumberMatcher::Val(4)));
+
+        let list = NumberMatcher::List(vec![range.clone(), NumberMatcher::Val(30)]);
+        assert!(list.covers(&NumberMatcher::Val(30)));
+        assert!(list.covers(
+            &NumberMatcher::List(vec![NumberMatcher::Val(15), NumberMatcher::Val(30)])
+        ));
+        assert!(!range.covers(&list));
+
+        assert!(None.covers(&Some(range.clone())));
+        assert!(!Some(range).covers(&None));
+        assert!(Some(NumberMatcher::Any).covers(&None));
+    }
+}
@@ -1,11 +1,30 @@
 pub mod adapters;
-pub use adapters::get_adapter;
+pub mod assembler;
+pub mod supervisor;
+pub use adapters::{get_adapter, get_output_adapter};
 use regex::Regex;
 use std::ops::Range;
 
+/// A MIDI message along with the name of the device it was received from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MidiEvent {
+    /// Name of the device the message came from. This is the name given to the device in the
+    /// config file, or the name of its MIDI port if it wasn't given one.
+    pub device: String,
+
+    /// The message itself
+    pub message: MidiMessage
+}
+
+impl MidiEvent {
+    pub fn new(device: &str, message: MidiMessage) -> MidiEvent {
+        MidiEvent { device: device.to_string(), message }
+    }
+}
+
 /// MidiMessage is a parsed MIDI message, structured to be easy to work with.
-/// It is parsed from 3 raw bytes of data.
-#[derive(Debug, Eq, PartialEq)]
+/// It is parsed from the raw bytes of a single MIDI message.
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum MidiMessage {
     /// Key released.
     /// channel: 0-15
@@ -46,20 +65,208 @@ pub enum MidiMessage {
     /// value: current position, 0-16,384 (14 bit)
     PitchBendChange { channel: u8, value: u16 },
 
-    // TODO: maybe implement these later
-    //TimingClock,
-    //Start,
-    //Continue,
-    //Stop,
+    /// High resolution control change, assembled from a pair of control changes: the most
+    /// significant byte on control 0-31, followed by the least significant byte on that control
+    /// number + 32. Only produced by `MessageAssembler`, when enabled for the device.
+    /// channel: 0-15
+    /// control: control number of the most significant byte, 0-31
+    /// value: 0-16,383 (14 bit)
+    ControlChange14 { channel: u8, control: u8, value: u16 },
+
+    /// Non-registered parameter number value, assembled from control changes 99 and 98
+    /// (parameter) and 6 and 38 (data entry). Only produced by `MessageAssembler`, when enabled for
+    /// the device.
+    /// channel: 0-15
+    /// parameter: 0-16,383 (14 bit)
+    /// value: 0-16,383 (14 bit)
+    Nrpn { channel: u8, parameter: u16, value: u16 },
+
+    /// Registered parameter number value, assembled from control changes 101 and 100
+    /// (parameter) and 6 and 38 (data entry). Only produced by `MessageAssembler`, when enabled for
+    /// the device.
+    /// channel: 0-15
+    /// parameter: 0-16,383 (14 bit)
+    /// value: 0-16,383 (14 bit)
+    Rpn { channel: u8, parameter: u16, value: u16 },
+
+    /// System exclusive message, used for manufacturer-specific data
+    /// data: message content, 7-bit bytes, excluding the SysEx start (0xF0) and end (0xF7) bytes
+    SysEx { data: Vec<u8> },
+
+    /// MIDI Time Code quarter frame, a piece of the current time code position
+    /// piece: which part of the time code this is, 0-7
+    /// value: value of that part, 0-15
+    MtcQuarterFrame { piece: u8, value: u8 },
+
+    /// Song position pointer, position in the song to continue playing from
+    /// position: number of MIDI beats (sixteenth notes) since the start of the song, 0-16,383
+    SongPosition { position: u16 },
+
+    /// A song or sequence was selected
+    /// song: song identifier, 0-127
+    SongSelect { song: u8 },
+
+    /// Request for analog synthesizers to tune their oscillators
+    TuneRequest,
+
+    /// Sent 24 times per quarter note when synchronisation is required
+    TimingClock,
+
+    /// Start playing the current sequence from the beginning
+    Start,
+
+    /// Continue playing the current sequence from where it was stopped
+    Continue,
+
+    /// Stop playing the current sequence
+    Stop,
+
+    /// Sent periodically by some devices to signal the connection is still alive
+    ActiveSensing,
+
+    /// Reset all receivers to their power-up status
+    Reset,
+
+    /// A button was pressed and released quickly. Only produced by MIDI state, which times
+    /// presses; see `PressTiming`.
+    /// channel: 0-15
+    /// button: the key or control that was tapped
+    Tap { channel: u8, button: Button },
+
+    /// A button was tapped twice in quick succession. Follows the `Tap` of the second tap.
+    /// channel: 0-15
+    /// button: the key or control that was tapped
+    DoubleTap { channel: u8, button: Button },
+
+    /// A button has been held down for a while, and still is.
+    /// channel: 0-15
+    /// button: the key or control being held
+    LongPress { channel: u8, button: Button },
+
+    /// A button is still being held down after a long press, sent repeatedly until it's released.
+    /// channel: 0-15
+    /// button: the key or control being held
+    /// count: how many times it repeated since the long press, starting at 1
+    HoldRepeat { channel: u8, button: Button, count: u32 },
+
+    /// A button was released, along with how long it was held down.
+    /// channel: 0-15
+    /// button: the key or control that was released
+    /// duration: how long it was held, in milliseconds
+    Release { channel: u8, button: Button, duration: u32 },
+
+    /// An endless encoder sending relative control changes was turned. Only produced for controls
+    /// set up as encoders; see `assembler::Encoder`.
+    /// channel: 0-15
+    /// control: 0-127
+    /// delta: how far it was turned; positive for clockwise, negative for counterclockwise
+    EncoderTurn { channel: u8, control: u8, delta: i8 },
 
     /// Catch-all for any non-implemented messages
     Other
 }
 
-/// Parses raw 3-byte MIDI messages into structured MIDI messages
+/// A button on a controller, which is either a key (or pad) sending note on and note off messages,
+/// or a control sending control changes when pressed and released
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Button {
+    /// key: 0-127
+    Note { key: u8 },
+
+    /// control: 0-127
+    Control { control: u8 }
+}
+
+/// Status byte starting a system exclusive message
+const SYSEX_START: u8 = 0xF0;
+
+/// Status byte ending a system exclusive message
+const SYSEX_END: u8 = 0xF7;
+
+impl MidiMessage {
+    /// Encodes this message back into raw MIDI bytes, as it would be sent to a MIDI device.
+    ///
+    /// Data bytes are masked to 7 bits so the output is always a valid message.
+    /// Returns None for `MidiMessage::Other`, since there is nothing known to encode, as well as
+    /// for messages assembled from multiple control changes or produced by timing presses, since
+    /// they aren't a single message.
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        Some(match self {
+            MidiMessage::NoteOff { channel, key, velocity } => {
+                vec![0x80 | (channel & 0x0F), key & 0x7F, velocity & 0x7F]
+            }
+
+            MidiMessage::NoteOn { channel, key, velocity } => {
+                vec![0x90 | (channel & 0x0F), key & 0x7F, velocity & 0x7F]
+            }
+
+            MidiMessage::PolyAftertouch { channel, key, value } => {
+                vec![0xA0 | (channel & 0x0F), key & 0x7F, value & 0x7F]
+            }
+
+            MidiMessage::ControlChange { channel, control, value } => {
+                vec![0xB0 | (channel & 0x0F), control & 0x7F, value & 0x7F]
+            }
+
+            MidiMessage::ProgramChange { channel, program } => {
+                vec![0xC0 | (channel & 0x0F), program & 0x7F]
+            }
+
+            MidiMessage::ChannelAftertouch { channel, value } => {
+                vec![0xD0 | (channel & 0x0F), value & 0x7F]
+            }
+
+            MidiMessage::PitchBendChange { channel, value } => {
+                vec![0xE0 | (channel & 0x0F), (value & 0x7F) as u8, ((value >> 7) & 0x7F) as u8]
+            }
+
+            MidiMessage::SysEx { data } => {
+                let mut bytes = Vec::with_capacity(data.len() + 2);
+                bytes.push(SYSEX_START);
+                bytes.extend(data.iter().map(|b| b & 0x7F));
+                bytes.push(SYSEX_END);
+                bytes
+            }
+
+            MidiMessage::MtcQuarterFrame { piece, value } => {
+                vec![0xF1, ((piece & 0x07) << 4) | (value & 0x0F)]
+            }
+
+            MidiMessage::SongPosition { position } => {
+                vec![0xF2, (position & 0x7F) as u8, ((position >> 7) & 0x7F) as u8]
+            }
+
+            MidiMessage::SongSelect { song } => vec![0xF3, song & 0x7F],
+            MidiMessage::TuneRequest => vec![0xF6],
+            MidiMessage::TimingClock => vec![0xF8],
+            MidiMessage::Start => vec![0xFA],
+            MidiMessage::Continue => vec![0xFB],
+            MidiMessage::Stop => vec![0xFC],
+            MidiMessage::ActiveSensing => vec![0xFE],
+            MidiMessage::Reset => vec![0xFF],
+
+            MidiMessage::ControlChange14 { .. }
+            | MidiMessage::Nrpn { .. }
+            | MidiMessage::Rpn { .. }
+            | MidiMessage::Tap { .. }
+            | MidiMessage::DoubleTap { .. }
+            | MidiMessage::LongPress { .. }
+            | MidiMessage::HoldRepeat { .. }
+            | MidiMessage::Release { .. }
+            | MidiMessage::EncoderTurn { .. }
+            | MidiMessage::Other => return None
+        })
+    }
+}
+
+/// Parses raw MIDI messages into structured MIDI messages
+///
+/// Channel messages are up to 3 bytes long. System messages (status 0xF0-0xFF) vary in length; a
+/// system exclusive message is expected to be complete, from its start byte up to its end byte,
+/// as delivered by the MIDI adapter.
 ///
 /// If there is an invalid amount of data available, or the most significant 4 bits of the first
-/// byte make no sense, returns None.
+/// byte make no sense, returns None. Undefined system status bytes parse as `MidiMessage::Other`.
 ///
 /// See the MIDI spec's summary of MIDI messages:
 /// https://www.midi.org/specifications-old/item/table-1-summary-of-midi-message
@@ -115,12 +322,51 @@ fn parse_message(bytes: &[u8]) -> Option<MidiMessage> {
                 | (((*bytes.get(2)? as u16 & 0x7Fu16) << 7) as u16),
         }),
 
-        0b1111 => Some(MidiMessage::Other),
+        0b1111 => parse_system_message(first_byte, bytes),
 
         _ => None
     }
 }
 
+/// Parses a system common, system real-time, or system exclusive message, the type of which is
+/// determined by the whole `status` byte rather than its most significant 4 bits.
+fn parse_system_message(status: u8, bytes: &[u8]) -> Option<MidiMessage> {
+    Some(match status {
+        SYSEX_START => MidiMessage::SysEx {
+            data: bytes[1..]
+                .iter()
+                .take_while(|b| **b != SYSEX_END)
+                .map(|b| b & 0x7F)
+                .collect()
+        },
+
+        0xF1 => {
+            let data = *bytes.get(1)?;
+
+            MidiMessage::MtcQuarterFrame {
+                piece: (data & 0x70) >> 4,
+                value: data & 0x0F
+            }
+        }
+
+        0xF2 => MidiMessage::SongPosition {
+            position: (*bytes.get(1)? as u16 & 0x7F) | ((*bytes.get(2)? as u16 & 0x7F) << 7)
+        },
+
+        0xF3 => MidiMessage::SongSelect { song: *bytes.get(1)? & 0x7F },
+        0xF6 => MidiMessage::TuneRequest,
+        0xF8 => MidiMessage::TimingClock,
+        0xFA => MidiMessage::Start,
+        0xFB => MidiMessage::Continue,
+        0xFC => MidiMessage::Stop,
+        0xFE => MidiMessage::ActiveSensing,
+        0xFF => MidiMessage::Reset,
+
+        // 0xF4, 0xF5, 0xF9, 0xFD are undefined, and 0xF7 should only appear at the end of SysEx
+        _ => MidiMessage::Other
+    })
+}
+
 /// Parses a string describing a note into MIDI note number(s)
 ///
 /// key_str should be in the format "<note name>[accidentals][octave]" where angle brackets denote
@@ -283,7 +529,74 @@ mod parse_message_tests {
         );
     }
 
-    // TODO more tests for other types of messages, as well as invalid messages
+    #[test]
+    fn parses_sysex() {
+        assert_eq!(
+            MidiMessage::SysEx { data: vec![0x00, 0x20, 0x29, 0x02] },
+            parse_message(&[0xF0, 0x00, 0x20, 0x29, 0x02, 0xF7]).unwrap()
+        );
+
+        // Empty
+        assert_eq!(
+            MidiMessage::SysEx { data: vec![] },
+            parse_message(&[0xF0, 0xF7]).unwrap()
+        );
+
+        // Missing end byte
+        assert_eq!(
+            MidiMessage::SysEx { data: vec![0x7D] },
+            parse_message(&[0xF0, 0x7D]).unwrap()
+        );
+    }
+
+    #[test]
+    fn parses_system_common_messages() {
+        assert_eq!(
+            MidiMessage::MtcQuarterFrame { piece: 3, value: 9 },
+            parse_message(&[0xF1, 0b0011_1001]).unwrap()
+        );
+
+        assert_eq!(
+            MidiMessage::SongPosition { position: 0x2001 },
+            parse_message(&[0xF2, 0x01, 0x40]).unwrap()
+        );
+
+        assert_eq!(
+            MidiMessage::SongSelect { song: 12 },
+            parse_message(&[0xF3, 12]).unwrap()
+        );
+
+        assert_eq!(MidiMessage::TuneRequest, parse_message(&[0xF6]).unwrap());
+    }
+
+    #[test]
+    fn parses_system_realtime_messages() {
+        assert_eq!(MidiMessage::TimingClock, parse_message(&[0xF8]).unwrap());
+        assert_eq!(MidiMessage::Start, parse_message(&[0xFA]).unwrap());
+        assert_eq!(MidiMessage::Continue, parse_message(&[0xFB]).unwrap());
+        assert_eq!(MidiMessage::Stop, parse_message(&[0xFC]).unwrap());
+        assert_eq!(MidiMessage::ActiveSensing, parse_message(&[0xFE]).unwrap());
+        assert_eq!(MidiMessage::Reset, parse_message(&[0xFF]).unwrap());
+    }
+
+    #[test]
+    fn parses_undefined_system_messages_as_other() {
+        for status in [0xF4u8, 0xF5, 0xF7, 0xF9, 0xFD].iter() {
+            assert_eq!(MidiMessage::Other, parse_message(&[*status]).unwrap());
+        }
+    }
+
+    #[test]
+    fn returns_none_for_incomplete_or_invalid_messages() {
+        assert_eq!(parse_message(&[]), None);
+        assert_eq!(parse_message(&[0x90, 60]), None);
+        assert_eq!(parse_message(&[0xC0]), None);
+        assert_eq!(parse_message(&[0xF2, 0x01]), None);
+        assert_eq!(parse_message(&[0xF3]), None);
+
+        // Data byte without status byte
+        assert_eq!(parse_message(&[0x40, 0x40]), None);
+    }
 
     #[test]
     fn disregards_msb_in_values() {
@@ -327,6 +640,87 @@ mod parse_message_tests {
     }
 }
 
+#[cfg(test)]
+mod to_bytes_tests {
+    use crate::midi::{MidiMessage, parse_message};
+
+    #[test]
+    fn encodes_channel_messages() {
+        assert_eq!(
+            MidiMessage::NoteOn { channel: 1, key: 60, velocity: 127 }.to_bytes(),
+            Some(vec![0x91, 60, 127])
+        );
+
+        assert_eq!(
+            MidiMessage::NoteOff { channel: 15, key: 0, velocity: 0 }.to_bytes(),
+            Some(vec![0x8F, 0, 0])
+        );
+
+        assert_eq!(
+            MidiMessage::ControlChange { channel: 0, control: 7, value: 100 }.to_bytes(),
+            Some(vec![0xB0, 7, 100])
+        );
+
+        assert_eq!(
+            MidiMessage::ProgramChange { channel: 2, program: 5 }.to_bytes(),
+            Some(vec![0xC2, 5])
+        );
+
+        assert_eq!(
+            MidiMessage::PitchBendChange { channel: 0, value: 0x2001 }.to_bytes(),
+            Some(vec![0xE0, 0x01, 0x40])
+        );
+    }
+
+    #[test]
+    fn round_trips_through_parse_message() {
+        let messages = vec![
+            MidiMessage::NoteOn { channel: 3, key: 64, velocity: 90 },
+            MidiMessage::PolyAftertouch { channel: 4, key: 64, value: 12 },
+            MidiMessage::ChannelAftertouch { channel: 5, value: 34 },
+            MidiMessage::PitchBendChange { channel: 6, value: 16383 },
+        ];
+
+        for msg in messages {
+            assert_eq!(parse_message(&msg.to_bytes().unwrap()), Some(msg));
+        }
+    }
+
+    #[test]
+    fn encodes_sysex_with_start_and_end_bytes() {
+        assert_eq!(
+            MidiMessage::SysEx { data: vec![0x00, 0x20, 0x29, 0xFF] }.to_bytes(),
+            Some(vec![0xF0, 0x00, 0x20, 0x29, 0x7F, 0xF7])
+        );
+    }
+
+    #[test]
+    fn round_trips_system_messages_through_parse_message() {
+        let messages = vec![
+            MidiMessage::SysEx { data: vec![0x01, 0x02] },
+            MidiMessage::MtcQuarterFrame { piece: 7, value: 15 },
+            MidiMessage::SongPosition { position: 1234 },
+            MidiMessage::SongSelect { song: 3 },
+            MidiMessage::TuneRequest,
+            MidiMessage::TimingClock,
+            MidiMessage::Start,
+            MidiMessage::Continue,
+            MidiMessage::Stop,
+            MidiMessage::ActiveSensing,
+            MidiMessage::Reset,
+        ];
+
+        for msg in messages {
+            assert_eq!(parse_message(&msg.to_bytes().unwrap()), Some(msg));
+        }
+    }
+
+    #[test]
+    fn does_not_encode_other_messages() {
+        assert_eq!(MidiMessage::Other.to_bytes(), None);
+    }
+}
+
 #[cfg(test)]
 mod parse_key_from_str_tests {
     use crate::midi::parse_keys_from_str;
@@ -362,11 +756,11 @@ mod parse_key_from_str_tests {
 
     #[test]
     fn returns_nothing_when_resulting_note_is_not_in_midi_range() {
-        assert_eq!(parse_keys_from_str("A-2"), vec![]);
-        assert_eq!(parse_keys_from_str("G#-2"), vec![]);
-        assert_eq!(parse_keys_from_str("A9"), vec![]);
-        assert_eq!(parse_keys_from_str("A#9"), vec![]);
-        assert_eq!(parse_keys_from_str("Bb9"), vec![]);
+        assert_eq!(parse_keys_from_str("A-2"), Vec::<u8>::new());
+        assert_eq!(parse_keys_from_str("G#-2"), Vec::<u8>::new());
+        assert_eq!(parse_keys_from_str("A9"), Vec::<u8>::new());
+        assert_eq!(parse_keys_from_str("A#9"), Vec::<u8>::new());
+        assert_eq!(parse_keys_from_str("Bb9"), Vec::<u8>::new());
     }
 
     #[test]
@@ -407,6 +801,6 @@ mod parse_key_from_str_tests {
     #[test]
     fn returns_nothing_when_format_is_invalid_or_unsupported() {
         // Gobbledygook
-        assert_eq!(parse_keys_from_str("NYERGH"), vec![]);
+        assert_eq!(parse_keys_from_str("NYERGH"), Vec::<u8>::new());
     }
 }
@@ -1,7 +1,12 @@
 use std::sync::mpsc::SyncSender;
 use std::thread;
-use crate::midi::adapters::midir::Midir;
+use std::fmt::{self, Display, Formatter};
+use crate::midi::adapters::midir::{Midir, MidirOutput};
 use crate::macros::event_matching::Event;
+use crate::midi::assembler::AssemblerOptions;
+
+#[cfg(test)]
+use mockall::automock;
 
 mod midir;
 
@@ -18,30 +23,119 @@ pub fn get_adapter() -> Option<Box<dyn MidiAdapter>> {
     }
 }
 
+/// Provides an adapter implementing MidiOutputAdapter based on platform
+/// At the moment it just provides the Midir implementation.
+pub fn get_output_adapter() -> Option<Box<dyn MidiOutputAdapter>> {
+    let adapter = MidirOutput::new();
+
+    match adapter {
+        Some(a) => Some(Box::new(a)),
+        None => None
+    }
+}
+
 /// Adapters implementing this trait can be used by the binary to get a list of available
 /// MIDI inputs, as well as instructed to start listening for MIDI messages on a port matching
 /// a pattern.
-pub trait MidiAdapter {
+#[cfg_attr(test, automock)]
+pub trait MidiAdapter: Send {
     /// Queries the implementation for available MIDI inputs and returns them as a list of Strings.
-    /// These strings can be used to match a pattern against in `start_listening`'s port_pattern
-    /// parameter.
+    /// These strings can be passed to `start_listening` as its port_name parameter.
     fn list_ports(&self) -> Vec<String>;
 
-    /// Instructs the implementation to start a thread listening for incoming MIDI messages,
-    /// providing a SyncSender to send received messages down. The implementation must present
-    /// incoming messages as MidiMessage structs, and can use the `parse_message` function to
-    /// convert from raw bytes to this struct.
+    /// Instructs the implementation to start a thread listening for incoming MIDI messages on the
+    /// port named exactly `port_name`, as listed by `list_ports`, providing a SyncSender to send
+    /// received messages down. The implementation must present incoming messages as MidiEvent
+    /// structs tagged with `device_name`, and can use the `parse_message` function to convert from
+    /// raw bytes to a MidiMessage.
+    ///
+    /// Incoming messages must also be passed through a `MessageAssembler` configured with
+    /// `assembler_options`, sending any messages it synthesizes right after the message that
+    /// completed them.
+    ///
+    /// This may be called more than once to listen to several ports at the same time, each on
+    /// its own thread.
     ///
-    /// Returns None if for any reason we cannot start listening; otherwise returns a thread join
-    /// handle.
+    /// Returns None if for any reason we cannot start listening, including the port failing to
+    /// open; otherwise returns a thread join handle.
     fn start_listening(
         &mut self,
-        port_pattern: &str,
+        port_name: &str,
+        device_name: &str,
+        assembler_options: AssemblerOptions,
+        tx: SyncSender<Event>
+    ) -> Option<thread::JoinHandle<()>>;
+
+    /// Instructs the implementation to create a virtual input port named `port_name`, which other
+    /// applications can connect to and send MIDI messages to, and to start a thread listening for
+    /// incoming messages on it. Incoming messages are processed the same way as in
+    /// `start_listening`, tagged with `device_name`.
+    ///
+    /// The port is closed by `stop_listening`, or by `stop_listening_to` with `port_name`.
+    ///
+    /// Returns None if the port can't be created, including on platforms that don't support
+    /// virtual ports; otherwise returns a thread join handle.
+    fn start_virtual_port(
+        &mut self,
+        port_name: &str,
+        device_name: &str,
+        assembler_options: AssemblerOptions,
         tx: SyncSender<Event>
     ) -> Option<thread::JoinHandle<()>>;
 
-    /// Instructs the implementation to abort the thread on which it is listening for incoming
+    /// Instructs the implementation to abort all threads on which it is listening for incoming
     /// messages, if any.
-    fn stop_listening(&self);
+    fn stop_listening(&mut self);
+
+    /// Instructs the implementation to abort the threads listening for incoming messages on
+    /// `port_name`, as passed to `start_listening`, if any.
+    fn stop_listening_to(&mut self, port_name: &str);
+}
+
+/// Adapters implementing this trait can send raw MIDI messages to MIDI output ports.
+#[cfg_attr(test, automock)]
+pub trait MidiOutputAdapter {
+    /// Queries the implementation for available MIDI outputs and returns them as a list of
+    /// Strings. These strings can be used to match a pattern against in `send`'s port_pattern
+    /// parameter.
+    fn list_ports(&self) -> Vec<String>;
+
+    /// Sends a raw MIDI message to the first output port whose name contains `port_pattern`.
+    ///
+    /// The implementation is expected to keep the connection to a port open after first use, so
+    /// subsequent messages to the same port don't need to reconnect.
+    fn send(&self, port_pattern: &str, message: &[u8]) -> MidiOutputResult;
+}
+
+/// Result type for MidiOutputAdapter functions indicating whether they worked correctly
+pub type MidiOutputResult = Result<(), MidiOutputError>;
+
+/// Errors that may occur when trying to use a MidiOutputAdapter
+#[derive(Debug)]
+pub enum MidiOutputError {
+    /// No output port could be found matching the given pattern
+    PortNotFound(
+        /// The pattern that didn't match any port
+        String
+    ),
+
+    /// Any other error
+    Other(
+        /// Description of the error
+        String
+    )
 }
 
+impl Display for MidiOutputError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MidiOutputError::PortNotFound(pattern) => {
+                write!(f, "MIDI Output Error: No output port found matching '{}'", pattern)
+            }
+
+            MidiOutputError::Other(description) => {
+                write!(f, "MIDI Output Error: {}", description)
+            }
+        }
+    }
+}
@@ -1,17 +1,19 @@
-use midir::{MidiInput, MidiInputPort};
+use midir::{MidiInput, MidiInputPort, MidiOutput, MidiOutputConnection};
+use std::collections::HashMap;
 use std::sync::mpsc::SyncSender;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
-use crate::midi::adapters::MidiAdapter;
-use crate::midi::parse_message;
+use crate::midi::adapters::{MidiAdapter, MidiOutputAdapter, MidiOutputResult, MidiOutputError};
+use crate::midi::{parse_message, MidiEvent};
+use crate::midi::assembler::{AssemblerOptions, MessageAssembler};
 use crate::macros::event_matching::Event;
 
 /// Handle for interfacing with the implementation from outside
 pub struct Midir {
-    /// Whether we are currently listening for incoming messages
-    /// Set to false to stop.
-    active: Arc<Mutex<bool>>,
+    /// Names of the ports we are currently listening on, each with a flag for whether its thread
+    /// should keep listening for incoming messages. Set a flag to false to stop that thread.
+    listeners: Vec<(String, Arc<Mutex<bool>>)>,
 }
 
 /// Client name provided to Midir library when creating a new instance
@@ -21,27 +23,21 @@ impl Midir {
     /// Creates a new instance of Midir
     pub fn new() -> Option<impl MidiAdapter> {
         Some(Midir {
-            active: Arc::new(Mutex::new(false)),
+            listeners: vec![],
         })
     }
 
-    /// Searches available MIDI inputs for a port which' name includes the contents of `pattern`.
-    /// If no such port can be found, or the Midir library can't instantiate, returns None,
-    /// otherwise returns a MidiInputPort.
+    /// Searches available MIDI inputs for the port named exactly `port_name`, as listed by
+    /// `list_ports`. If no such port can be found, or the Midir library can't instantiate,
+    /// returns None, otherwise returns a MidiInputPort.
     ///
-    /// If more than one MIDI input port matches the pattern, the first one encountered will be
-    /// used.
-    fn get_port(&self, pattern: &str) -> Option<MidiInputPort> {
-        let midi_in = MidiInput::new(CLIENT_NAME).ok()?;
-
+    /// Names are compared exactly rather than by pattern, so that a port named "X" doesn't open
+    /// "X 2" instead.
+    fn get_port(&self, midi_in: &MidiInput, port_name: &str) -> Option<MidiInputPort> {
         midi_in
             .ports()
             .iter()
-            .find(|p|
-                midi_in.port_name(p)
-                    .unwrap_or(String::from(""))
-                    .contains(pattern)
-            )
+            .find(|p| midi_in.port_name(p).is_ok_and(|name| name == port_name))
             .cloned()
     }
 }
@@ -49,8 +45,7 @@ impl Midir {
 impl MidiAdapter for Midir {
 
     /// Queries Midir for available MIDI inputs and returns them as a list of Strings.
-    /// These strings can be used to match a pattern against in `start_listening`'s port_pattern
-    /// parameter.
+    /// These strings can be passed to `start_listening` as its port_name parameter.
     fn list_ports(&self) -> Vec<String> {
         let midi_in = MidiInput::new(CLIENT_NAME);
 
@@ -72,70 +67,229 @@ impl MidiAdapter for Midir {
     }
 
 
-    /// Starts a thread listening for incoming MIDI messages, sending incoming MIDI messages as
-    /// MidiMessage structs along tx.
+    /// Starts a thread listening for incoming MIDI messages on the port named `port_name`,
+    /// sending incoming MIDI messages along tx as MidiEvent structs tagged with `device_name`.
+    /// Any messages synthesized by a `MessageAssembler` set up with `assembler_options` are sent
+    /// as well.
     ///
-    /// Returns None if for any reason we cannot start listening; otherwise returns a thread join
-    /// handle. Further returns None if we're already listening.
+    /// Each call starts a separate thread, so several ports can be listened to at once.
+    ///
+    /// Returns None if for any reason we cannot start listening, including the port failing to
+    /// open; otherwise returns a thread join handle.
     fn start_listening(
         &mut self,
-        port_pattern: &str,
+        port_name: &str,
+        device_name: &str,
+        assembler_options: AssemblerOptions,
         tx: SyncSender<Event>,
     ) -> Option<thread::JoinHandle<()>> {
-        let active = Arc::clone(&self.active);
-        let mut is_active = active.lock().unwrap();
-        if *is_active {
-            return None;
-        }
-        *is_active = true;
+        let midi_in = MidiInput::new(CLIENT_NAME).ok()?;
+        let port = self.get_port(&midi_in, port_name)?;
+        let forward = forward_messages(device_name, assembler_options, tx);
+
+        // The connection is made before starting the thread, so failing to open the port can be
+        // reported, and the port is tried again later.
+        let connection = midi_in.connect(&port, port_name, forward, ()).ok()?;
+
+        let active = Arc::new(Mutex::new(true));
+        self.listeners.push((port_name.to_string(), Arc::clone(&active)));
 
-        let active = Arc::clone(&self.active);
+        let handle = thread::spawn(move || {
+            // Midir unsubscribes the callback when the connection is dropped, so it's kept until
+            // stop_listening() or stop_listening_to() is called
+            let _connection = connection;
+            wait_while_active(&active);
+        });
+
+        Some(handle)
+    }
 
-        let tx = tx.clone();
-        let port = self.get_port(&port_pattern)?;
+
+    /// Creates a virtual input port named `port_name` and starts a thread listening for incoming
+    /// MIDI messages on it, the same way as `start_listening` does for an existing port.
+    ///
+    /// Virtual ports are supported by Midir on every platform except Windows. Returns None if the
+    /// port can't be created.
+    #[cfg(unix)]
+    fn start_virtual_port(
+        &mut self,
+        port_name: &str,
+        device_name: &str,
+        assembler_options: AssemblerOptions,
+        tx: SyncSender<Event>,
+    ) -> Option<thread::JoinHandle<()>> {
+        use midir::os::unix::VirtualInput;
 
         let midi_in = MidiInput::new(CLIENT_NAME).ok()?;
+        let forward = forward_messages(device_name, assembler_options, tx);
+
+        // As with `start_listening`, the port is created before starting the thread, so failing
+        // to create it can be reported.
+        let connection = midi_in.create_virtual(port_name, forward, ()).ok()?;
+
+        let active = Arc::new(Mutex::new(true));
+        self.listeners.push((port_name.to_string(), Arc::clone(&active)));
 
         let handle = thread::spawn(move || {
-            let port_name = midi_in
-                .port_name(&port)
-                .unwrap_or(String::from("(unknown port)"));
-
-            // Assignment here is necessary to keep the connection alive, since Midir unsubscribes
-            // the callback when _connection is destroyed. If it wasn't assigned to anything, it
-            // would be destroyed as soon as the `midi_in.connect` call concludes, not when the
-            // scope of this thread ends.
-            let _connection = midi_in.connect(
-                &port,
-                port_name.as_str(),
-                move |_, bytes, _| {
-                    if let Some(msg) = parse_message(bytes) {
-                        let _ = tx.send(Event::Midi(msg));
-                    }
-                },
-                (),
-            );
-
-            // Keep the thread alive until stop() is called
-            loop {
-                thread::sleep(Duration::from_micros(100));
-                let is_active = active.lock().unwrap();
-
-                if !*is_active {
-                    break;
-                }
-            }
+            // Keeps the port open until stop_listening() or stop_listening_to() is called
+            let _connection = connection;
+            wait_while_active(&active);
         });
 
         Some(handle)
     }
 
+    /// Virtual ports aren't supported by Midir on Windows, so this always returns None.
+    #[cfg(not(unix))]
+    fn start_virtual_port(
+        &mut self,
+        _port_name: &str,
+        _device_name: &str,
+        _assembler_options: AssemblerOptions,
+        _tx: SyncSender<Event>,
+    ) -> Option<thread::JoinHandle<()>> {
+        None
+    }
 
-    /// Instructs the implementation to abort the thread on which it is listening for incoming
+    /// Instructs the implementation to abort all threads on which it is listening for incoming
     /// messages, if any.
-    fn stop_listening(&self) {
-        let active = Arc::clone(&self.active);
-        let mut is_active = active.lock().unwrap();
-        *is_active = false;
+    fn stop_listening(&mut self) {
+        for (_, active) in self.listeners.drain(..) {
+            *active.lock().unwrap() = false;
+        }
+    }
+
+    /// Instructs the implementation to abort the threads listening on `port_name`, if any.
+    fn stop_listening_to(&mut self, port_name: &str) {
+        self.listeners.retain(|(listener_name, active)| {
+            if listener_name != port_name {
+                return true;
+            }
+
+            *active.lock().unwrap() = false;
+            false
+        });
+    }
+}
+
+/// Builds a Midir input callback that parses incoming messages and sends them along `tx` as
+/// MidiEvent structs tagged with `device_name`, followed by any messages synthesized by a
+/// `MessageAssembler` set up with `assembler_options`.
+fn forward_messages(
+    device_name: &str,
+    assembler_options: AssemblerOptions,
+    tx: SyncSender<Event>
+) -> impl FnMut(u64, &[u8], &mut ()) + Send + 'static {
+    let mut assembler = MessageAssembler::new(assembler_options);
+    let device_name = device_name.to_string();
+
+    move |_, bytes, _| {
+        if let Some(msg) = parse_message(bytes) {
+            let assembled_msg = assembler.process(&msg);
+            let _ = tx.send(Event::Midi(MidiEvent::new(&device_name, msg)));
+
+            if let Some(assembled_msg) = assembled_msg {
+                let _ = tx.send(Event::Midi(MidiEvent::new(&device_name, assembled_msg)));
+            }
+        }
+    }
+}
+
+/// Blocks the current thread until `active` is set to false
+fn wait_while_active(active: &Mutex<bool>) {
+    loop {
+        thread::sleep(Duration::from_micros(100));
+        let is_active = active.lock().unwrap();
+
+        if !*is_active {
+            break;
+        }
+    }
+}
+
+/// Handle for sending MIDI messages to output ports through Midir
+pub struct MidirOutput {
+    /// Open connections to output ports, keyed by the port pattern they were opened with
+    connections: Mutex<HashMap<String, MidiOutputConnection>>,
+}
+
+impl MidirOutput {
+    /// Creates a new instance of MidirOutput
+    pub fn new() -> Option<MidirOutput> {
+        Some(MidirOutput {
+            connections: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Opens a connection to the first output port which' name includes the contents of
+    /// `pattern`.
+    fn connect(&self, pattern: &str) -> Result<MidiOutputConnection, MidiOutputError> {
+        let midi_out = MidiOutput::new(CLIENT_NAME)
+            .map_err(|e| MidiOutputError::Other(e.to_string()))?;
+
+        let port = midi_out
+            .ports()
+            .iter()
+            .find(|p|
+                midi_out.port_name(p)
+                    .unwrap_or(String::from(""))
+                    .contains(pattern)
+            )
+            .cloned()
+            .ok_or_else(|| MidiOutputError::PortNotFound(pattern.to_string()))?;
+
+        let port_name = midi_out
+            .port_name(&port)
+            .unwrap_or(String::from("(unknown port)"));
+
+        midi_out
+            .connect(&port, port_name.as_str())
+            .map_err(|e| MidiOutputError::Other(e.to_string()))
+    }
+}
+
+impl MidiOutputAdapter for MidirOutput {
+    /// Queries Midir for available MIDI outputs and returns them as a list of Strings.
+    fn list_ports(&self) -> Vec<String> {
+        let midi_out = MidiOutput::new(CLIENT_NAME);
+
+        if let Err(_e) = midi_out {
+            return Vec::new();
+        }
+
+        let midi_out = midi_out.unwrap();
+
+        midi_out
+            .ports()
+            .iter()
+            .map(|p| {
+                midi_out
+                    .port_name(p)
+                    .unwrap_or(String::from("(unknown port)"))
+            })
+            .collect()
+    }
+
+    /// Sends `message` to the output port matching `port_pattern`, connecting to it first if this
+    /// is the first message sent to it.
+    ///
+    /// If sending fails on an existing connection, the connection is dropped, so that the next
+    /// message attempts to connect again (for example after the device was reconnected).
+    fn send(&self, port_pattern: &str, message: &[u8]) -> MidiOutputResult {
+        let mut connections = self.connections.lock().unwrap();
+
+        if !connections.contains_key(port_pattern) {
+            let connection = self.connect(port_pattern)?;
+            connections.insert(port_pattern.to_string(), connection);
+        }
+
+        let result = connections.get_mut(port_pattern).unwrap().send(message);
+
+        if let Err(e) = result {
+            connections.remove(port_pattern);
+            return Err(MidiOutputError::Other(e.to_string()));
+        }
+
+        Ok(())
     }
 }
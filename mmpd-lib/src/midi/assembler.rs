@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+use crate::match_checker::{MatchChecker, NumMatch, NumberMatcher};
+use crate::midi::MidiMessage;
+
+/// Highest control number that is the most significant byte of a 14-bit control change pair.
+/// Controls 0-31 are paired with controls 32-63 for their least significant byte.
+const MAX_14_BIT_MSB_CONTROL: u8 = 31;
+
+/// Offset between a 14-bit control's MSB control number and its LSB control number
+const LSB_CONTROL_OFFSET: u8 = 32;
+
+const NRPN_PARAMETER_MSB: u8 = 99;
+const NRPN_PARAMETER_LSB: u8 = 98;
+const RPN_PARAMETER_MSB: u8 = 101;
+const RPN_PARAMETER_LSB: u8 = 100;
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+
+/// Value of both RPN parameter bytes that deselects any selected parameter ("RPN null")
+const RPN_NULL: u8 = 127;
+
+/// Which kinds of multi-message values a `MessageAssembler` should put together, and which
+/// controls are encoders. All are disabled by default, since devices that don't use them may send
+/// control changes on the same control numbers for unrelated purposes.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AssemblerOptions {
+    /// Combine control changes on controls 0-31 (MSB) and 32-63 (LSB) into
+    /// `MidiMessage::ControlChange14`
+    pub control_change_14: bool,
+
+    /// Combine control changes 99/98 (parameter) and 6/38 (data entry) into `MidiMessage::Nrpn`
+    pub nrpn: bool,
+
+    /// Combine control changes 101/100 (parameter) and 6/38 (data entry) into `MidiMessage::Rpn`
+    pub rpn: bool,
+
+    /// Controls that are endless encoders, whose control changes are decoded into
+    /// `MidiMessage::EncoderTurn`
+    pub encoders: Vec<Encoder>
+}
+
+/// A control that is an endless encoder, sending how far it was turned rather than a position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Encoder {
+    /// Which channels the encoder sends on. If `None`, any channel.
+    pub channel_match: NumMatch,
+
+    /// Which control numbers are encoders
+    pub control_match: NumberMatcher,
+
+    /// How the encoder encodes how far it was turned
+    pub mode: EncoderMode
+}
+
+/// Ways encoders encode how far they were turned in a control change value. Encoders differ in
+/// which of these they use; the device's manual should say.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncoderMode {
+    /// 1-63 are clockwise, 127 down to 64 are counterclockwise (127 being -1)
+    TwosComplement,
+
+    /// 65-127 are clockwise, 63 down to 0 are counterclockwise (63 being -1); 64 is no change
+    BinaryOffset,
+
+    /// 1-63 are clockwise, 65-127 are counterclockwise (65 being -1); the 64 bit is the sign
+    SignMagnitude
+}
+
+impl EncoderMode {
+    /// Decodes a control change `value` into how far the encoder was turned
+    pub fn decode(&self, value: u8) -> i8 {
+        let value = value & 0x7F;
+
+        match self {
+            EncoderMode::TwosComplement if value >= 64 => (value as i16 - 128) as i8,
+            EncoderMode::TwosComplement => value as i8,
+            EncoderMode::BinaryOffset => (value as i16 - 64) as i8,
+            EncoderMode::SignMagnitude if value & 0x40 != 0 => -((value & 0x3F) as i8),
+            EncoderMode::SignMagnitude => value as i8
+        }
+    }
+}
+
+impl Encoder {
+    fn matches(&self, channel: u8, control: u8) -> bool {
+        self.channel_match.matches(&u32::from(channel))
+            && self.control_match.matches(&u32::from(control))
+    }
+}
+
+impl AssemblerOptions {
+    /// Whether any of the options is enabled, meaning an assembler would do anything
+    pub fn any_enabled(&self) -> bool {
+        self.control_change_14 || self.nrpn || self.rpn || !self.encoders.is_empty()
+    }
+
+    /// Combines two sets of options, enabling anything that is enabled in either, and including
+    /// the encoders of both
+    pub fn merge(&self, other: &AssemblerOptions) -> AssemblerOptions {
+        AssemblerOptions {
+            control_change_14: self.control_change_14 || other.control_change_14,
+            nrpn: self.nrpn || other.nrpn,
+            rpn: self.rpn || other.rpn,
+            encoders: self.encoders.iter().chain(other.encoders.iter()).cloned().collect()
+        }
+    }
+}
+
+/// Which kind of parameter is selected for data entry on a channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParameterKind {
+    Nrpn,
+    Rpn
+}
+
+/// Parameter number being assembled from its MSB and LSB control changes
+#[derive(Debug, Default)]
+struct ParameterSelection {
+    kind: Option<ParameterKind>,
+    msb: Option<u8>,
+    lsb: Option<u8>
+}
+
+/// Assembly progress for a single MIDI channel
+#[derive(Debug, Default)]
+struct ChannelState {
+    /// Last received MSB for 14-bit controls, keyed by MSB control number
+    control_msbs: HashMap<u8, u8>,
+
+    /// Currently selected (N)RPN parameter
+    parameter: ParameterSelection,
+
+    /// Last received data entry MSB for the selected parameter
+    data_entry_msb: Option<u8>
+}
+
+/// Stateful converter that watches incoming control change messages from a single device and
+/// puts together values that are spread over multiple messages: 14-bit control changes, NRPN, and
+/// RPN. It also decodes control changes from encoders into how far they were turned.
+///
+/// It sits between a MIDI adapter and the event bus: every incoming message is still passed on as
+/// is, and `process` returns an additional synthesized message whenever a value is complete.
+///
+/// Values are considered complete when their least significant byte arrives, which by convention
+/// is sent after the most significant byte. This way a single knob movement results in a single
+/// synthesized message.
+pub struct MessageAssembler {
+    options: AssemblerOptions,
+    channels: HashMap<u8, ChannelState>
+}
+
+impl MessageAssembler {
+    pub fn new(options: AssemblerOptions) -> MessageAssembler {
+        MessageAssembler {
+            options,
+            channels: HashMap::new()
+        }
+    }
+
+    /// Processes an incoming message, returning a synthesized message if `msg` completes one.
+    /// Messages that aren't control changes are ignored.
+    pub fn process(&mut self, msg: &MidiMessage) -> Option<MidiMessage> {
+        if !self.options.any_enabled() {
+            return None;
+        }
+
+        let (channel, control, value) = match msg {
+            MidiMessage::ControlChange { channel, control, value } => (*channel, *control, *value),
+            _ => return None
+        };
+
+        // An encoder's control changes are only about the encoder
+        if let Some(encoder) = self.options.encoders.iter().find(|e| e.matches(channel, control)) {
+            let delta = encoder.mode.decode(value);
+
+            return if delta == 0 {
+                None
+            } else {
+                Some(MidiMessage::EncoderTurn { channel, control, delta })
+            };
+        }
+
+        let options = &self.options;
+        let state = self.channels.entry(channel).or_default();
+
+        match control {
+            NRPN_PARAMETER_MSB | NRPN_PARAMETER_LSB if options.nrpn => {
+                state.select_parameter(ParameterKind::Nrpn, control == NRPN_PARAMETER_MSB, value);
+                None
+            }
+
+            RPN_PARAMETER_MSB | RPN_PARAMETER_LSB if options.rpn => {
+                state.select_parameter(ParameterKind::Rpn, control == RPN_PARAMETER_MSB, value);
+                None
+            }
+
+            DATA_ENTRY_MSB | DATA_ENTRY_LSB if state.parameter_number().is_some() => {
+                if control == DATA_ENTRY_MSB {
+                    state.data_entry_msb = Some(value);
+                    return None;
+                }
+
+                let (kind, parameter) = state.parameter_number()?;
+                let value = combine(state.data_entry_msb?, value);
+
+                Some(match kind {
+                    ParameterKind::Nrpn => MidiMessage::Nrpn { channel, parameter, value },
+                    ParameterKind::Rpn => MidiMessage::Rpn { channel, parameter, value }
+                })
+            }
+
+            0..=MAX_14_BIT_MSB_CONTROL if options.control_change_14 => {
+                state.control_msbs.insert(control, value);
+                None
+            }
+
+            _ if options.control_change_14
+                && (LSB_CONTROL_OFFSET..=MAX_14_BIT_MSB_CONTROL + LSB_CONTROL_OFFSET)
+                    .contains(&control) => {
+
+                let msb_control = control - LSB_CONTROL_OFFSET;
+                let msb = *state.control_msbs.get(&msb_control)?;
+
+                Some(MidiMessage::ControlChange14 {
+                    channel,
+                    control: msb_control,
+                    value: combine(msb, value)
+                })
+            }
+
+            _ => None
+        }
+    }
+}
+
+impl ChannelState {
+    /// Updates the selected parameter with one of its bytes. Selecting a byte of a different kind
+    /// of parameter than currently selected starts a new selection.
+    fn select_parameter(&mut self, kind: ParameterKind, is_msb: bool, value: u8) {
+        if self.parameter.kind != Some(kind) {
+            self.parameter = ParameterSelection { kind: Some(kind), msb: None, lsb: None };
+        }
+
+        if is_msb {
+            self.parameter.msb = Some(value);
+        } else {
+            self.parameter.lsb = Some(value);
+        }
+
+        self.data_entry_msb = None;
+    }
+
+    /// The fully selected parameter, if both its bytes are known and it isn't the RPN null
+    /// parameter.
+    fn parameter_number(&self) -> Option<(ParameterKind, u16)> {
+        let kind = self.parameter.kind?;
+        let msb = self.parameter.msb?;
+        let lsb = self.parameter.lsb?;
+
+        if kind == ParameterKind::Rpn && msb == RPN_NULL && lsb == RPN_NULL {
+            return None;
+        }
+
+        Some((kind, combine(msb, lsb)))
+    }
+}
+
+/// Combines 7-bit most and least significant bytes into a 14-bit value
+fn combine(msb: u8, lsb: u8) -> u16 {
+    ((msb as u16 & 0x7F) << 7) | (lsb as u16 & 0x7F)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::match_checker::NumberMatcher;
+    use crate::midi::assembler::{MessageAssembler, AssemblerOptions, Encoder, EncoderMode};
+    use crate::midi::MidiMessage;
+
+    fn cc(channel: u8, control: u8, value: u8) -> MidiMessage {
+        MidiMessage::ControlChange { channel, control, value }
+    }
+
+    fn all_options() -> AssemblerOptions {
+        AssemblerOptions { control_change_14: true, nrpn: true, rpn: true, encoders: vec![] }
+    }
+
+    #[test]
+    fn does_nothing_without_options_enabled() {
+        let mut assembler = MessageAssembler::new(AssemblerOptions::default());
+
+        assert_eq!(assembler.process(&cc(0, 1, 64)), None);
+        assert_eq!(assembler.process(&cc(0, 33, 10)), None);
+    }
+
+    #[test]
+    fn assembles_14_bit_control_changes() {
+        let mut assembler = MessageAssembler::new(
+            AssemblerOptions { control_change_14: true, ..Default::default() }
+        );
+
+        assert_eq!(assembler.process(&cc(2, 7, 0x40)), None);
+
+        assert_eq!(
+            assembler.process(&cc(2, 39, 0x01)),
+            Some(MidiMessage::ControlChange14 { channel: 2, control: 7, value: 0x2001 })
+        );
+
+        // Only LSB changes, MSB is remembered
+        assert_eq!(
+            assembler.process(&cc(2, 39, 0x02)),
+            Some(MidiMessage::ControlChange14 { channel: 2, control: 7, value: 0x2002 })
+        );
+    }
+
+    #[test]
+    fn ignores_14_bit_lsb_without_known_msb() {
+        let mut assembler = MessageAssembler::new(
+            AssemblerOptions { control_change_14: true, ..Default::default() }
+        );
+
+        assert_eq!(assembler.process(&cc(0, 33, 0x01)), None);
+
+        // MSB on a different channel doesn't count
+        assert_eq!(assembler.process(&cc(1, 1, 0x10)), None);
+        assert_eq!(assembler.process(&cc(0, 33, 0x01)), None);
+    }
+
+    #[test]
+    fn ignores_controls_outside_14_bit_range() {
+        let mut assembler = MessageAssembler::new(
+            AssemblerOptions { control_change_14: true, ..Default::default() }
+        );
+
+        assert_eq!(assembler.process(&cc(0, 64, 127)), None);
+        assert_eq!(assembler.process(&cc(0, 96, 1)), None);
+    }
+
+    #[test]
+    fn assembles_nrpn() {
+        let mut assembler = MessageAssembler::new(
+            AssemblerOptions { nrpn: true, ..Default::default() }
+        );
+
+        assert_eq!(assembler.process(&cc(0, 99, 0x01)), None);
+        assert_eq!(assembler.process(&cc(0, 98, 0x02)), None);
+        assert_eq!(assembler.process(&cc(0, 6, 0x10)), None);
+
+        assert_eq!(
+            assembler.process(&cc(0, 38, 0x20)),
+            Some(MidiMessage::Nrpn { channel: 0, parameter: 0x82, value: 0x820 })
+        );
+    }
+
+    #[test]
+    fn assembles_rpn() {
+        let mut assembler = MessageAssembler::new(
+            AssemblerOptions { rpn: true, ..Default::default() }
+        );
+
+        assert_eq!(assembler.process(&cc(3, 101, 0)), None);
+        assert_eq!(assembler.process(&cc(3, 100, 0)), None);
+        assert_eq!(assembler.process(&cc(3, 6, 2)), None);
+
+        assert_eq!(
+            assembler.process(&cc(3, 38, 0)),
+            Some(MidiMessage::Rpn { channel: 3, parameter: 0, value: 0x100 })
+        );
+    }
+
+    #[test]
+    fn does_not_assemble_rpn_after_rpn_null() {
+        let mut assembler = MessageAssembler::new(
+            AssemblerOptions { rpn: true, ..Default::default() }
+        );
+
+        assembler.process(&cc(0, 101, 127));
+        assembler.process(&cc(0, 100, 127));
+        assembler.process(&cc(0, 6, 2));
+
+        assert_eq!(assembler.process(&cc(0, 38, 0)), None);
+    }
+
+    #[test]
+    fn does_not_assemble_disabled_parameter_kinds() {
+        let mut assembler = MessageAssembler::new(
+            AssemblerOptions { rpn: true, ..Default::default() }
+        );
+
+        assembler.process(&cc(0, 99, 1));
+        assembler.process(&cc(0, 98, 1));
+        assembler.process(&cc(0, 6, 2));
+
+        assert_eq!(assembler.process(&cc(0, 38, 0)), None);
+    }
+
+    #[test]
+    fn most_recently_selected_parameter_kind_receives_data_entry() {
+        let mut assembler = MessageAssembler::new(all_options());
+
+        assembler.process(&cc(0, 99, 0));
+        assembler.process(&cc(0, 98, 5));
+        assembler.process(&cc(0, 101, 0));
+        assembler.process(&cc(0, 100, 1));
+        assembler.process(&cc(0, 6, 0));
+
+        assert_eq!(
+            assembler.process(&cc(0, 38, 3)),
+            Some(MidiMessage::Rpn { channel: 0, parameter: 1, value: 3 })
+        );
+    }
+
+    #[test]
+    fn data_entry_is_not_a_14_bit_control_while_a_parameter_is_selected() {
+        let mut assembler = MessageAssembler::new(all_options());
+
+        // Without a parameter selected, data entry controls are a regular 14-bit control
+        assembler.process(&cc(0, 6, 1));
+
+        assert_eq!(
+            assembler.process(&cc(0, 38, 1)),
+            Some(MidiMessage::ControlChange14 { channel: 0, control: 6, value: 0x81 })
+        );
+
+        assembler.process(&cc(0, 99, 0));
+        assembler.process(&cc(0, 98, 7));
+        assembler.process(&cc(0, 6, 1));
+
+        assert_eq!(
+            assembler.process(&cc(0, 38, 1)),
+            Some(MidiMessage::Nrpn { channel: 0, parameter: 7, value: 0x81 })
+        );
+    }
+
+    #[test]
+    fn ignores_messages_other_than_control_change() {
+        let mut assembler = MessageAssembler::new(all_options());
+
+        let msg = MidiMessage::NoteOn { channel: 0, key: 38, velocity: 1 };
+        assert_eq!(assembler.process(&msg), None);
+    }
+
+    #[test]
+    fn merges_options() {
+        let a = AssemblerOptions { control_change_14: true, ..Default::default() };
+        let b = AssemblerOptions { rpn: true, ..Default::default() };
+
+        assert_eq!(
+            a.merge(&b),
+            AssemblerOptions { control_change_14: true, nrpn: false, rpn: true, encoders: vec![] }
+        );
+    }
+
+    #[test]
+    fn decodes_encoder_modes() {
+        let decode = |mode: EncoderMode, values: [u8; 5]| values.map(|value| mode.decode(value));
+
+        assert_eq!(decode(EncoderMode::TwosComplement, [1, 5, 127, 123, 64]), [1, 5, -1, -5, -64]);
+        assert_eq!(decode(EncoderMode::BinaryOffset, [65, 69, 63, 59, 64]), [1, 5, -1, -5, 0]);
+        assert_eq!(decode(EncoderMode::SignMagnitude, [1, 5, 65, 69, 64]), [1, 5, -1, -5, 0]);
+    }
+
+    #[test]
+    fn turns_control_changes_from_encoders_into_encoder_turns() {
+        let mut assembler = MessageAssembler::new(AssemblerOptions {
+            control_change_14: true,
+            encoders: vec![Encoder {
+                channel_match: None,
+                control_match: NumberMatcher::Range { min: Some(20), max: Some(23) },
+                mode: EncoderMode::BinaryOffset
+            }],
+            ..Default::default()
+        });
+
+        assert_eq!(
+            assembler.process(&cc(1, 21, 61)),
+            Some(MidiMessage::EncoderTurn { channel: 1, control: 21, delta: -3 })
+        );
+
+        // Not turned at all
+        assert_eq!(assembler.process(&cc(1, 21, 64)), None);
+
+        // Other controls are left alone, so other options still apply to them
+        assert_eq!(assembler.process(&cc(0, 24, 65)), None);
+        assembler.process(&cc(0, 1, 0x10));
+
+        assert_eq!(
+            assembler.process(&cc(0, 33, 0x01)),
+            Some(MidiMessage::ControlChange14 { channel: 0, control: 1, value: 0x801 })
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use std::sync::mpsc::{self, SyncSender, Sender, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use crate::config::{MidiDevice, MidiDeviceOptions};
+use crate::macros::event_matching::Event;
+use crate::match_checker::MatchChecker;
+use crate::midi::adapters::MidiAdapter;
+
+/// How often the supervisor checks for devices being connected or disconnected
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Keeps the configured MIDI devices connected while the program runs.
+///
+/// On every poll, the supervisor asks the adapter which ports are available. Devices whose port
+/// has appeared are connected, and devices whose port has disappeared are disconnected, so they
+/// can be reconnected once they're back. Each of these is announced on the event bus with an
+/// `Event::DeviceConnected` or `Event::DeviceDisconnected`.
+pub struct MidiSupervisor {
+    adapter: Box<dyn MidiAdapter>,
+    devices: Vec<SupervisedDevice>,
+    device_options: Vec<MidiDeviceOptions>,
+    tx: SyncSender<Event>
+}
+
+/// A configured device, along with the port it is currently connected on, if any
+struct SupervisedDevice {
+    device: MidiDevice,
+    port: Option<String>
+}
+
+impl SupervisedDevice {
+    /// Name that events from this device are tagged with, given the port it is connected on
+    fn name(&self, port: &str) -> String {
+        self.device.name.clone().unwrap_or_else(|| port.to_string())
+    }
+}
+
+impl MidiSupervisor {
+    /// Creates a supervisor for `devices`, which will listen to them through `adapter` and send
+    /// their events, as well as connection events, along `tx`. `device_options` determine how
+    /// incoming messages are processed for each device; see `MidiDeviceOptions`.
+    ///
+    /// Nothing is connected until `poll` or `start` is called.
+    pub fn new(
+        adapter: Box<dyn MidiAdapter>,
+        devices: Vec<MidiDevice>,
+        device_options: Vec<MidiDeviceOptions>,
+        tx: SyncSender<Event>
+    ) -> MidiSupervisor {
+        MidiSupervisor {
+            adapter,
+            devices: devices
+                .into_iter()
+                .map(|device| SupervisedDevice { device, port: None })
+                .collect(),
+            device_options,
+            tx
+        }
+    }
+
+    /// Checks the available ports once, disconnecting any devices whose port has disappeared, and
+    /// connecting any devices whose port is available.
+    ///
+    /// A device that can't be connected to is retried on the next poll. Each port is only
+    /// connected to once: a device whose port matches one that's already taken by another device
+    /// gets the next matching port instead, if there is one.
+    pub fn poll(&mut self) {
+        let ports = self.adapter.list_ports();
+
+        // Ports of devices that are still connected stay theirs
+        let mut claimed: Vec<String> = self.devices
+            .iter()
+            .filter_map(|supervised| supervised.port.clone())
+            .filter(|port| ports.contains(port))
+            .collect();
+
+        for supervised in self.devices.iter_mut() {
+            if let Some(port) = &supervised.port {
+                if ports.contains(port) {
+                    continue;
+                }
+
+                self.adapter.stop_listening_to(port);
+                let device = supervised.name(port);
+                supervised.port = None;
+
+                let _ = self.tx.send(Event::DeviceDisconnected { device });
+            }
+
+            let port = ports
+                .iter()
+                .filter(|port| !claimed.contains(port))
+                .find(|port| supervised.device.port_matcher.matches(&port.as_str()));
+
+            if let Some(port) = port {
+                let device = supervised.name(port);
+
+                let assembler_options = MidiDeviceOptions::combined_assembler_options(
+                    &self.device_options,
+                    port
+                );
+
+                let handle = self.adapter.start_listening(
+                    port,
+                    &device,
+                    assembler_options,
+                    self.tx.clone()
+                );
+
+                if handle.is_some() {
+                    claimed.push(port.to_string());
+                    supervised.port = Some(port.to_string());
+                    let _ = self.tx.send(Event::DeviceConnected { device });
+                }
+            }
+        }
+    }
+
+    /// Creates a virtual input port named `port_name`, which other applications can send MIDI
+    /// messages to. Events from it are tagged with `port_name` as their device, and it is
+    /// announced with an `Event::DeviceConnected` as soon as it's created.
+    ///
+    /// Unlike configured devices, a virtual port isn't retried if it can't be created; returns
+    /// whether creating it worked. It is closed along with the other devices when the supervisor
+    /// is stopped.
+    pub fn open_virtual_port(&mut self, port_name: &str) -> bool {
+        let assembler_options = MidiDeviceOptions::combined_assembler_options(
+            &self.device_options,
+            port_name
+        );
+
+        let handle = self.adapter.start_virtual_port(
+            port_name,
+            port_name,
+            assembler_options,
+            self.tx.clone()
+        );
+
+        if handle.is_none() {
+            return false;
+        }
+
+        let _ = self.tx.send(Event::DeviceConnected { device: port_name.to_string() });
+        true
+    }
+
+    /// Starts a thread that polls every `interval` until the returned handle is stopped.
+    pub fn start(mut self, interval: Duration) -> SupervisorHandle {
+        let (stop_tx, stop_rx) = mpsc::channel();
+
+        let thread = thread::spawn(move || {
+            loop {
+                self.poll();
+
+                // Waiting on the channel rather than sleeping lets `stop` take effect immediately.
+                // A dropped handle stops the supervisor too.
+                match stop_rx.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break
+                }
+            }
+
+            self.adapter.stop_listening();
+        });
+
+        SupervisorHandle { stop_tx, thread }
+    }
+}
+
+/// Handle to a running `MidiSupervisor`, used to stop it
+pub struct SupervisorHandle {
+    stop_tx: Sender<()>,
+    thread: thread::JoinHandle<()>
+}
+
+impl SupervisorHandle {
+    /// Stops the supervisor, including listening to any devices it connected to, and waits for
+    /// it to finish.
+    pub fn stop(self) {
+        let _ = self.stop_tx.send(());
+        let _ = self.thread.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use mockall::predicate::{eq, always};
+    use crate::config::{MidiDevice, MidiDeviceOptions};
+    use crate::macros::event_matching::{get_event_bus, Event};
+    use crate::match_checker::StringMatcher;
+    use crate::midi::adapters::MockMidiAdapter;
+    use crate::midi::assembler::AssemblerOptions;
+    use crate::midi::supervisor::MidiSupervisor;
+
+    /// Sets up a mock adapter whose available ports are whatever is in the returned list at the
+    /// time `list_ports` is called
+    fn mock_adapter_with_ports() -> (MockMidiAdapter, Arc<Mutex<Vec<String>>>) {
+        let ports = Arc::new(Mutex::new(vec![]));
+        let mut adapter = MockMidiAdapter::new();
+
+        let listed_ports = Arc::clone(&ports);
+        adapter.expect_list_ports().returning(move || listed_ports.lock().unwrap().clone());
+
+        (adapter, ports)
+    }
+
+    fn device(name: Option<&str>, contains: &str) -> MidiDevice {
+        MidiDevice {
+            name: name.map(|name| name.to_string()),
+            port_matcher: StringMatcher::Contains(contains.to_string())
+        }
+    }
+
+    fn name_of(event: Event) -> (&'static str, String) {
+        match event {
+            Event::DeviceConnected { device } => ("connected", device),
+            Event::DeviceDisconnected { device } => ("disconnected", device),
+            _ => ("other", String::new())
+        }
+    }
+
+    #[test]
+    fn connects_devices_when_they_appear_and_reconnects_after_disconnect() {
+        let (mut adapter, ports) = mock_adapter_with_ports();
+
+        adapter.expect_start_listening()
+            .with(eq("Launchpad MK2 20:0"), eq("pads"), always(), always())
+            .times(2)
+            .returning(|_, _, _, _| Some(thread::spawn(|| {})));
+
+        adapter.expect_stop_listening_to()
+            .with(eq("Launchpad MK2 20:0"))
+            .times(1)
+            .return_const(());
+
+        let (tx, rx) = get_event_bus();
+
+        let mut supervisor = MidiSupervisor::new(
+            Box::new(adapter),
+            vec![device(Some("pads"), "Launchpad")],
+            vec![],
+            tx
+        );
+
+        // Not plugged in yet
+        supervisor.poll();
+        assert!(rx.try_recv().is_err());
+
+        ports.lock().unwrap().push("Launchpad MK2 20:0".to_string());
+        supervisor.poll();
+        assert_eq!(name_of(rx.try_recv().unwrap()), ("connected", "pads".to_string()));
+
+        // Still plugged in; nothing happens
+        supervisor.poll();
+        assert!(rx.try_recv().is_err());
+
+        ports.lock().unwrap().clear();
+        supervisor.poll();
+        assert_eq!(name_of(rx.try_recv().unwrap()), ("disconnected", "pads".to_string()));
+        assert!(rx.try_recv().is_err());
+
+        ports.lock().unwrap().push("Launchpad MK2 20:0".to_string());
+        supervisor.poll();
+        assert_eq!(name_of(rx.try_recv().unwrap()), ("connected", "pads".to_string()));
+    }
+
+    #[test]
+    fn names_unnamed_devices_after_their_port_and_applies_device_options() {
+        let (mut adapter, ports) = mock_adapter_with_ports();
+        ports.lock().unwrap().push("Arturia KeyStep 32".to_string());
+
+        let expected_options = AssemblerOptions { control_change_14: true, ..Default::default() };
+
+        adapter.expect_start_listening()
+            .with(
+                eq("Arturia KeyStep 32"),
+                eq("Arturia KeyStep 32"),
+                eq(expected_options.clone()),
+                always()
+            )
+            .times(1)
+            .returning(|_, _, _, _| Some(thread::spawn(|| {})));
+
+        let (tx, rx) = get_event_bus();
+
+        let mut supervisor = MidiSupervisor::new(
+            Box::new(adapter),
+            vec![device(None, "KeyStep")],
+            vec![MidiDeviceOptions {
+                device_matcher: Some(StringMatcher::Contains("KeyStep".to_string())),
+                assembler_options: expected_options
+            }],
+            tx
+        );
+
+        supervisor.poll();
+
+        assert_eq!(
+            name_of(rx.try_recv().unwrap()),
+            ("connected", "Arturia KeyStep 32".to_string())
+        );
+    }
+
+    #[test]
+    fn retries_devices_that_failed_to_connect() {
+        let (mut adapter, ports) = mock_adapter_with_ports();
+        ports.lock().unwrap().push("Launchpad".to_string());
+
+        let attempts = Arc::new(Mutex::new(0));
+        let counted_attempts = Arc::clone(&attempts);
+
+        adapter.expect_start_listening()
+            .times(2)
+            .returning(move |_, _, _, _| {
+                let mut attempts = counted_attempts.lock().unwrap();
+                *attempts += 1;
+
+                if *attempts == 1 {
+                    None
+                } else {
+                    Some(thread::spawn(|| {}))
+                }
+            });
+
+        let (tx, rx) = get_event_bus();
+
+        let mut supervisor = MidiSupervisor::new(
+            Box::new(adapter),
+            vec![device(Some("pads"), "Launchpad")],
+            vec![],
+            tx
+        );
+
+        supervisor.poll();
+        assert!(rx.try_recv().is_err());
+
+        supervisor.poll();
+        assert_eq!(name_of(rx.try_recv().unwrap()), ("connected", "pads".to_string()));
+    }
+
+    #[test]
+    fn connects_each_port_to_only_one_device() {
+        let (mut adapter, ports) = mock_adapter_with_ports();
+        ports.lock().unwrap().push("Launchpad MK2 20:0".to_string());
+
+        adapter.expect_start_listening()
+            .with(eq("Launchpad MK2 20:0"), eq("pads"), always(), always())
+            .times(1)
+            .returning(|_, _, _, _| Some(thread::spawn(|| {})));
+
+        adapter.expect_start_listening()
+            .with(eq("Launchpad X 24:0"), eq("more pads"), always(), always())
+            .times(1)
+            .returning(|_, _, _, _| Some(thread::spawn(|| {})));
+
+        let (tx, rx) = get_event_bus();
+
+        let mut supervisor = MidiSupervisor::new(
+            Box::new(adapter),
+            vec![device(Some("pads"), "Launchpad"), device(Some("more pads"), "Launch")],
+            vec![],
+            tx
+        );
+
+        // Both match the only port, which goes to the first device
+        supervisor.poll();
+        assert_eq!(name_of(rx.try_recv().unwrap()), ("connected", "pads".to_string()));
+        assert!(rx.try_recv().is_err());
+
+        supervisor.poll();
+        assert!(rx.try_recv().is_err());
+
+        ports.lock().unwrap().push("Launchpad X 24:0".to_string());
+        supervisor.poll();
+        assert_eq!(name_of(rx.try_recv().unwrap()), ("connected", "more pads".to_string()));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn opens_virtual_ports() {
+        let (mut adapter, _) = mock_adapter_with_ports();
+
+        adapter.expect_start_virtual_port()
+            .with(eq("mmpd in"), eq("mmpd in"), always(), always())
+            .times(1)
+            .returning(|_, _, _, _| Some(thread::spawn(|| {})));
+
+        adapter.expect_start_virtual_port()
+            .with(eq("unsupported"), always(), always(), always())
+            .times(1)
+            .returning(|_, _, _, _| None);
+
+        let (tx, rx) = get_event_bus();
+        let mut supervisor = MidiSupervisor::new(Box::new(adapter), vec![], vec![], tx);
+
+        assert!(supervisor.open_virtual_port("mmpd in"));
+        assert_eq!(name_of(rx.try_recv().unwrap()), ("connected", "mmpd in".to_string()));
+
+        assert!(!supervisor.open_virtual_port("unsupported"));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -1,14 +1,32 @@
 mod midi_state;
+pub(crate) mod event_history;
+mod variables;
+mod throttle;
+pub mod persistence;
 
+pub use midi_state::PressTiming;
+pub use variables::Value;
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 use crate::macros::Scope;
 use crate::focus::FocusAdapter;
 use crate::match_checker::MatchChecker;
 use crate::macros::preconditions::{Precondition, PreconditionType};
+use crate::macros::actions::template::ConditionVariable;
+use crate::macros::preconditions::midi::MidiPrecondition;
+use crate::macros::preconditions::variable::VariablePrecondition;
+use crate::macros::event_matching::gesture::GestureMatcher;
+use crate::clock::{Clock, SystemClock};
 
 #[cfg(test)]
 use mockall::automock;
 use crate::macros::event_matching::Event;
 use crate::state::midi_state::MidiState;
+use crate::state::event_history::EventHistory;
+use crate::state::variables::Variables;
+use crate::state::throttle::Throttles;
+use crate::midi::{MidiEvent, MidiMessage};
 
 #[cfg_attr(test, automock)]
 pub trait State {
@@ -17,35 +35,186 @@ pub trait State {
     fn matches_scope(&self, scope: &Option<Scope>) -> bool;
 
     fn matches_precondition(&self, precondition: &Precondition) -> bool;
+
+    /// Checks whether `event`, which must be the last event passed to `process_event`, completes
+    /// `gesture`, given the events that came before it.
+    fn matches_gesture(&self, gesture: &GestureMatcher, event: &Event) -> bool;
+
+    /// Sets the thresholds for telling taps, double taps, and long presses apart
+    fn set_press_timing(&mut self, press_timing: PressTiming);
+
+    /// Takes the events that resulted from buttons being pressed, held and released, such as
+    /// taps and long presses, which are due by now. These should be processed like any other
+    /// event.
+    fn take_press_events(&mut self) -> Vec<Event>;
+
+    /// The next time `take_press_events` will have something to return because a button has been
+    /// held long enough, if any buttons are held.
+    fn next_press_deadline(&self) -> Option<Instant>;
+
+    /// Retrieves a value from state for use in a variable, formatted as a string.
+    /// Returns `None` if the value isn't known.
+    fn get_condition_value(&self, variable: &ConditionVariable) -> Option<String>;
+
+    /// Retrieves the value of the variable named `name`, if it has been set
+    fn get_variable(&self, name: &str) -> Option<Value>;
+
+    /// Sets the variable named `name` to `value`
+    fn set_variable(&mut self, name: &str, value: Value);
+
+    /// Checks whether whatever is identified by `key` may run now for `event`, having last run
+    /// at least `interval` ago. If not, `event` is kept, and handed out by
+    /// `take_throttled_events` once the interval has passed, so the last event isn't lost.
+    fn throttle(&mut self, key: &str, interval: Duration, event: &Event) -> bool;
+
+    /// Drops the event `throttle` kept for `key`, if any, for when it's out of date
+    fn discard_throttled(&mut self, key: &str);
+
+    /// Takes the events kept by `throttle` that are due by now, along with their keys. These
+    /// should be run for their key only, rather than processed like new events.
+    fn take_throttled_events(&mut self) -> Vec<(String, Event)>;
+
+    /// The next time `take_throttled_events` will have something to return, if anything was
+    /// throttled.
+    fn next_throttle_deadline(&self) -> Option<Instant>;
+
+    /// Takes a copy of the parts of state worth keeping across restarts
+    fn save(&self) -> SavedState;
+
+    /// Restores state from a copy taken with `save`, on top of whatever is already known
+    fn restore(&mut self, saved: SavedState);
+}
+
+/// Parts of state that are worth keeping across restarts: remembered MIDI values, and variables.
+/// See `persistence` for storing it in a file.
+#[derive(Debug, PartialEq, Default)]
+pub struct SavedState {
+    /// Messages that rebuild the MIDI state of all devices combined
+    pub midi: Vec<MidiMessage>,
+
+    /// Messages that rebuild the MIDI state of each device, by device name
+    pub devices: Vec<(String, Vec<MidiMessage>)>,
+
+    /// Values of variables, by name
+    pub variables: Vec<(String, Value)>
 }
 
 pub fn new(
     focus_adapter: Box<dyn FocusAdapter>
 ) -> Box<dyn State> {
-    StateImpl::new(focus_adapter)
+    StateImpl::new(focus_adapter, Box::new(SystemClock))
+}
+
+/// Same as `new`, but timing events by `clock` rather than the system clock
+pub fn with_clock(
+    focus_adapter: Box<dyn FocusAdapter>,
+    clock: Box<dyn Clock>
+) -> Box<dyn State> {
+    StateImpl::new(focus_adapter, clock)
 }
 
 struct StateImpl {
     focus_adapter: Box<dyn FocusAdapter>,
-    midi: MidiState
+
+    /// MIDI state combined from all devices
+    midi: MidiState,
+
+    /// MIDI state for each device separately, keyed by device name
+    midi_devices: HashMap<String, MidiState>,
+
+    /// Recent events, for matching gestures
+    event_history: EventHistory,
+
+    /// Source of the times events are recorded at
+    clock: Box<dyn Clock>,
+
+    /// Thresholds for timing button presses
+    press_timing: PressTiming,
+
+    /// Events resulting from button presses and releases, waiting to be taken
+    press_events: VecDeque<Event>,
+
+    /// Named variables set by actions
+    variables: Variables,
+
+    /// Keeps things like mapped values from being acted on too often
+    throttles: Throttles
 }
 
 impl StateImpl {
     pub fn new(
-        focus_adapter: Box<dyn FocusAdapter>
+        focus_adapter: Box<dyn FocusAdapter>,
+        clock: Box<dyn Clock>
     ) -> Box<dyn State> {
         Box::new(StateImpl {
             focus_adapter,
-            midi: MidiState::new()
+            midi: MidiState::new(),
+            midi_devices: HashMap::new(),
+            event_history: EventHistory::new(),
+            clock,
+            press_timing: PressTiming::default(),
+            press_events: VecDeque::new(),
+            variables: Variables::new(),
+            throttles: Throttles::new()
         })
     }
+
+    /// Checks a MIDI precondition against the combined state of all devices, or, if the
+    /// precondition is for specific devices, against the state of each of those devices.
+    fn matches_midi_precondition(&self, condition: &MidiPrecondition) -> bool {
+        match condition {
+            MidiPrecondition::Device { device_match, condition } => {
+                self.midi_devices.iter().any(|(device, midi)| {
+                    device_match.matches(&device.as_str()) && midi.matches(condition)
+                })
+            }
+
+            _ => self.midi.matches(condition)
+        }
+    }
+
+    fn matches_variable_precondition(&self, condition: &VariablePrecondition) -> bool {
+        self.variables
+            .get(&condition.name)
+            .is_some_and(|value| condition.value_match.matches(value))
+    }
 }
 
 impl State for StateImpl {
     fn process_event(&mut self, event: &Event) {
+        let now = self.clock.now();
+        self.event_history.record(now, event);
+
         match event {
-            Event::Midi(midi_msg) => self.midi.process_message(midi_msg),
-            Event::Other => {}
+            Event::Midi(midi_event) => {
+                self.midi.process_message(&midi_event.message);
+
+                // Presses are timed per device, so the resulting events can say which device
+                // they're from.
+                let device_state = self.midi_devices
+                    .entry(midi_event.device.clone())
+                    .or_insert_with(MidiState::new);
+
+                device_state.process_message(&midi_event.message);
+
+                let messages = device_state.process_press(
+                    &midi_event.message,
+                    now,
+                    &self.press_timing
+                );
+
+                self.press_events.extend(messages.into_iter().map(|message| {
+                    Event::Midi(MidiEvent::new(&midi_event.device, message))
+                }));
+            }
+
+            // Whatever was known about a device is stale once it's gone; it starts afresh when it
+            // is connected again.
+            Event::DeviceDisconnected { device } => {
+                self.midi_devices.remove(device);
+            }
+
+            Event::DeviceConnected { .. } | Event::Other => {}
         }
     }
 
@@ -106,7 +275,8 @@ impl State for StateImpl {
 
     fn matches_precondition(&self, precondition: &Precondition) -> bool {
         let normal_match = match &precondition.condition {
-            PreconditionType::Midi(condition) => self.midi.matches(condition),
+            PreconditionType::Midi(condition) => self.matches_midi_precondition(condition),
+            PreconditionType::Variable(condition) => self.matches_variable_precondition(condition),
             PreconditionType::Other => true
         };
 
@@ -117,6 +287,317 @@ impl State for StateImpl {
             normal_match
         }
     }
+
+    fn matches_gesture(&self, gesture: &GestureMatcher, event: &Event) -> bool {
+        gesture.matches(event, &self.event_history)
+    }
+
+    fn set_press_timing(&mut self, press_timing: PressTiming) {
+        self.press_timing = press_timing;
+    }
+
+    fn take_press_events(&mut self) -> Vec<Event> {
+        let now = self.clock.now();
+
+        for (device, midi) in self.midi_devices.iter_mut() {
+            let messages = midi.poll_presses(now, &self.press_timing);
+
+            self.press_events.extend(messages.into_iter().map(|message| {
+                Event::Midi(MidiEvent::new(device, message))
+            }));
+        }
+
+        self.press_events.drain(..).collect()
+    }
+
+    fn next_press_deadline(&self) -> Option<Instant> {
+        self.midi_devices
+            .values()
+            .filter_map(|midi| midi.next_press_deadline(&self.press_timing))
+            .min()
+    }
+
+    fn get_condition_value(&self, variable: &ConditionVariable) -> Option<String> {
+        match variable {
+            ConditionVariable::Midi(variable) => self.midi.get_value(variable),
+
+            ConditionVariable::Variable(name) => {
+                self.variables.get(name).map(|value| value.to_string())
+            }
+        }
+    }
+
+    fn get_variable(&self, name: &str) -> Option<Value> {
+        self.variables.get(name).cloned()
+    }
+
+    fn set_variable(&mut self, name: &str, value: Value) {
+        self.variables.set(name, value);
+    }
+
+    fn throttle(&mut self, key: &str, interval: Duration, event: &Event) -> bool {
+        self.throttles.try_run(key, interval, self.clock.now(), event)
+    }
+
+    fn discard_throttled(&mut self, key: &str) {
+        self.throttles.discard(key);
+    }
+
+    fn take_throttled_events(&mut self) -> Vec<(String, Event)> {
+        self.throttles.take_due(self.clock.now())
+    }
+
+    fn next_throttle_deadline(&self) -> Option<Instant> {
+        self.throttles.next_deadline()
+    }
+
+    fn save(&self) -> SavedState {
+        let mut devices: Vec<(String, Vec<MidiMessage>)> = self.midi_devices
+            .iter()
+            .map(|(device, midi)| (device.to_string(), midi.snapshot()))
+            .filter(|(_, messages)| !messages.is_empty())
+            .collect();
+
+        devices.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        SavedState {
+            midi: self.midi.snapshot(),
+            devices,
+            variables: self.variables.all()
+        }
+    }
+
+    fn restore(&mut self, saved: SavedState) {
+        for message in saved.midi.iter() {
+            self.midi.process_message(message);
+        }
+
+        for (device, messages) in saved.devices {
+            let device_state = self.midi_devices.entry(device).or_insert_with(MidiState::new);
+
+            for message in messages.iter() {
+                device_state.process_message(message);
+            }
+        }
+
+        for (name, value) in saved.variables {
+            self.variables.set(&name, value);
+        }
+    }
 }
 
-// TODO: tests for StateImpl
\ No newline at end of file
+// TODO: tests for StateImpl beyond MIDI devices
+
+#[cfg(test)]
+mod tests {
+    use crate::state::StateImpl;
+    use crate::focus::MockFocusAdapter;
+    use crate::macros::event_matching::Event;
+    use crate::macros::preconditions::{Precondition, PreconditionType};
+    use crate::macros::preconditions::midi::MidiPrecondition;
+    use crate::macros::preconditions::variable::{VariablePrecondition, ValueMatcher};
+    use crate::macros::actions::template::ConditionVariable;
+    use crate::state::{Value, SavedState};
+    use crate::match_checker::{NumberMatcher, StringMatcher};
+    use crate::midi::{MidiEvent, MidiMessage, Button};
+    use crate::clock::{Clock, ManualClock, SystemClock};
+    use crate::macros::event_matching::MatcherType;
+    use crate::macros::event_matching::gesture::GestureMatcher;
+    use crate::macros::event_matching::midi::MidiEventMatcher;
+    use std::time::Duration;
+
+    fn note_on_precondition(device: Option<&str>) -> Precondition {
+        let condition = MidiPrecondition::NoteOn {
+            channel_match: None,
+            key_match: Some(NumberMatcher::Val(36))
+        };
+
+        let condition = match device {
+            Some(device) => MidiPrecondition::Device {
+                device_match: Box::new(StringMatcher::Is(device.to_string())),
+                condition: Box::new(condition)
+            },
+
+            None => condition
+        };
+
+        Precondition { invert: false, condition: PreconditionType::Midi(condition) }
+    }
+
+    #[test]
+    fn keeps_midi_state_per_device() {
+        let mut state = StateImpl::new(Box::new(MockFocusAdapter::new()), Box::new(SystemClock));
+
+        state.process_event(&Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::NoteOn { channel: 0, key: 36, velocity: 100 }
+        )));
+
+        assert!(state.matches_precondition(&note_on_precondition(None)));
+        assert!(state.matches_precondition(&note_on_precondition(Some("pads"))));
+        assert!(!state.matches_precondition(&note_on_precondition(Some("keys"))));
+
+        state.process_event(&Event::Midi(MidiEvent::new(
+            "keys",
+            MidiMessage::NoteOn { channel: 0, key: 36, velocity: 100 }
+        )));
+
+        state.process_event(&Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::NoteOff { channel: 0, key: 36, velocity: 0 }
+        )));
+
+        assert!(!state.matches_precondition(&note_on_precondition(Some("pads"))));
+        assert!(state.matches_precondition(&note_on_precondition(Some("keys"))));
+    }
+
+    #[test]
+    fn forgets_midi_state_of_disconnected_device() {
+        let mut state = StateImpl::new(Box::new(MockFocusAdapter::new()), Box::new(SystemClock));
+
+        state.process_event(&Event::Midi(MidiEvent::new(
+            "keys",
+            MidiMessage::NoteOn { channel: 0, key: 36, velocity: 100 }
+        )));
+
+        state.process_event(&Event::DeviceDisconnected { device: "keys".to_string() });
+
+        assert!(!state.matches_precondition(&note_on_precondition(Some("keys"))));
+    }
+
+    #[test]
+    fn matches_gestures_timed_by_its_clock() {
+        let clock = ManualClock::new();
+        let mut state = StateImpl::new(Box::new(MockFocusAdapter::new()), Box::new(clock.clone()));
+
+        let note_on_matcher = |key: u32| MatcherType::Midi(MidiEventMatcher::NoteOn {
+            channel_match: None,
+            key_match: Some(NumberMatcher::Val(key)),
+            velocity_match: None
+        });
+
+        let sequence = GestureMatcher::Sequence {
+            steps: vec![note_on_matcher(36), note_on_matcher(37)],
+            timeout: Duration::from_millis(500)
+        };
+
+        let note_on = |key: u8| Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::NoteOn { channel: 0, key, velocity: 100 }
+        ));
+
+        state.process_event(&note_on(36));
+        clock.advance(Duration::from_millis(400));
+        state.process_event(&note_on(37));
+
+        assert!(state.matches_gesture(&sequence, &note_on(37)));
+
+        state.process_event(&note_on(36));
+        clock.advance(Duration::from_millis(600));
+        state.process_event(&note_on(37));
+
+        assert!(!state.matches_gesture(&sequence, &note_on(37)));
+    }
+
+    #[test]
+    fn produces_press_events_per_device() {
+        let clock = ManualClock::new();
+        let mut state = StateImpl::new(Box::new(MockFocusAdapter::new()), Box::new(clock.clone()));
+
+        state.process_event(&Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::NoteOn { channel: 0, key: 36, velocity: 100 }
+        )));
+
+        assert!(state.take_press_events().is_empty());
+        assert_eq!(state.next_press_deadline(), Some(clock.now() + Duration::from_millis(500)));
+
+        clock.advance(Duration::from_millis(500));
+
+        assert_eq!(
+            state.take_press_events(),
+            [Event::Midi(MidiEvent::new(
+                "pads",
+                MidiMessage::LongPress { channel: 0, button: Button::Note { key: 36 } }
+            ))]
+        );
+
+        state.process_event(&Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::NoteOff { channel: 0, key: 36, velocity: 0 }
+        )));
+
+        assert_eq!(
+            state.take_press_events(),
+            [Event::Midi(MidiEvent::new(
+                "pads",
+                MidiMessage::Release { channel: 0, button: Button::Note { key: 36 }, duration: 500 }
+            ))]
+        );
+
+        assert_eq!(state.next_press_deadline(), None);
+    }
+
+    #[test]
+    fn keeps_variables_for_preconditions_and_templates() {
+        let mut state = StateImpl::new(Box::new(MockFocusAdapter::new()), Box::new(SystemClock));
+
+        let precondition = |invert: bool| Precondition {
+            invert,
+            condition: PreconditionType::Variable(VariablePrecondition {
+                name: "layer".to_string(),
+                value_match: ValueMatcher::String(Box::new(StringMatcher::Is("media".to_string())))
+            })
+        };
+
+        let variable = ConditionVariable::Variable("layer".to_string());
+
+        // A variable that hasn't been set matches nothing, unless inverted
+        assert!(!state.matches_precondition(&precondition(false)));
+        assert!(state.matches_precondition(&precondition(true)));
+        assert_eq!(state.get_condition_value(&variable), None);
+
+        state.set_variable("layer", Value::String("media".to_string()));
+
+        assert!(state.matches_precondition(&precondition(false)));
+        assert_eq!(state.get_variable("layer"), Some(Value::String("media".to_string())));
+        assert_eq!(state.get_condition_value(&variable), Some("media".to_string()));
+    }
+
+    #[test]
+    fn restores_saved_state() {
+        let mut state = StateImpl::new(Box::new(MockFocusAdapter::new()), Box::new(SystemClock));
+
+        state.process_event(&Event::Midi(MidiEvent::new(
+            "faders",
+            MidiMessage::ControlChange { channel: 0, control: 7, value: 100 }
+        )));
+
+        state.process_event(&Event::Midi(MidiEvent::new(
+            "pads",
+            MidiMessage::NoteOn { channel: 0, key: 36, velocity: 100 }
+        )));
+
+        state.set_variable("layer", Value::String("media".to_string()));
+
+        let saved = state.save();
+
+        assert_eq!(
+            saved,
+            SavedState {
+                midi: vec![MidiMessage::ControlChange { channel: 0, control: 7, value: 100 }],
+                devices: vec![(
+                    "faders".to_string(),
+                    vec![MidiMessage::ControlChange { channel: 0, control: 7, value: 100 }]
+                )],
+                variables: vec![("layer".to_string(), Value::String("media".to_string()))]
+            }
+        );
+
+        let mut restored = StateImpl::new(Box::new(MockFocusAdapter::new()), Box::new(SystemClock));
+        restored.restore(saved);
+
+        assert_eq!(restored.save(), state.save());
+        assert!(!restored.matches_precondition(&note_on_precondition(None)));
+    }
+}
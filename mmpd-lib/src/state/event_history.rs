@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+use crate::macros::event_matching::Event;
+use crate::midi::MidiMessage;
+
+/// How many events are remembered at most. Gestures only look at the last few events, so this
+/// only needs to cover the longest gesture along with whatever else was played in between.
+const MAX_LENGTH: usize = 64;
+
+/// An event along with the time it was received
+pub(crate) struct RecordedEvent {
+    pub(crate) time: Instant,
+    pub(crate) event: Event
+}
+
+/// Recently received MIDI events, oldest first, for matching gestures made up of several events.
+///
+/// Messages that some devices send many times per second (timing clock and active sensing) are
+/// left out, so they don't push out the events that matter.
+pub(crate) struct EventHistory {
+    events: VecDeque<RecordedEvent>
+}
+
+impl EventHistory {
+    pub(crate) fn new() -> EventHistory {
+        EventHistory { events: VecDeque::with_capacity(MAX_LENGTH) }
+    }
+
+    /// Remembers `event` as received at `time`, if it's a kind of event that is kept, forgetting
+    /// the oldest event if the history is full.
+    pub(crate) fn record(&mut self, time: Instant, event: &Event) {
+        let is_kept = match event {
+            Event::Midi(midi_event) => !matches!(
+                midi_event.message,
+                MidiMessage::TimingClock | MidiMessage::ActiveSensing | MidiMessage::Other
+            ),
+
+            _ => false
+        };
+
+        if !is_kept {
+            return;
+        }
+
+        if self.events.len() == MAX_LENGTH {
+            self.events.pop_front();
+        }
+
+        self.events.push_back(RecordedEvent { time, event: event.clone() });
+    }
+
+    /// The most recently recorded event, if any
+    pub(crate) fn latest(&self) -> Option<&RecordedEvent> {
+        self.events.back()
+    }
+
+    /// All recorded events, oldest first
+    pub(crate) fn events(&self) -> &VecDeque<RecordedEvent> {
+        &self.events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+    use crate::macros::event_matching::Event;
+    use crate::midi::{MidiEvent, MidiMessage};
+    use crate::state::event_history::{EventHistory, MAX_LENGTH};
+
+    fn note_on(key: u8) -> Event {
+        Event::Midi(MidiEvent::new("pads", MidiMessage::NoteOn { channel: 0, key, velocity: 100 }))
+    }
+
+    #[test]
+    fn keeps_a_limited_number_of_relevant_events() {
+        let mut history = EventHistory::new();
+        let now = Instant::now();
+
+        history.record(now, &Event::Midi(MidiEvent::new("pads", MidiMessage::TimingClock)));
+        history.record(now, &Event::DeviceConnected { device: "pads".to_string() });
+        assert!(history.latest().is_none());
+
+        for key in 0..(MAX_LENGTH as u8 + 1) {
+            history.record(now, &note_on(key));
+        }
+
+        assert_eq!(history.events().len(), MAX_LENGTH);
+        assert!(history.events().front().unwrap().event == note_on(1));
+        assert!(history.latest().unwrap().event == note_on(MAX_LENGTH as u8));
+    }
+}
@@ -1,15 +1,52 @@
-use crate::midi::MidiMessage;
+use crate::midi::{MidiMessage, Button};
 use std::collections::{HashSet, HashMap};
+use std::time::{Duration, Instant};
 use crate::macros::preconditions::midi::MidiPrecondition;
-use crate::match_checker::MatchChecker;
+use crate::match_checker::{MatchChecker, NumMatch, NumberMatcher};
+use crate::macros::actions::template::MidiVariable;
+
+/// Thresholds for telling apart different ways of pressing a button, used by `MidiState` to
+/// produce `Tap`, `DoubleTap`, `LongPress`, `HoldRepeat`, and `Release` messages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PressTiming {
+    /// A press released within this time is a tap
+    pub tap: Duration,
+
+    /// A tap released within this time of the previous tap's release is a double tap
+    pub double_tap: Duration,
+
+    /// A button held for this long is long-pressed
+    pub long_press: Duration,
+
+    /// How often a long-pressed button repeats while it is still held
+    pub hold_repeat: Duration,
+
+    /// Which controls are buttons, sending a value of 64 or more when pressed, and less when
+    /// released. If `None`, no controls are treated as buttons, only keys.
+    pub button_controls: Option<NumberMatcher>
+}
+
+impl Default for PressTiming {
+    fn default() -> Self {
+        PressTiming {
+            tap: Duration::from_millis(250),
+            double_tap: Duration::from_millis(300),
+            long_press: Duration::from_millis(500),
+            hold_repeat: Duration::from_millis(100),
+            button_controls: None
+        }
+    }
+}
 
 /// State tracking container for MIDI messages.
 ///
 /// MidiState keeps track off:
 /// - Which notes are currently pressed / "on" for each channel
 /// - All known values of controls per channel
+/// - All known values of high resolution controls, NRPN, and RPN parameters per channel
 /// - All known selected programs for each channel
 /// - All known pitch bend values for each channel
+/// - When each button that is currently held was pressed, and when each button was last tapped
 ///
 /// It only starts storing values for each of these the moment a MIDI message with relevant
 /// data comes in. If for example, a key was pressed before the program was running, MidiState
@@ -27,7 +64,42 @@ pub(crate) struct MidiState {
 
     // Pitch bend positions for each channel that we've received pitch bend messages for
     // The key number here is the channel.
-    pitch_bend_values: HashMap<u8, u16>
+    pitch_bend_values: HashMap<u8, u16>,
+
+    // 14-bit values for any high resolution controls we've received assembled messages about
+    controls_14: HashMap<Control, u16>,
+
+    // Values for any non-registered parameters we've received assembled messages about
+    nrpn_values: HashMap<Parameter, u16>,
+
+    // Values for any registered parameters we've received assembled messages about
+    rpn_values: HashMap<Parameter, u16>,
+
+    // Buttons that are currently held, along with when they were pressed
+    presses: HashMap<ChannelButton, Press>,
+
+    // When each button was last tapped, if that tap didn't complete a double tap
+    last_taps: HashMap<ChannelButton, Instant>
+}
+
+/// Represents a unique button, scoped by channel
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Copy)]
+struct ChannelButton {
+    channel: u8,
+    button: Button
+}
+
+/// A button that is being held down
+#[derive(Debug)]
+struct Press {
+    /// When the button was pressed
+    time: Instant,
+
+    /// Whether a `LongPress` was sent for this press yet
+    long_pressed: bool,
+
+    /// How many `HoldRepeat`s were counted for this press so far
+    repeats: u32
 }
 
 /// Represents a unique note, scoped by channel and key
@@ -44,14 +116,158 @@ struct Control {
     control: u8,
 }
 
+/// Represents a unique (N)RPN parameter, scoped by channel and parameter number
+#[derive(Hash, Eq, PartialEq, Debug)]
+struct Parameter {
+    channel: u8,
+    parameter: u16,
+}
+
 impl MidiState {
     pub fn new() -> MidiState {
         MidiState {
             notes_on: HashSet::new(),
             controls: HashMap::new(),
             programs: HashMap::new(),
-            pitch_bend_values: HashMap::new()
+            pitch_bend_values: HashMap::new(),
+            controls_14: HashMap::new(),
+            nrpn_values: HashMap::new(),
+            rpn_values: HashMap::new(),
+            presses: HashMap::new(),
+            last_taps: HashMap::new()
+        }
+    }
+
+    /// Keeps track of buttons being pressed and released in `msg`, received at `now`, returning
+    /// any `Release`, `Tap`, and `DoubleTap` messages that result from it.
+    ///
+    /// Keys are pressed by note on messages, and released by note off messages or note on messages
+    /// with a velocity of 0. Controls are only buttons if `timing.button_controls` says so.
+    pub fn process_press(
+        &mut self,
+        msg: &MidiMessage,
+        now: Instant,
+        timing: &PressTiming
+    ) -> Vec<MidiMessage> {
+        let (channel, button, is_pressed) = match msg {
+            MidiMessage::NoteOn { channel, key, velocity } => {
+                (*channel, Button::Note { key: *key }, *velocity > 0)
+            }
+
+            MidiMessage::NoteOff { channel, key, .. } => {
+                (*channel, Button::Note { key: *key }, false)
+            }
+
+            MidiMessage::ControlChange { channel, control, value } => {
+                let is_button = timing.button_controls
+                    .as_ref()
+                    .is_some_and(|matcher| matcher.matches(&u32::from(*control)));
+
+                if !is_button {
+                    return vec![];
+                }
+
+                (*channel, Button::Control { control: *control }, *value >= 64)
+            }
+
+            _ => return vec![]
+        };
+
+        let channel_button = ChannelButton { channel, button };
+
+        if is_pressed {
+            // A button that is already held keeps its original press time
+            self.presses
+                .entry(channel_button)
+                .or_insert(Press { time: now, long_pressed: false, repeats: 0 });
+
+            return vec![];
+        }
+
+        let press = match self.presses.remove(&channel_button) {
+            Some(press) => press,
+            None => return vec![]
+        };
+
+        let held = now.saturating_duration_since(press.time);
+
+        let mut messages = vec![MidiMessage::Release {
+            channel,
+            button,
+            duration: held.as_millis().min(u32::MAX as u128) as u32
+        }];
+
+        if press.long_pressed || held > timing.tap {
+            return messages;
         }
+
+        messages.push(MidiMessage::Tap { channel, button });
+
+        let is_double_tap = self.last_taps
+            .get(&channel_button)
+            .is_some_and(|last_tap| now.saturating_duration_since(*last_tap) <= timing.double_tap);
+
+        if is_double_tap {
+            // Starting over after a double tap, so a third tap doesn't make another double tap
+            self.last_taps.remove(&channel_button);
+            messages.push(MidiMessage::DoubleTap { channel, button });
+        } else {
+            self.last_taps.insert(channel_button, now);
+        }
+
+        messages
+    }
+
+    /// Checks the buttons that are currently held, returning `LongPress` and `HoldRepeat`
+    /// messages for any that have been held long enough by `now`.
+    ///
+    /// If more than one repeat is due for a button, because this wasn't called in time, only one
+    /// `HoldRepeat` is returned for it, counting all the repeats that were due.
+    pub fn poll_presses(&mut self, now: Instant, timing: &PressTiming) -> Vec<MidiMessage> {
+        let mut messages = vec![];
+
+        for (channel_button, press) in self.presses.iter_mut() {
+            let held = now.saturating_duration_since(press.time);
+
+            if held < timing.long_press {
+                continue;
+            }
+
+            let ChannelButton { channel, button } = *channel_button;
+
+            if !press.long_pressed {
+                press.long_pressed = true;
+                messages.push(MidiMessage::LongPress { channel, button });
+            }
+
+            if timing.hold_repeat.is_zero() {
+                continue;
+            }
+
+            let repeats = ((held - timing.long_press).as_millis()
+                / timing.hold_repeat.as_millis().max(1)) as u32;
+
+            if repeats > press.repeats {
+                press.repeats = repeats;
+                messages.push(MidiMessage::HoldRepeat { channel, button, count: repeats });
+            }
+        }
+
+        messages
+    }
+
+    /// The next time `poll_presses` will have something to return, if any buttons are held
+    pub fn next_press_deadline(&self, timing: &PressTiming) -> Option<Instant> {
+        self.presses
+            .values()
+            .map(|press| {
+                if !press.long_pressed || timing.hold_repeat.is_zero() {
+                    press.time + timing.long_press
+                } else {
+                    press.time + timing.long_press + timing.hold_repeat * (press.repeats + 1)
+                }
+            })
+            .min()
     }
 
     /// Processes an incoming MIDI message, mutating itself as a result.
@@ -61,6 +277,9 @@ impl MidiState {
     /// - ControlChange
     /// - ProgramChange
     /// - PitchBendChange
+    /// - ControlChange14
+    /// - Nrpn
+    /// - Rpn
     ///
     /// Any other messages are ignored.
     pub fn process_message(&mut self, msg: &MidiMessage) {
@@ -92,10 +311,101 @@ impl MidiState {
                 self.pitch_bend_values.insert(*channel, *value);
             }
 
+            MidiMessage::ControlChange14 { channel, control, value } => {
+                self.controls_14.insert(
+                    Control { channel: *channel, control: *control },
+                    *value
+                );
+            }
+
+            MidiMessage::Nrpn { channel, parameter, value } => {
+                self.nrpn_values.insert(
+                    Parameter { channel: *channel, parameter: *parameter },
+                    *value
+                );
+            }
+
+            MidiMessage::Rpn { channel, parameter, value } => {
+                self.rpn_values.insert(
+                    Parameter { channel: *channel, parameter: *parameter },
+                    *value
+                );
+            }
+
             _ => {}
         }
     }
 
+    /// Lists messages that, passed to `process_message` on a new `MidiState`, rebuild the values
+    /// remembered here: controls, programs, pitch bend, and (N)RPN parameters. Which notes are
+    /// held and button presses are left out, since they won't be true anymore by the time the
+    /// messages are used.
+    ///
+    /// Messages are ordered by type, then channel, then control or parameter number.
+    pub fn snapshot(&self) -> Vec<MidiMessage> {
+        let mut controls: Vec<(&Control, &u8)> = self.controls.iter().collect();
+        controls.sort_by_key(|(control, _)| (control.channel, control.control));
+
+        let mut programs: Vec<(&u8, &u8)> = self.programs.iter().collect();
+        programs.sort();
+
+        let mut pitch_bend_values: Vec<(&u8, &u16)> = self.pitch_bend_values.iter().collect();
+        pitch_bend_values.sort();
+
+        let mut controls_14: Vec<(&Control, &u16)> = self.controls_14.iter().collect();
+        controls_14.sort_by_key(|(control, _)| (control.channel, control.control));
+
+        let mut nrpn_values: Vec<(&Parameter, &u16)> = self.nrpn_values.iter().collect();
+        nrpn_values.sort_by_key(|(parameter, _)| (parameter.channel, parameter.parameter));
+
+        let mut rpn_values: Vec<(&Parameter, &u16)> = self.rpn_values.iter().collect();
+        rpn_values.sort_by_key(|(parameter, _)| (parameter.channel, parameter.parameter));
+
+        let mut messages = vec![];
+
+        messages.extend(controls.into_iter().map(|(control, value)| {
+            MidiMessage::ControlChange {
+                channel: control.channel,
+                control: control.control,
+                value: *value
+            }
+        }));
+
+        messages.extend(programs.into_iter().map(|(channel, program)| {
+            MidiMessage::ProgramChange { channel: *channel, program: *program }
+        }));
+
+        messages.extend(pitch_bend_values.into_iter().map(|(channel, value)| {
+            MidiMessage::PitchBendChange { channel: *channel, value: *value }
+        }));
+
+        messages.extend(controls_14.into_iter().map(|(control, value)| {
+            MidiMessage::ControlChange14 {
+                channel: control.channel,
+                control: control.control,
+                value: *value
+            }
+        }));
+
+        messages.extend(nrpn_values.into_iter().map(|(parameter, value)| {
+            MidiMessage::Nrpn {
+                channel: parameter.channel,
+                parameter: parameter.parameter,
+                value: *value
+            }
+        }));
+
+        messages.extend(rpn_values.into_iter().map(|(parameter, value)| {
+            MidiMessage::Rpn {
+                channel: parameter.channel,
+                parameter: parameter.parameter,
+                value: *value
+            }
+        }));
+
+        messages
+    }
+
     /// Checks if a MidiPrecondition matches against this MIDI state
     pub fn matches(&self, precondition: &MidiPrecondition) -> bool {
         match precondition {
@@ -131,6 +441,69 @@ impl MidiState {
                         value_match.matches(&(*value as u32))
                 })
             }
+
+            MidiPrecondition::Control14 {
+                channel_match,
+                control_match,
+                value_match
+            } => {
+                self.controls_14.iter().any(|(control, value)| {
+                    channel_match.matches(&(control.channel as u32)) &&
+                        control_match.matches(&(control.control as u32)) &&
+                        value_match.matches(&(*value as u32))
+                })
+            }
+
+            MidiPrecondition::Nrpn { channel_match, parameter_match, value_match } => {
+                Self::matches_parameter(&self.nrpn_values, channel_match, parameter_match, value_match)
+            }
+
+            MidiPrecondition::Rpn { channel_match, parameter_match, value_match } => {
+                Self::matches_parameter(&self.rpn_values, channel_match, parameter_match, value_match)
+            }
+
+            // Which device this state belongs to is tracked by its owner, so only the wrapped
+            // condition can be checked here.
+            MidiPrecondition::Device { condition, .. } => self.matches(condition)
+        }
+    }
+
+    fn matches_parameter(
+        values: &HashMap<Parameter, u16>,
+        channel_match: &NumMatch,
+        parameter_match: &NumMatch,
+        value_match: &NumMatch
+    ) -> bool {
+        values.iter().any(|(parameter, value)| {
+            channel_match.matches(&(parameter.channel as u32)) &&
+                parameter_match.matches(&(parameter.parameter as u32)) &&
+                value_match.matches(&(*value as u32))
+        })
+    }
+
+    /// Retrieves the value for a MIDI variable, formatted as a string.
+    /// Notes are always known: "1" if held, "0" if not. Other values return `None` if no
+    /// relevant message has been received yet.
+    pub fn get_value(&self, variable: &MidiVariable) -> Option<String> {
+        match variable {
+            MidiVariable::NoteOn { channel, key } => {
+                let is_on = self.notes_on.contains(&Note { channel: *channel, key: *key });
+                Some(if is_on { "1" } else { "0" }.to_string())
+            }
+
+            MidiVariable::Control { channel, control } => {
+                self.controls
+                    .get(&Control { channel: *channel, control: *control })
+                    .map(|value| value.to_string())
+            }
+
+            MidiVariable::Program { channel } => {
+                self.programs.get(channel).map(|program| program.to_string())
+            }
+
+            MidiVariable::PitchBend { channel } => {
+                self.pitch_bend_values.get(channel).map(|value| value.to_string())
+            }
         }
     }
 }
@@ -138,7 +511,7 @@ impl MidiState {
 #[cfg(test)]
 mod state_keeping_tests {
     use crate::midi::MidiMessage;
-    use crate::state::midi_state::{MidiState, Note, Control};
+    use crate::state::midi_state::{MidiState, Note, Control, Parameter};
 
     #[test]
     fn keeps_track_of_notes_held() {
@@ -347,6 +720,74 @@ mod state_keeping_tests {
 
         assert_eq!(state.pitch_bend_values.get(&channel), Some(&421));
     }
+
+    #[test]
+    fn keeps_track_of_high_resolution_values() {
+        let mut state = MidiState::new();
+
+        state.process_message(&MidiMessage::ControlChange14 { channel: 1, control: 7, value: 9000 });
+        state.process_message(&MidiMessage::Nrpn { channel: 1, parameter: 300, value: 12 });
+        state.process_message(&MidiMessage::Rpn { channel: 1, parameter: 300, value: 34 });
+
+        assert_eq!(state.controls_14.get(&Control { channel: 1, control: 7 }), Some(&9000));
+
+        // 14-bit values are kept separate from 7-bit values
+        assert!(!state.controls.contains_key(&Control { channel: 1, control: 7 }));
+
+        assert_eq!(state.nrpn_values.get(&Parameter { channel: 1, parameter: 300 }), Some(&12));
+        assert_eq!(state.rpn_values.get(&Parameter { channel: 1, parameter: 300 }), Some(&34));
+    }
+}
+
+#[cfg(test)]
+mod snapshot_tests {
+    use crate::midi::MidiMessage;
+    use crate::state::midi_state::MidiState;
+
+    #[test]
+    fn rebuilds_remembered_values_from_snapshot() {
+        let mut state = MidiState::new();
+
+        let messages = [
+            MidiMessage::ControlChange { channel: 1, control: 20, value: 64 },
+            MidiMessage::ControlChange { channel: 0, control: 7, value: 100 },
+            MidiMessage::ProgramChange { channel: 2, program: 5 },
+            MidiMessage::PitchBendChange { channel: 0, value: 8192 },
+            MidiMessage::ControlChange14 { channel: 0, control: 1, value: 1000 },
+            MidiMessage::Nrpn { channel: 3, parameter: 300, value: 12 },
+            MidiMessage::Rpn { channel: 3, parameter: 0, value: 2 },
+            MidiMessage::NoteOn { channel: 0, key: 36, velocity: 100 },
+        ];
+
+        for message in messages.iter() {
+            state.process_message(message);
+        }
+
+        let snapshot = state.snapshot();
+
+        // Held notes aren't part of it, and the rest is in a fixed order
+        assert_eq!(
+            snapshot,
+            vec![
+                MidiMessage::ControlChange { channel: 0, control: 7, value: 100 },
+                MidiMessage::ControlChange { channel: 1, control: 20, value: 64 },
+                MidiMessage::ProgramChange { channel: 2, program: 5 },
+                MidiMessage::PitchBendChange { channel: 0, value: 8192 },
+                MidiMessage::ControlChange14 { channel: 0, control: 1, value: 1000 },
+                MidiMessage::Nrpn { channel: 3, parameter: 300, value: 12 },
+                MidiMessage::Rpn { channel: 3, parameter: 0, value: 2 },
+            ]
+        );
+
+        let mut restored = MidiState::new();
+
+        for message in snapshot.iter() {
+            restored.process_message(message);
+        }
+
+        assert_eq!(restored.snapshot(), snapshot);
+        assert!(restored.notes_on.is_empty());
+    }
 }
 
 #[cfg(test)]
@@ -547,4 +988,261 @@ mod precondition_matching_tests {
 
         assert!(!state.matches(&condition));
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod high_resolution_precondition_matching_tests {
+    use crate::macros::preconditions::midi::MidiPrecondition;
+    use crate::match_checker::NumberMatcher;
+    use crate::state::midi_state::{MidiState, Control, Parameter};
+
+    #[test]
+    fn matches_a_14_bit_control_value() {
+        let condition = MidiPrecondition::Control14 {
+            channel_match: Some(NumberMatcher::Val(1)),
+            control_match: Some(NumberMatcher::Val(7)),
+            value_match: Some(NumberMatcher::Range { min: Some(8192), max: None })
+        };
+
+        let mut state = MidiState::new();
+
+        assert!(!state.matches(&condition));
+
+        state.controls_14.insert(Control { channel: 1, control: 7 }, 8000);
+        assert!(!state.matches(&condition));
+
+        state.controls_14.insert(Control { channel: 1, control: 7 }, 9000);
+        assert!(state.matches(&condition));
+
+        // A 7-bit value for the same control doesn't count
+        let mut state = MidiState::new();
+        state.controls.insert(Control { channel: 1, control: 7 }, 127);
+        assert!(!state.matches(&condition));
+    }
+
+    #[test]
+    fn matches_nrpn_and_rpn_values_separately() {
+        let nrpn_condition = MidiPrecondition::Nrpn {
+            channel_match: None,
+            parameter_match: Some(NumberMatcher::Val(300)),
+            value_match: Some(NumberMatcher::Val(12))
+        };
+
+        let rpn_condition = MidiPrecondition::Rpn {
+            channel_match: None,
+            parameter_match: Some(NumberMatcher::Val(300)),
+            value_match: Some(NumberMatcher::Val(12))
+        };
+
+        let mut state = MidiState::new();
+        state.nrpn_values.insert(Parameter { channel: 0, parameter: 300 }, 12);
+
+        assert!(state.matches(&nrpn_condition));
+        assert!(!state.matches(&rpn_condition));
+
+        let mut state = MidiState::new();
+        state.rpn_values.insert(Parameter { channel: 0, parameter: 300 }, 12);
+
+        assert!(!state.matches(&nrpn_condition));
+        assert!(state.matches(&rpn_condition));
+    }
+}
+
+#[cfg(test)]
+mod variable_value_tests {
+    use crate::macros::actions::template::MidiVariable;
+    use crate::state::midi_state::{MidiState, Note, Control};
+
+    #[test]
+    fn gets_note_on_value() {
+        let variable = MidiVariable::NoteOn { channel: 1, key: 20 };
+
+        let mut state = MidiState::new();
+
+        assert_eq!(state.get_value(&variable), Some("0".to_string()));
+
+        state.notes_on.insert(Note { channel: 1, key: 20 });
+
+        assert_eq!(state.get_value(&variable), Some("1".to_string()));
+    }
+
+    #[test]
+    fn gets_control_value() {
+        let variable = MidiVariable::Control { channel: 1, control: 20 };
+
+        let mut state = MidiState::new();
+
+        assert_eq!(state.get_value(&variable), None);
+
+        state.controls.insert(Control { channel: 1, control: 20 }, 40);
+        state.controls.insert(Control { channel: 2, control: 20 }, 50);
+
+        assert_eq!(state.get_value(&variable), Some("40".to_string()));
+    }
+
+    #[test]
+    fn gets_program_value() {
+        let variable = MidiVariable::Program { channel: 1 };
+
+        let mut state = MidiState::new();
+
+        assert_eq!(state.get_value(&variable), None);
+
+        state.programs.insert(1, 20);
+
+        assert_eq!(state.get_value(&variable), Some("20".to_string()));
+    }
+
+    #[test]
+    fn gets_pitch_bend_value() {
+        let variable = MidiVariable::PitchBend { channel: 1 };
+
+        let mut state = MidiState::new();
+
+        assert_eq!(state.get_value(&variable), None);
+
+        state.pitch_bend_values.insert(1, 9001);
+
+        assert_eq!(state.get_value(&variable), Some("9001".to_string()));
+    }
+}
+
+#[cfg(test)]
+mod press_timing_tests {
+    use std::time::{Duration, Instant};
+    use crate::match_checker::NumberMatcher;
+    use crate::midi::{MidiMessage, Button};
+    use crate::state::midi_state::{MidiState, PressTiming};
+
+    const PAD: Button = Button::Note { key: 36 };
+
+    fn press(state: &mut MidiState, at: Instant) -> Vec<MidiMessage> {
+        let msg = MidiMessage::NoteOn { channel: 0, key: 36, velocity: 100 };
+        state.process_press(&msg, at, &PressTiming::default())
+    }
+
+    fn release(state: &mut MidiState, at: Instant) -> Vec<MidiMessage> {
+        let msg = MidiMessage::NoteOff { channel: 0, key: 36, velocity: 0 };
+        state.process_press(&msg, at, &PressTiming::default())
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn produces_taps_and_double_taps() {
+        let mut state = MidiState::new();
+        let start = Instant::now();
+
+        assert!(press(&mut state, start).is_empty());
+
+        assert_eq!(
+            release(&mut state, start + ms(100)),
+            [
+                MidiMessage::Release { channel: 0, button: PAD, duration: 100 },
+                MidiMessage::Tap { channel: 0, button: PAD },
+            ]
+        );
+
+        press(&mut state, start + ms(200));
+
+        assert_eq!(
+            release(&mut state, start + ms(300)),
+            [
+                MidiMessage::Release { channel: 0, button: PAD, duration: 100 },
+                MidiMessage::Tap { channel: 0, button: PAD },
+                MidiMessage::DoubleTap { channel: 0, button: PAD },
+            ]
+        );
+
+        // A third tap starts over
+        press(&mut state, start + ms(400));
+        assert_eq!(release(&mut state, start + ms(450)).len(), 2);
+    }
+
+    #[test]
+    fn produces_only_a_release_for_slow_presses() {
+        let mut state = MidiState::new();
+        let start = Instant::now();
+
+        press(&mut state, start);
+
+        assert_eq!(
+            release(&mut state, start + ms(400)),
+            [MidiMessage::Release { channel: 0, button: PAD, duration: 400 }]
+        );
+
+        // Releasing a button that wasn't pressed does nothing
+        assert!(release(&mut state, start + ms(500)).is_empty());
+    }
+
+    #[test]
+    fn produces_long_presses_and_hold_repeats() {
+        let timing = PressTiming::default();
+        let mut state = MidiState::new();
+        let start = Instant::now();
+
+        assert_eq!(state.next_press_deadline(&timing), None);
+
+        press(&mut state, start);
+        assert_eq!(state.next_press_deadline(&timing), Some(start + ms(500)));
+        assert!(state.poll_presses(start + ms(499), &timing).is_empty());
+
+        assert_eq!(
+            state.poll_presses(start + ms(500), &timing),
+            [MidiMessage::LongPress { channel: 0, button: PAD }]
+        );
+
+        assert_eq!(state.next_press_deadline(&timing), Some(start + ms(600)));
+
+        assert_eq!(
+            state.poll_presses(start + ms(600), &timing),
+            [MidiMessage::HoldRepeat { channel: 0, button: PAD, count: 1 }]
+        );
+
+        // Late poll: the missed repeats are counted, but only sent once
+        assert_eq!(
+            state.poll_presses(start + ms(950), &timing),
+            [MidiMessage::HoldRepeat { channel: 0, button: PAD, count: 4 }]
+        );
+
+        // No tap after a long press, even if released quickly afterwards
+        assert_eq!(
+            release(&mut state, start + ms(1000)),
+            [MidiMessage::Release { channel: 0, button: PAD, duration: 1000 }]
+        );
+
+        assert_eq!(state.next_press_deadline(&timing), None);
+    }
+
+    #[test]
+    fn treats_only_configured_controls_as_buttons() {
+        let timing = PressTiming {
+            button_controls: Some(NumberMatcher::Val(64)),
+            ..PressTiming::default()
+        };
+
+        let mut state = MidiState::new();
+        let start = Instant::now();
+
+        let cc = |control: u8, value: u8| MidiMessage::ControlChange { channel: 2, control, value };
+
+        assert!(state.process_press(&cc(1, 127), start, &timing).is_empty());
+        assert!(state.process_press(&cc(1, 0), start + ms(10), &timing).is_empty());
+
+        state.process_press(&cc(64, 127), start, &timing);
+
+        assert_eq!(
+            state.process_press(&cc(64, 0), start + ms(10), &timing),
+            [
+                MidiMessage::Release {
+                    channel: 2,
+                    button: Button::Control { control: 64 },
+                    duration: 10
+                },
+                MidiMessage::Tap { channel: 2, button: Button::Control { control: 64 } },
+            ]
+        );
+    }
+}
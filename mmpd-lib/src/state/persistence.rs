@@ -0,0 +1,344 @@
+//! Stores `SavedState` as YAML text, so state can be written to a file and restored from it after
+//! a restart.
+//!
+//! The text looks as follows:
+//!
+//! ```yaml
+//! schema_version: 1
+//! midi:
+//!   - message_type: control_change
+//!     channel: 0
+//!     control: 7
+//!     value: 100
+//! devices:
+//!   faders:
+//!     - message_type: control_change
+//!       channel: 0
+//!       control: 7
+//!       value: 100
+//! variables:
+//!   layer: editing
+//! ```
+
+use yaml_rust::{Yaml, YamlEmitter};
+use yaml_rust::yaml::Hash;
+use crate::config::ConfigError;
+use crate::config::input_formats::get_parser_for_extension;
+use crate::config::raw_config::{RawConfig, RCHash, AccessHelpers};
+use crate::midi::MidiMessage;
+use crate::state::{SavedState, Value};
+
+/// Version of the format written by `to_yaml`. Text with any other version is refused by
+/// `from_yaml`, rather than guessing at what it means.
+pub const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA_VERSION_FIELD: &str = "schema_version";
+const MIDI_FIELD: &str = "midi";
+const DEVICES_FIELD: &str = "devices";
+const VARIABLES_FIELD: &str = "variables";
+
+const MESSAGE_TYPE_FIELD: &str = "message_type";
+const CHANNEL_FIELD: &str = "channel";
+const CONTROL_FIELD: &str = "control";
+const PROGRAM_FIELD: &str = "program";
+const PARAMETER_FIELD: &str = "parameter";
+const VALUE_FIELD: &str = "value";
+
+const CONTROL_CHANGE_TYPE: &str = "control_change";
+const PROGRAM_CHANGE_TYPE: &str = "program_change";
+const PITCH_BEND_CHANGE_TYPE: &str = "pitch_bend_change";
+const CONTROL_CHANGE_14_TYPE: &str = "control_change_14";
+const NRPN_TYPE: &str = "nrpn";
+const RPN_TYPE: &str = "rpn";
+
+/// Writes `saved` out as YAML text
+pub fn to_yaml(saved: &SavedState) -> String {
+    let mut doc = Hash::new();
+    doc.insert(key(SCHEMA_VERSION_FIELD), Yaml::Integer(SCHEMA_VERSION));
+    doc.insert(key(MIDI_FIELD), messages_to_yaml(&saved.midi));
+
+    doc.insert(key(DEVICES_FIELD), Yaml::Hash(
+        saved.devices
+            .iter()
+            .map(|(device, messages)| (key(device), messages_to_yaml(messages)))
+            .collect()
+    ));
+
+    doc.insert(key(VARIABLES_FIELD), Yaml::Hash(
+        saved.variables
+            .iter()
+            .map(|(name, value)| (key(name), value_to_yaml(value)))
+            .collect()
+    ));
+
+    let mut text = String::new();
+
+    // Writing to a String can't fail
+    YamlEmitter::new(&mut text).dump(&Yaml::Hash(doc)).expect("Unable to write YAML");
+
+    text.push('\n');
+    text
+}
+
+/// Reads `SavedState` back from YAML text written by `to_yaml`.
+///
+/// ## Errors
+/// This function returns `ConfigError` if the text isn't valid YAML, is from a different
+/// `SCHEMA_VERSION`, or contains anything `to_yaml` wouldn't have written.
+pub fn from_yaml(text: &str) -> Result<SavedState, ConfigError> {
+    let parser = get_parser_for_extension("yml").expect("YAML parser missing");
+
+    let doc = match parser.parse(text)? {
+        RawConfig::Hash(doc) => doc,
+        _ => return Err(invalid("expected a hash at the top level"))
+    };
+
+    match doc.get_integer(SCHEMA_VERSION_FIELD) {
+        Some(SCHEMA_VERSION) => {}
+
+        Some(version) => {
+            return Err(ConfigError::UnsupportedVersion(format!(
+                "State file has schema version {}, only version {} is supported",
+                version, SCHEMA_VERSION
+            )));
+        }
+
+        None => return Err(invalid(&format!("missing '{}'", SCHEMA_VERSION_FIELD)))
+    }
+
+    let midi = match doc.get_array(MIDI_FIELD) {
+        Some(raw_messages) => messages_from_raw(raw_messages)?,
+        None => vec![]
+    };
+
+    let mut devices = vec![];
+
+    if let Some(raw_devices) = doc.get_hash(DEVICES_FIELD) {
+        for (device, raw_messages) in raw_devices {
+            match (device, raw_messages) {
+                (RawConfig::String(device), RawConfig::Array(raw_messages)) => {
+                    devices.push((device.to_string(), messages_from_raw(raw_messages)?));
+                }
+
+                _ => return Err(invalid(&format!("invalid device '{}'", device)))
+            }
+        }
+    }
+
+    let mut variables = vec![];
+
+    if let Some(raw_variables) = doc.get_hash(VARIABLES_FIELD) {
+        for (name, raw_value) in raw_variables {
+            let name = match name {
+                RawConfig::String(name) => name,
+                _ => return Err(invalid(&format!("invalid variable name '{}'", name)))
+            };
+
+            let value = match raw_value {
+                RawConfig::String(s) => Value::String(s.to_string()),
+                RawConfig::Integer(i) => Value::Integer(*i),
+                RawConfig::Bool(b) => Value::Bool(*b),
+                _ => return Err(invalid(&format!("invalid value for variable '{}'", name)))
+            };
+
+            variables.push((name.to_string(), value));
+        }
+    }
+
+    Ok(SavedState { midi, devices, variables })
+}
+
+fn key(name: &str) -> Yaml {
+    Yaml::String(name.to_string())
+}
+
+fn invalid(description: &str) -> ConfigError {
+    ConfigError::InvalidConfig(format!("State file: {}", description))
+}
+
+fn value_to_yaml(value: &Value) -> Yaml {
+    match value {
+        Value::String(s) => Yaml::String(s.to_string()),
+        Value::Integer(i) => Yaml::Integer(*i),
+        Value::Bool(b) => Yaml::Boolean(*b)
+    }
+}
+
+fn messages_to_yaml(messages: &[MidiMessage]) -> Yaml {
+    Yaml::Array(messages.iter().filter_map(message_to_yaml).collect())
+}
+
+/// Writes `message` out as a hash, if it is one of the types of messages `MidiState::snapshot`
+/// produces
+fn message_to_yaml(message: &MidiMessage) -> Option<Yaml> {
+    let (message_type, channel, fields) = match message {
+        MidiMessage::ControlChange { channel, control, value } => (
+            CONTROL_CHANGE_TYPE,
+            channel,
+            vec![(CONTROL_FIELD, *control as i64), (VALUE_FIELD, *value as i64)]
+        ),
+
+        MidiMessage::ProgramChange { channel, program } => (
+            PROGRAM_CHANGE_TYPE,
+            channel,
+            vec![(PROGRAM_FIELD, *program as i64)]
+        ),
+
+        MidiMessage::PitchBendChange { channel, value } => (
+            PITCH_BEND_CHANGE_TYPE,
+            channel,
+            vec![(VALUE_FIELD, *value as i64)]
+        ),
+
+        MidiMessage::ControlChange14 { channel, control, value } => (
+            CONTROL_CHANGE_14_TYPE,
+            channel,
+            vec![(CONTROL_FIELD, *control as i64), (VALUE_FIELD, *value as i64)]
+        ),
+
+        MidiMessage::Nrpn { channel, parameter, value } => (
+            NRPN_TYPE,
+            channel,
+            vec![(PARAMETER_FIELD, *parameter as i64), (VALUE_FIELD, *value as i64)]
+        ),
+
+        MidiMessage::Rpn { channel, parameter, value } => (
+            RPN_TYPE,
+            channel,
+            vec![(PARAMETER_FIELD, *parameter as i64), (VALUE_FIELD, *value as i64)]
+        ),
+
+        _ => return None
+    };
+
+    let mut hash = Hash::new();
+    hash.insert(key(MESSAGE_TYPE_FIELD), key(message_type));
+    hash.insert(key(CHANNEL_FIELD), Yaml::Integer(*channel as i64));
+
+    for (field, value) in fields {
+        hash.insert(key(field), Yaml::Integer(value));
+    }
+
+    Some(Yaml::Hash(hash))
+}
+
+fn messages_from_raw(raw_messages: &[RawConfig]) -> Result<Vec<MidiMessage>, ConfigError> {
+    raw_messages
+        .iter()
+        .map(|raw_message| match raw_message {
+            RawConfig::Hash(raw_message) => message_from_raw(raw_message),
+            _ => Err(invalid("expected MIDI messages to be hashes"))
+        })
+        .collect()
+}
+
+fn message_from_raw(raw_message: &RCHash) -> Result<MidiMessage, ConfigError> {
+    let get = |field: &str, max: i64| {
+        raw_message
+            .get_integer(field)
+            .filter(|value| (0..=max).contains(value))
+            .ok_or_else(|| invalid(&format!("missing or out of range MIDI message '{}'", field)))
+    };
+
+    let message_type = raw_message
+        .get_string(MESSAGE_TYPE_FIELD)
+        .ok_or_else(|| invalid(&format!("missing MIDI message '{}'", MESSAGE_TYPE_FIELD)))?;
+
+    let channel = get(CHANNEL_FIELD, 15)? as u8;
+
+    Ok(match message_type {
+        CONTROL_CHANGE_TYPE => MidiMessage::ControlChange {
+            channel,
+            control: get(CONTROL_FIELD, 127)? as u8,
+            value: get(VALUE_FIELD, 127)? as u8
+        },
+
+        PROGRAM_CHANGE_TYPE => MidiMessage::ProgramChange {
+            channel,
+            program: get(PROGRAM_FIELD, 127)? as u8
+        },
+
+        PITCH_BEND_CHANGE_TYPE => MidiMessage::PitchBendChange {
+            channel,
+            value: get(VALUE_FIELD, 16383)? as u16
+        },
+
+        CONTROL_CHANGE_14_TYPE => MidiMessage::ControlChange14 {
+            channel,
+            control: get(CONTROL_FIELD, 31)? as u8,
+            value: get(VALUE_FIELD, 16383)? as u16
+        },
+
+        NRPN_TYPE => MidiMessage::Nrpn {
+            channel,
+            parameter: get(PARAMETER_FIELD, 16383)? as u16,
+            value: get(VALUE_FIELD, 16383)? as u16
+        },
+
+        RPN_TYPE => MidiMessage::Rpn {
+            channel,
+            parameter: get(PARAMETER_FIELD, 16383)? as u16,
+            value: get(VALUE_FIELD, 16383)? as u16
+        },
+
+        _ => return Err(invalid(&format!("unknown MIDI message type '{}'", message_type)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ConfigError;
+    use crate::midi::MidiMessage;
+    use crate::state::{SavedState, Value};
+    use crate::state::persistence::{to_yaml, from_yaml};
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let saved = SavedState {
+            midi: vec![
+                MidiMessage::ControlChange { channel: 0, control: 7, value: 100 },
+                MidiMessage::ProgramChange { channel: 2, program: 5 },
+                MidiMessage::PitchBendChange { channel: 0, value: 8192 },
+                MidiMessage::ControlChange14 { channel: 0, control: 1, value: 1000 },
+                MidiMessage::Nrpn { channel: 3, parameter: 300, value: 12 },
+                MidiMessage::Rpn { channel: 3, parameter: 0, value: 2 },
+            ],
+            devices: vec![(
+                "faders".to_string(),
+                vec![MidiMessage::ControlChange { channel: 0, control: 7, value: 100 }]
+            )],
+            variables: vec![
+                ("layer".to_string(), Value::String("media".to_string())),
+                ("looks_like_a_number".to_string(), Value::String("3".to_string())),
+                ("looks_like_a_bool".to_string(), Value::String("true".to_string())),
+                ("page".to_string(), Value::Integer(-2)),
+                ("shift".to_string(), Value::Bool(true)),
+            ]
+        };
+
+        let text = to_yaml(&saved);
+        assert!(text.contains("schema_version: 1"));
+        assert_eq!(from_yaml(&text).ok().unwrap(), saved);
+
+        assert_eq!(from_yaml(&to_yaml(&SavedState::default())).ok().unwrap(), SavedState::default());
+    }
+
+    #[test]
+    fn refuses_other_schema_versions_and_invalid_messages() {
+        assert!(matches!(
+            from_yaml("schema_version: 2\nmidi: []\n"),
+            Err(ConfigError::UnsupportedVersion(_))
+        ));
+
+        assert!(from_yaml("midi: []\n").is_err());
+
+        let out_of_range = "schema_version: 1\n\
+                            midi:\n  \
+                              - message_type: control_change\n    \
+                                channel: 16\n    \
+                                control: 7\n    \
+                                value: 100\n";
+
+        assert!(from_yaml(out_of_range).is_err());
+    }
+}
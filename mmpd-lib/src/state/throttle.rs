@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use crate::macros::event_matching::Event;
+
+/// Keeps things that shouldn't happen too often, such as macros mapping a fader's value, from
+/// happening more than once per interval, each kept apart by a key.
+///
+/// An event that comes in too soon isn't dropped outright: the last one is kept until the interval
+/// has passed, so whatever it's for still ends up reflecting the last value.
+pub(crate) struct Throttles {
+    throttles: HashMap<String, Throttle>
+}
+
+struct Throttle {
+    /// When the key was last let through
+    last_run: Instant,
+
+    /// Least time between letting the key through
+    interval: Duration,
+
+    /// The last event that came in too soon, to be let through once the interval has passed
+    pending: Option<Event>
+}
+
+impl Throttles {
+    pub(crate) fn new() -> Throttles {
+        Throttles { throttles: HashMap::new() }
+    }
+
+    /// Checks whether `key` may be let through at `now`, being at least `interval` since it was
+    /// last let through. If not, `event` is kept until then, replacing any event kept before.
+    pub(crate) fn try_run(
+        &mut self,
+        key: &str,
+        interval: Duration,
+        now: Instant,
+        event: &Event
+    ) -> bool {
+        match self.throttles.get_mut(key) {
+            Some(throttle) if now.saturating_duration_since(throttle.last_run) < interval => {
+                throttle.interval = interval;
+                throttle.pending = Some(event.clone());
+                false
+            }
+
+            _ => {
+                self.throttles.insert(
+                    key.to_string(),
+                    Throttle { last_run: now, interval, pending: None }
+                );
+
+                true
+            }
+        }
+    }
+
+    /// Drops the event kept for `key`, if any, leaving when it was last let through as is
+    pub(crate) fn discard(&mut self, key: &str) {
+        if let Some(throttle) = self.throttles.get_mut(key) {
+            throttle.pending = None;
+        }
+    }
+
+    /// Takes the kept events whose interval has passed by `now`, along with their keys
+    pub(crate) fn take_due(&mut self, now: Instant) -> Vec<(String, Event)> {
+        let mut due: Vec<(String, Event)> = self.throttles
+            .iter_mut()
+            .filter(|(_, throttle)| throttle.last_run + throttle.interval <= now)
+            .filter_map(|(key, throttle)| Some((key.to_string(), throttle.pending.take()?)))
+            .collect();
+
+        due.sort_by(|(a, _), (b, _)| a.cmp(b));
+        due
+    }
+
+    /// The next time `take_due` will have something to return, if any events are kept
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.throttles
+            .values()
+            .filter(|throttle| throttle.pending.is_some())
+            .map(|throttle| throttle.last_run + throttle.interval)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use crate::macros::event_matching::Event;
+    use crate::midi::{MidiEvent, MidiMessage};
+    use crate::state::throttle::Throttles;
+
+    fn cc(value: u8) -> Event {
+        Event::Midi(MidiEvent::new(
+            "faders",
+            MidiMessage::ControlChange { channel: 0, control: 7, value }
+        ))
+    }
+
+    #[test]
+    fn lets_through_once_per_interval_and_keeps_the_last_event() {
+        let mut throttles = Throttles::new();
+        let interval = Duration::from_millis(50);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert!(throttles.try_run("volume", interval, at(0), &cc(1)));
+        assert!(!throttles.try_run("volume", interval, at(10), &cc(2)));
+        assert!(!throttles.try_run("volume", interval, at(20), &cc(3)));
+
+        // Other keys are separate
+        assert!(throttles.try_run("pan", interval, at(20), &cc(4)));
+
+        assert_eq!(throttles.next_deadline(), Some(at(50)));
+        assert!(throttles.take_due(at(49)).is_empty());
+        assert_eq!(throttles.take_due(at(50)), vec![("volume".to_string(), cc(3))]);
+
+        assert_eq!(throttles.next_deadline(), None);
+        assert!(throttles.take_due(at(100)).is_empty());
+
+        assert!(throttles.try_run("volume", interval, at(60), &cc(5)));
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// Value of a named variable, set by actions and checked by preconditions
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool)
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Bool(b) => write!(f, "{}", b)
+        }
+    }
+}
+
+/// Named variables, which live for as long as the state does. A variable doesn't exist until it
+/// is first set.
+pub(crate) struct Variables {
+    values: HashMap<String, Value>
+}
+
+impl Variables {
+    pub(crate) fn new() -> Variables {
+        Variables { values: HashMap::new() }
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub(crate) fn set(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// Lists all variables that have been set, ordered by name
+    pub(crate) fn all(&self) -> Vec<(String, Value)> {
+        let mut all: Vec<(String, Value)> = self.values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+
+        all.sort_by(|(a, _), (b, _)| a.cmp(b));
+        all
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::variables::{Variables, Value};
+
+    #[test]
+    fn stores_values_by_name() {
+        let mut variables = Variables::new();
+        assert_eq!(variables.get("layer"), None);
+
+        variables.set("layer", Value::String("editing".to_string()));
+        variables.set("layer", Value::String("media".to_string()));
+        variables.set("page", Value::Integer(-2));
+
+        assert_eq!(variables.get("layer"), Some(&Value::String("media".to_string())));
+        assert_eq!(variables.get("page").map(|value| value.to_string()), Some("-2".to_string()));
+
+        assert_eq!(
+            variables.all(),
+            vec![
+                ("layer".to_string(), Value::String("media".to_string())),
+                ("page".to_string(), Value::Integer(-2))
+            ]
+        );
+    }
+}